@@ -0,0 +1,30 @@
+/* The C ABI of zeddy, which is built into the shared library with `--features ffi`. Strings are
+ * NUL-terminated UTF-8, and every string returned by zeddy must be freed with
+ * `zeddy_string_free`. */
+#ifndef ZEDDY_H
+#define ZEDDY_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Generates the JSON theme family of the KDL theme file `source`, written in version
+ * `kdl_version` of KDL ("1" or "2"), for version `schema_version` of Zed's theme schema, such as
+ * "0.2.0". Includes are resolved relative to `path`, which can be NULL if the theme includes
+ * nothing. Returns NULL on failure and, if `error` is not NULL, sets it to the error message. */
+char *zeddy_kdl_to_json(const char *source, const char *path, const char *kdl_version,
+                        const char *schema_version, char **error);
+
+/* Resolves the palette of the KDL theme file `source` into a JSON object of the hex color of
+ * every palette entry by name. The arguments are the same as those of `zeddy_kdl_to_json`. */
+char *zeddy_resolve_palette(const char *source, const char *path, const char *kdl_version,
+                            char **error);
+
+/* Frees a string returned by zeddy. Does nothing if `string` is NULL. */
+void zeddy_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
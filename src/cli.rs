@@ -1,3 +1,16 @@
+mod bench;
 mod commands;
+mod completions;
+mod config;
+mod doctor;
+mod edit;
+mod installed;
+mod keys;
+mod logging;
+mod manifest;
+mod notification;
 mod paths;
+mod preview;
+mod review;
 pub use commands::*;
+pub use completions::complete;
@@ -0,0 +1,75 @@
+//! Times the stages of generating a theme family separately, so that changes to the palette
+//! resolver, the generator, or the serializer can be measured.
+
+use std::hint::black_box;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result as Res;
+use tracing::info;
+
+use zeddy::generate::generate_json;
+use zeddy::schema::json::SchemaVersion;
+use zeddy::schema::{KdlThemeFamily, KdlVersion};
+
+/// Runs every stage `iterations` times and prints the mean, fastest, and slowest time of each.
+/// Reading the file includes decoding it and resolving its includes, and generating includes
+/// expanding templates and resolving the palette again, since that is what `generate` does.
+pub fn bench_cmd(
+    file: &Path,
+    iterations: u32,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    out: &mut impl Write,
+) -> Res<()> {
+    info!("Timing {iterations} iterations of {}", file.display());
+    let mut stages = [
+        ("read", Vec::new()),
+        ("resolve palette", Vec::new()),
+        ("generate", Vec::new()),
+        ("serialize", Vec::new()),
+    ];
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let family = KdlThemeFamily::read(file, kdl_version)?;
+        stages[0].1.push(start.elapsed());
+
+        let mut expanded = family.clone();
+        expanded.expand_templates()?;
+        let start = Instant::now();
+        black_box(expanded.palette.into_palette().resolve()?);
+        stages[1].1.push(start.elapsed());
+
+        let start = Instant::now();
+        let json = generate_json(family, version)?;
+        stages[2].1.push(start.elapsed());
+
+        let start = Instant::now();
+        black_box(serde_json::to_vec_pretty(&json)?);
+        stages[3].1.push(start.elapsed());
+    }
+
+    writeln!(
+        out,
+        "{:<16} {:>12} {:>12} {:>12}",
+        "stage", "mean", "min", "max"
+    )?;
+    for (name, times) in &stages {
+        let total = times.iter().sum::<Duration>();
+        let min = times.iter().min().copied().unwrap_or_default();
+        let max = times.iter().max().copied().unwrap_or_default();
+        writeln!(
+            out,
+            "{name:<16} {:>12} {:>12} {:>12}",
+            millis(total / iterations),
+            millis(min),
+            millis(max)
+        )?;
+    }
+    Ok(())
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
@@ -1,17 +1,80 @@
 use std::path::PathBuf;
 
-use crate::cli::paths::{default_install_location, default_output_location};
-use crate::generate::{generate_json, generate_kdl, serialize_kdl};
-use crate::schema::{JsonThemeFamily, KdlThemeFamily};
-use crate::util::LogExpect;
+use crate::cli::bench::bench_cmd;
+use crate::cli::completions::{completions_cmd, installed_theme_names, manpages_cmd, theme_names};
+use crate::cli::config::{config, load_config};
+use crate::cli::doctor::doctor_cmd;
+use crate::cli::edit::Editor;
+use crate::cli::installed::{
+    confirm, install_file, list_cmd, needs_backup, uninstall_cmd, InstalledSnapshot,
+};
+use crate::cli::keys::{open, read_keys, Key};
+use crate::cli::logging::LogArgs;
+use crate::cli::manifest::{Manifest, Target, MANIFEST_NAME};
+use crate::cli::notification::{Notifier, NotifyOn};
+use crate::cli::paths::{
+    channel_install_locations, default_install_locations, default_output_location,
+    schema_cache_location, templated_output_location, themes_dir, wal_colors_location, Channel,
+};
+use crate::cli::preview::Preview;
+use crate::cli::review::review_palette;
 use anyhow::{anyhow, Result as Res};
-use clap::{Parser, ValueEnum};
-use log::{debug, error, info, warn};
-use notify::event::{AccessKind, AccessMode, Event};
-use notify::{EventKind, Watcher};
-use std::fs::{File, OpenOptions};
-use std::io::BufWriter;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCompleter, Shell};
+use notify::event::{AccessKind, AccessMode, Event, MetadataKind, ModifyKind};
+use notify::{EventKind, PollWatcher, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tracing::{debug, error, info, info_span, warn};
+use zeddy::color::naming::{BuiltinDictionary, Namer, Naming};
+use zeddy::color::palette::{PaletteGenerator, RawPalette};
+use zeddy::color::{set_gamut_map, BaseColorKind, Gamut, GamutMap, HexColor};
+use zeddy::diff::{diff_families, render_change};
+use zeddy::explain::explain;
+use zeddy::generate::{
+    bump_version, derive_light, design_tokens, editor_svg, export_helix, export_nvim,
+    export_terminal, extension_manifest, format_kdl, gallery_markdown, generate_json, generate_kdl,
+    harmonize_palette, kdl_schema, make_overlay, merge_families, palette_svg, rasterize,
+    resolve_theme, scheme_palette, serialize_kdl, serialize_overlay_kdl, serialize_theme_kdl,
+    set_meta_version, set_palette_colors, split_family, swatch_svg, tailwind_colors,
+    terminal_scheme, vscode_theme, ColorConflict, ColorScheme, GalleryFamily, GenerationCache,
+    Harmony, Indent, KdlFormat, TerminalFormat, VersionPart,
+};
+use zeddy::import::{
+    read_design_tokens, read_extension, read_helix_theme, read_jetbrains_scheme,
+    read_tailwind_colors, read_terminal_schemes, read_tm_theme, read_wal_colors,
+};
+use zeddy::lint::{
+    lint_generated, lint_levels, lint_source, report_findings, set_lint_levels, Finding, Level,
+    LintLevels, LintTarget,
+};
+use zeddy::lsp::run_server;
+use zeddy::provenance::{source_map, source_map_location, SourceMap};
+use zeddy::schema::include::resolve_includes;
+use zeddy::schema::json::{JsonTheme, SchemaVersion};
+use zeddy::schema::location::with_source;
+use zeddy::schema::overlay::Overlay;
+use zeddy::schema::overrides::Override;
+use zeddy::schema::{zed, JsonThemeFamily, KdlThemeFamily, KdlVersion};
+use zeddy::util::{
+    create_output, download, is_stdio, is_url, read_input, slug, write_if_changed, LogExpect,
+    STDIO_PATH,
+};
+use zeddy::validate::schema_violations;
+
+/// The name of the gallery that `gallery` writes when no `outfile` is given.
+const GALLERY_NAME: &str = "GALLERY.md";
+/// The directory next to the gallery that swatch images are written to.
+const SWATCH_DIR: &str = "swatches";
+/// The largest input file that is downloaded when `infile` is a URL.
+const MAX_INPUT_SIZE: u64 = 16 * 1024 * 1024;
 
 /// A helper tool for making Zed themes using a custom KDL
 /// format that allows naming colors, reusing components, and much
@@ -19,17 +82,49 @@ use std::path::Path;
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Cli {
-    /// The input file used to generate a new theme file
-    infile: PathBuf,
+    /// The input file used to generate a new theme file. Required by every command
+    /// that reads a theme. `-` reads from standard input, and `generate` and `migrate` also
+    /// accept an HTTP(S) URL.
+    infile: Option<PathBuf>,
     /// The output file for the generated file. This is not the final install location.
-    /// Creates parent directories if they do not exist.
-    /// Defaults to `./generated/{relative-path-to-file}.{extension}`.
+    /// Creates parent directories if they do not exist. `-` writes to standard output.
+    /// Defaults to `./generated/{relative-path-to-file}.{extension}`, or standard output
+    /// for `generate` and `migrate` when reading from standard input.
     #[arg(short, long)]
     outfile: Option<PathBuf>,
-    /// The install location for the theme after generation. By default, it
-    /// is automatically detected the same way that Zed does it.
+    /// Names the output file after a template instead of the input file, in the same directory
+    /// as the default one. `{stem}` is the name of the input file without its extension, and
+    /// `{family}`, `{slug}`, `{author}`, and `{version}` come from the `meta` of the theme family,
+    /// such as `{slug}-{version}.json`
+    #[arg(long, conflicts_with = "outfile")]
+    outfile_template: Option<String>,
+    /// The install location for the theme after generation. Can be given more than once to
+    /// install the theme into every one of them. By default, it is automatically detected the
+    /// same way that Zed does it, along with the other themes directories of the configuration
+    /// file.
     #[arg(short, long)]
-    install_location: Option<PathBuf>,
+    install_location: Vec<PathBuf>,
+    /// The release channel of Zed whose themes directory is the default install location.
+    /// Defaults to the one in the configuration file, or stable
+    #[arg(long, value_enum)]
+    channel: Option<Channel>,
+    /// The version of Zed's theme schema to target when generating JSON. Defaults to the one in
+    /// the configuration file, or 0.1.0
+    #[arg(long)]
+    schema_version: Option<SchemaVersion>,
+    /// The version of the KDL language that theme files are read and written in, either 1 or 2.
+    /// Defaults to the one in the configuration file, or 1
+    #[arg(long)]
+    kdl_version: Option<KdlVersion>,
+    /// How colors that modifiers take outside of sRGB are brought back into it. Clipping every
+    /// channel can shift the hue of a color, while lowering its chroma keeps its hue and
+    /// lightness
+    #[arg(long, value_enum, default_value_t)]
+    gamut_map: GamutMap,
+    #[command(flatten)]
+    lints: LintArgs,
+    #[command(flatten)]
+    logging: LogArgs,
 
     #[command(subcommand)]
     command: Command,
@@ -38,23 +133,629 @@ pub struct Cli {
 #[derive(Parser, Debug, PartialEq)]
 pub enum Command {
     /// Generates a theme family JSON file from a KDL `infile`
-    Generate,
+    Generate {
+        #[command(flatten)]
+        schema: SchemaCheck,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
     /// Generates a theme family from a KDL `infile` and installs it. Note that this does not
     /// generate an extension from the theme: it just simply generates the JSON file.
-    Install,
+    Install {
+        #[command(flatten)]
+        overrides: OverrideArgs,
+        /// Install the theme into the themes directory of every release channel of Zed
+        #[arg(long)]
+        all_channels: bool,
+        #[command(flatten)]
+        options: InstallArgs,
+    },
     /// Watches for changes on the KDL `infile`, generates a theme from it,
-    /// and installs it into `install_location`, allowing
-    /// for a hot swap loop if the theme is selected.
-    Watch,
+    /// and installs it into every `install_location`, allowing
+    /// for a hot swap loop if the theme is selected. Without an `infile`, every target of the
+    /// project manifest `zeddy.kdl` is watched.
+    Watch {
+        #[command(flatten)]
+        overrides: OverrideArgs,
+        #[command(flatten)]
+        options: InstallArgs,
+        #[command(flatten)]
+        watch: WatchArgs,
+    },
+    /// Generates every target of a project manifest, which is the `infile` or `zeddy.kdl` in the
+    /// current directory. Each target names a theme file and optionally its output file, install
+    /// location, and overlays.
+    Build {
+        /// Also install every theme after generating it
+        #[arg(long)]
+        install: bool,
+        #[command(flatten)]
+        options: InstallArgs,
+    },
     /// Converts an existing JSON theme family into the custom KDL format. It attempts
-    /// to extract all colors into a palette and names the colors at best effort.
-    Migrate,
+    /// to extract all colors into a palette and names the colors at best effort. Modifiers are
+    /// grouped by the section of the theme that they apply to, such as `editor` or `terminal`.
+    Migrate {
+        #[command(flatten)]
+        format: FormatArgs,
+        /// Add a comment with the hex color after every color that refers to the palette
+        #[arg(long)]
+        annotate_colors: bool,
+        #[command(flatten)]
+        palette: PaletteArgs,
+        /// Go through the generated palette before writing the file, showing where each color
+        /// is used and asking whether to keep, rename, merge, or inline it
+        #[arg(long)]
+        interactive: bool,
+        /// More JSON theme files, or directories of them, whose themes are migrated into the
+        /// same family as `infile` with a shared palette. A directory as `infile` reads every
+        /// JSON file in it
+        files: Vec<PathBuf>,
+        /// Overwrite `outfile` if it already exists, which throws away any changes made to it by
+        /// hand
+        #[arg(long)]
+        force: bool,
+    },
+    /// Regenerates the theme from a KDL `infile` in memory and compares it with the existing
+    /// `outfile`, printing the differences and exiting with an error if it is out of date
+    Check {
+        /// Also compare against the themes at every `install_location`
+        #[arg(long)]
+        installed: bool,
+    },
+    /// Rewrites a KDL `infile` in a canonical format, with consistent indentation, a sorted
+    /// palette, and lowercase hex colors. The file is formatted in place unless `outfile`
+    /// is given. Comments and blank lines between nodes are kept.
+    Fmt {
+        #[command(flatten)]
+        format: FormatArgs,
+        /// Write the file in this version of KDL instead of the one it is read in, which
+        /// converts a theme to KDL v2 with `--to-kdl-version 2`
+        #[arg(long)]
+        to_kdl_version: Option<KdlVersion>,
+    },
+    /// Snaps the palette colors of a KDL `infile` to the closest of a set of `OKLCH` lightness
+    /// levels and into a range of chroma, keeping their hues, so that colors such as the syntax
+    /// colors are equally vivid. Colors that refer to other colors, grays, and colors with
+    /// `lock=true` are left alone. The palette is written back in place unless `outfile` is
+    /// given
+    Harmonize {
+        /// The lightness levels from 0 to 1, separated by commas
+        #[arg(
+            long,
+            value_name = "LIGHTNESS",
+            value_delimiter = ',',
+            required_unless_present_any = ["min_chroma", "max_chroma"]
+        )]
+        levels: Vec<f32>,
+        /// Raise the chroma of duller colors to this
+        #[arg(long, value_name = "CHROMA")]
+        min_chroma: Option<f32>,
+        /// Lower the chroma of more vivid colors to this
+        #[arg(long, value_name = "CHROMA")]
+        max_chroma: Option<f32>,
+        /// Print the colors that would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Checks a KDL `infile` for mistakes such as unknown style keys or missing palette
+    /// colors without writing anything. Exits with an error if any are found, including lints
+    /// that would only warn elsewhere. The generated JSON is also validated against Zed's
+    /// theme JSON Schema.
+    Validate {
+        /// Download the latest theme JSON Schema instead of using the cached or bundled copy
+        #[arg(long)]
+        fetch_schema: bool,
+    },
+    /// Derives a candidate light theme from a dark theme in the KDL `infile` by inverting
+    /// the lightness of its colors, and writes the new `theme` node to standard output
+    /// to be refined by hand.
+    DeriveLight {
+        /// The name of the theme to derive from
+        #[arg(long, add = ArgValueCompleter::new(theme_names))]
+        theme: String,
+    },
+    /// Explains where the value of a key in a theme of the KDL `infile` comes from: the
+    /// modifiers that set it, the palette entries its color goes through, and the final color
+    Explain {
+        /// The name of the theme
+        #[arg(add = ArgValueCompleter::new(theme_names))]
+        theme: String,
+        /// The key to explain, written like the keys printed by `diff`, e.g. `editor.background`,
+        /// `syntax.comment` or `syntax.comment.color`
+        key: String,
+    },
+    /// Prints where a key of a generated JSON file got its value, from the source map that
+    /// `--source-map` writes next to it: the lines of the KDL file that set it, the palette
+    /// entries its color went through, and the final color
+    Blame {
+        /// The generated JSON file, whose source map is the same file with `.map` added
+        file: PathBuf,
+        /// The key to look up, written like the keys printed by `diff`, e.g. `editor.background`,
+        /// `syntax.comment` or `syntax.comment.color`
+        key: String,
+        /// The theme to look the key up in, which can be left out if the family has only one
+        #[arg(long, value_name = "NAME")]
+        theme: Option<String>,
+    },
+    /// Writes a single theme of the KDL `infile` to standard output as it is generated: merged
+    /// with the `common` theme, with templates expanded and every color resolved
+    Resolve {
+        /// The name of the theme
+        #[arg(add = ArgValueCompleter::new(theme_names))]
+        theme: String,
+        /// The format to write the theme in
+        #[arg(long, value_enum, default_value_t = ResolveFormat::Kdl)]
+        format: ResolveFormat,
+    },
+    /// Compares the KDL `infile` with a modified copy of it and writes an overlay that turns
+    /// one into the other to standard output, to be used with `--overlay`. Overlays can only
+    /// replace palette colors and add modifiers, so other changes are reported and left out.
+    Patch {
+        /// The modified theme file
+        modified: PathBuf,
+    },
+    /// Splits the KDL `infile` into one file per theme. `dir` gets a file with the same name as
+    /// the `infile` that has everything the themes share and includes the theme files, which
+    /// are written to `dir/themes`
+    Split {
+        /// The directory to write the files to
+        dir: PathBuf,
+        /// Overwrite the files that already exist in `dir`, which throws away any changes made to
+        /// them by hand
+        #[arg(long)]
+        force: bool,
+    },
+    /// Renders every theme of the KDL `infile` to SVG images in `dir`: `<theme>.svg` shows the
+    /// theme in a mock editor window, and `<theme>-palette.svg` is a strip of the palette colors
+    /// that the theme uses
+    Image {
+        /// The directory to write the images to
+        dir: PathBuf,
+        /// Also rasterize every image to PNG, which needs zeddy to be built with the `png`
+        /// feature
+        #[arg(long)]
+        png: bool,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Opens an interactive editor for the palette of the KDL `infile`, which shows a swatch of
+    /// every color and adjusts the selected one with HSL and OKLCH sliders. The theme is
+    /// installed to every `install_location` after every change, and the file is only written when
+    /// the changes are saved
+    Edit {
+        #[command(flatten)]
+        replace: ReplaceArgs,
+    },
+    /// Writes a Markdown gallery of every theme family in `dir` and its subdirectories to
+    /// `outfile`, or `dir/GALLERY.md`. Every theme gets a section with its appearance and a table
+    /// of the palette colors it uses, whose swatch images are written to `swatches` next to the
+    /// gallery. Files that are not theme families are skipped.
+    Gallery {
+        /// The directory to look for theme files in
+        dir: PathBuf,
+    },
     /// Writes the palette of a theme file to standard output in a given format
     ExportPalette {
         /// The format to export to
         #[arg(value_enum)]
         format: PaletteFormat,
+        /// Only export the palette colors that the theme with this name uses
+        #[arg(long, value_name = "NAME", add = ArgValueCompleter::new(theme_names))]
+        theme: Option<String>,
+        /// The gamut of the exported colors. With `p3`, Display P3 colors are written as they are
+        /// instead of being mapped into sRGB
+        #[arg(long, value_enum, default_value_t)]
+        gamut: Gamut,
+    },
+    /// Writes the terminal colors of a theme of the KDL `infile` to `outfile` as the color scheme
+    /// of a terminal emulator: the 16 ANSI colors, the background and foreground, and the cursor
+    /// and selection colors of the first player. Translucent colors are drawn over the background
+    ExportTerminal {
+        /// The terminal emulator to write the scheme for
+        #[arg(long, value_enum)]
+        format: TerminalFormat,
+        /// The theme to export, which can be left out if the family has only one
+        #[arg(long, value_name = "NAME", add = ArgValueCompleter::new(theme_names))]
+        theme: Option<String>,
+    },
+    /// Writes a theme of the KDL `infile` to `outfile` as a Helix `theme.toml`. UI colors and
+    /// syntax scopes are mapped to Helix's scopes, colors that are in the palette are written as
+    /// references to its `[palette]` table, and translucent colors are drawn over the background
+    ExportHelix {
+        /// The theme to export, which can be left out if the family has only one
+        #[arg(long, value_name = "NAME", add = ArgValueCompleter::new(theme_names))]
+        theme: Option<String>,
+    },
+    /// Writes a theme of the KDL `infile` to `outfile` as a Neovim colorscheme in Lua, to be put
+    /// in a `colors` directory. It sets the standard highlight groups from the UI colors and
+    /// syntax scopes, the treesitter captures, diagnostics, and the terminal colors
+    ExportNvim {
+        /// The theme to export, which can be left out if the family has only one
+        #[arg(long, value_name = "NAME", add = ArgValueCompleter::new(theme_names))]
+        theme: Option<String>,
+    },
+    /// Writes a theme of the KDL `infile` to `outfile` as a VS Code color theme, which defaults to
+    /// `<infile>.color-theme.json`. Style keys become workbench colors, and syntax scopes become
+    /// token colors for the scopes that VS Code grammars give to the same tokens
+    ExportVscode {
+        /// The theme to export, which can be left out if the family has only one
+        #[arg(long, value_name = "NAME", add = ArgValueCompleter::new(theme_names))]
+        theme: Option<String>,
+    },
+    /// Generates the theme family of the KDL `infile` into the Zed extension in `dir`, as
+    /// `themes/<name>.json` next to an `extension.toml` with the name, version, authors,
+    /// description, and homepage of its `meta`. The other fields of an existing `extension.toml`
+    /// are kept
+    Package {
+        /// The directory of the extension
+        dir: PathBuf,
+        /// Print the files that would be written and how they would change instead of writing
+        /// anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Bumps the `version` in the `meta` of the KDL `infile` and generates the theme family to
+    /// `outfile` again, so that a release is a single command. A missing version counts as
+    /// `0.0.0`
+    Bump {
+        /// The part of the version to increment. The parts after it are reset to zero
+        part: VersionPart,
+        /// Add an entry for the new version to the top of this changelog, above the entries of
+        /// the earlier versions
+        #[arg(long)]
+        changelog: Option<PathBuf>,
+        /// A line of the changelog entry, which can be given several times
+        #[arg(short, long, requires = "changelog")]
+        message: Vec<String>,
+        /// Also package the theme family into the Zed extension in this directory, like
+        /// `package` does
+        #[arg(long, value_name = "DIR")]
+        package: Option<PathBuf>,
+    },
+    /// Lists the theme families in Zed's themes directory with their author and appearances,
+    /// and whether they were installed by zeddy
+    List,
+    /// Removes an installed theme family from Zed's themes directory
+    Uninstall {
+        /// The name of the theme family or of its file
+        #[arg(add = ArgValueCompleter::new(installed_theme_names))]
+        name: String,
+        /// Remove the theme without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Checks that Zed's config and themes directories exist and are writable and that
+    /// watching files works on this platform, and suggests fixes for any problems
+    Doctor {
+        /// Also check that Zed is installed
+        #[arg(long)]
+        check_zed: bool,
+    },
+    /// Times reading a KDL theme file, resolving its palette, generating its themes, and
+    /// serializing the JSON separately over a number of iterations, and prints a table of how
+    /// long each stage took, so that performance regressions can be measured
+    Bench {
+        /// The KDL theme file to time
+        file: PathBuf,
+        /// How many times to run every stage
+        #[arg(short = 'n', long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+    },
+    /// Compares the generated styles of two theme files, each of which can be either KDL or
+    /// JSON, and prints the keys that changed in every theme
+    Diff {
+        /// The original theme file
+        before: PathBuf,
+        /// The changed theme file
+        after: PathBuf,
+        /// The format to print the differences in
+        #[arg(long, value_enum, default_value_t = DiffFormat::Text)]
+        format: DiffFormat,
+    },
+    /// Merges several KDL theme files into one theme family, which is written to `outfile` or
+    /// standard output. Templates are instantiated and a new `common` theme is extracted from
+    /// all themes
+    Merge {
+        /// The theme files to merge. The meta of the first one is used for the merged family
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+        /// What to do with palette colors that have the same name but different colors
+        #[arg(long, value_enum, default_value_t)]
+        on_conflict: ColorConflict,
+        /// Overwrite `outfile` if it already exists, which throws away any changes made to it by
+        /// hand
+        #[arg(long)]
+        force: bool,
+    },
+    /// Applies a KDL overlay on top of an existing JSON theme family, such as a third-party theme,
+    /// and writes the patched family to `outfile`, which defaults to a file named after `base` in
+    /// the output directory. The family is migrated first, with its palette colors named like
+    /// `base-red`, so the overlay's palette colors are added next to them and its modifiers apply
+    /// after the theme's own styles
+    ApplyOverrides {
+        /// The JSON theme family to patch
+        base: PathBuf,
+        /// The KDL overlay to apply, in the format that `--overlay` reads
+        overlay: PathBuf,
+        /// Overrides a color after the overlay, like `--set` of `generate`. Can be given multiple
+        /// times
+        #[arg(long = "set", value_name = "KEY=COLOR")]
+        set: Vec<Override>,
+    },
+    /// Converts themes that were made elsewhere into KDL theme files
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+        /// Overwrite the KDL files that already exist, which throws away any changes made to them
+        /// by hand. `import wal` always overwrites its file, since it follows the wallpaper
+        #[arg(long, global = true)]
+        force: bool,
     },
+    /// Works with palettes on their own, without a theme file
+    Palette {
+        #[command(subcommand)]
+        action: PaletteCommand,
+    },
+    /// Runs a language server for theme files on standard input and output. It reports the
+    /// same mistakes as `validate` while typing, completes palette colors and style keys, shows
+    /// resolved colors on hover, and goes to the palette entry of a color reference
+    Lsp,
+    /// Writes a machine-readable schema of the theme file format to `outfile` or standard
+    /// output, so that other tools can check theme files without running zeddy
+    Schema {
+        /// The kind of schema to write
+        #[arg(value_enum)]
+        format: SchemaFormat,
+    },
+    /// Lists every style key that Zed's theme schema accepts
+    ListStyles {
+        /// Only list keys matching this glob pattern, e.g. `editor.*`
+        pattern: Option<String>,
+        /// Also print a short description of each key
+        #[arg(short, long)]
+        describe: bool,
+    },
+    /// Lists the standard syntax scopes highlighted by Zed's bundled languages
+    ListSyntax {
+        /// Only list scopes matching this glob pattern, e.g. `string.*`
+        pattern: Option<String>,
+        /// Also print a short description of each scope
+        #[arg(short, long)]
+        describe: bool,
+    },
+    /// Writes a completion script for `shell` to standard output, to be sourced by the shell on
+    /// startup. The script asks zeddy for completions, which include the names of the themes in
+    /// the theme file on the command line and of the installed themes
+    Completions {
+        /// The shell to complete zeddy in
+        #[arg(value_enum)]
+        shell: Shell,
+        /// Write a script that completes commands and options by itself, without theme names.
+        /// It does not have to be written again when zeddy is updated, but the options that it
+        /// knows about do
+        #[arg(long)]
+        standalone: bool,
+    },
+    /// Writes a manual page for zeddy and one for each of its commands to `dir`
+    Manpages {
+        /// The directory to write the pages to, such as `/usr/local/share/man/man1`
+        dir: PathBuf,
+    },
+}
+
+#[derive(Args, Debug, Default, PartialEq, Clone, Copy)]
+pub struct SchemaCheck {
+    /// Validate the generated JSON against Zed's theme JSON Schema before writing it
+    #[arg(long)]
+    check_schema: bool,
+    /// Download the latest theme JSON Schema instead of using the cached or bundled copy.
+    /// Implies `--check-schema`.
+    #[arg(long)]
+    fetch_schema: bool,
+}
+
+/// Lint levels given on the command line, which win over the ones in the configuration file.
+#[derive(Args, Debug, Default, Clone)]
+pub struct LintArgs {
+    /// Turns off a lint. Can be given multiple times.
+    #[arg(short = 'A', long = "allow", value_name = "LINT")]
+    allow: Vec<LintTarget>,
+    /// Reports a lint as a warning. Can be given multiple times.
+    #[arg(short = 'W', long = "warn", value_name = "LINT")]
+    warn: Vec<LintTarget>,
+    /// Reports a lint as an error. `--deny warnings` turns every warning into an error, which is
+    /// useful in CI. Can be given multiple times.
+    #[arg(short = 'D', long = "deny", value_name = "LINT")]
+    deny: Vec<LintTarget>,
+}
+
+impl LintArgs {
+    fn levels(&self) -> LintLevels {
+        let mut levels = LintLevels::default();
+        for (target, level) in &config().lint_levels {
+            levels.set(*target, *level);
+        }
+        let given = [
+            (&self.allow, Level::Allow),
+            (&self.warn, Level::Warn),
+            (&self.deny, Level::Deny),
+        ];
+        for (targets, level) in given {
+            for target in targets {
+                levels.set(*target, level);
+            }
+        }
+        levels
+    }
+}
+
+/// Options for writing generated JSON files.
+#[derive(Args, Debug, Default, PartialEq, Clone, Copy)]
+pub struct OutputArgs {
+    /// Write the output file without whitespace
+    #[arg(long)]
+    minify: bool,
+    /// Print the files that would be written and how their styles would change instead of
+    /// writing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Also write a source map next to the output file, with `.map` added to its name, which
+    /// records the line of the KDL file that every key of every theme got its value from
+    #[arg(long)]
+    source_map: bool,
+}
+
+/// Options for the files written by commands that install themes.
+#[derive(Args, Debug, Default, PartialEq, Clone, Copy)]
+pub struct InstallArgs {
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Write the installed theme without whitespace. This is independent of `--minify`, so the
+    /// installed theme can be compact while the output file stays readable.
+    #[arg(long)]
+    minify_installed: bool,
+    /// Overwrite an installed theme that zeddy did not write without backing it up
+    #[arg(long)]
+    no_backup: bool,
+    #[command(flatten)]
+    replace: ReplaceArgs,
+}
+
+/// Whether installing replaces a theme that zeddy did not write. Without either option, it is
+/// backed up and replaced.
+#[derive(Args, Debug, Default, PartialEq, Clone, Copy)]
+pub struct ReplaceArgs {
+    /// Ask before replacing an installed theme that zeddy did not write. Standard input has to be
+    /// a terminal
+    #[arg(long, conflicts_with = "no_clobber")]
+    confirm: bool,
+    /// Never replace an installed theme that zeddy did not write, and skip installing over it
+    /// instead
+    #[arg(long)]
+    no_clobber: bool,
+}
+
+/// Options for how `watch` notices changes and reports rebuilds.
+#[derive(Args, Debug, PartialEq, Clone, Copy)]
+pub struct WatchArgs {
+    /// Serve a live preview of the themes on this address, e.g. `127.0.0.1:7878`, which is
+    /// updated on every rebuild. This allows tweaking a theme where Zed is not running.
+    #[arg(long, value_name = "ADDRESS")]
+    serve: Option<SocketAddr>,
+    /// Show a desktop notification when rebuilding a theme fails, or after every rebuild with
+    /// `--notify always`. Needs zeddy to be built with the `notifications` feature
+    #[arg(long, value_enum, value_name = "WHEN", num_args = 0..=1, default_missing_value = "failure")]
+    notify: Option<NotifyOn>,
+    /// How many milliseconds to wait after a file changes before rebuilding, so that the several
+    /// events of a single save lead to a single rebuild
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    debounce: u64,
+    /// Check the files for changes every this many milliseconds instead of being told about them
+    /// by the system, which does not work on network filesystems and in some containers.
+    /// Polling is also used when the system cannot watch files
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..))]
+    poll: Option<u64>,
+    /// When watching stops, put back the installed themes as they were before it started, or
+    /// remove the ones that did not exist, so that trying out changes leaves nothing behind
+    #[arg(long)]
+    restore_on_exit: bool,
+}
+
+#[derive(Args, Debug, Default, PartialEq, Clone)]
+pub struct OverrideArgs {
+    /// Overrides a color before generating, e.g. `style.editor.background=#101418`,
+    /// `syntax.comment=muted` or `palette.accent=#ff7733`. Style and syntax overrides apply to
+    /// every theme after all of its modifiers. Can be given multiple times.
+    #[arg(long = "set", value_name = "KEY=COLOR")]
+    pub(super) set: Vec<Override>,
+    /// Applies a partial theme family on top of the `infile` before generating. Its palette
+    /// entries replace the ones of the `infile`, and its modifiers are applied after all
+    /// others. Can be given multiple times, and `--set` is applied after every overlay.
+    #[arg(long = "overlay", value_name = "FILE")]
+    pub(super) overlays: Vec<PathBuf>,
+    /// Only generates the theme with this name, instead of every theme of the family. Can be
+    /// given multiple times to keep several themes.
+    #[arg(long = "theme", value_name = "NAME", add = ArgValueCompleter::new(theme_names))]
+    pub(super) themes: Vec<String>,
+}
+
+impl OverrideArgs {
+    fn apply(&self, family: &mut KdlThemeFamily, kdl_version: KdlVersion) -> Res<()> {
+        for path in &self.overlays {
+            debug!("Applying overlay {}", path.display());
+            Overlay::read(path, kdl_version)
+                .and_then(|overlay| overlay.apply(family))
+                .map_err(|e| anyhow!("could not apply overlay {}: {e:?}", path.display()))?;
+        }
+        for item in &self.set {
+            item.apply(family);
+        }
+        if !self.themes.is_empty() {
+            family.retain_themes(&self.themes)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug, PartialEq, Clone, Copy)]
+pub struct FormatArgs {
+    /// The number of spaces to indent each block with
+    #[arg(long, default_value_t = 4, conflicts_with = "tabs")]
+    indent: usize,
+    /// Indent blocks with tabs instead of spaces
+    #[arg(long)]
+    tabs: bool,
+    /// Sort the properties of each node by name instead of keeping them in their original order
+    #[arg(long)]
+    sort_properties: bool,
+    /// Write `apply` blocks on a single line if they fit within this many columns
+    #[arg(long)]
+    max_width: Option<usize>,
+}
+
+#[derive(Args, Debug, PartialEq, Clone)]
+pub struct PaletteArgs {
+    /// The built-in list of names that palette colors are named after
+    #[arg(long, value_enum, default_value_t)]
+    dictionary: BuiltinDictionary,
+    /// A CSV file of `hex,name` lines to name palette colors after instead of a built-in list
+    #[arg(long, conflicts_with = "dictionary")]
+    dictionary_file: Option<PathBuf>,
+    /// How to name palette colors
+    #[arg(long, value_enum, default_value_t)]
+    naming: Naming,
+    /// Give translucent colors their own palette entries instead of referring to the opaque
+    /// color with an `alpha` modifier
+    #[arg(long)]
+    keep_alpha: bool,
+}
+
+impl PaletteArgs {
+    fn generator(&self) -> Res<PaletteGenerator> {
+        let namer = match &self.dictionary_file {
+            Some(path) => Namer::read(path)?,
+            None => Namer::builtin(self.dictionary),
+        };
+        Ok(PaletteGenerator::with_namer(namer).keep_alpha(self.keep_alpha))
+    }
+}
+
+impl From<FormatArgs> for KdlFormat {
+    fn from(args: FormatArgs) -> Self {
+        Self {
+            indent: if args.tabs {
+                Indent::Tabs
+            } else {
+                Indent::Spaces(args.indent)
+            },
+            sort_properties: args.sort_properties,
+            max_width: args.max_width,
+            ..Self::default()
+        }
+    }
 }
 
 #[derive(ValueEnum, Debug, PartialEq, Clone)]
@@ -63,18 +764,58 @@ pub enum PaletteFormat {
     ArrayOfTuples,
     /// Export as a newline-separated list of `name color`
     SpaceSeparated,
+    /// Export as color tokens in the JSON format of the Design Tokens Community Group, where
+    /// entries that only refer to another entry become aliases
+    Dtcg,
+    /// Export as the `colors` object of a Tailwind config, with colors like `blue-500` grouped
+    /// into scales
+    Tailwind,
 }
 
 impl PaletteFormat {
-    fn output(&self, infile: &Path) -> Res<()> {
+    fn output(
+        &self,
+        infile: &Path,
+        kdl_version: KdlVersion,
+        theme: Option<&str>,
+        gamut: Gamut,
+    ) -> Res<()> {
         debug!("Reading KDL data from {}", infile.display());
-        let kdl = KdlThemeFamily::read(infile)?;
+        let mut kdl = KdlThemeFamily::read(infile, kdl_version)?;
+        let used = match theme {
+            Some(name) => {
+                kdl.retain_themes(&[name.to_owned()])?;
+                Some(kdl.used_palette_colors(kdl.themes.iter().chain(&kdl.common)))
+            }
+            None => None,
+        };
+        match self {
+            Self::Dtcg => {
+                print!("{}", design_tokens(&kdl.palette, used.as_ref(), gamut)?);
+                return Ok(());
+            }
+            Self::Tailwind => {
+                print!("{}", tailwind_colors(kdl.palette, used.as_ref(), gamut)?);
+                return Ok(());
+            }
+            Self::ArrayOfTuples | Self::SpaceSeparated => {}
+        }
+        let display_p3 = match gamut {
+            Gamut::Srgb => HashMap::new(),
+            Gamut::P3 => kdl.palette.display_p3_colors(),
+        };
         let palette = kdl.palette.into_palette().resolve()?;
 
         let mut data = palette
             .colors
             .into_iter()
-            .map(|(name, color)| (name, color.to_string()))
+            .filter(|(name, _)| used.as_ref().is_none_or(|used| used.contains(&**name)))
+            .map(|(name, color)| {
+                let color = display_p3
+                    .get(&name)
+                    .map_or_else(|| color.to_string(), ToString::to_string);
+                (name, color)
+            })
             .collect::<Vec<_>>();
         data.sort_unstable_by(|(key1, _), (key2, _)| key1.cmp(key2));
 
@@ -89,146 +830,2725 @@ impl PaletteFormat {
                 }
                 Ok(())
             }
+            Self::Dtcg | Self::Tailwind => {
+                unreachable!("nested formats are written before resolving the palette")
+            }
+        }
+    }
+}
+#[derive(ValueEnum, Debug, PartialEq, Clone, Copy)]
+pub enum ResolveFormat {
+    /// A flat `theme` node with hex colors and only the modifiers that apply to it
+    Kdl,
+    /// The theme object of the generated JSON, including its style map
+    Json,
+}
+
+#[derive(ValueEnum, Debug, PartialEq, Clone, Copy)]
+pub enum DiffFormat {
+    /// One line per changed key, with color swatches when printing to a terminal
+    Text,
+    /// A JSON array of themes and their changed keys
+    Json,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum PaletteCommand {
+    /// Generates a starter palette from a base color with a rule of color harmony and writes it
+    /// as a KDL `palette` node to `outfile` or standard output. The colors of the scheme keep
+    /// the lightness and chroma of the base in `OKLCH` and are named after their role, like
+    /// `primary` and `secondary-light`, and the neutrals are grays tinted with the hue of the
+    /// base, from `neutral-1`, the darkest, to the lightest
+    Generate {
+        /// The hex color that the other colors are derived from
+        #[arg(long)]
+        base: HexColor,
+        /// The rule that picks the hues that go with the base
+        #[arg(long, value_enum)]
+        scheme: ColorScheme,
+        /// The number of colors of the scheme. Once every hue is used, the hues are used again a
+        /// little lighter or darker
+        #[arg(long, default_value_t = 6)]
+        count: usize,
+        /// The number of neutrals
+        #[arg(long, default_value_t = 5)]
+        neutrals: usize,
+        /// Overwrite `outfile` if it already exists, which throws away any changes made to it by
+        /// hand
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum ImportSource {
+    /// Migrates every theme family of a Zed extension into a KDL file in the `outfile`
+    /// directory, which defaults to the id of the extension. The extension can be a directory,
+    /// a zip archive or a URL to one, or a git URL. The authors in `extension.toml` become the
+    /// author of every family
+    Extension {
+        /// The directory, zip archive, or git URL of the extension
+        source: String,
+        #[command(flatten)]
+        palette: PaletteArgs,
+    },
+    /// Builds a theme family from the color scheme of a terminal emulator, with its ANSI colors
+    /// as the terminal colors and its background and foreground as the colors of the editor.
+    /// iTerm2 presets (`.itermcolors`), Terminal.app profiles (`.terminal`), Alacritty configs in
+    /// TOML or YAML, kitty configs, wezterm schemes, Gogh schemes in YAML or JSON, and
+    /// terminal.sexy exports are detected from their extension or contents. The palette colors
+    /// are named after their role in the terminal
+    Terminal {
+        /// The color scheme to import, or a collection of them: a JSON list of schemes or a
+        /// directory such as a clone of Gogh
+        file: PathBuf,
+        /// Print the names of the schemes in the collection instead of importing one
+        #[arg(long, conflicts_with = "scheme")]
+        list: bool,
+        /// The scheme of the collection to import, which can be left out if it has only one.
+        /// Letters are compared without case, and spaces and dashes are the same
+        #[arg(long, value_name = "NAME")]
+        scheme: Option<String>,
+    },
+    /// Builds a theme family from a `.tmTheme` color scheme of Sublime Text or the editors before
+    /// it. The colors of the editor become Zed's, and every syntax scope gets the color of the
+    /// rule that matches the `.tmTheme` scope that grammars give to the same tokens. Rules that
+    /// only apply inside of other scopes are ignored
+    Tmtheme {
+        /// The `.tmTheme` file to import
+        file: PathBuf,
+        #[command(flatten)]
+        palette: PaletteArgs,
+    },
+    /// Builds a theme family from the editor color scheme of IDEA or another IDE built on it,
+    /// exported as an `.icls` file or taken from the `.xml` of a theme plugin. Editor colors and
+    /// the default text attributes are mapped to Zed's style keys and syntax scopes at best
+    /// effort, and the keys that have no equivalent, such as the attributes for a single
+    /// language, are listed
+    Jetbrains {
+        /// The `.icls` or `.xml` scheme to import
+        file: PathBuf,
+        #[command(flatten)]
+        palette: PaletteArgs,
+    },
+    /// Builds a theme family from a Helix `theme.toml`. Helix's UI and syntax scopes are mapped to
+    /// Zed's style keys and syntax scopes, and the colors of its `[palette]` become the palette
+    /// with the same names, with other colors named after the closest one. Themes that inherit
+    /// from another theme only get the styles they set themselves
+    Helix {
+        /// The `theme.toml` to import
+        file: PathBuf,
+        #[command(flatten)]
+        palette: PaletteArgs,
+    },
+    /// Reads the color tokens of a design tokens file in the format of the Design Tokens
+    /// Community Group into a file with only a `palette` node, which can be included in a theme
+    /// family or applied with `--overlay`. The names of nested groups are joined with dashes, and
+    /// aliases become references to other palette colors
+    Tokens {
+        /// The JSON file of design tokens
+        file: PathBuf,
+    },
+    /// Reads Tailwind color scales from a JSON file into a file with only a `palette` node, like
+    /// `import tokens`. Shades are named after their scale, such as `blue-500`, and the file can
+    /// be an object of colors or a Tailwind config with `theme.colors` or `theme.extend.colors`
+    Tailwind {
+        /// The JSON file of Tailwind colors
+        file: PathBuf,
+    },
+    /// Builds a theme named `wal` from the colors that pywal writes to its cache, like `import
+    /// terminal`: the 16 colors become the terminal colors and the special colors become the
+    /// background, foreground, and cursor. The theme is written to `generated/wal.kdl` by default
+    Wal {
+        /// The `colors.json` to read, which defaults to the one in pywal's cache
+        file: Option<PathBuf>,
+        /// Keep running and write the theme again whenever the colors change, such as when the
+        /// wallpaper is changed
+        #[arg(long)]
+        watch_wal: bool,
+    },
+}
+
+#[derive(ValueEnum, Debug, PartialEq, Clone, Copy)]
+pub enum SchemaFormat {
+    /// A KDL Schema Language document, written in the version of KDL given by `--kdl-version`
+    Kdl,
+}
+
+/// Reads a theme family from either a generated JSON file or a KDL file, which is
+/// generated in memory.
+fn read_json_family(
+    path: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<JsonThemeFamily> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        debug!("Reading JSON data from {}", path.display());
+        Ok(serde_json::from_str(&read_input(path)?)?)
+    } else {
+        debug!("Reading KDL data from {}", path.display());
+        generate_json(KdlThemeFamily::read(path, kdl_version)?, version)
+            .map_err(|e| with_source(e, path, kdl_version))
+    }
+}
+
+fn diff_cmd(
+    before: &Path,
+    after: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    format: DiffFormat,
+) -> Res<()> {
+    let before = read_json_family(before, version, kdl_version)?;
+    let after = read_json_family(after, version, kdl_version)?;
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        DiffFormat::Json => {
+            let diff = diff_families(&before, &after)?;
+            serde_json::to_writer_pretty(&mut stdout, &diff)?;
+            writeln!(stdout)?;
+        }
+        DiffFormat::Text => {
+            let swatches = stdout.is_terminal();
+            if !print_diff(&mut stdout, &before, &after, swatches)? {
+                info!("No differences found");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Merges the theme families in `files` into one and writes it to `outfile`, or to stdout if there
+/// is none. Palette colors that conflict are handled according to `on_conflict`.
+fn merge_cmd(
+    files: &[PathBuf],
+    outfile: Option<&Path>,
+    force: bool,
+    kdl_version: KdlVersion,
+    on_conflict: ColorConflict,
+) -> Res<()> {
+    if let Some(outfile) = outfile {
+        check_overwrite(outfile, force)?;
+    }
+    let mut families = Vec::with_capacity(files.len());
+    for path in files {
+        debug!("Reading KDL data from {}", path.display());
+        let family = KdlThemeFamily::read(path, kdl_version)
+            .map_err(|e| with_source(e, path, kdl_version))?;
+        let label = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |x| x.to_string_lossy().into_owned(),
+        );
+        families.push((label, family));
+    }
+    let merged = merge_families(families, on_conflict)?;
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    match outfile {
+        Some(outfile) => {
+            debug!("Writing KDL data to {}", outfile.display());
+            serialize_kdl(create_output(outfile)?, &merged, format, None)?;
+        }
+        None => serialize_kdl(std::io::stdout().lock(), &merged, format, None)?,
+    }
+    Ok(())
+}
+
+/// Prints the differences between two families, returning whether there were any.
+fn print_diff(
+    stdout: &mut impl Write,
+    before: &JsonThemeFamily,
+    after: &JsonThemeFamily,
+    swatches: bool,
+) -> Res<bool> {
+    let diff = diff_families(before, after)?;
+    for theme in &diff {
+        writeln!(stdout, "theme {:?}", theme.name)?;
+        for change in &theme.changes {
+            writeln!(stdout, "    {}", render_change(change, swatches))?;
         }
     }
+    Ok(!diff.is_empty())
 }
-fn generate_json_cmd(infile: &Path, outfile: &Path) -> Res<()> {
+
+fn check_cmd(
+    infile: &Path,
+    targets: &[&Path],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
     debug!("Reading KDL data from {}", infile.display());
-    let kdl = KdlThemeFamily::read(infile)?;
-    let json = generate_json(kdl)?;
-    debug!("Writing JSON data to {}", outfile.display());
-    let prefix = outfile
-        .parent()
-        .ok_or_else(|| anyhow!("output file has no parent"))?;
-    std::fs::create_dir_all(prefix)?;
-
-    let writer = BufWriter::new(
-        OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(outfile)?,
-    );
-    serde_json::to_writer_pretty(writer, &json)?;
+    let json = generate_json(KdlThemeFamily::read(infile, kdl_version)?, version)?;
+    let mut stdout = std::io::stdout().lock();
+    let swatches = stdout.is_terminal();
+    let mut stale = 0;
+    for target in targets {
+        let existing: JsonThemeFamily = serde_json::from_reader(
+            File::open(target).map_err(|e| anyhow!("could not open {}: {e}", target.display()))?,
+        )?;
+        // themes are compared key by key since the order of keys in the file is not stable
+        if print_diff(&mut stdout, &existing, &json, swatches)? {
+            error!("{} is out of date", target.display());
+            stale += 1;
+        } else {
+            info!("{} is up to date", target.display());
+        }
+    }
+    if stale == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{stale} generated file(s) are out of date. Regenerate them with `zeddy {} generate`",
+            infile.display()
+        ))
+    }
+}
+
+fn list_keys_cmd(keys: &[(&str, &str)], pattern: Option<&str>, describe: bool) -> Res<()> {
+    let pattern = pattern.map(glob::Pattern::new).transpose()?;
+    let matching = keys
+        .iter()
+        .filter(|(key, _)| pattern.as_ref().is_none_or(|p| p.matches(key)))
+        .collect::<Vec<_>>();
+    let width = matching.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, description) in matching {
+        if describe {
+            println!("{key:width$}  {description}");
+        } else {
+            println!("{key}");
+        }
+    }
+    Ok(())
+}
+
+fn import_extension_cmd(
+    source: &str,
+    outdir: Option<&Path>,
+    force: bool,
+    palette: &PaletteArgs,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    let extension = read_extension(source)?;
+    let outdir = outdir.map_or_else(|| PathBuf::from(&extension.id), Path::to_owned);
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    let families = extension
+        .families
+        .into_iter()
+        .map(|(name, family)| (outdir.join(format!("{name}.kdl")), family))
+        .collect::<Vec<_>>();
+    // nothing is written unless every file can be
+    for (path, _) in &families {
+        check_overwrite(path, force)?;
+    }
+    for (path, family) in families {
+        let file = generate_kdl(family, palette.generator()?, palette.naming);
+        info!("Writing {}", path.display());
+        serialize_kdl(create_output(&path)?, &file, format, None)?;
+    }
+    Ok(())
+}
+
+fn import_terminal_cmd(
+    file: &Path,
+    outfile: Option<&Path>,
+    force: bool,
+    list: bool,
+    scheme: Option<&str>,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    let mut schemes = read_terminal_schemes(file)?;
+    if list {
+        let mut names = schemes.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        names.sort_unstable_by_key(|name| name.to_lowercase());
+        let mut stdout = std::io::stdout().lock();
+        for name in names {
+            writeln!(stdout, "{name}")?;
+        }
+        return Ok(());
+    }
+    let is_collection = file.is_dir() || schemes.len() > 1;
+    let scheme = match scheme {
+        Some(name) => {
+            let idx = schemes
+                .iter()
+                .position(|x| slug(&x.name) == slug(name))
+                .ok_or_else(|| {
+                    anyhow!("there is no scheme named {name:?}, see the names with `--list`")
+                })?;
+            schemes.swap_remove(idx)
+        }
+        None if schemes.len() == 1 => schemes.remove(0),
+        None => {
+            return Err(anyhow!(
+                "{} has {} schemes, pick one with `--scheme` or see their names with `--list`",
+                file.display(),
+                schemes.len()
+            ))
+        }
+    };
+    // a scheme of a collection is named after itself rather than after the collection
+    let outfile = match outfile {
+        Some(outfile) => outfile.to_owned(),
+        None if is_collection => default_output_location(Path::new(&slug(&scheme.name)), "kdl")?,
+        None => default_output_location(file, "kdl")?,
+    };
+    check_overwrite(&outfile, force)?;
+    let family = scheme.into_kdl_family()?;
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_kdl(create_output(&outfile)?, &family, format, None)?;
+    Ok(())
+}
+
+fn import_tm_theme_cmd(
+    file: &Path,
+    outfile: Option<&Path>,
+    force: bool,
+    palette: &PaletteArgs,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading color scheme from {}", file.display());
+    let bytes =
+        std::fs::read(file).map_err(|e| anyhow!("could not read {}: {e}", file.display()))?;
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let family = read_tm_theme(&bytes, &stem)?;
+    let outfile = match outfile {
+        Some(outfile) => outfile.to_owned(),
+        None => default_output_location(file, "kdl")?,
+    };
+    check_overwrite(&outfile, force)?;
+    let file = generate_kdl(family, palette.generator()?, palette.naming);
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_kdl(create_output(&outfile)?, &file, format, None)?;
+    Ok(())
+}
+
+fn import_jetbrains_cmd(
+    file: &Path,
+    outfile: Option<&Path>,
+    force: bool,
+    palette: &PaletteArgs,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading color scheme from {}", file.display());
+    let scheme = read_jetbrains_scheme(&read_input(file)?)?;
+    if !scheme.unmapped.is_empty() {
+        warn!(
+            "{} keys of the scheme have no equivalent in Zed and were left out:",
+            scheme.unmapped.len()
+        );
+        for key in &scheme.unmapped {
+            info!("  {key}");
+        }
+    }
+    let outfile = match outfile {
+        Some(outfile) => outfile.to_owned(),
+        None => default_output_location(file, "kdl")?,
+    };
+    check_overwrite(&outfile, force)?;
+    let file = generate_kdl(scheme.family, palette.generator()?, palette.naming);
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_kdl(create_output(&outfile)?, &file, format, None)?;
     Ok(())
 }
 
-fn install_cmd(infile: &Path, outfile: &Path, installfile: &Path) -> Res<()> {
-    generate_json_cmd(infile, outfile)?;
-    std::fs::copy(outfile, installfile)?;
+fn import_helix_cmd(
+    file: &Path,
+    outfile: Option<&Path>,
+    force: bool,
+    palette: &PaletteArgs,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading Helix theme from {}", file.display());
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let theme = read_helix_theme(&read_input(file)?, &stem)?;
+    if let Some(parent) = &theme.inherits {
+        warn!("The theme inherits from {parent:?}, whose styles are not included");
+    }
+    if !theme.unknown.is_empty() {
+        warn!(
+            "Colors that are not in the palette were left out: {}",
+            theme.unknown.join(", ")
+        );
+    }
+    let outfile = match outfile {
+        Some(outfile) => outfile.to_owned(),
+        None => default_output_location(file, "kdl")?,
+    };
+    check_overwrite(&outfile, force)?;
+    // the palette of the theme names its colors, like the roles of a terminal scheme
+    let (generator, naming) = if theme.palette.is_empty() {
+        (palette.generator()?, palette.naming)
+    } else {
+        let namer = Namer::new(
+            theme
+                .palette
+                .iter()
+                .map(|(name, color)| (*color, name.as_str())),
+        )?;
+        let generator = PaletteGenerator::with_namer(namer).keep_alpha(palette.keep_alpha);
+        (generator, Naming::Nearest)
+    };
+    let file = generate_kdl(theme.family, generator, naming);
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_kdl(create_output(&outfile)?, &file, format, None)?;
     Ok(())
 }
 
-fn watch_cmd(infile: &Path, outfile: &Path, installfile: &Path) -> Res<()> {
-    info!("Watching for changes on {}", infile.display());
+fn import_wal_cmd(
+    file: Option<&Path>,
+    outfile: Option<&Path>,
+    watch: bool,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    let file = file.map_or_else(wal_colors_location, |file| Ok(file.to_owned()))?;
+    let outfile = outfile.map_or_else(
+        || default_output_location(Path::new("wal"), "kdl"),
+        |outfile| Ok(outfile.to_owned()),
+    )?;
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    let write = || -> Res<()> {
+        let family = read_wal_colors(&file)?.into_kdl_family()?;
+        info!("Writing {}", outfile.display());
+        serialize_kdl(create_output(&outfile)?, &family, format, None)?;
+        Ok(())
+    };
+    write()?;
+    if !watch {
+        return Ok(());
+    }
 
+    // pywal may replace the file rather than write to it, which a watch on the file itself would
+    // not notice after the first time
     let (tx, rx) = std::sync::mpsc::channel::<notify::Result<Event>>();
     let mut watcher = notify::recommended_watcher(tx)?;
-    watcher.watch(infile, notify::RecursiveMode::NonRecursive)?;
-
+    let dir = file
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    info!("Watching for changes on {}", file.display());
     for res in rx {
-        let res = match res {
-            Ok(res) => res.kind,
+        let event = match res {
+            Ok(event) => event,
             Err(e) => {
                 warn!("Error while watching file: {e}. Continuing to wait...");
                 continue;
             }
         };
-        match res {
-            // we only want to update after closing with write permissions. If we listened for other modification events,
-            // we would not only receive surplus events, but not have the full contents of the file.
-            EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
-                debug!("{} was modified. Updating...", infile.display());
-
-                match install_cmd(infile, outfile, installfile) {
-                    Ok(()) => {}
-                    Err(e) => {
-                        warn!("Failed to update: {e}");
-                    }
-                }
-            }
-            EventKind::Access(_) => {
-                debug!("{} was accessed. Ignoring...", infile.display());
+        let written = matches!(
+            event.kind,
+            EventKind::Access(AccessKind::Close(AccessMode::Write))
+                | EventKind::Modify(ModifyKind::Name(_))
+        );
+        if written
+            && event
+                .paths
+                .iter()
+                .any(|x| x.file_name() == file.file_name())
+        {
+            debug!("{} was modified. Updating...", file.display());
+            if let Err(e) = write() {
+                warn!("Failed to update {}: {e}", outfile.display());
             }
-            EventKind::Create(_) => {
-                debug!("{} was created. Ignoring...", infile.display());
-            }
-            EventKind::Remove(_) => {
-                error!("{} was deleted. Stopping...", infile.display());
-                return Ok(());
-            }
-            _ => {}
         }
     }
     Ok(())
 }
 
-fn migrate_cmd(infile: &Path, outfile: &Path) -> Res<()> {
-    let reader = File::open(infile)?;
-    let json: JsonThemeFamily = serde_json::from_reader(reader)?;
-    let file = generate_kdl(json);
+/// Writes the palette that `read` reads from `file` as a file with only a `palette` node.
+fn import_palette_cmd(
+    file: &Path,
+    outfile: Option<&Path>,
+    force: bool,
+    kdl_version: KdlVersion,
+    read: fn(&str) -> Res<RawPalette>,
+) -> Res<()> {
+    debug!("Reading colors from {}", file.display());
+    let palette = read(&read_input(file)?)?;
+    let outfile = match outfile {
+        Some(outfile) => outfile.to_owned(),
+        None => default_output_location(file, "kdl")?,
+    };
+    check_overwrite(&outfile, force)?;
+    let overlay = Overlay {
+        palette,
+        ..Overlay::default()
+    };
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_overlay_kdl(create_output(&outfile)?, &overlay, format)?;
+    Ok(())
+}
 
-    let prefix = outfile
-        .parent()
-        .ok_or_else(|| anyhow!("Output file has no parent"))?;
-    std::fs::create_dir_all(prefix)?;
-    let writer = BufWriter::new(
-        OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(outfile)?,
+fn generate_palette_cmd(
+    base: HexColor,
+    scheme: ColorScheme,
+    count: usize,
+    neutrals: usize,
+    outfile: Option<&Path>,
+    force: bool,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    if let Some(outfile) = outfile {
+        check_overwrite(outfile, force)?;
+    }
+    let overlay = Overlay {
+        palette: scheme_palette(base, scheme, count, neutrals)?,
+        ..Overlay::default()
+    };
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    match outfile {
+        Some(outfile) => {
+            debug!("Writing KDL data to {}", outfile.display());
+            serialize_overlay_kdl(create_output(outfile)?, &overlay, format)?;
+        }
+        None => serialize_overlay_kdl(std::io::stdout().lock(), &overlay, format)?,
+    }
+    Ok(())
+}
+
+fn schema_cmd(format: SchemaFormat, outfile: Option<&Path>, kdl_version: KdlVersion) -> Res<()> {
+    let schema = match format {
+        SchemaFormat::Kdl => kdl_version.render(kdl_schema())?,
+    };
+    let mut writer = create_output(outfile.unwrap_or(Path::new(STDIO_PATH)))?;
+    writer.write_all(schema.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Loads Zed's theme JSON Schema, preferring a previously fetched copy over the bundled one.
+fn load_theme_schema(version: SchemaVersion, fetch: bool) -> Res<serde_json::Value> {
+    let cached = schema_cache_location(version)?;
+    if fetch {
+        let url = version.url();
+        info!("Fetching theme schema from {url}");
+        let body = ureq::get(&url).call()?.into_string()?;
+        let schema = serde_json::from_str(&body)?;
+        if let Some(prefix) = cached.parent() {
+            std::fs::create_dir_all(prefix)?;
+        }
+        std::fs::write(&cached, body)?;
+        debug!("Cached theme schema at {}", cached.display());
+        return Ok(schema);
+    }
+    if let Ok(content) = std::fs::read_to_string(&cached) {
+        debug!("Using cached theme schema at {}", cached.display());
+        return Ok(serde_json::from_str(&content)?);
+    }
+    debug!("Using bundled theme schema");
+    let bundled = version.bundled_schema().ok_or_else(|| {
+        anyhow!("no copy of the v{version} theme schema is bundled. Pass `--fetch-schema` to download it")
+    })?;
+    Ok(serde_json::from_str(bundled)?)
+}
+
+/// Recovers the schema version a generated family targets from its `$schema` URL.
+fn json_schema_version(json: &JsonThemeFamily) -> SchemaVersion {
+    SchemaVersion::ALL
+        .iter()
+        .copied()
+        .find(|version| version.url() == json.schema)
+        .unwrap_or_default()
+}
+
+fn apply_overrides_cmd(
+    base: &Path,
+    overlay: &Path,
+    set: &[Override],
+    outfile: Option<&Path>,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    let json = read_json_inputs(&[base.to_owned()])?;
+    // the patched family keeps targeting the schema of the original
+    let version = json_schema_version(&json);
+    let mut family = generate_kdl(json, PaletteGenerator::default(), Naming::default());
+    // the generated names are namespaced, so that an overlay color with the same name as one of
+    // them is added instead of silently recoloring the theme
+    let names = family
+        .palette
+        .colors
+        .iter()
+        .map(|node| node.name.clone())
+        .collect::<Vec<_>>();
+    for name in names {
+        family.rename_palette_color(&name, &format!("base-{name}"))?;
+    }
+    debug!("Applying overlay {}", overlay.display());
+    Overlay::read(overlay, kdl_version)
+        .and_then(|x| x.apply(&mut family))
+        .map_err(|e| anyhow!("could not apply overlay {}: {e:?}", overlay.display()))?;
+    for item in set {
+        item.apply(&mut family);
+    }
+    let json = generate_json(family, version)?;
+    let outfile = match outfile {
+        Some(outfile) => outfile.to_owned(),
+        None => default_output_location(base, "json")?,
+    };
+    debug!("Writing JSON data to {}", outfile.display());
+    let mut writer = create_output(&outfile)?;
+    writer.write_all(&json_bytes(&json, false)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn check_json_schema(json: &JsonThemeFamily, fetch: bool) -> Res<()> {
+    let schema = load_theme_schema(json_schema_version(json), fetch)?;
+    let violations = schema_violations(json, &schema)?;
+    for violation in &violations {
+        error!("{violation}");
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "generated JSON violates the theme schema in {} place(s)",
+            violations.len()
+        ))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_json_cmd(
+    infile: &Path,
+    outfile: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    schema: SchemaCheck,
+    overrides: &OverrideArgs,
+    output: OutputArgs,
+    cache: &mut GenerationCache,
+) -> Res<JsonThemeFamily> {
+    debug!("Reading KDL data from {}", infile.display());
+    let mut kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    // lints look at the file as it was written, not at the overlays or the selected themes
+    let mut findings = lint_source(&kdl);
+    overrides.apply(&mut kdl, kdl_version)?;
+    let sources = output.source_map.then(|| kdl.clone());
+    let json = cache.generate(kdl, version)?;
+    findings.extend(lint_generated(&json));
+    report_lints(infile, kdl_version, &findings, lint_levels())?;
+    if schema.check_schema || schema.fetch_schema {
+        check_json_schema(&json, schema.fetch_schema)?;
+    }
+    if output.dry_run {
+        print_dry_run(outfile, &json)?;
+        return Ok(json);
+    }
+    debug!("Writing JSON data to {}", outfile.display());
+    if !write_if_changed(outfile, &json_bytes(&json, output.minify)?)? {
+        debug!("{} is already up to date", outfile.display());
+    }
+    if let Some(kdl) = sources {
+        write_source_map(infile, outfile, kdl, &json, version, kdl_version)?;
+    }
+    Ok(json)
+}
+
+/// Writes the source map of the generated `outfile` next to it.
+fn write_source_map(
+    infile: &Path,
+    outfile: &Path,
+    kdl: KdlThemeFamily,
+    json: &JsonThemeFamily,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    if is_stdio(outfile) {
+        warn!("A theme written to standard output has no source map");
+        return Ok(());
+    }
+    let source = read_input(infile)?;
+    let map = source_map(kdl, json, infile, &kdl_version.to_v1(&source)?, version)?;
+    let path = source_map_location(outfile);
+    debug!("Writing source map to {}", path.display());
+    write_if_changed(&path, &serde_json::to_vec_pretty(&map)?)?;
+    Ok(())
+}
+
+/// Prints whether `path` would be created or overwritten with `json`, and how the styles of its
+/// themes would change.
+fn print_dry_run(path: &Path, json: &JsonThemeFamily) -> Res<()> {
+    if is_stdio(path) {
+        info!("Would write the theme to standard output");
+        return Ok(());
+    }
+    if !path.exists() {
+        info!("Would create {}", path.display());
+        return Ok(());
+    }
+    let existing: JsonThemeFamily = serde_json::from_str(&read_input(path)?)
+        .map_err(|e| anyhow!("could not read the existing {}: {e}", path.display()))?;
+    info!("Would overwrite {}", path.display());
+    let mut stdout = std::io::stdout().lock();
+    let swatches = stdout.is_terminal();
+    if !print_diff(&mut stdout, &existing, json, swatches)? {
+        info!("The styles in {} would not change", path.display());
+    }
+    Ok(())
+}
+
+/// Installs a generated theme, or prints what installing it would do with `--dry-run`.
+fn install_json(json: &JsonThemeFamily, installfile: &Path, options: InstallArgs) -> Res<()> {
+    let backup = !options.no_backup;
+    if options.output.dry_run {
+        if backup && needs_backup(installfile)? {
+            info!(
+                "Would back up {} because zeddy did not install it",
+                installfile.display()
+            );
+        }
+        return print_dry_run(installfile, json);
+    }
+    if !may_replace(installfile, options.replace)? {
+        return Ok(());
+    }
+    install_file(
+        &json_bytes(json, options.minify_installed)?,
+        installfile,
+        backup,
+    )
+}
+
+/// Whether `installfile` may be written. A theme that zeddy did not install is never replaced with
+/// `--no-clobber`, and only after asking with `--confirm`.
+fn may_replace(installfile: &Path, replace: ReplaceArgs) -> Res<bool> {
+    if !(replace.confirm || replace.no_clobber) || !needs_backup(installfile)? {
+        return Ok(true);
+    }
+    if replace.no_clobber {
+        warn!(
+            "Not replacing {} because zeddy did not install it",
+            installfile.display()
+        );
+        return Ok(false);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "not asking whether to replace {} because standard input is not a terminal, \
+            leave out `--confirm` to back it up and replace it",
+            installfile.display()
+        ));
+    }
+    let question = format!(
+        "Replace {}, which zeddy did not install?",
+        installfile.display()
     );
-    serialize_kdl(writer, &file)?;
+    if confirm(&question)? {
+        Ok(true)
+    } else {
+        info!("Keeping {}", installfile.display());
+        Ok(false)
+    }
+}
+
+/// Reports lint findings in `infile`, along with the lines they were found on.
+fn report_lints(
+    infile: &Path,
+    kdl_version: KdlVersion,
+    findings: &[Finding],
+    levels: &LintLevels,
+) -> Res<()> {
+    let source = read_input(infile)
+        .ok()
+        .and_then(|source| Some(kdl_version.to_v1(&source).ok()?.into_owned()));
+    report_findings(findings, levels, source.as_deref())
+}
+
+/// Fails if `outfile` already exists and `force` is not set. KDL files are edited by hand once
+/// they have been migrated or imported, so writing over one could throw away work that is in no
+/// other file.
+fn check_overwrite(outfile: &Path, force: bool) -> Res<()> {
+    if !force && !is_stdio(outfile) && outfile.exists() {
+        return Err(anyhow!(
+            "{} already exists, pass `--force` to overwrite it",
+            outfile.display()
+        ));
+    }
     Ok(())
 }
 
-impl Cli {
-    pub fn run(self) {
-        let Cli {
-            command,
-            infile,
-            install_location,
-            outfile,
-        } = self;
-        let ext = if command == Command::Migrate {
-            "kdl"
+/// Serializes a theme family, without whitespace if `minify` is set.
+fn json_bytes(json: &JsonThemeFamily, minify: bool) -> Res<Vec<u8>> {
+    Ok(if minify {
+        serde_json::to_vec(json)?
+    } else {
+        serde_json::to_vec_pretty(json)?
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn install_cmd(
+    infile: &Path,
+    outfile: &Path,
+    installfiles: &[PathBuf],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    overrides: &OverrideArgs,
+    options: InstallArgs,
+    cache: &mut GenerationCache,
+) -> Res<JsonThemeFamily> {
+    let json = generate_json_cmd(
+        infile,
+        outfile,
+        version,
+        kdl_version,
+        SchemaCheck::default(),
+        overrides,
+        options.output,
+        cache,
+    )?;
+    for installfile in installfiles {
+        let _span = info_span!("install", file = %installfile.display()).entered();
+        install_json(&json, installfile, options)?;
+    }
+    Ok(json)
+}
+
+/// Reads the targets of the project manifest at `path`, or `zeddy.kdl` in the current directory.
+fn read_manifest(
+    path: Option<&Path>,
+    kdl_version: KdlVersion,
+    channel: Channel,
+) -> Res<Vec<Target>> {
+    let path = path.unwrap_or(Path::new(MANIFEST_NAME));
+    debug!("Reading project manifest {}", path.display());
+    let targets = Manifest::read(path, kdl_version)
+        .map_err(|e| anyhow!("could not read project manifest {}: {e:?}", path.display()))?
+        .targets(path, channel)?;
+    if targets.is_empty() {
+        warn!("{} has no targets", path.display());
+    }
+    Ok(targets)
+}
+
+fn build_cmd(
+    targets: &[Target],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    install: bool,
+    options: InstallArgs,
+) -> Res<()> {
+    let mut failed = 0;
+    for target in targets {
+        info!("Building {}", target.name);
+        let res = if install {
+            install_cmd(
+                &target.infile,
+                &target.outfile,
+                &target.install_locations,
+                version,
+                kdl_version,
+                &target.overrides,
+                options,
+                &mut GenerationCache::default(),
+            )
         } else {
-            "json"
+            generate_json_cmd(
+                &target.infile,
+                &target.outfile,
+                version,
+                kdl_version,
+                SchemaCheck::default(),
+                &target.overrides,
+                options.output,
+                &mut GenerationCache::default(),
+            )
         };
-        let outfile = outfile.unwrap_or_else(|| {
-            debug!(
-                "User did not provide an outfile, generating default based on the input file {}",
-                infile.display()
-            );
-            default_output_location(&infile, ext)
-                .log_expect("Error generating output file location")
-        });
-        let install_location = install_location.unwrap_or_else(|| {
-            debug!(
-                "User did not provide an install location, defaulting to the default Zed config path based on the output file `{}`",
-                infile.display()
+        if let Err(e) = res {
+            let e = with_source(e, &target.infile, kdl_version);
+            error!("Failed to build {}: {e}", target.name);
+            failed += 1;
+        }
+    }
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{failed} of {} targets failed to build",
+            targets.len()
+        ))
+    }
+}
+
+fn install_all_channels_cmd(
+    infile: &Path,
+    outfile: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    overrides: &OverrideArgs,
+    options: InstallArgs,
+) -> Res<()> {
+    let json = generate_json_cmd(
+        infile,
+        outfile,
+        version,
+        kdl_version,
+        SchemaCheck::default(),
+        overrides,
+        options.output,
+        &mut GenerationCache::default(),
+    )?;
+    for location in channel_install_locations(outfile)? {
+        if location.parent().is_some_and(Path::is_dir) {
+            info!("Installing to {}", location.display());
+            install_json(&json, &location, options)?;
+        } else {
+            warn!(
+                "Skipping {} because its directory does not exist",
+                location.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// What the watch loop waits for.
+enum WatchEvent {
+    File(notify::Result<Event>),
+    Key(Key),
+    /// SIGINT or SIGTERM was received
+    Stop,
+}
+
+/// What `watch` keeps between rebuilds.
+struct Watch<'a> {
+    targets: &'a [Target],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    options: InstallArgs,
+    notifier: Notifier,
+    preview: Option<Arc<Preview>>,
+    /// What each target generated the last time, so that saving a file only regenerates the
+    /// themes that changed
+    caches: Vec<GenerationCache>,
+    /// The files that each target is generated from, canonicalized to compare them with the
+    /// paths of events
+    sources: Vec<Vec<PathBuf>>,
+    watcher: Box<dyn Watcher>,
+    /// Every file in `sources`, which are the ones that `watcher` watches
+    files: HashSet<PathBuf>,
+    /// The files in `files` that were deleted. Their directories are watched until they are
+    /// created again
+    missing: HashSet<PathBuf>,
+}
+
+impl Watch<'_> {
+    /// Generates and installs a target again. Failing to is only reported, since the next save
+    /// may fix it.
+    fn rebuild(&mut self, idx: usize) {
+        let target = &self.targets[idx];
+        match install_cmd(
+            &target.infile,
+            &target.outfile,
+            &target.install_locations,
+            self.version,
+            self.kdl_version,
+            &target.overrides,
+            self.options,
+            &mut self.caches[idx],
+        ) {
+            Ok(json) => {
+                self.notifier.updated(&target.name);
+                if let Some(preview) = &self.preview {
+                    preview.update(&target.name, &json);
+                }
+            }
+            Err(e) => {
+                let e = with_source(e, &target.infile, self.kdl_version);
+                warn!("Failed to update {}: {e}", target.name);
+                self.notifier.failed(&target.name, &e);
+                if let Some(preview) = &self.preview {
+                    preview.fail(&target.name, &e);
+                }
+            }
+        }
+        self.rescan(idx);
+    }
+
+    /// Finds the files that a target is generated from again, since saving a file may have added
+    /// or removed includes, and starts or stops watching files to match.
+    fn rescan(&mut self, idx: usize) {
+        let target = &self.targets[idx];
+        match target_files(target, self.kdl_version) {
+            Ok(files) => self.sources[idx] = files,
+            // the file that cannot be read still has to be watched to notice when it is fixed
+            Err(e) => debug!("Keeping the watched files of {}: {e}", target.name),
+        }
+        let files = self
+            .sources
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<HashSet<_>>();
+        for file in files.difference(&self.files) {
+            info!("Watching for changes on {}", file.display());
+            if let Err(e) = self
+                .watcher
+                .watch(file, notify::RecursiveMode::NonRecursive)
+            {
+                warn!("Failed to watch {}: {e}", file.display());
+            }
+        }
+        for file in self.files.difference(&files) {
+            debug!("No longer watching {}", file.display());
+            let _ = self.watcher.unwatch(file);
+        }
+        self.files = files;
+    }
+
+    /// Adds the files of an event that a rebuild may need to `changed`.
+    fn file_event(&mut self, event: &Event, changed: &mut HashSet<PathBuf>) {
+        let paths = event
+            .paths
+            .iter()
+            .map(|x| x.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match event.kind {
+            // editors save in different ways, and polling only notices the modification time, so
+            // any of these can be the only event of a save. They are debounced, so that a save
+            // that causes several only rebuilds once the file is complete
+            EventKind::Access(AccessKind::Close(AccessMode::Write))
+            | EventKind::Create(_)
+            | EventKind::Modify(
+                ModifyKind::Any
+                | ModifyKind::Data(_)
+                | ModifyKind::Metadata(MetadataKind::WriteTime),
+            ) => {
+                debug!("{paths} was modified. Updating...");
+                for path in &event.paths {
+                    self.reappeared(path);
+                }
+                changed.extend(event.paths.iter().filter_map(|x| x.canonicalize().ok()));
+            }
+            // the watch follows a file that is renamed, so a watched file that is renamed away
+            // is as good as deleted
+            EventKind::Modify(ModifyKind::Name(_)) | EventKind::Remove(_) => {
+                for path in &event.paths {
+                    if self.reappeared(path) || self.removed(path) {
+                        changed.insert(path.clone());
+                    }
+                }
+            }
+            _ => {
+                debug!("{paths} was accessed. Ignoring...");
+            }
+        }
+    }
+
+    /// Handles a watched file being deleted. Returns whether it is already there again, which is
+    /// how editors that save by replacing the file look. Otherwise its directory is watched until
+    /// it is created again.
+    fn removed(&mut self, path: &Path) -> bool {
+        if !self.files.contains(path) {
+            return false;
+        }
+        if path.exists() {
+            debug!("{} was replaced", path.display());
+            self.watch_again(path);
+            return true;
+        }
+        warn!(
+            "{} was deleted. Waiting for it to be created again...",
+            path.display()
+        );
+        if let Some(dir) = path.parent() {
+            if let Err(e) = self.watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+                warn!("Failed to watch {}: {e}", dir.display());
+            }
+        }
+        self.missing.insert(path.to_owned());
+        false
+    }
+
+    /// Watches a deleted file again if `path` is it and it was created again. Returns whether it
+    /// was.
+    fn reappeared(&mut self, path: &Path) -> bool {
+        if !path.exists() || !self.missing.remove(path) {
+            return false;
+        }
+        info!("{} was created again", path.display());
+        self.watch_again(path);
+        if let Some(dir) = path.parent() {
+            if !self.missing.iter().any(|x| x.parent() == Some(dir)) {
+                let _ = self.watcher.unwatch(dir);
+            }
+        }
+        true
+    }
+
+    /// Watches a file that was replaced, since the watch was on the file that is gone.
+    fn watch_again(&mut self, path: &Path) {
+        let _ = self.watcher.unwatch(path);
+        if let Err(e) = self
+            .watcher
+            .watch(path, notify::RecursiveMode::NonRecursive)
+        {
+            warn!("Failed to watch {}: {e}", path.display());
+        }
+    }
+
+    /// Does what a key that was typed asks for. Returns whether to keep watching.
+    fn run_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Rebuild => {
+                info!("Rebuilding every theme...");
+                for idx in 0..self.targets.len() {
+                    self.caches[idx] = GenerationCache::default();
+                    self.rebuild(idx);
+                }
+            }
+            Key::Palette => {
+                for target in self.targets {
+                    if self.targets.len() > 1 {
+                        println!("{}:", target.name);
+                    }
+                    let res = PaletteFormat::SpaceSeparated.output(
+                        &target.infile,
+                        self.kdl_version,
+                        None,
+                        Gamut::Srgb,
+                    );
+                    if let Err(e) = res {
+                        let e = with_source(e, &target.infile, self.kdl_version);
+                        warn!("Failed to resolve the palette of {}: {e}", target.name);
+                    }
+                }
+            }
+            Key::Open => {
+                for target in self.targets {
+                    if let Err(e) = open(&target.outfile) {
+                        warn!("{e}");
+                    }
+                }
+            }
+            Key::Quit => return false,
+        }
+        true
+    }
+}
+
+/// Returns the files that `target` is generated from: its theme file, the files that it includes,
+/// and its overlays, canonicalized.
+fn target_files(target: &Target, kdl_version: KdlVersion) -> Res<Vec<PathBuf>> {
+    let mut files = vec![target.infile.canonicalize()?];
+    files.extend(KdlThemeFamily::included_files(&target.infile, kdl_version)?);
+    for overlay in &target.overrides.overlays {
+        files.push(overlay.canonicalize()?);
+    }
+    Ok(files)
+}
+
+/// How often files are polled when the system cannot watch them and `--poll` was not given.
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+fn watch_cmd(
+    targets: &[Target],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    mut options: InstallArgs,
+    args: WatchArgs,
+) -> Res<()> {
+    let notifier = Notifier::new(args.notify)?;
+    // the keys that are typed while watching are read from standard input, so replacing themes
+    // that zeddy did not install is asked about before that starts
+    if options.replace.confirm && !options.output.dry_run {
+        for location in targets.iter().flat_map(|target| &target.install_locations) {
+            if !may_replace(location, options.replace)? {
+                return Err(anyhow!("not replacing {}", location.display()));
+            }
+        }
+        options.replace.confirm = false;
+    }
+    // the included files are found once the watcher is running, since they may not be readable
+    // yet
+    let mut sources = Vec::with_capacity(targets.len());
+    for target in targets {
+        if is_stdio(&target.infile) {
+            return Err(anyhow!("standard input cannot be watched"));
+        }
+        info!("Watching for changes on {}", target.infile.display());
+        let files = std::iter::once(&target.infile).chain(&target.overrides.overlays);
+        sources.push(
+            files
+                .map(|file| Ok(file.canonicalize()?))
+                .collect::<Res<Vec<_>>>()?,
+        );
+    }
+    let snapshots = if args.restore_on_exit {
+        targets
+            .iter()
+            .flat_map(|target| &target.install_locations)
+            .map(|location| InstalledSnapshot::take(location))
+            .collect::<Res<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+    let (tx, rx) = std::sync::mpsc::channel();
+    let stop = tx.clone();
+    // a build that is running when the signal arrives finishes first, since the loop only sees
+    // the signal afterwards
+    ctrlc::set_handler(move || {
+        let _ = stop.send(WatchEvent::Stop);
+    })?;
+    let files = sources.iter().flatten().cloned().collect::<HashSet<_>>();
+    let poll = args.poll.map(Duration::from_millis);
+    let watcher = file_watcher(tx.clone(), &files, poll)?;
+    let preview = args
+        .serve
+        .map(|address| serve_preview(address, targets, version, kdl_version))
+        .transpose()?;
+    let mut watch = Watch {
+        targets,
+        version,
+        kdl_version,
+        options,
+        notifier,
+        preview,
+        caches: targets.iter().map(|_| GenerationCache::default()).collect(),
+        sources,
+        watcher,
+        files,
+        missing: HashSet::new(),
+    };
+    for idx in 0..targets.len() {
+        watch.rescan(idx);
+    }
+    read_keys(move |key| tx.send(WatchEvent::Key(key)).is_ok());
+
+    // the files that changed since the last rebuild, which happens once none have changed for
+    // the debounce time
+    let debounce = Duration::from_millis(args.debounce);
+    let mut changed = HashSet::new();
+    loop {
+        let event = if changed.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(debounce)
+        };
+        match event {
+            Ok(WatchEvent::File(Ok(event))) => watch.file_event(&event, &mut changed),
+            Ok(WatchEvent::File(Err(e))) => {
+                warn!("Error while watching file: {e}. Continuing to wait...");
+            }
+            Ok(WatchEvent::Key(key)) => {
+                if !watch.run_key(key) {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                for idx in 0..targets.len() {
+                    if watch.sources[idx].iter().any(|x| changed.contains(x)) {
+                        watch.rebuild(idx);
+                    }
+                }
+                changed.clear();
+            }
+            Ok(WatchEvent::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    info!("Stopping...");
+    restore_snapshots(snapshots)
+}
+
+/// Puts back every installed theme that `watch` replaced. One that cannot be put back is logged
+/// and does not stop the others from being restored.
+fn restore_snapshots(snapshots: Vec<InstalledSnapshot>) -> Res<()> {
+    let mut failed = 0;
+    for snapshot in snapshots {
+        let path = snapshot.path().to_owned();
+        if let Err(e) = snapshot.restore() {
+            error!("Failed to restore {}: {e:#}", path.display());
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("Failed to restore {failed} installed theme(s)"));
+    }
+    Ok(())
+}
+
+/// Starts watching `files`, which sends their events to `tx`. They are polled every `poll` if it
+/// is given, or if the system cannot watch them.
+fn file_watcher(
+    tx: Sender<WatchEvent>,
+    files: &HashSet<PathBuf>,
+    poll: Option<Duration>,
+) -> Res<Box<dyn Watcher>> {
+    let handler = move |res| {
+        let _ = tx.send(WatchEvent::File(res));
+    };
+    let watch_all = |watcher: &mut dyn Watcher| {
+        files
+            .iter()
+            .try_for_each(|file| watcher.watch(file, notify::RecursiveMode::NonRecursive))
+    };
+    if poll.is_none() {
+        let native = notify::recommended_watcher(handler.clone()).and_then(|mut watcher| {
+            watch_all(&mut watcher)?;
+            Ok(watcher)
+        });
+        match native {
+            Ok(watcher) => return Ok(Box::new(watcher)),
+            Err(e) => warn!("Cannot watch files: {e}. Polling them instead..."),
+        }
+    }
+    let interval = poll.unwrap_or(FALLBACK_POLL_INTERVAL);
+    debug!("Polling files every {} ms", interval.as_millis());
+    let config = notify::Config::default().with_poll_interval(interval);
+    let mut watcher = PollWatcher::new(handler, config)?;
+    watch_all(&mut watcher)?;
+    Ok(Box::new(watcher))
+}
+
+/// Starts serving the preview of `targets`, which starts out with their current themes without
+/// installing them.
+fn serve_preview(
+    address: SocketAddr,
+    targets: &[Target],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<Arc<Preview>> {
+    let names = targets.iter().map(|x| x.name.clone()).collect::<Vec<_>>();
+    let preview = Preview::serve(address, &names)?;
+    for target in targets {
+        let json = KdlThemeFamily::read(&target.infile, kdl_version).and_then(|mut kdl| {
+            target.overrides.apply(&mut kdl, kdl_version)?;
+            generate_json(kdl, version)
+        });
+        match json {
+            Ok(json) => preview.update(&target.name, &json),
+            Err(e) => preview.fail(&target.name, &with_source(e, &target.infile, kdl_version)),
+        }
+    }
+    Ok(preview)
+}
+
+fn validate_cmd(
+    infile: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    fetch_schema: bool,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let mut findings = lint_source(&kdl);
+    let json = generate_json(kdl, version)?;
+    findings.extend(lint_generated(&json));
+    report_lints(infile, kdl_version, &findings, &lint_levels().strict())?;
+    check_json_schema(&json, fetch_schema)?;
+    info!("{} is valid", infile.display());
+    Ok(())
+}
+
+fn derive_light_cmd(infile: &Path, theme: &str, kdl_version: KdlVersion) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let light = derive_light(kdl, theme)?;
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_theme_kdl(std::io::stdout().lock(), &light, format)?;
+    Ok(())
+}
+
+fn resolve_cmd(
+    infile: &Path,
+    theme: &str,
+    format: ResolveFormat,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let stdout = std::io::stdout().lock();
+    match format {
+        ResolveFormat::Kdl => {
+            let theme = resolve_theme(kdl, theme)?;
+            let format = KdlFormat {
+                version: kdl_version,
+                ..KdlFormat::default()
+            };
+            serialize_theme_kdl(stdout, &theme, format)?;
+        }
+        ResolveFormat::Json => {
+            let json = generate_json(kdl, version)?;
+            let theme = json
+                .themes
+                .iter()
+                .find(|x| x.name == theme)
+                .ok_or_else(|| anyhow!("there is no theme named {theme:?}"))?;
+            serde_json::to_writer_pretty(stdout, theme)?;
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn export_terminal_cmd(
+    infile: &Path,
+    outfile: &Path,
+    format: TerminalFormat,
+    theme: Option<&str>,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let json = generate_json(kdl, version)?;
+    let theme = pick_theme(&json, theme)?;
+    let text = export_terminal(&terminal_scheme(theme)?, format)?;
+    let mut out = create_output(outfile)?;
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_helix_cmd(
+    infile: &Path,
+    outfile: &Path,
+    theme: Option<&str>,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let palette = kdl.palette.clone().into_palette().resolve()?;
+    let json = generate_json(kdl, version)?;
+    let text = export_helix(pick_theme(&json, theme)?, &palette)?;
+    let mut out = create_output(outfile)?;
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_nvim_cmd(
+    infile: &Path,
+    outfile: &Path,
+    theme: Option<&str>,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let json = generate_json(kdl, version)?;
+    let text = export_nvim(pick_theme(&json, theme)?)?;
+    let mut out = create_output(outfile)?;
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_vscode_cmd(
+    infile: &Path,
+    outfile: &Path,
+    theme: Option<&str>,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let json = generate_json(kdl, version)?;
+    let text = vscode_theme(pick_theme(&json, theme)?)?;
+    let mut out = create_output(outfile)?;
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+fn package_cmd(
+    infile: &Path,
+    dir: &Path,
+    dry_run: bool,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let manifest_path = dir.join("extension.toml");
+    let existing = std::fs::read_to_string(&manifest_path).ok();
+    let manifest = extension_manifest(&kdl.meta, existing.as_deref())?;
+    let themefile = dir
+        .join("themes")
+        .join(slug(&kdl.meta.name))
+        .with_extension("json");
+    let json = generate_json(kdl, version)?;
+    if dry_run {
+        print_dry_run(&themefile, &json)?;
+        if existing.as_deref() == Some(manifest.as_str()) {
+            info!("{} would not change", manifest_path.display());
+        } else {
+            let action = if existing.is_some() {
+                "overwrite"
+            } else {
+                "create"
+            };
+            info!("Would {action} {} with:", manifest_path.display());
+            print!("{manifest}");
+        }
+        return Ok(());
+    }
+    if write_if_changed(&themefile, &json_bytes(&json, false)?)? {
+        info!("Wrote {}", themefile.display());
+    }
+    if write_if_changed(&manifest_path, manifest.as_bytes())? {
+        info!("Wrote {}", manifest_path.display());
+    }
+    Ok(())
+}
+
+fn bump_cmd(
+    infile: &Path,
+    outfile: &Path,
+    part: VersionPart,
+    changelog: Option<&Path>,
+    message: &[String],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    if is_stdio(infile) {
+        return Err(anyhow!(
+            "`bump` saves to the input file, so it cannot read standard input"
+        ));
+    }
+    let name = infile.display().to_string();
+    let source = read_input(infile)?;
+    let meta = KdlThemeFamily::parse(&name, &source, kdl_version)?.meta;
+    let new_version = bump_version(meta.version.as_deref(), part)?;
+    // everything is generated before anything is written, so that a theme that fails to generate
+    // leaves the source, the changelog, and the output file as they were
+    let bumped = set_meta_version(&source, kdl_version, &new_version)?;
+    let mut kdl = KdlThemeFamily::parse(&name, &bumped, kdl_version)?;
+    resolve_includes(&mut kdl, infile, kdl_version)?;
+    let mut findings = lint_source(&kdl);
+    let json = generate_json(kdl, version)?;
+    findings.extend(lint_generated(&json));
+    report_lints(infile, kdl_version, &findings, lint_levels())?;
+    let changelog = changelog
+        .map(|path| {
+            Ok::<_, anyhow::Error>((path, changelog_with_entry(path, &new_version, message)?))
+        })
+        .transpose()?;
+
+    std::fs::write(infile, bumped)?;
+    info!(
+        "Bumped {} from {} to {new_version}",
+        meta.name,
+        meta.version.as_deref().unwrap_or("nothing")
+    );
+    if let Some((path, text)) = changelog {
+        std::fs::write(path, text)?;
+        info!("Added {new_version} to {}", path.display());
+    }
+    write_if_changed(outfile, &json_bytes(&json, false)?)?;
+    info!("Generated {}", outfile.display());
+    Ok(())
+}
+
+/// Returns the changelog at `path` with a Markdown section for `version` that has a bullet point
+/// for every line of `message`. It goes above the first section of an existing changelog, which
+/// is where the latest version is usually kept, and at the end of one without sections.
+fn changelog_with_entry(path: &Path, version: &str, message: &[String]) -> Res<String> {
+    let mut entry = format!("## {version}\n\n");
+    for line in message {
+        entry.extend(["- ", line, "\n"]);
+    }
+    let mut changelog = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::from("# Changelog\n"),
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(start) = changelog
+        .match_indices("## ")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || changelog[..i].ends_with('\n'))
+    {
+        if !message.is_empty() {
+            entry.push('\n');
+        }
+        changelog.insert_str(start, &entry);
+    } else {
+        if !changelog.ends_with("\n\n") {
+            changelog.push_str(if changelog.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        changelog += &entry;
+    }
+    Ok(changelog)
+}
+
+/// Finds the theme named `name`, or the only theme of the family when there is no name.
+fn pick_theme<'a>(json: &'a JsonThemeFamily, name: Option<&str>) -> Res<&'a JsonTheme> {
+    match name {
+        Some(name) => json
+            .themes
+            .iter()
+            .find(|x| x.name == name)
+            .ok_or_else(|| anyhow!("there is no theme named {name:?}")),
+        None => match &json.themes[..] {
+            [theme] => Ok(theme),
+            themes => {
+                let names = themes.iter().map(|x| format!("{:?}", x.name));
+                Err(anyhow!(
+                    "the family has several themes, pick one with `--theme`: {}",
+                    names.collect::<Vec<_>>().join(", ")
+                ))
+            }
+        },
+    }
+}
+
+fn patch_cmd(infile: &Path, modified: &Path, kdl_version: KdlVersion) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let original = KdlThemeFamily::read(infile, kdl_version)?;
+    debug!("Reading KDL data from {}", modified.display());
+    let modified = KdlThemeFamily::read(modified, kdl_version)?;
+    let overlay = make_overlay(original, modified)?;
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    serialize_overlay_kdl(std::io::stdout().lock(), &overlay, format)?;
+    Ok(())
+}
+
+fn edit_cmd(
+    infile: &Path,
+    installfiles: &[PathBuf],
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    replace: ReplaceArgs,
+) -> Res<()> {
+    if is_stdio(infile) {
+        return Err(anyhow!(
+            "`edit` saves to the input file, so it cannot read standard input"
+        ));
+    }
+    let family = KdlThemeFamily::read(infile, kdl_version)?;
+    // the editor takes over the terminal, so replacing themes that zeddy did not install is
+    // asked about before it starts, like in `watch`
+    let mut kept = Vec::with_capacity(installfiles.len());
+    for installfile in installfiles {
+        if may_replace(installfile, replace)? {
+            kept.push(installfile);
+        } else if replace.confirm {
+            return Err(anyhow!("not replacing {}", installfile.display()));
+        }
+    }
+    let installfiles = kept;
+    let options = InstallArgs::default();
+    let install = |family: &KdlThemeFamily| {
+        let json = generate_json(family.clone(), version)?;
+        installfiles
+            .iter()
+            .try_for_each(|installfile| install_json(&json, installfile, options))
+    };
+    // the installed theme starts out matching the file, like in `watch`
+    install(&family)?;
+    for installfile in &installfiles {
+        info!("Installed {}", installfile.display());
+    }
+    Editor::new(infile, kdl_version, family)?.run(install)
+}
+
+fn split_cmd(infile: &Path, dir: &Path, force: bool, kdl_version: KdlVersion) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let mut family = KdlThemeFamily::read(infile, kdl_version)?;
+    let themes = split_family(&mut family, "themes");
+    let format = KdlFormat {
+        version: kdl_version,
+        ..KdlFormat::default()
+    };
+    let name = infile
+        .file_name()
+        .ok_or_else(|| anyhow!("input file has no name"))?;
+    let path = dir.join(name);
+    // the family was read with its includes inlined, so writing it over the `infile` would lose
+    // them along with its comments, even with `--force`
+    let source = std::fs::canonicalize(infile).ok();
+    for written in themes
+        .iter()
+        .map(|(path, _)| dir.join(path))
+        .chain([path.clone()])
+    {
+        if source.is_some() && std::fs::canonicalize(&written).ok() == source {
+            return Err(anyhow!(
+                "{} is the file being split, split it into another directory",
+                written.display()
+            ));
+        }
+        check_overwrite(&written, force)?;
+    }
+    std::fs::create_dir_all(dir.join("themes"))?;
+    for (path, theme) in &themes {
+        let path = dir.join(path);
+        debug!("Writing theme {:?} to {}", theme.name, path.display());
+        serialize_theme_kdl(BufWriter::new(File::create(path)?), theme, format)?;
+    }
+    debug!(
+        "Writing the shared parts of the family to {}",
+        path.display()
+    );
+    serialize_kdl(BufWriter::new(File::create(path)?), &family, format, None)?;
+    Ok(())
+}
+
+fn image_cmd(
+    infile: &Path,
+    dir: &Path,
+    png: bool,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    overrides: &OverrideArgs,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let mut kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    overrides.apply(&mut kdl, kdl_version)?;
+    kdl.expand_templates()?;
+    let palettes = kdl.theme_palettes()?;
+    let json = generate_json(kdl, version)?;
+
+    std::fs::create_dir_all(dir)?;
+    for (theme, palette) in json.themes.iter().zip(palettes) {
+        let slug = slug(&theme.name);
+        let mut images = vec![(format!("{slug}.svg"), editor_svg(theme))];
+        if palette.is_empty() {
+            info!(
+                "Theme {:?} does not use the palette, skipping its palette image",
+                theme.name
             );
-            default_install_location(&outfile).log_expect("Error generating install location")
+        } else {
+            images.push((format!("{slug}-palette.svg"), palette_svg(&palette)));
+        }
+        for (name, svg) in images {
+            let path = dir.join(&name);
+            if png {
+                let path = path.with_extension("png");
+                debug!("Writing {}", path.display());
+                std::fs::write(path, rasterize(&svg)?)?;
+            }
+            debug!("Writing {}", path.display());
+            std::fs::write(path, svg)?;
+        }
+    }
+    Ok(())
+}
+
+fn gallery_cmd(
+    dir: &Path,
+    outfile: Option<&Path>,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    let outfile = outfile.map_or_else(|| dir.join(GALLERY_NAME), Path::to_owned);
+    let gallery_dir = std::path::absolute(outfile.parent().unwrap_or_else(|| Path::new("")))?;
+    let pattern = dir.join("**").join("*.kdl");
+    let mut paths = glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+    // sorted so that the gallery does not depend on the order of the directory entries
+    paths.sort();
+
+    let mut families = Vec::new();
+    for path in paths {
+        let read = |path: &Path| -> Res<_> {
+            let mut kdl = KdlThemeFamily::read(path, kdl_version)?;
+            kdl.expand_templates()?;
+            let palettes = kdl.theme_palettes()?;
+            Ok((generate_json(kdl, version)?, palettes))
+        };
+        let (family, palettes) = match read(&path) {
+            Ok(family) => family,
+            Err(e) => {
+                debug!("Skipping {}: {e}", path.display());
+                continue;
+            }
+        };
+        let relative = pathdiff::diff_paths(std::path::absolute(&path)?, &gallery_dir)
+            .unwrap_or_else(|| path.clone());
+        families.push(GalleryFamily {
+            // forward slashes so that links work on every platform
+            path: relative
+                .components()
+                .map(|x| x.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            family,
+            palettes,
         });
+    }
+    info!("Found {} theme families", families.len());
+
+    let swatch_dir = gallery_dir.join(SWATCH_DIR);
+    std::fs::create_dir_all(&swatch_dir)?;
+    let colors = families
+        .iter()
+        .flat_map(|family| family.palettes.iter().flatten())
+        .map(|(_, color)| *color)
+        .collect::<HashSet<_>>();
+    for color in colors {
+        let name = color.to_string();
+        std::fs::write(
+            swatch_dir.join(format!("{}.svg", &name[1..])),
+            swatch_svg(color),
+        )?;
+    }
+    debug!("Writing the gallery to {}", outfile.display());
+    std::fs::write(&outfile, gallery_markdown(&families, SWATCH_DIR)?)?;
+    Ok(())
+}
+
+fn explain_cmd(
+    infile: &Path,
+    theme: &str,
+    key: &str,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    let kdl = KdlThemeFamily::read(infile, kdl_version)?;
+    let explanation = explain(kdl, theme, key, version)?;
+    // locations point into the decoded source, which is the converted one for KDL v2 files
+    let source = std::fs::read_to_string(infile)?;
+    let source = kdl_version.to_v1(&source)?;
+    explanation.render(&source, &mut std::io::stdout().lock())?;
+    Ok(())
+}
+
+fn fmt_cmd(infile: &Path, outfile: &Path, kdl_version: KdlVersion, format: KdlFormat) -> Res<()> {
+    debug!("Reading KDL data from {}", infile.display());
+    // make sure that the file is a valid theme before rewriting it
+    KdlThemeFamily::read(infile, kdl_version)?;
+    let formatted = format_kdl(&read_input(infile)?, kdl_version, format)?;
+    debug!("Writing formatted KDL data to {}", outfile.display());
+    let mut writer = create_output(outfile)?;
+    writer.write_all(formatted.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
 
+fn harmonize_cmd(
+    infile: &Path,
+    outfile: &Path,
+    harmony: &Harmony,
+    dry_run: bool,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    if let Some(level) = harmony.levels.iter().find(|x| !(0.0..=1.0).contains(*x)) {
+        return Err(anyhow!(
+            "the lightness level {level} is not between 0 and 1"
+        ));
+    }
+    if let (Some(min), Some(max)) = (harmony.min_chroma, harmony.max_chroma) {
+        if min > max {
+            return Err(anyhow!(
+                "the minimum chroma {min} is above the maximum chroma {max}"
+            ));
+        }
+    }
+    let source = read_input(infile)?;
+    let family = KdlThemeFamily::parse(&infile.display().to_string(), &source, kdl_version)?;
+    let colors = harmonize_palette(&family.palette, harmony);
+    for node in &family.palette.colors {
+        if let (Some(color), BaseColorKind::Hex(old)) = (colors.get(&node.name), &node.base) {
+            let old =
+                old.apply_modifiers(node.modifiers.or_preserve_hue(family.palette.preserve_hue));
+            info!(
+                "{}: {old} -> {color} (delta E {:.1})",
+                node.name,
+                old.delta_e(*color)
+            );
+        }
+    }
+    if colors.is_empty() {
+        info!("The palette is already harmonized");
+        return Ok(());
+    }
+    if dry_run {
+        return Ok(());
+    }
+    let harmonized = set_palette_colors(&source, kdl_version, &colors)?;
+    debug!("Writing harmonized KDL data to {}", outfile.display());
+    let mut writer = create_output(outfile)?;
+    writer.write_all(harmonized.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads JSON theme families from files and directories of them into one family with the meta of
+/// the first one.
+fn read_json_inputs(inputs: &[PathBuf]) -> Res<JsonThemeFamily> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let pattern = input.join("*.json");
+            let mut paths =
+                glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+            if paths.is_empty() {
+                return Err(anyhow!("there are no JSON files in {}", input.display()));
+            }
+            // sorted so that the themes do not depend on the order of the directory entries
+            paths.sort();
+            files.extend(paths);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    let mut family: Option<JsonThemeFamily> = None;
+    for path in &files {
+        debug!("Reading JSON data from {}", path.display());
+        let json: JsonThemeFamily = serde_json::from_str(&read_input(path)?)
+            .map_err(|e| anyhow!("could not read {}: {e}", path.display()))?;
+        let Some(family) = &mut family else {
+            family = Some(json);
+            continue;
+        };
+        for theme in json.themes {
+            if family.themes.iter().any(|other| other.name == theme.name) {
+                warn!(
+                    "{} has a theme called {:?} like an earlier file",
+                    path.display(),
+                    theme.name
+                );
+            }
+            family.themes.push(theme);
+        }
+    }
+    family.ok_or_else(|| anyhow!("there are no theme files to migrate"))
+}
+
+fn migrate_cmd(
+    inputs: &[PathBuf],
+    outfile: &Path,
+    format: KdlFormat,
+    annotate_colors: bool,
+    palette: &PaletteArgs,
+    interactive: bool,
+    force: bool,
+) -> Res<()> {
+    if interactive && inputs.iter().any(|input| is_stdio(input)) {
+        return Err(anyhow!(
+            "`--interactive` reads its answers from standard input, so the theme has to be read from a file"
+        ));
+    }
+    // before going through the palette, which would be wasted if the file cannot be written
+    check_overwrite(outfile, force)?;
+    let json = read_json_inputs(inputs)?;
+    let mut file = generate_kdl(json, palette.generator()?, palette.naming);
+    if interactive {
+        review_palette(&mut file, &mut std::io::stdin().lock())?;
+    }
+    let annotations = if annotate_colors {
+        Some(file.palette.clone().into_palette().resolve()?)
+    } else {
+        None
+    };
+
+    serialize_kdl(create_output(outfile)?, &file, format, annotations)?;
+    Ok(())
+}
+
+impl Cli {
+    #[allow(
+        clippy::too_many_lines,
+        reason = "every command that does not need an input file gets an arm here"
+    )]
+    pub fn run(self) {
+        let Cli {
+            command,
+            infile,
+            install_location,
+            channel,
+            outfile,
+            outfile_template,
+            schema_version,
+            kdl_version,
+            gamut_map,
+            lints,
+            logging,
+        } = self;
+        logging.init();
+        load_config().log_expect("Failed to load configuration");
+        set_lint_levels(lints.levels());
+        set_gamut_map(gamut_map);
+        let schema_version = schema_version
+            .or(config().schema_version)
+            .unwrap_or_default();
+        let kdl_version = kdl_version.or(config().kdl_version).unwrap_or_default();
+        let channel = channel.or(config().default_channel).unwrap_or_default();
         match command {
-            Command::Generate => {
-                generate_json_cmd(&infile, &outfile).log_expect("Could not write JSON file");
+            Command::ListStyles { pattern, describe } => {
+                list_keys_cmd(zed::STYLE_KEYS, pattern.as_deref(), describe)
+                    .log_expect("Failed to list style keys");
+            }
+            Command::Completions { shell, standalone } => {
+                completions_cmd(shell, standalone).log_expect("Failed to write completions");
             }
-            Command::Install => {
-                install_cmd(&infile, &outfile, &install_location)
-                    .log_expect("Failed to install theme");
+            Command::Manpages { dir } => {
+                manpages_cmd(&dir).log_expect("Failed to write manual pages");
             }
-            Command::Watch => {
-                watch_cmd(&infile, &outfile, &install_location).log_expect("Failed to watch file");
+            Command::ListSyntax { pattern, describe } => {
+                list_keys_cmd(zed::SYNTAX_KEYS, pattern.as_deref(), describe)
+                    .log_expect("Failed to list syntax scopes");
             }
-            Command::Migrate => {
-                migrate_cmd(&infile, &outfile).log_expect("Failed to migrate theme");
+            Command::List => {
+                list_cmd(&themes_dir(channel)).log_expect("Failed to list installed themes");
             }
-            Command::ExportPalette { format } => {
-                format.output(&infile).log_expect("Failed to write data");
+            Command::Uninstall { name, yes } => {
+                uninstall_cmd(&themes_dir(channel), &name, yes)
+                    .log_expect("Failed to uninstall theme");
             }
+            Command::Doctor { check_zed } => {
+                doctor_cmd(channel, check_zed).log_expect("Found problems with the environment");
+            }
+            Command::Bench { file, iterations } => {
+                bench_cmd(
+                    &file,
+                    iterations,
+                    schema_version,
+                    kdl_version,
+                    &mut std::io::stdout().lock(),
+                )
+                .map_err(|e| with_source(e, &file, kdl_version))
+                .log_expect("Failed to time theme");
+            }
+            Command::Diff {
+                before,
+                after,
+                format,
+            } => {
+                diff_cmd(&before, &after, schema_version, kdl_version, format)
+                    .log_expect("Failed to diff themes");
+            }
+            Command::Blame { file, key, theme } => {
+                SourceMap::read(&file)
+                    .and_then(|map| {
+                        map.blame(theme.as_deref(), &key, &mut std::io::stdout().lock())
+                    })
+                    .log_expect("Failed to blame key");
+            }
+            Command::Merge {
+                files,
+                on_conflict,
+                force,
+            } => {
+                merge_cmd(&files, outfile.as_deref(), force, kdl_version, on_conflict)
+                    .log_expect("Failed to merge themes");
+            }
+            Command::ApplyOverrides { base, overlay, set } => {
+                apply_overrides_cmd(&base, &overlay, &set, outfile.as_deref(), kdl_version)
+                    .log_expect("Failed to apply overrides");
+            }
+            Command::Import {
+                source: ImportSource::Extension { source, palette },
+                force,
+            } => {
+                import_extension_cmd(&source, outfile.as_deref(), force, &palette, kdl_version)
+                    .log_expect("Failed to import extension");
+            }
+            Command::Import {
+                source: ImportSource::Terminal { file, list, scheme },
+                force,
+            } => {
+                import_terminal_cmd(
+                    &file,
+                    outfile.as_deref(),
+                    force,
+                    list,
+                    scheme.as_deref(),
+                    kdl_version,
+                )
+                .log_expect("Failed to import terminal color scheme");
+            }
+            Command::Import {
+                source: ImportSource::Tmtheme { file, palette },
+                force,
+            } => {
+                import_tm_theme_cmd(&file, outfile.as_deref(), force, &palette, kdl_version)
+                    .log_expect("Failed to import color scheme");
+            }
+            Command::Import {
+                source: ImportSource::Jetbrains { file, palette },
+                force,
+            } => {
+                import_jetbrains_cmd(&file, outfile.as_deref(), force, &palette, kdl_version)
+                    .log_expect("Failed to import color scheme");
+            }
+            Command::Import {
+                source: ImportSource::Helix { file, palette },
+                force,
+            } => {
+                import_helix_cmd(&file, outfile.as_deref(), force, &palette, kdl_version)
+                    .log_expect("Failed to import Helix theme");
+            }
+            Command::Import {
+                source: ImportSource::Tokens { file },
+                force,
+            } => {
+                import_palette_cmd(
+                    &file,
+                    outfile.as_deref(),
+                    force,
+                    kdl_version,
+                    read_design_tokens,
+                )
+                .log_expect("Failed to import design tokens");
+            }
+            Command::Import {
+                source: ImportSource::Tailwind { file },
+                force,
+            } => {
+                import_palette_cmd(
+                    &file,
+                    outfile.as_deref(),
+                    force,
+                    kdl_version,
+                    read_tailwind_colors,
+                )
+                .log_expect("Failed to import Tailwind colors");
+            }
+            Command::Import {
+                source: ImportSource::Wal { file, watch_wal },
+                ..
+            } => {
+                import_wal_cmd(file.as_deref(), outfile.as_deref(), watch_wal, kdl_version)
+                    .log_expect("Failed to import wal colors");
+            }
+            Command::Palette {
+                action:
+                    PaletteCommand::Generate {
+                        base,
+                        scheme,
+                        count,
+                        neutrals,
+                        force,
+                    },
+            } => {
+                generate_palette_cmd(
+                    base,
+                    scheme,
+                    count,
+                    neutrals,
+                    outfile.as_deref(),
+                    force,
+                    kdl_version,
+                )
+                .log_expect("Failed to generate palette");
+            }
+            Command::Lsp => {
+                run_server(schema_version, kdl_version).log_expect("The language server failed");
+            }
+            Command::Schema { format } => {
+                schema_cmd(format, outfile.as_deref(), kdl_version)
+                    .log_expect("Failed to write schema");
+            }
+            Command::Gallery { dir } => {
+                gallery_cmd(&dir, outfile.as_deref(), schema_version, kdl_version)
+                    .log_expect("Failed to write gallery");
+            }
+            Command::Build { install, options } => {
+                let targets = read_manifest(infile.as_deref(), kdl_version, channel)
+                    .log_expect("Failed to read project manifest");
+                build_cmd(&targets, schema_version, kdl_version, install, options)
+                    .log_expect("Failed to build themes");
+            }
+            Command::Watch {
+                overrides,
+                options,
+                watch,
+            } if infile.is_none() => {
+                let mut targets = read_manifest(None, kdl_version, channel)
+                    .log_expect("Failed to read project manifest");
+                for target in &mut targets {
+                    target
+                        .overrides
+                        .overlays
+                        .extend_from_slice(&overrides.overlays);
+                    target.overrides.set.extend_from_slice(&overrides.set);
+                    target.overrides.themes.extend_from_slice(&overrides.themes);
+                }
+                watch_cmd(&targets, schema_version, kdl_version, options, watch)
+                    .log_expect("Failed to watch files");
+            }
+            command => {
+                let infile = infile.log_expect("This command requires an input file");
+                let url = infile
+                    .to_str()
+                    .filter(|infile| is_url(infile))
+                    .map(str::to_owned);
+                let template = outfile_template.as_deref();
+                let outfile = match template {
+                    // a download is only named once it has been saved
+                    Some(template) if url.is_none() => Some(
+                        templated_outfile(&command, template, &infile, &infile, kdl_version)
+                            .log_expect("Failed to name the output file"),
+                    ),
+                    _ => outfile,
+                };
+                let Some(url) = url else {
+                    run_on_file(
+                        command,
+                        &infile,
+                        outfile,
+                        install_location,
+                        channel,
+                        schema_version,
+                        kdl_version,
+                    );
+                    return;
+                };
+                let (download, outfile) =
+                    fetch_infile(&command, &url, outfile, template, kdl_version)
+                        .log_expect("Failed to fetch input file");
+                let path = download.path().display().to_string();
+                let message = failure_message(&command);
+                let result = generate_or_migrate(
+                    command,
+                    download.path(),
+                    &outfile,
+                    schema_version,
+                    kdl_version,
+                );
+                // exiting on an error skips destructors, so the download is removed first, and
+                // errors name the URL instead of where it was saved
+                drop(download);
+                result
+                    .map_err(|e| anyhow!("{}", e.to_string().replace(&path, &url)))
+                    .log_expect(message);
+            }
+        }
+    }
+}
+
+/// Downloads an `infile` that is a URL to a temporary file, which is removed when it is dropped
+/// and is returned with the output file. The default output file, and `{stem}` in `template`,
+/// are named after the last segment of the URL.
+fn fetch_infile(
+    command: &Command,
+    url: &str,
+    outfile: Option<PathBuf>,
+    template: Option<&str>,
+    kdl_version: KdlVersion,
+) -> Res<(NamedTempFile, PathBuf)> {
+    let ext = match command {
+        Command::Generate { .. } => "json",
+        Command::Migrate { .. } => "kdl",
+        _ => {
+            return Err(anyhow!(
+                "only `generate` and `migrate` can read their input file from a URL"
+            ))
+        }
+    };
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.trim_end_matches('/').rsplit('/').next())
+        .filter(|name| !name.is_empty() && !name.contains(':'))
+        .unwrap_or("theme");
+    info!("Downloading {url}");
+    let body = download(url, MAX_INPUT_SIZE)?;
+    // the name is kept at the end, since the format of some inputs is told by their extension
+    let file = tempfile::Builder::new()
+        .prefix("zeddy-")
+        .suffix(&format!("-{name}"))
+        .tempfile()
+        .and_then(|mut file| file.write_all(&body).map(|()| file))
+        .map_err(|e| anyhow!("could not save the download: {e}"))?;
+    let outfile = match (outfile, template) {
+        (Some(outfile), _) => outfile,
+        (None, Some(template)) => {
+            templated_outfile(command, template, Path::new(name), file.path(), kdl_version)?
+        }
+        (None, None) => default_output_location(Path::new(name), ext)?,
+    };
+    Ok((file, outfile))
+}
+
+/// Names the output file of `command` after `template`. The metadata of the theme family is read
+/// from `infile`, which is a JSON theme family for `migrate`, and `{stem}` comes from `name`,
+/// since a downloaded file is saved under another name.
+fn templated_outfile(
+    command: &Command,
+    template: &str,
+    name: &Path,
+    infile: &Path,
+    kdl_version: KdlVersion,
+) -> Res<PathBuf> {
+    templated_output_location(template, name, || {
+        let source = read_input(infile)?;
+        if matches!(command, Command::Migrate { .. }) {
+            let json: JsonThemeFamily = serde_json::from_str(&source)?;
+            Ok(json.meta)
+        } else {
+            let family =
+                KdlThemeFamily::parse(&infile.display().to_string(), &source, kdl_version)?;
+            Ok(family.meta)
+        }
+    })
+}
+
+/// Runs `generate` or `migrate`, the commands that can read their input file from a URL. Errors
+/// are returned rather than logged, so that a downloaded input file can be removed first.
+fn generate_or_migrate(
+    command: Command,
+    infile: &Path,
+    outfile: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) -> Res<()> {
+    match command {
+        Command::Generate {
+            schema,
+            output,
+            overrides,
+        } => {
+            generate_json_cmd(
+                infile,
+                outfile,
+                version,
+                kdl_version,
+                schema,
+                &overrides,
+                output,
+                &mut GenerationCache::default(),
+            )
+            .map_err(|e| with_source(e, infile, kdl_version))?;
+        }
+        Command::Migrate {
+            format,
+            annotate_colors,
+            palette,
+            interactive,
+            files,
+            force,
+        } => {
+            let format = KdlFormat {
+                version: kdl_version,
+                ..format.into()
+            };
+            let inputs = std::iter::once(infile.to_owned())
+                .chain(files)
+                .collect::<Vec<_>>();
+            migrate_cmd(
+                &inputs,
+                outfile,
+                format,
+                annotate_colors,
+                &palette,
+                interactive,
+                force,
+            )?;
+        }
+        _ => unreachable!("only `generate` and `migrate` are run by `generate_or_migrate`"),
+    }
+    Ok(())
+}
+
+/// The message that a failed `generate` or `migrate` is logged with.
+fn failure_message(command: &Command) -> &'static str {
+    if matches!(command, Command::Migrate { .. }) {
+        "Failed to migrate theme"
+    } else {
+        "Could not write JSON file"
+    }
+}
+
+/// Fills in the default output file and install locations of a command that reads `infile`.
+fn output_locations(
+    command: &Command,
+    infile: &Path,
+    outfile: Option<PathBuf>,
+    install_locations: Vec<PathBuf>,
+    channel: Channel,
+) -> (PathBuf, Vec<PathBuf>) {
+    let ext = match command {
+        Command::Migrate { .. } => "kdl",
+        Command::ExportTerminal { format, .. } => format.extension(),
+        Command::ExportHelix { .. } => "toml",
+        Command::ExportNvim { .. } => "lua",
+        // the generated Zed theme is already `<infile>.json`
+        Command::ExportVscode { .. } => "color-theme.json",
+        _ => "json",
+    };
+    let outfile = if matches!(command, Command::Fmt { .. } | Command::Harmonize { .. }) {
+        outfile.unwrap_or_else(|| infile.to_owned())
+    } else if is_stdio(infile)
+        && matches!(command, Command::Generate { .. } | Command::Migrate { .. })
+    {
+        // the output of a pipeline goes on to the next command in it
+        outfile.unwrap_or_else(|| PathBuf::from(STDIO_PATH))
+    } else {
+        outfile.unwrap_or_else(|| {
+            debug!(
+                "User did not provide an outfile, generating default based on the input file {}",
+                infile.display()
+            );
+            default_output_location(infile, ext).log_expect("Error generating output file location")
+        })
+    };
+    let install_locations = if install_locations.is_empty() {
+        debug!(
+            "User did not provide an install location, defaulting to the default Zed config path based on the output file `{}`",
+            infile.display()
+        );
+        default_install_locations(&outfile, channel).log_expect("Error generating install location")
+    } else {
+        install_locations
+    };
+    (outfile, install_locations)
+}
+
+#[allow(
+    clippy::too_many_lines,
+    reason = "every command that writes a file gets an arm here, and splitting them up only hides the dispatch"
+)]
+fn run_on_file(
+    command: Command,
+    infile: &Path,
+    outfile: Option<PathBuf>,
+    install_locations: Vec<PathBuf>,
+    channel: Channel,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) {
+    let (outfile, install_locations) =
+        output_locations(&command, infile, outfile, install_locations, channel);
+    // points errors that were found after decoding at the offending lines of the input file
+    let located = |e| with_source(e, infile, kdl_version);
+
+    match command {
+        command @ (Command::Generate { .. } | Command::Migrate { .. }) => {
+            let message = failure_message(&command);
+            generate_or_migrate(command, infile, &outfile, version, kdl_version)
+                .log_expect(message);
+        }
+        Command::Install {
+            overrides,
+            all_channels: true,
+            options,
+        } => {
+            install_all_channels_cmd(infile, &outfile, version, kdl_version, &overrides, options)
+                .map_err(located)
+                .log_expect("Failed to install theme");
+        }
+        Command::Install {
+            overrides, options, ..
+        } => {
+            install_cmd(
+                infile,
+                &outfile,
+                &install_locations,
+                version,
+                kdl_version,
+                &overrides,
+                options,
+                &mut GenerationCache::default(),
+            )
+            .map_err(located)
+            .log_expect("Failed to install theme");
+        }
+        Command::Watch {
+            overrides,
+            options,
+            watch,
+        } => {
+            let target = Target {
+                name: infile.display().to_string(),
+                infile: infile.to_owned(),
+                outfile,
+                install_locations,
+                overrides,
+            };
+            watch_cmd(&[target], version, kdl_version, options, watch)
+                .log_expect("Failed to watch file");
+        }
+        Command::Edit { replace } => {
+            edit_cmd(infile, &install_locations, version, kdl_version, replace)
+                .map_err(located)
+                .log_expect("Failed to edit theme");
+        }
+        Command::Fmt {
+            format,
+            to_kdl_version,
+        } => {
+            let format = KdlFormat {
+                version: to_kdl_version.unwrap_or(kdl_version),
+                ..format.into()
+            };
+            fmt_cmd(infile, &outfile, kdl_version, format).log_expect("Failed to format theme");
+        }
+        Command::Harmonize {
+            levels,
+            min_chroma,
+            max_chroma,
+            dry_run,
+        } => {
+            let harmony = Harmony {
+                levels,
+                min_chroma,
+                max_chroma,
+            };
+            harmonize_cmd(infile, &outfile, &harmony, dry_run, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to harmonize palette");
+        }
+        Command::Check { installed } => {
+            let targets = if installed {
+                std::iter::once(&outfile)
+                    .chain(&install_locations)
+                    .map(PathBuf::as_path)
+                    .collect()
+            } else {
+                vec![outfile.as_path()]
+            };
+            check_cmd(infile, &targets, version, kdl_version)
+                .map_err(located)
+                .log_expect("Check failed");
+        }
+        Command::Validate { fetch_schema } => {
+            validate_cmd(infile, version, kdl_version, fetch_schema)
+                .map_err(located)
+                .log_expect("Validation failed");
+        }
+        Command::ExportTerminal { format, theme } => {
+            export_terminal_cmd(
+                infile,
+                &outfile,
+                format,
+                theme.as_deref(),
+                version,
+                kdl_version,
+            )
+            .map_err(located)
+            .log_expect("Failed to export terminal colors");
+        }
+        Command::ExportHelix { theme } => {
+            export_helix_cmd(infile, &outfile, theme.as_deref(), version, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to export Helix theme");
+        }
+        Command::ExportNvim { theme } => {
+            export_nvim_cmd(infile, &outfile, theme.as_deref(), version, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to export Neovim colorscheme");
+        }
+        Command::ExportVscode { theme } => {
+            export_vscode_cmd(infile, &outfile, theme.as_deref(), version, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to export VS Code theme");
+        }
+        Command::Bump {
+            part,
+            changelog,
+            message,
+            package,
+        } => {
+            bump_cmd(
+                infile,
+                &outfile,
+                part,
+                changelog.as_deref(),
+                &message,
+                version,
+                kdl_version,
+            )
+            .map_err(located)
+            .log_expect("Failed to bump version");
+            if let Some(dir) = package {
+                package_cmd(infile, &dir, false, version, kdl_version)
+                    .map_err(located)
+                    .log_expect("Failed to package theme");
+            }
+        }
+        command => run_without_outfile(command, infile, version, kdl_version),
+    }
+}
+
+/// Runs a command that reads `infile` but does not write to the output file or install location.
+fn run_without_outfile(
+    command: Command,
+    infile: &Path,
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+) {
+    let located = |e| with_source(e, infile, kdl_version);
+    match command {
+        Command::Package { dir, dry_run } => {
+            package_cmd(infile, &dir, dry_run, version, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to package theme");
+        }
+        Command::DeriveLight { theme } => {
+            derive_light_cmd(infile, &theme, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to derive a light theme");
+        }
+        Command::Explain { theme, key } => {
+            explain_cmd(infile, &theme, &key, version, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to explain key");
+        }
+        Command::Resolve { theme, format } => {
+            resolve_cmd(infile, &theme, format, version, kdl_version)
+                .map_err(located)
+                .log_expect("Failed to resolve theme");
+        }
+        Command::Patch { modified } => {
+            patch_cmd(infile, &modified, kdl_version).log_expect("Failed to create overlay");
+        }
+        Command::Split { dir, force } => {
+            split_cmd(infile, &dir, force, kdl_version).log_expect("Failed to split theme");
+        }
+        Command::Image {
+            dir,
+            png,
+            overrides,
+        } => {
+            image_cmd(infile, &dir, png, version, kdl_version, &overrides)
+                .map_err(located)
+                .log_expect("Failed to render images");
+        }
+        Command::ExportPalette {
+            format,
+            theme,
+            gamut,
+        } => {
+            format
+                .output(infile, kdl_version, theme.as_deref(), gamut)
+                .map_err(located)
+                .log_expect("Failed to write data");
+        }
+        _ => {
+            unreachable!("the other commands are run by `Cli::run` and `run_on_file`")
         }
     }
 }
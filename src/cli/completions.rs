@@ -0,0 +1,106 @@
+//! Shell completions and manual pages. The completion script only calls back into zeddy, which
+//! completes the command line itself, so that it can offer the names of the themes in the theme
+//! file on the command line and of the installed themes.
+
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result as Res};
+use clap::CommandFactory;
+use clap_complete::env::Shells;
+use clap_complete::{CompleteEnv, CompletionCandidate, Shell};
+use tracing::info;
+
+use crate::cli::commands::Cli;
+use crate::cli::config::{config, load_config};
+use crate::cli::installed::installed_names;
+use crate::cli::paths::themes_dir;
+use zeddy::schema::KdlThemeFamily;
+
+/// The environment variable that the completion script sets when it calls zeddy.
+const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Answers the completion script and exits, if it is what ran zeddy.
+pub fn complete() {
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+/// Writes the completion script for `shell` to standard output. With `standalone`, the script
+/// completes commands and options without calling zeddy, which leaves out theme names.
+pub fn completions_cmd(shell: Shell, standalone: bool) -> Res<()> {
+    let mut stdout = std::io::stdout().lock();
+    if standalone {
+        // written to a buffer first, since the generator panics if it cannot write
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut Cli::command(), "zeddy", &mut script);
+        stdout.write_all(&script)?;
+        return Ok(());
+    }
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| anyhow!("zeddy cannot complete {shell} without `--standalone`"))?;
+    // the script calls this executable rather than whichever `zeddy` is on the `PATH`
+    let exe = std::env::current_exe()?;
+    completer.write_registration(
+        COMPLETE_VAR,
+        "zeddy",
+        "zeddy",
+        &exe.to_string_lossy(),
+        &mut stdout,
+    )?;
+    Ok(())
+}
+
+/// Writes a manual page for zeddy and for each of its commands to `dir`, such as `zeddy.1` and
+/// `zeddy-install.1`.
+pub fn manpages_cmd(dir: &Path) -> Res<()> {
+    std::fs::create_dir_all(dir).map_err(|e| anyhow!("could not create {}: {e}", dir.display()))?;
+    clap_mangen::generate_to(Cli::command(), dir)?;
+    info!("Wrote manual pages to {}", dir.display());
+    Ok(())
+}
+
+/// Completes the names of the themes in the first KDL file on the command line.
+pub fn theme_names(current: &OsStr) -> Vec<CompletionCandidate> {
+    // the arguments that are being completed come after `--`, starting with the executable
+    let infile = std::env::args_os()
+        .skip_while(|arg| arg != "--")
+        .skip(2)
+        .map(PathBuf::from)
+        .find(|arg| arg.extension().is_some_and(|ext| ext == "kdl") && arg.is_file());
+    let Some(infile) = infile else {
+        return Vec::new();
+    };
+    let _ = load_config();
+    let kdl_version = config().kdl_version.unwrap_or_default();
+    let Ok(mut family) = KdlThemeFamily::read(&infile, kdl_version) else {
+        return Vec::new();
+    };
+    if family.expand_templates().is_err() {
+        return Vec::new();
+    }
+    let names = family.themes.into_iter().map(|theme| theme.name);
+    candidates(names, current)
+}
+
+/// Completes the names of the theme families in the themes directory of the default channel.
+pub fn installed_theme_names(current: &OsStr) -> Vec<CompletionCandidate> {
+    let _ = load_config();
+    let channel = config().default_channel.unwrap_or_default();
+    match installed_names(&themes_dir(channel)) {
+        Ok(names) => candidates(names.into_iter(), current),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn candidates(names: impl Iterator<Item = String>, current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    names
+        .filter(|name| name.starts_with(current.as_ref()))
+        .map(CompletionCandidate::new)
+        .collect()
+}
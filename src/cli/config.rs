@@ -0,0 +1,185 @@
+//! The user configuration file, which sets defaults for options that would otherwise have to be
+//! passed on every invocation.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+use knus::Decode;
+use tracing::debug;
+
+use crate::cli::paths::Channel;
+use zeddy::lint::{Level, LintTarget};
+use zeddy::schema::{json::SchemaVersion, KdlVersion};
+use zeddy::util::ToAnyhow;
+
+/// The environment variable that overrides the location of the configuration file.
+pub const CONFIG_VAR: &str = "ZEDDY_CONFIG";
+
+#[derive(Debug, Decode)]
+struct ConfigFile {
+    #[knus(children(name = "install-dir"), unwrap(argument))]
+    install_dirs: Vec<String>,
+    #[knus(child, unwrap(argument))]
+    output_dir: Option<String>,
+    #[knus(child, unwrap(argument))]
+    schema_version: Option<String>,
+    #[knus(child, unwrap(argument))]
+    kdl_version: Option<u32>,
+    #[knus(child, unwrap(argument))]
+    default_channel: Option<String>,
+    #[knus(children(name = "channel"))]
+    channels: Vec<ChannelProfile>,
+    #[knus(child, default)]
+    lints: LintLevelsNode,
+}
+
+/// The `lints` node, with a child for each lint, such as `low-contrast "deny"`.
+#[derive(Debug, Default, Decode)]
+struct LintLevelsNode {
+    #[knus(children)]
+    levels: Vec<LintLevelNode>,
+}
+
+#[derive(Debug, Decode)]
+struct LintLevelNode {
+    #[knus(node_name)]
+    lint: String,
+    #[knus(argument)]
+    level: String,
+}
+
+/// Settings for a single release channel of Zed.
+#[derive(Debug, Decode)]
+struct ChannelProfile {
+    #[knus(argument)]
+    name: String,
+    #[knus(children(name = "install-dir"), unwrap(argument))]
+    install_dirs: Vec<String>,
+}
+
+/// Defaults that are used when the corresponding option is not given on the command line.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// The directories that themes are installed into instead of Zed's themes directory. The
+    /// first one is the one that Zed reads themes from
+    pub install_dirs: Vec<PathBuf>,
+    /// The directory that generated files are written to instead of `./generated`
+    pub output_dir: Option<PathBuf>,
+    pub schema_version: Option<SchemaVersion>,
+    pub kdl_version: Option<KdlVersion>,
+    /// The channel whose install location is used when `--channel` is not given
+    pub default_channel: Option<Channel>,
+    /// The themes directories of channels that do not use the shared one
+    pub channel_install_dirs: Vec<(Channel, Vec<PathBuf>)>,
+    pub lint_levels: Vec<(LintTarget, Level)>,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Returns the loaded configuration, or the default one if [`load_config`] was not called.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Reads the configuration file, if there is one, and makes it available through [`config`].
+pub fn load_config() -> Res<()> {
+    let config = match std::env::var_os(CONFIG_VAR) {
+        Some(path) => Config::read(Path::new(&path))?,
+        None => match default_config_location() {
+            Some(path) if path.exists() => Config::read(&path)?,
+            _ => Config::default(),
+        },
+    };
+    debug!("Using configuration {config:?}");
+    CONFIG
+        .set(config)
+        .map_err(|_| anyhow!("the configuration was already loaded"))
+}
+
+/// Returns `~/.config/zeddy/config.kdl`, or the equivalent on Windows and Linux systems with a
+/// different XDG config directory.
+fn default_config_location() -> Option<PathBuf> {
+    let dir = if cfg!(target_os = "windows") || cfg!(target_os = "linux") {
+        dirs::config_dir()?
+    } else {
+        dirs::home_dir()?.join(".config")
+    };
+    Some(dir.join("zeddy").join("config.kdl"))
+}
+
+impl Config {
+    fn read(path: &Path) -> Res<Self> {
+        debug!("Reading configuration from {}", path.display());
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("could not read configuration file {}: {e}", path.display()))?;
+        let path_name = path.display().to_string();
+        // the configuration decides the KDL version of everything else, so it accepts both
+        let file = knus::parse::<ConfigFile>(&path_name, &content).or_else(|_| {
+            let content = KdlVersion::V2.to_v1(&content)?;
+            knus::parse::<ConfigFile>(&path_name, &content).to_anyhow()
+        })?;
+        Ok(Self {
+            install_dirs: file
+                .install_dirs
+                .iter()
+                .map(|dir| expand_home(dir))
+                .collect(),
+            output_dir: file.output_dir.as_deref().map(expand_home),
+            schema_version: file
+                .schema_version
+                .map(|version| version.parse())
+                .transpose()?,
+            kdl_version: file
+                .kdl_version
+                .map(|version| version.to_string().parse())
+                .transpose()?,
+            default_channel: file
+                .default_channel
+                .as_deref()
+                .map(parse_channel)
+                .transpose()?,
+            channel_install_dirs: file
+                .channels
+                .iter()
+                .filter(|profile| !profile.install_dirs.is_empty())
+                .map(|profile| {
+                    let dirs = profile.install_dirs.iter().map(|dir| expand_home(dir));
+                    Ok((parse_channel(&profile.name)?, dirs.collect()))
+                })
+                .collect::<Res<_>>()?,
+            lint_levels: file
+                .lints
+                .levels
+                .iter()
+                .map(|node| {
+                    let level = Level::from_str(&node.level, false)
+                        .map_err(|e| anyhow!("invalid level for lint {:?}: {e}", node.lint))?;
+                    Ok((node.lint.parse()?, level))
+                })
+                .collect::<Res<_>>()?,
+        })
+    }
+
+    /// Returns the themes directories that the configuration file sets for `channel`, or the
+    /// shared ones if it does not give the channel its own.
+    pub fn install_dirs(&self, channel: Channel) -> &[PathBuf] {
+        self.channel_install_dirs
+            .iter()
+            .find(|(x, _)| *x == channel)
+            .map_or(&self.install_dirs, |(_, dirs)| dirs)
+    }
+}
+
+fn parse_channel(name: &str) -> Res<Channel> {
+    Channel::from_str(name, true).map_err(|e| anyhow!("invalid channel {name:?}: {e}"))
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
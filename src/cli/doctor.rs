@@ -0,0 +1,154 @@
+//! Checks for problems with the environment that keep themes from being installed or watched.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result as Res};
+use notify::event::{AccessKind, AccessMode, Event};
+use notify::{EventKind, Watcher};
+use tracing::{error, info, warn};
+
+use super::paths::{config_dir, themes_dir, Channel};
+
+/// How long to wait for the file watcher to report a change before giving up.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The names that Zed's command line interface is installed under by various packages.
+const ZED_EXECUTABLES: &[&str] = &["zed", "zeditor", "zedit", "zed-editor"];
+
+/// The result of a single check. Failures come with a fix that the user can try.
+enum Outcome {
+    Ok(String),
+    Warning(String, String),
+    Failure(String, String),
+}
+
+/// Runs every check and reports the results, returning an error if any of them failed.
+pub fn doctor_cmd(channel: Channel, check_zed: bool) -> Res<()> {
+    let mut outcomes = vec![
+        check_dir(config_dir(), "Zed's config directory"),
+        check_dir(&themes_dir(channel), "the themes directory"),
+        check_watcher(),
+    ];
+    if check_zed {
+        outcomes.push(check_zed_installed());
+    }
+
+    let mut failed = 0;
+    for outcome in outcomes {
+        match outcome {
+            Outcome::Ok(msg) => info!("{msg}"),
+            Outcome::Warning(msg, fix) => warn!("{msg}\n    fix: {fix}"),
+            Outcome::Failure(msg, fix) => {
+                error!("{msg}\n    fix: {fix}");
+                failed += 1;
+            }
+        }
+    }
+    if failed == 0 {
+        info!("Everything looks good");
+        Ok(())
+    } else {
+        Err(anyhow!("{failed} check(s) failed"))
+    }
+}
+
+/// Checks that `dir` exists and that files can be written into it.
+fn check_dir(dir: &Path, what: &str) -> Outcome {
+    if !dir.is_dir() {
+        return Outcome::Failure(
+            format!("{what} {} does not exist", dir.display()),
+            format!(
+                "start Zed once or create it with `mkdir -p {}`",
+                dir.display()
+            ),
+        );
+    }
+    let probe = dir.join(".zeddy-doctor");
+    match std::fs::write(&probe, "").and_then(|()| std::fs::remove_file(&probe)) {
+        Ok(()) => Outcome::Ok(format!("{what} {} is writable", dir.display())),
+        Err(e) => Outcome::Failure(
+            format!("{what} {} is not writable: {e}", dir.display()),
+            "check the permissions of the directory, or pass `--install-location`".to_owned(),
+        ),
+    }
+}
+
+/// Checks that the file watcher reports a file being closed after writing, which is the event
+/// that `watch` regenerates on.
+fn check_watcher() -> Outcome {
+    let dir = std::env::temp_dir().join(format!("zeddy-doctor-{}", std::process::id()));
+    let outcome = watch_probe(&dir).unwrap_or_else(|e| {
+        Outcome::Failure(
+            format!("the file watcher could not be started: {e}"),
+            "on Linux, raise `fs.inotify.max_user_watches` or `fs.inotify.max_user_instances`"
+                .to_owned(),
+        )
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    outcome
+}
+
+fn watch_probe(dir: &Path) -> Res<Outcome> {
+    std::fs::create_dir_all(dir)?;
+    let (tx, rx) = std::sync::mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    std::fs::write(dir.join("probe.kdl"), "")?;
+
+    let deadline = Instant::now() + WATCH_TIMEOUT;
+    let mut saw_event = false;
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(event))
+                if event.kind == EventKind::Access(AccessKind::Close(AccessMode::Write)) =>
+            {
+                return Ok(Outcome::Ok("the file watcher works".to_owned()));
+            }
+            Ok(Ok(_)) => saw_event = true,
+            Ok(Err(e)) => return Err(e.into()),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Ok(if saw_event {
+        Outcome::Failure(
+            "the file watcher does not report files being closed after writing on this platform, so `watch` will not notice changes".to_owned(),
+            "run `install` after saving instead of using `watch`".to_owned(),
+        )
+    } else {
+        Outcome::Failure(
+            format!(
+                "the file watcher did not report any changes within {} seconds",
+                WATCH_TIMEOUT.as_secs()
+            ),
+            "make sure that the temporary directory is not on a network drive".to_owned(),
+        )
+    })
+}
+
+/// Looks for Zed's command line interface on the `PATH` and for the app bundle on macOS.
+fn check_zed_installed() -> Outcome {
+    let paths = std::env::var_os("PATH").unwrap_or_default();
+    let mut candidates = std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            ZED_EXECUTABLES.iter().map(move |name| {
+                dir.join(name)
+                    .with_extension(std::env::consts::EXE_EXTENSION)
+            })
+        })
+        .collect::<Vec<_>>();
+    if cfg!(target_os = "macos") {
+        candidates.push(PathBuf::from("/Applications/Zed.app"));
+        candidates.push(PathBuf::from("/Applications/Zed Preview.app"));
+    }
+    match candidates.iter().find(|path| path.exists()) {
+        Some(path) => Outcome::Ok(format!("Zed is installed at {}", path.display())),
+        None => Outcome::Warning(
+            "could not find Zed".to_owned(),
+            "install Zed from https://zed.dev, or ignore this if it is installed somewhere else"
+                .to_owned(),
+        ),
+    }
+}
@@ -0,0 +1,553 @@
+//! An interactive editor for the palette of a theme file. It lists every palette color with a
+//! swatch and adjusts the selected one with HSL and OKLCH sliders, installing the theme after
+//! every change so that it can be tuned while looking at it in Zed.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result as Res};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{
+    Color as TermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use palette::{Clamp, Hsla, IntoColor, Oklcha, Srgba};
+
+use crate::cli::logging;
+use zeddy::color::palette::ColorNode;
+use zeddy::color::{BaseColorKind, ColorModifiers, ColorName, HexColor};
+use zeddy::generate::set_palette_colors;
+use zeddy::schema::{KdlThemeFamily, KdlVersion};
+use zeddy::util::read_input;
+
+/// The width of the palette list, which the sliders are drawn to the right of.
+const LIST_WIDTH: u16 = 44;
+/// The number of cells in a slider.
+const SLIDER_WIDTH: u16 = 36;
+/// How many steps a key press moves a slider by while shift is held.
+const LARGE_STEP: f32 = 10.0;
+
+const HELP: &str = "↑↓ select  tab channel  ←→ adjust (shift: ×10)  u revert  s save  q quit";
+
+/// A channel of the selected color that a slider adjusts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Channel {
+    HslHue,
+    HslSaturation,
+    HslLightness,
+    OklchLightness,
+    OklchChroma,
+    OklchHue,
+    Alpha,
+}
+
+impl Channel {
+    const ALL: [Self; 7] = [
+        Self::HslHue,
+        Self::HslSaturation,
+        Self::HslLightness,
+        Self::OklchLightness,
+        Self::OklchChroma,
+        Self::OklchHue,
+        Self::Alpha,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::HslHue => "HSL hue",
+            Self::HslSaturation => "HSL saturation",
+            Self::HslLightness => "HSL lightness",
+            Self::OklchLightness => "OKLCH lightness",
+            Self::OklchChroma => "OKLCH chroma",
+            Self::OklchHue => "OKLCH hue",
+            Self::Alpha => "Alpha",
+        }
+    }
+
+    /// The largest value of the channel, and how much one step changes it. Every channel
+    /// starts at 0.
+    fn range(self) -> (f32, f32) {
+        match self {
+            Self::HslHue | Self::OklchHue => (360.0, 1.0),
+            // the most saturated colors in sRGB have a chroma of about 0.32
+            Self::OklchChroma => (0.37, 0.002),
+            Self::HslSaturation | Self::HslLightness | Self::OklchLightness | Self::Alpha => {
+                (1.0, 0.01)
+            }
+        }
+    }
+
+    fn get(self, color: Srgba) -> f32 {
+        let hsl: Hsla = color.into_color();
+        let oklch: Oklcha = color.into_color();
+        match self {
+            Self::HslHue => hsl.hue.into_positive_degrees(),
+            Self::HslSaturation => hsl.saturation,
+            Self::HslLightness => hsl.lightness,
+            Self::OklchLightness => oklch.l,
+            Self::OklchChroma => oklch.chroma,
+            Self::OklchHue => oklch.hue.into_positive_degrees(),
+            Self::Alpha => color.alpha,
+        }
+    }
+
+    fn set(self, color: Srgba, value: f32) -> Srgba {
+        match self {
+            Self::HslHue | Self::HslSaturation | Self::HslLightness => {
+                let mut hsl: Hsla = color.into_color();
+                match self {
+                    Self::HslHue => hsl.hue = value.into(),
+                    Self::HslSaturation => hsl.saturation = value,
+                    _ => hsl.lightness = value,
+                }
+                hsl.into_color()
+            }
+            Self::OklchLightness | Self::OklchChroma | Self::OklchHue => {
+                let mut oklch: Oklcha = color.into_color();
+                match self {
+                    Self::OklchLightness => oklch.l = value,
+                    Self::OklchChroma => oklch.chroma = value,
+                    _ => oklch.hue = value.into(),
+                }
+                // the color can end up outside of sRGB
+                let color: Srgba = oklch.into_color();
+                color.clamp()
+            }
+            Self::Alpha => Srgba {
+                alpha: value,
+                ..color
+            },
+        }
+    }
+
+    /// Moves the channel of `color` by `steps` steps, wrapping hues around.
+    fn adjust(self, color: Srgba, steps: f32) -> Srgba {
+        let (max, step) = self.range();
+        let value = self.get(color) + steps * step;
+        let value = match self {
+            Self::HslHue | Self::OklchHue => value.rem_euclid(max),
+            _ => value.clamp(0.0, max),
+        };
+        self.set(color, value)
+    }
+}
+
+/// What the editor has to do after a key press.
+enum Outcome {
+    Redraw,
+    /// A color changed, so the theme has to be installed again
+    Changed,
+    Quit,
+}
+
+/// Puts the terminal into raw mode on the alternate screen until it is dropped, which also
+/// happens when the editor fails.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> Res<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(std::io::stdout(), EnterAlternateScreen, Hide)?;
+        // log messages would be drawn over the editor, which shows them in its status line
+        logging::mute();
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+        logging::unmute();
+    }
+}
+
+pub struct Editor<'a> {
+    infile: &'a Path,
+    kdl_version: KdlVersion,
+    family: KdlThemeFamily,
+    /// The palette as it is in the file, to revert to
+    saved: Vec<ColorNode>,
+    /// The resolved color of every palette entry
+    colors: HashMap<ColorName, HexColor>,
+    /// The new colors of the palette entries that changed since the file was saved
+    edits: BTreeMap<String, HexColor>,
+    /// The palette entries that come from included files, which cannot be saved to `infile`
+    included: HashSet<String>,
+    selected: usize,
+    channel: Channel,
+    /// The selected color before it is rounded to a hex color, so that small steps add up
+    current: Srgba,
+    status: String,
+    /// Whether quitting was asked for once while there are unsaved changes
+    quitting: bool,
+}
+
+impl<'a> Editor<'a> {
+    /// Creates an editor for the palette of `family`, which was read from `infile`.
+    pub fn new(infile: &'a Path, kdl_version: KdlVersion, family: KdlThemeFamily) -> Res<Self> {
+        if family.palette.colors.is_empty() {
+            return Err(anyhow!(
+                "the palette of {} is empty, so there is nothing to edit",
+                infile.display()
+            ));
+        }
+        // included colors are the ones that the file does not define itself
+        let source = read_input(infile)?;
+        let own = KdlThemeFamily::parse(&infile.display().to_string(), &source, kdl_version)?;
+        let included = family
+            .palette
+            .colors
+            .iter()
+            .filter(|node| !own.palette.colors.iter().any(|own| own.name == node.name))
+            .map(|node| node.name.clone())
+            .collect();
+        let mut editor = Self {
+            infile,
+            kdl_version,
+            saved: family.palette.colors.clone(),
+            family,
+            colors: HashMap::new(),
+            edits: BTreeMap::new(),
+            included,
+            selected: 0,
+            channel: Channel::HslHue,
+            current: Srgba::new(0.0, 0.0, 0.0, 1.0),
+            status: String::new(),
+            quitting: false,
+        };
+        editor.resolve()?;
+        editor.select(0);
+        Ok(editor)
+    }
+
+    /// Runs the editor until it is quit. `install` is called with the theme family after every
+    /// change, and with the saved one when quitting without saving.
+    pub fn run(mut self, install: impl Fn(&KdlThemeFamily) -> Res<()>) -> Res<()> {
+        let _terminal = RawTerminal::enter()?;
+        let mut stdout = std::io::stdout().lock();
+        loop {
+            self.draw(&mut stdout)?;
+            let mut changed = false;
+            // every pending key press is handled before installing, so that holding down a key
+            // does not install the theme for every step
+            loop {
+                if let Event::Key(key) = event::read()? {
+                    match self.handle(key) {
+                        Outcome::Redraw => {}
+                        Outcome::Changed => changed = true,
+                        Outcome::Quit => return self.quit(&install),
+                    }
+                }
+                if !event::poll(Duration::ZERO)? {
+                    break;
+                }
+            }
+            if changed {
+                self.status = match install(&self.family) {
+                    Ok(()) => "Installed the theme".to_owned(),
+                    Err(e) => format!("Could not install the theme: {e}"),
+                };
+            }
+        }
+    }
+
+    fn quit(self, install: impl Fn(&KdlThemeFamily) -> Res<()>) -> Res<()> {
+        if self.edits.is_empty() {
+            return Ok(());
+        }
+        // the installed theme should match the file again
+        let mut family = self.family;
+        family.palette.colors = self.saved;
+        install(&family)
+    }
+
+    fn handle(&mut self, key: KeyEvent) -> Outcome {
+        if key.kind != KeyEventKind::Press {
+            return Outcome::Redraw;
+        }
+        let steps = if key.modifiers.contains(KeyModifiers::SHIFT) {
+            LARGE_STEP
+        } else {
+            1.0
+        };
+        let channels = Channel::ALL.len();
+        let channel = Channel::ALL
+            .iter()
+            .position(|channel| *channel == self.channel)
+            .unwrap_or_default();
+        let outcome = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.select(self.selected.saturating_sub(1));
+                Outcome::Redraw
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.select(self.selected + 1);
+                Outcome::Redraw
+            }
+            KeyCode::Tab => {
+                self.channel = Channel::ALL[(channel + 1) % channels];
+                Outcome::Redraw
+            }
+            KeyCode::BackTab => {
+                self.channel = Channel::ALL[(channel + channels - 1) % channels];
+                Outcome::Redraw
+            }
+            KeyCode::Left | KeyCode::Char('h' | 'H') => self.adjust(-steps),
+            KeyCode::Right | KeyCode::Char('l' | 'L') => self.adjust(steps),
+            KeyCode::Char('u') => self.revert(),
+            KeyCode::Char('s') => {
+                self.save();
+                Outcome::Redraw
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Outcome::Quit,
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if self.edits.is_empty() || self.quitting {
+                    return Outcome::Quit;
+                }
+                self.quitting = true;
+                self.status = String::from(
+                    "There are unsaved changes. Press q again to quit without saving them, which \
+                     installs the saved theme again",
+                );
+                return Outcome::Redraw;
+            }
+            _ => Outcome::Redraw,
+        };
+        self.quitting = false;
+        outcome
+    }
+
+    fn name(&self) -> &str {
+        &self.family.palette.colors[self.selected].name
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.family.palette.colors.len() - 1);
+        let color = self.colors.get(self.name()).copied();
+        self.current = color.map_or(self.current, to_srgba);
+    }
+
+    fn resolve(&mut self) -> Res<()> {
+        self.colors = self.family.palette.clone().into_palette().resolve()?.colors;
+        Ok(())
+    }
+
+    /// Moves the selected channel of the selected color, which turns it into a hex color.
+    fn adjust(&mut self, steps: f32) -> Outcome {
+        if self.included.contains(self.name()) {
+            self.status = format!(
+                "{} comes from an included file, so it cannot be saved to {}",
+                self.name(),
+                self.infile.display()
+            );
+            return Outcome::Redraw;
+        }
+        self.current = self.channel.adjust(self.current, steps);
+        let color = to_hex(self.current);
+        let node = &mut self.family.palette.colors[self.selected];
+        if let BaseColorKind::PaletteReference(reference) = &node.base {
+            self.status = format!("{} no longer refers to {reference}", node.name);
+        }
+        node.base = BaseColorKind::Hex(color);
+        node.modifiers = ColorModifiers::default();
+        self.edits.insert(node.name.clone(), color);
+        self.update()
+    }
+
+    /// Puts the selected color back the way it is in the file.
+    fn revert(&mut self) -> Outcome {
+        let name = self.name().to_owned();
+        if self.edits.remove(&name).is_none() {
+            return Outcome::Redraw;
+        }
+        if let Some(saved) = self.saved.iter().find(|node| node.name == name) {
+            self.family.palette.colors[self.selected] = saved.clone();
+        }
+        let outcome = self.update();
+        self.select(self.selected);
+        outcome
+    }
+
+    /// Resolves the palette again after a color changed.
+    fn update(&mut self) -> Outcome {
+        if let Err(e) = self.resolve() {
+            self.status = format!("Could not resolve the palette: {e}");
+            return Outcome::Redraw;
+        }
+        Outcome::Changed
+    }
+
+    fn save(&mut self) {
+        if self.edits.is_empty() {
+            self.status = String::from("There is nothing to save");
+            return;
+        }
+        let saved = read_input(self.infile)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| set_palette_colors(&source, self.kdl_version, &self.edits))
+            .and_then(|source| Ok(std::fs::write(self.infile, source)?));
+        self.status = match saved {
+            Ok(()) => {
+                let status = format!(
+                    "Saved {} color(s) to {}",
+                    self.edits.len(),
+                    self.infile.display()
+                );
+                self.edits.clear();
+                self.saved.clone_from(&self.family.palette.colors);
+                status
+            }
+            Err(e) => format!("Could not save: {e}"),
+        };
+    }
+
+    fn draw(&self, out: &mut impl Write) -> Res<()> {
+        let (_, height) = terminal::size()?;
+        queue!(
+            out,
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            Print(format!("Palette of {}", self.infile.display()))
+        )?;
+        self.draw_list(out, height.saturating_sub(4))?;
+        self.draw_sliders(out)?;
+        queue!(
+            out,
+            MoveTo(0, height.saturating_sub(2)),
+            Print(&self.status),
+            MoveTo(0, height.saturating_sub(1)),
+            SetForegroundColor(TermColor::DarkGrey),
+            Print(HELP),
+            ResetColor
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Draws as many palette entries as fit in `rows`, scrolled so that the selected one is
+    /// visible.
+    fn draw_list(&self, out: &mut impl Write, rows: u16) -> Res<()> {
+        let rows = usize::from(rows.max(1));
+        let nodes = &self.family.palette.colors;
+        let first = self
+            .selected
+            .saturating_sub(rows / 2)
+            .min(nodes.len().saturating_sub(rows));
+        for (row, (index, node)) in nodes.iter().enumerate().skip(first).take(rows).enumerate() {
+            let color = self
+                .colors
+                .get(node.name.as_str())
+                .copied()
+                .unwrap_or(HexColor([0; 4]));
+            let marker = if index == self.selected { ">" } else { " " };
+            let edited = if self.edits.contains_key(&node.name) {
+                "*"
+            } else {
+                " "
+            };
+            queue!(
+                out,
+                MoveTo(0, cell(row + 2)),
+                Print(format!("{marker} ")),
+                SetBackgroundColor(term_color(color)),
+                Print("    "),
+                ResetColor,
+                Print(format!(" {color} {edited}{}", node.name))
+            )?;
+            if let BaseColorKind::PaletteReference(reference) = &node.base {
+                queue!(
+                    out,
+                    SetForegroundColor(TermColor::DarkGrey),
+                    Print(format!(" → {reference}")),
+                    ResetColor
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws a slider for every channel of the selected color, whose cells show the color that
+    /// moving the slider there would give, and a swatch of the color below them.
+    fn draw_sliders(&self, out: &mut impl Write) -> Res<()> {
+        let mut row = 2;
+        for channel in Channel::ALL {
+            let value = channel.get(self.current);
+            let selected = if channel == self.channel { ">" } else { " " };
+            queue!(
+                out,
+                MoveTo(LIST_WIDTH, row),
+                Print(format!("{selected} {:<16}{value:>8.3}", channel.label()))
+            )?;
+            let (max, _) = channel.range();
+            let cell_width = max / f32::from(SLIDER_WIDTH - 1);
+            queue!(out, MoveTo(LIST_WIDTH + 2, row + 1))?;
+            for step in 0..SLIDER_WIDTH {
+                let cell_value = cell_width * f32::from(step);
+                let color = to_hex(channel.set(self.current, cell_value));
+                let knob = if (cell_value - value).abs() <= cell_width / 2.0 {
+                    "┃"
+                } else {
+                    " "
+                };
+                queue!(
+                    out,
+                    SetBackgroundColor(term_color(color)),
+                    SetForegroundColor(term_color(knob_color(color))),
+                    Print(knob)
+                )?;
+            }
+            queue!(out, ResetColor)?;
+            row += 3;
+        }
+        let color = to_hex(self.current);
+        for offset in 0..2 {
+            queue!(
+                out,
+                MoveTo(LIST_WIDTH + 2, row + offset),
+                SetBackgroundColor(term_color(color)),
+                Print(" ".repeat(usize::from(SLIDER_WIDTH))),
+                ResetColor
+            )?;
+        }
+        queue!(
+            out,
+            MoveTo(LIST_WIDTH + 2, row + 2),
+            Print(format!("{} {color}", self.name()))
+        )?;
+        Ok(())
+    }
+}
+
+/// The row or column of a terminal cell.
+fn cell(index: usize) -> u16 {
+    u16::try_from(index).unwrap_or(u16::MAX)
+}
+
+fn to_srgba(HexColor([r, g, b, a]): HexColor) -> Srgba {
+    Srgba::from((r, g, b, a)).into_format()
+}
+
+fn to_hex(color: Srgba) -> HexColor {
+    let color: Srgba<u8> = color.clamp().into_format();
+    HexColor([color.red, color.green, color.blue, color.alpha])
+}
+
+fn term_color(HexColor([r, g, b, _]): HexColor) -> TermColor {
+    TermColor::Rgb { r, g, b }
+}
+
+/// Black or white, whichever stands out more on `background`.
+fn knob_color(background: HexColor) -> HexColor {
+    let black = HexColor([0, 0, 0, u8::MAX]);
+    let white = HexColor([u8::MAX; 4]);
+    if black.contrast_ratio(background) > white.contrast_ratio(background) {
+        black
+    } else {
+        white
+    }
+}
@@ -0,0 +1,321 @@
+//! Keeps track of the themes that zeddy installed, and lists and removes installed themes.
+
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result as Res};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use super::paths::install_record_location;
+
+/// The files that zeddy installed, with a hash of the contents that it wrote. A file whose hash
+/// no longer matches was changed by something else after it was installed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstallRecord {
+    files: BTreeMap<PathBuf, u64>,
+}
+
+impl InstallRecord {
+    pub fn read() -> Res<Self> {
+        let path = install_record_location()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("could not read the install record {}: {e}", path.display()))
+    }
+
+    pub fn write(&self) -> Res<()> {
+        let path = install_record_location()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether `path` still has the contents that zeddy installed there.
+    pub fn installed(&self, path: &Path) -> bool {
+        let Ok(content) = std::fs::read(path) else {
+            return false;
+        };
+        self.files.get(&absolute(path)) == Some(&hash(&content))
+    }
+
+    pub fn insert(&mut self, path: &Path, content: &[u8]) {
+        self.files.insert(absolute(path), hash(content));
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.files.remove(&absolute(path));
+    }
+}
+
+/// Writes `content` to `installfile` and records that zeddy installed it. If `backup` is set and
+/// `installfile` is a file that zeddy did not write, it is copied to `<name>.json.bak` first. A
+/// file that already has `content` is left alone, since Zed reloads a theme whenever it is
+/// written.
+pub fn install_file(content: &[u8], installfile: &Path, backup: bool) -> Res<()> {
+    let mut record = InstallRecord::read()?;
+    if std::fs::read(installfile).is_ok_and(|existing| existing == content) {
+        debug!("{} is already up to date", installfile.display());
+        if !record.installed(installfile) {
+            record.insert(installfile, content);
+            record.write()?;
+        }
+        return Ok(());
+    }
+    if backup && needs_backup(installfile)? {
+        let backup = backup_location(installfile);
+        warn!(
+            "{} was not installed by zeddy, backing it up to {}",
+            installfile.display(),
+            backup.display()
+        );
+        std::fs::copy(installfile, &backup)?;
+    }
+    std::fs::write(installfile, content)?;
+    record.insert(installfile, content);
+    record.write()
+}
+
+/// An installed theme as it was before `watch` started replacing it, to be put back when it
+/// stops.
+pub struct InstalledSnapshot {
+    path: PathBuf,
+    /// The contents of the file, or nothing if there was none
+    content: Option<Vec<u8>>,
+    /// Whether zeddy installed the file
+    recorded: bool,
+}
+
+impl InstalledSnapshot {
+    pub fn take(installfile: &Path) -> Res<Self> {
+        let content = match std::fs::read(installfile) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let recorded = InstallRecord::read()?.installed(installfile);
+        Ok(Self {
+            path: installfile.to_owned(),
+            content,
+            recorded,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Puts the file back the way it was, or removes it if there was none, along with its entry
+    /// in the install record.
+    pub fn restore(self) -> Res<()> {
+        let mut record = InstallRecord::read()?;
+        if let Some(content) = &self.content {
+            info!("Restoring {}", self.path.display());
+            std::fs::write(&self.path, content)?;
+            if self.recorded {
+                record.insert(&self.path, content);
+            } else {
+                record.remove(&self.path);
+            }
+        } else {
+            info!("Removing {}", self.path.display());
+            if self.path.exists() {
+                std::fs::remove_file(&self.path)?;
+            }
+            record.remove(&self.path);
+        }
+        record.write()
+    }
+}
+
+/// Whether `installfile` exists and has contents that zeddy did not write.
+pub fn needs_backup(installfile: &Path) -> Res<bool> {
+    Ok(installfile.exists() && !InstallRecord::read()?.installed(installfile))
+}
+
+/// Returns `<name>.json.bak`, or `<name>.json.<n>.bak` with the first `n` that is not taken
+/// so that an earlier backup is never overwritten.
+fn backup_location(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    let mut backup = PathBuf::from(name);
+    let mut idx = 1;
+    while backup.exists() {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{idx}.bak"));
+        backup = PathBuf::from(name);
+        idx += 1;
+    }
+    backup
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// The 64-bit FNV-1a hash, which unlike the hasher of the standard library is the same on every
+/// version of Rust.
+fn hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A theme family in Zed's themes directory.
+struct InstalledFamily {
+    path: PathBuf,
+    name: String,
+    author: String,
+    appearances: Vec<String>,
+}
+
+impl InstalledFamily {
+    fn read(path: PathBuf) -> Res<Self> {
+        let content = std::fs::read_to_string(&path)?;
+        let json: Value = serde_json::from_str(&content)?;
+        let field = |key: &str| json[key].as_str().unwrap_or("?").to_owned();
+        let mut appearances = json["themes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|theme| theme["appearance"].as_str().map(str::to_owned))
+            .collect::<Vec<_>>();
+        appearances.sort();
+        appearances.dedup();
+        Ok(Self {
+            name: field("name"),
+            author: field("author"),
+            appearances,
+            path,
+        })
+    }
+
+    fn file_stem(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Reads every theme family in `dir`, sorted by name.
+fn installed_families(dir: &Path) -> Res<Vec<InstalledFamily>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("could not read the themes directory {}: {e}", dir.display()))?;
+    let mut families = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        match InstalledFamily::read(path.clone()) {
+            Ok(family) => families.push(family),
+            Err(e) => debug!("Skipping {}: {e}", path.display()),
+        }
+    }
+    families.sort_by(|x, y| x.name.cmp(&y.name));
+    Ok(families)
+}
+
+/// Returns the names of the theme families in `dir`.
+pub fn installed_names(dir: &Path) -> Res<Vec<String>> {
+    Ok(installed_families(dir)?
+        .into_iter()
+        .map(|family| family.name)
+        .collect())
+}
+
+pub fn list_cmd(dir: &Path) -> Res<()> {
+    let families = installed_families(dir)?;
+    let record = InstallRecord::read()?;
+    let rows = families
+        .iter()
+        .map(|family| {
+            [
+                family.name.clone(),
+                family.author.clone(),
+                family.appearances.join("/"),
+                if record.installed(&family.path) {
+                    "zeddy".to_owned()
+                } else {
+                    String::new()
+                },
+                family.file_stem(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["NAME", "AUTHOR", "APPEARANCE", "GENERATED", "FILE"].map(str::to_owned);
+    let widths = std::iter::once(&header)
+        .chain(&rows)
+        .fold([0; 5], |mut widths, row| {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+            widths
+        });
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// Removes the theme family called `name`, which can also be the name of its file.
+pub fn uninstall_cmd(dir: &Path, name: &str, yes: bool) -> Res<()> {
+    let families = installed_families(dir)?;
+    let matching = families
+        .iter()
+        .filter(|family| family.name == name || family.file_stem() == name)
+        .collect::<Vec<_>>();
+    let family = match matching.as_slice() {
+        [] => return Err(anyhow!("there is no installed theme called {name:?}")),
+        [family] => family,
+        _ => {
+            let files = matching
+                .iter()
+                .map(|family| family.file_stem())
+                .collect::<Vec<_>>();
+            return Err(anyhow!(
+                "{name:?} is the name of several installed themes, pass the file name instead: {}",
+                files.join(", ")
+            ));
+        }
+    };
+    if !yes && !confirm(&format!("Remove {}?", family.path.display()))? {
+        info!("Keeping {}", family.path.display());
+        return Ok(());
+    }
+    std::fs::remove_file(&family.path)?;
+    let mut record = InstallRecord::read()?;
+    record.remove(&family.path);
+    record.write()?;
+    info!("Removed {}", family.path.display());
+    Ok(())
+}
+
+/// Asks the user a yes or no question on the terminal.
+pub fn confirm(question: &str) -> Res<bool> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Err(anyhow!(
+            "not asking for confirmation because standard input is not a terminal, pass `--yes` instead"
+        ));
+    }
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    stdin.read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
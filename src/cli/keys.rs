@@ -0,0 +1,81 @@
+//! Commands that can be typed into the terminal while watching. Each one is a single key followed
+//! by Enter, since reading keys without Enter would put the terminal into raw mode and garble the
+//! log messages that are written at the same time.
+
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Result as Res};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// `r`: generates and installs every theme again, even the ones that did not change
+    Rebuild,
+    /// `p`: prints the resolved palette of every theme file
+    Palette,
+    /// `o`: opens the generated files in the default application for them
+    Open,
+    /// `q`: stops watching
+    Quit,
+}
+
+impl Key {
+    fn parse(line: &str) -> Option<Self> {
+        match line.trim() {
+            "r" => Some(Self::Rebuild),
+            "p" => Some(Self::Palette),
+            "o" => Some(Self::Open),
+            "q" => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Reads keys from standard input in the background and passes them to `on_key` until it returns
+/// `false`. Nothing is read if standard input is not a terminal, since watch mode may be run with
+/// its input redirected from somewhere else.
+pub fn read_keys(on_key: impl Fn(Key) -> bool + Send + 'static) {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return;
+    }
+    info!("Type r to rebuild, p to print the palette, o to open the generated files, or q to quit, followed by Enter");
+    std::thread::spawn(move || {
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Key::parse(&line) {
+                Some(key) if !on_key(key) => break,
+                Some(_) => {}
+                None => warn!("Unknown key {:?}, expected r, p, o, or q", line.trim()),
+            }
+        }
+    });
+}
+
+/// Opens `path` in the default application for it, without waiting for it to close.
+pub fn open(path: &Path) -> Res<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("could not open {}: {e}", path.display()))?;
+    Ok(())
+}
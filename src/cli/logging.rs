@@ -0,0 +1,82 @@
+//! Sets up where log messages go. They are written to standard error, either for people to read
+//! or as one JSON object per line for other tools, such as the ones that watch mode runs under.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::{ArgAction, Args, ValueEnum};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Whether log messages are thrown away, since they would be drawn over the color editor.
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Args, Debug, Default, Clone, Copy)]
+pub struct LogArgs {
+    /// Logs more, up to `-vv` to log everything. Overrides `RUST_LOG`.
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+    /// Logs less: `-q` only logs warnings, `-qq` only errors, and `-qqq` nothing. Overrides
+    /// `RUST_LOG`.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    quiet: u8,
+    /// How log messages are written. `json` writes one object per line, with the phase that the
+    /// message came from, and one more when each phase ends with how long it took
+    #[arg(long, global = true, value_enum, default_value_t)]
+    log_format: LogFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl LogArgs {
+    /// Installs the logger. Without `-v` or `-q`, the level comes from `RUST_LOG`, or is `info`.
+    pub fn init(self) {
+        let level = match (self.verbose, self.quiet) {
+            (0, 0) => None,
+            (1, _) => Some(LevelFilter::DEBUG),
+            (_, 0) => Some(LevelFilter::TRACE),
+            (_, 1) => Some(LevelFilter::WARN),
+            (_, 2) => Some(LevelFilter::ERROR),
+            _ => Some(LevelFilter::OFF),
+        };
+        let filter = match level {
+            Some(level) => EnvFilter::default().add_directive(level.into()),
+            None => EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        };
+        let builder = tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(writer);
+        match self.log_format {
+            LogFormat::Human => builder
+                .without_time()
+                .with_ansi(io::stderr().is_terminal())
+                .init(),
+            LogFormat::Json => builder.json().with_span_events(FmtSpan::CLOSE).init(),
+        }
+    }
+}
+
+/// Stops writing log messages until [`unmute`] is called.
+pub fn mute() {
+    MUTED.store(true, Ordering::Relaxed);
+}
+
+pub fn unmute() {
+    MUTED.store(false, Ordering::Relaxed);
+}
+
+fn writer() -> Box<dyn Write> {
+    if MUTED.load(Ordering::Relaxed) {
+        Box::new(io::sink())
+    } else {
+        Box::new(io::stderr())
+    }
+}
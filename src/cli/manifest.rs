@@ -0,0 +1,91 @@
+//! Project manifests (`zeddy.kdl`) that list every theme a repository builds, so that `build`
+//! and `watch` can work on all of them at once.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result as Res;
+use knus::Decode;
+
+use super::commands::OverrideArgs;
+use super::paths::{default_install_locations, default_output_location, Channel};
+use zeddy::schema::KdlVersion;
+use zeddy::util::ToAnyhow;
+
+/// The name of the manifest that is used when no input file is given.
+pub const MANIFEST_NAME: &str = "zeddy.kdl";
+
+#[derive(Debug, Decode)]
+pub struct Manifest {
+    #[knus(children(name = "target"))]
+    pub targets: Vec<TargetNode>,
+}
+
+/// A theme to build. Paths are relative to the directory of the manifest, and the output file
+/// and install locations default to the same locations as for `generate` and `install`.
+/// `install-location` can be given more than once to install the theme into several places.
+#[derive(Debug, Decode)]
+pub struct TargetNode {
+    #[knus(argument)]
+    pub name: String,
+    #[knus(child, unwrap(argument))]
+    pub source: String,
+    #[knus(child, unwrap(argument))]
+    pub outfile: Option<String>,
+    #[knus(children(name = "install-location"), unwrap(argument))]
+    pub install_locations: Vec<String>,
+    #[knus(children(name = "overlay"), unwrap(argument))]
+    pub overlays: Vec<String>,
+}
+
+/// A theme to generate and install, with all of its paths resolved.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub name: String,
+    pub infile: PathBuf,
+    pub outfile: PathBuf,
+    /// Where the generated theme is installed, in order. There is at least one
+    pub install_locations: Vec<PathBuf>,
+    pub overrides: OverrideArgs,
+}
+
+impl Manifest {
+    pub fn read(path: impl AsRef<Path>, version: KdlVersion) -> Res<Self> {
+        let p = path.as_ref();
+        let path_name = p.display().to_string();
+        let content = std::fs::read_to_string(p)?;
+        let content = version.to_v1(&content)?;
+        knus::parse::<Self>(&path_name, &content).to_anyhow()
+    }
+
+    /// Resolves the targets of a manifest that was read from `path`, installing them into the
+    /// themes directory of `channel` unless they have an install location.
+    pub fn targets(self, path: &Path, channel: Channel) -> Res<Vec<Target>> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        self.targets
+            .into_iter()
+            .map(|node| {
+                let infile = dir.join(&node.source);
+                let outfile = match node.outfile {
+                    Some(outfile) => dir.join(outfile),
+                    None => default_output_location(&infile, "json")?,
+                };
+                let install_locations = if node.install_locations.is_empty() {
+                    default_install_locations(&outfile, channel)?
+                } else {
+                    node.install_locations.iter().map(|x| dir.join(x)).collect()
+                };
+                Ok(Target {
+                    name: node.name,
+                    infile,
+                    outfile,
+                    install_locations,
+                    overrides: OverrideArgs {
+                        set: Vec::new(),
+                        overlays: node.overlays.iter().map(|x| dir.join(x)).collect(),
+                        themes: Vec::new(),
+                    },
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,64 @@
+//! Desktop notifications of rebuilds in watch mode, which usually runs in a background terminal
+//! where failures go unseen.
+
+use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+use tracing::warn;
+
+/// Which rebuilds `watch --notify` shows a notification for.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyOn {
+    /// Only the ones that failed
+    Failure,
+    /// Every one
+    Always,
+}
+
+/// Shows notifications of the rebuilds that were asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct Notifier {
+    on: Option<NotifyOn>,
+}
+
+impl Notifier {
+    /// Fails if notifications were asked for but zeddy was built without them, so that this is
+    /// found out before anything goes wrong.
+    pub fn new(on: Option<NotifyOn>) -> Res<Self> {
+        if on.is_some() && !cfg!(feature = "notifications") {
+            return Err(anyhow!(
+                "zeddy was built without desktop notifications, reinstall it with `--features notifications`"
+            ));
+        }
+        Ok(Self { on })
+    }
+
+    pub fn updated(self, name: &str) {
+        if self.on == Some(NotifyOn::Always) {
+            show(&format!("Updated {name}"), "The theme was installed");
+        }
+    }
+
+    pub fn failed(self, name: &str, error: &anyhow::Error) {
+        if self.on.is_some() {
+            show(&format!("Failed to update {name}"), &error.to_string());
+        }
+    }
+}
+
+/// Shows a notification. Failing to is only logged, since it must not stop watching.
+#[cfg(feature = "notifications")]
+fn show(summary: &str, body: &str) {
+    let res = notify_rust::Notification::new()
+        .appname("zeddy")
+        .summary(summary)
+        .body(body)
+        .show();
+    if let Err(e) = res {
+        warn!("Failed to show a desktop notification: {e}");
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+fn show(summary: &str, _body: &str) {
+    warn!("Cannot show the desktop notification {summary:?} without the `notifications` feature");
+}
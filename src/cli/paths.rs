@@ -5,6 +5,12 @@ use std::{
 };
 
 use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+
+use crate::cli::config::config;
+use zeddy::schema::json::SchemaVersion;
+use zeddy::schema::Meta;
+use zeddy::util::slug;
 
 #[allow(
     clippy::missing_panics_doc,
@@ -46,13 +52,139 @@ pub fn default_output_location(infile: &Path, ext: &str) -> Res<PathBuf> {
         pathdiff::diff_paths(infile, &current_dir).expect("Failed to diff infile and with the cwd. This should not be able to happen as both are absolute.")
     };
 
-    let dir = current_dir.join("generated");
-    Ok(dir.join(rel.with_extension(ext)))
+    Ok(output_dir(&current_dir).join(rel.with_extension(ext)))
+}
+
+fn output_dir(current_dir: &Path) -> PathBuf {
+    match &config().output_dir {
+        Some(dir) => current_dir.join(dir),
+        None => current_dir.join("generated"),
+    }
+}
+
+/// Returns the output file that `template` names in the output directory. The placeholders are
+/// `{stem}`, the name of `infile` without its extension, and `{family}`, `{slug}`, `{author}`, and
+/// `{version}`, which come from the `meta` of the theme family. `meta` is only called if the
+/// template uses one of them.
+pub fn templated_output_location(
+    template: &str,
+    infile: &Path,
+    meta: impl FnOnce() -> Res<Meta>,
+) -> Res<PathBuf> {
+    let uses_meta = ["{family}", "{slug}", "{author}", "{version}"]
+        .iter()
+        .any(|placeholder| template.contains(placeholder));
+    let meta = uses_meta.then(meta).transpose()?;
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed placeholder in output file template {template:?}"))?;
+        let placeholder = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let value = match (placeholder, &meta) {
+            ("stem", _) => infile
+                .file_stem()
+                .ok_or_else(|| anyhow!("Input file does not have a file name"))?
+                .to_string_lossy()
+                .into_owned(),
+            ("family", Some(meta)) => meta.name.clone(),
+            ("slug", Some(meta)) => slug(&meta.name),
+            ("author", Some(meta)) => meta.author.clone(),
+            ("version", Some(meta)) => meta
+                .version
+                .clone()
+                .ok_or_else(|| anyhow!("the theme family has no `version` for {{version}}"))?,
+            _ => {
+                return Err(anyhow!(
+                    "unknown placeholder {{{placeholder}}} in output file template {template:?}, expected {{stem}}, {{family}}, {{slug}}, {{author}}, or {{version}}"
+                ))
+            }
+        };
+        // a name with a slash in it must not put the file in another directory
+        name.push_str(&value.replace(['/', '\\'], "-"));
+    }
+    name.push_str(rest);
+    Ok(output_dir(&current_dir()?).join(name))
+}
+
+/// A release channel of Zed. Every channel uses the same config directory, but the configuration
+/// file can give each one its own themes directory.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Channel {
+    #[default]
+    Stable,
+    Preview,
+    Dev,
+}
+
+/// Returns the directory that Zed reads the themes of `channel` from.
+pub fn themes_dir(channel: Channel) -> PathBuf {
+    match config().install_dirs(channel).first() {
+        Some(dir) => dir.clone(),
+        None => config_dir().join("themes"),
+    }
+}
+
+/// Returns every directory that themes are installed into for `channel`: the one that Zed reads,
+/// followed by any others that the configuration file lists, such as a dotfiles repository.
+pub fn themes_dirs(channel: Channel) -> Vec<PathBuf> {
+    match config().install_dirs(channel) {
+        [] => vec![config_dir().join("themes")],
+        dirs => dirs.to_vec(),
+    }
 }
 
-pub fn default_install_location(outfile: &Path) -> Res<PathBuf> {
+/// Returns where `outfile` is installed to for `channel`, once for every themes directory.
+pub fn default_install_locations(outfile: &Path, channel: Channel) -> Res<Vec<PathBuf>> {
     let base_name = outfile
         .file_name()
         .ok_or_else(|| anyhow!("Output file does not have a file name"))?;
-    Ok(config_dir().join("themes").join(base_name))
+    Ok(themes_dirs(channel)
+        .into_iter()
+        .map(|dir| dir.join(base_name))
+        .collect())
+}
+
+/// Returns the install locations of `outfile` for every channel, without duplicates.
+pub fn channel_install_locations(outfile: &Path) -> Res<Vec<PathBuf>> {
+    let mut locations = Vec::new();
+    for channel in Channel::value_variants() {
+        for location in default_install_locations(outfile, *channel)? {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+    }
+    Ok(locations)
+}
+
+/// Returns where a theme JSON Schema downloaded with `--fetch-schema` is cached.
+pub fn schema_cache_location(version: SchemaVersion) -> Res<PathBuf> {
+    Ok(dirs::cache_dir()
+        .ok_or_else(|| anyhow!("Could not determine the cache directory"))?
+        .join("zeddy")
+        .join(format!("theme-schema-v{version}.json")))
+}
+
+/// Returns where pywal writes `colors.json`. pywal uses `~/.cache` on every platform unless
+/// `XDG_CACHE_HOME` is set.
+pub fn wal_colors_location() -> Res<PathBuf> {
+    let cache = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir()
+            .ok_or_else(|| anyhow!("Could not determine the home directory"))?
+            .join(".cache"),
+    };
+    Ok(cache.join("wal").join("colors.json"))
+}
+
+/// Returns where the record of the files that zeddy installed is kept.
+pub fn install_record_location() -> Res<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow!("Could not determine the data directory"))?
+        .join("zeddy")
+        .join("installed.json"))
 }
@@ -0,0 +1,150 @@
+//! A small HTTP server for `watch --serve` that shows every theme being watched in a mock editor
+//! window, and tells the page to reload with server-sent events whenever a theme is rebuilt.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result as Res};
+use tracing::{debug, info};
+
+use zeddy::generate::editor_svg;
+use zeddy::schema::JsonThemeFamily;
+
+/// How long to wait for a client to send its request before giving up on it.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const PAGE_STYLE: &str = "body{font-family:sans-serif;background:#1b1b1f;color:#e4e4e7;margin:2em}\
+svg{display:block;max-width:100%;height:auto;border-radius:8px;margin-bottom:1.5em}\
+pre{color:#f87171;white-space:pre-wrap}";
+
+/// Reloads the page when the server sends an event. The page is reloaded instead of patched so
+/// that it always matches what a fresh visit would show.
+const PAGE_SCRIPT: &str = "new EventSource('/events').onmessage = () => location.reload();";
+
+/// The latest build of every watched target, and the pages that are waiting for updates.
+pub struct Preview {
+    /// The name of each target with the HTML of its section, in the order of the targets
+    sections: Mutex<Vec<(String, String)>>,
+    clients: Mutex<Vec<TcpStream>>,
+}
+
+impl Preview {
+    /// Starts serving the preview on `address` in the background. `targets` are the names of
+    /// the watched targets, which have an empty section until they are built.
+    pub fn serve(address: SocketAddr, targets: &[String]) -> Res<Arc<Self>> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| anyhow!("could not serve the preview on {address}: {e}"))?;
+        let preview = Arc::new(Self {
+            sections: Mutex::new(
+                targets
+                    .iter()
+                    .map(|name| (name.clone(), String::new()))
+                    .collect(),
+            ),
+            clients: Mutex::new(Vec::new()),
+        });
+        let server = Arc::clone(&preview);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // a client that is slow to send its request does not hold up the others
+                let server = Arc::clone(&server);
+                std::thread::spawn(move || {
+                    if let Err(e) = server.respond(stream) {
+                        debug!("Failed to respond to a preview request: {e}");
+                    }
+                });
+            }
+        });
+        info!("Serving the preview on http://{address}");
+        Ok(preview)
+    }
+
+    /// Shows the themes of a target that was built.
+    pub fn update(&self, target: &str, json: &JsonThemeFamily) {
+        let section = json.themes.iter().map(editor_svg).collect::<String>();
+        self.set_section(target, section);
+    }
+
+    /// Shows the error that a target failed to build with, instead of its themes.
+    pub fn fail(&self, target: &str, error: &anyhow::Error) {
+        self.set_section(target, format!("<pre>{}</pre>", escape(&error.to_string())));
+    }
+
+    fn set_section(&self, target: &str, html: String) {
+        if let Some((_, section)) = lock(&self.sections)
+            .iter_mut()
+            .find(|(name, _)| name == target)
+        {
+            *section = html;
+        }
+        // clients that cannot be written to anymore have closed the page
+        lock(&self.clients).retain_mut(|client| {
+            client
+                .write_all(b"data: reload\n\n")
+                .and_then(|()| client.flush())
+                .is_ok()
+        });
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> Res<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // the headers are not needed, but have to be read before responding
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+            header.clear();
+        }
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+        debug!("Preview request: {}", request.trim_end());
+        match path {
+            "/" => {
+                let page = self.page();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n{page}",
+                    page.len()
+                )?;
+            }
+            "/events" => {
+                stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n")?;
+                stream.flush()?;
+                lock(&self.clients).push(stream);
+            }
+            _ => {
+                stream.write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn page(&self) -> String {
+        let mut sections = String::new();
+        for (name, html) in lock(&self.sections).iter() {
+            sections.push_str("<h2>");
+            sections.push_str(&escape(name));
+            sections.push_str("</h2>");
+            sections.push_str(html);
+        }
+        format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>zeddy preview</title><style>{PAGE_STYLE}</style></head><body>{sections}<script>{PAGE_SCRIPT}</script></body></html>"
+        )
+    }
+}
+
+/// Locks a mutex, ignoring that another thread panicked while holding it since the data is
+/// still usable.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
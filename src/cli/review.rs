@@ -0,0 +1,128 @@
+//! The questions that `migrate --interactive` asks about every color of the generated palette
+//! before the theme file is written. Questions go to standard error so that the answers can be
+//! piped in from a file.
+
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::Result as Res;
+
+use zeddy::color::{ColorName, HexColor};
+use zeddy::schema::KdlThemeFamily;
+
+/// How many places that use a color are listed before the rest are only counted.
+const MAX_USES: usize = 6;
+
+const HELP: &str = "Press enter to keep a color, or answer `r NAME` to rename it, `m NAME` to merge it into another palette color, `i` to inline it, or `d` to keep every remaining color";
+
+enum Answer {
+    Keep,
+    Done,
+    Rename(String),
+    Merge(String),
+    Inline,
+}
+
+impl Answer {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let (command, argument) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, argument)| (command, argument.trim()));
+        match (command, argument) {
+            ("", _) => Some(Self::Keep),
+            ("d" | "done", "") => Some(Self::Done),
+            ("i" | "inline", "") => Some(Self::Inline),
+            ("r" | "rename", name) if !name.is_empty() => Some(Self::Rename(name.to_owned())),
+            ("m" | "merge", name) if !name.is_empty() => Some(Self::Merge(name.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+/// Goes through the palette of `family` one color at a time, showing where it is used and
+/// applying the answers read from `input`. Running out of input keeps the remaining colors.
+pub fn review_palette(family: &mut KdlThemeFamily, input: &mut impl BufRead) -> Res<()> {
+    let swatches = std::io::stderr().is_terminal();
+    let names = family
+        .palette
+        .colors
+        .iter()
+        .map(|node| node.name.clone())
+        .collect::<Vec<_>>();
+    eprintln!("{HELP}");
+    for (idx, name) in names.iter().enumerate() {
+        let colors = family.palette.clone().into_palette().resolve()?.colors;
+        let Some(&color) = colors.get(name.as_str()) else {
+            continue;
+        };
+        eprintln!();
+        eprintln!(
+            "[{}/{}] {}{name} = {color}",
+            idx + 1,
+            names.len(),
+            swatch(color, swatches)
+        );
+        let uses = family.palette_color_uses(name);
+        for place in uses.iter().take(MAX_USES) {
+            eprintln!("    {place}");
+        }
+        if uses.len() > MAX_USES {
+            eprintln!("    and {} more", uses.len() - MAX_USES);
+        }
+        if !ask(family, name, &colors, input)? {
+            break;
+        }
+    }
+    family
+        .palette
+        .colors
+        .sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(())
+}
+
+/// Asks what to do with the palette color `name` until an answer can be applied. Returns false
+/// once the remaining colors should be kept as they are.
+fn ask(
+    family: &mut KdlThemeFamily,
+    name: &str,
+    colors: &HashMap<ColorName, HexColor>,
+    input: &mut impl BufRead,
+) -> Res<bool> {
+    loop {
+        eprint!("{name}> ");
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            eprintln!();
+            return Ok(false);
+        }
+        let applied = match Answer::parse(&line) {
+            Some(Answer::Keep) => return Ok(true),
+            Some(Answer::Done) => return Ok(false),
+            Some(Answer::Rename(new_name)) => family.rename_palette_color(name, &new_name),
+            Some(Answer::Merge(into)) => family.merge_palette_color(name, &into).inspect(|()| {
+                if let Some(color) = colors.get(into.as_str()) {
+                    eprintln!("Merged into {into} = {color}");
+                }
+            }),
+            Some(Answer::Inline) => family.inline_palette_color(name),
+            None => {
+                eprintln!("{HELP}");
+                continue;
+            }
+        };
+        match applied {
+            Ok(()) => return Ok(true),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+fn swatch(HexColor([r, g, b, _]): HexColor, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[48;2;{r};{g};{b}m  \x1b[0m ")
+    } else {
+        String::new()
+    }
+}
@@ -1,4 +1,5 @@
 #[allow(clippy::module_inception)]
 mod color;
+pub mod naming;
 pub mod palette;
 pub use color::*;
@@ -1,11 +1,22 @@
 use anyhow::anyhow;
+use clap::ValueEnum;
 use knus::{errors::DecodeError, traits::ErrorSpan, Decode, DecodeScalar};
 use palette::{
-    DarkenAssign, DesaturateAssign, IntoColor, Lcha, LightenAssign, SaturateAssign, ShiftHueAssign,
-    Srgba,
+    color_difference::{Ciede2000, Wcag21RelativeContrast},
+    convert::IntoColorUnclamped,
+    Clamp, DarkenAssign, DesaturateAssign, IntoColor, Lab, Laba, Lcha, LightenAssign, LinSrgb,
+    LinSrgba, Mix, Oklcha, SaturateAssign, ShiftHueAssign, Srgb, Srgba,
 };
 use serde_with::{DeserializeFromStr, SerializeDisplay};
-use std::{convert::Infallible, fmt::Display, hash::Hash, num::FpCategory, str::FromStr};
+use std::{
+    fmt::Display,
+    hash::Hash,
+    num::FpCategory,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+
+use crate::schema::location::Location;
 
 /// A color in the custom KDL format.
 #[derive(Debug, Clone, Decode, Default, PartialEq, Eq, Hash)]
@@ -14,6 +25,8 @@ pub struct Color {
     pub base: BaseColorKind,
     #[knus(flatten(property))]
     pub modifiers: ColorModifiers,
+    #[knus(span)]
+    pub location: Location,
 }
 
 #[derive(Clone, Copy, Debug, Decode, Default, PartialEq)]
@@ -30,6 +43,33 @@ pub struct ColorModifiers {
     pub desaturate: Option<f32>,
     #[knus(property)]
     pub hue_shift: Option<f32>,
+    /// Raises the lightness to this power, so that values above 1 darken the midtones and values
+    /// below 1 lighten them, while black and white stay as they are
+    #[knus(property)]
+    pub gamma: Option<Gamma>,
+    /// Shifts the color along the blue-yellow axis of `Lab` by this much, so that positive values
+    /// warm it up and negative values cool it down
+    #[knus(property)]
+    pub temperature: Option<f32>,
+    /// Lightens, darkens, and applies the gamma in `OKLCH` instead, where the hue that is seen
+    /// stays the same. Colors that do not set it follow the `preserve-hue` of the palette.
+    #[knus(property)]
+    pub preserve_hue: Option<bool>,
+}
+
+impl ColorModifiers {
+    /// Returns the modifiers with `preserve_hue` set to `default` unless they set it themselves.
+    pub fn or_preserve_hue(self, default: bool) -> Self {
+        Self {
+            preserve_hue: Some(self.preserve_hue.unwrap_or(default)),
+            ..self
+        }
+    }
+
+    /// Returns whether the modifiers lighten or darken the color, or change its gamma.
+    pub fn changes_lightness(&self) -> bool {
+        self.lighten.is_some() || self.darken.is_some() || self.gamma.is_some()
+    }
 }
 
 // trust me bro
@@ -50,15 +90,70 @@ impl Hash for ColorModifiers {
         hash_opt_f32(self.saturate);
         hash_opt_f32(self.desaturate);
         hash_opt_f32(self.hue_shift);
+        hash_opt_f32(self.gamma.map(Gamma::get));
+        hash_opt_f32(self.temperature);
+        self.preserve_hue.hash(state);
     }
 }
+
+/// The exponent of the `gamma` modifier, which is a finite number above 0. Anything else would
+/// turn every color white.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gamma(f32);
+
+impl Gamma {
+    pub fn new(gamma: f32) -> anyhow::Result<Self> {
+        if gamma.is_finite() && gamma > 0.0 {
+            Ok(Self(gamma))
+        } else {
+            Err(anyhow!(
+                "the gamma has to be a number above 0, found {gamma}"
+            ))
+        }
+    }
+
+    pub fn get(self) -> f32 {
+        self.0
+    }
+}
+
+impl<S: ErrorSpan> DecodeScalar<S> for Gamma {
+    fn raw_decode(
+        value: &knus::span::Spanned<knus::ast::Literal, S>,
+        ctx: &mut knus::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        let gamma = f32::raw_decode(value, ctx)?;
+        Self::new(gamma).map_err(|e| DecodeError::conversion(value, e))
+    }
+    fn decode(
+        value: &knus::ast::Value<S>,
+        ctx: &mut knus::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        Self::raw_decode(&value.literal, ctx)
+    }
+    fn type_check(
+        _: &Option<knus::span::Spanned<knus::ast::TypeName, S>>,
+        _: &mut knus::decode::Context<S>,
+    ) {
+    }
+}
+
+/// The name of a palette color. Every reference to a palette color and every entry of a resolved
+/// palette holds one, so they share the name instead of copying it.
+pub type ColorName = Arc<str>;
+
 /// The base type of a color entry before
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum BaseColorKind {
     /// A Reference to a color name in the palette
-    PaletteReference(String),
+    PaletteReference(ColorName),
     /// A hex color (#rrggbb(aa))
     Hex(HexColor),
+    /// A color in Display P3 (`color(display-p3 r g b)`), which is mapped into sRGB
+    DisplayP3(DisplayP3),
+    /// Whichever of two colors contrasts more with another color, such as the text of a button
+    /// in an accent color (`on(accent)` or `on(accent, dark, light)`)
+    On(Box<OnColor>),
 }
 impl Default for BaseColorKind {
     fn default() -> Self {
@@ -66,14 +161,148 @@ impl Default for BaseColorKind {
     }
 }
 
+/// The colors that `on(background)` picks from when no others are given.
+const ON_CANDIDATES: [HexColor; 2] = [HexColor([0, 0, 0, 255]), HexColor([255, 255, 255, 255])];
+
+/// A color that is picked to be drawn on top of `background`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct OnColor {
+    /// The color that the picked color is drawn on
+    pub background: BaseColorKind,
+    /// The colors to pick from, which are black and white unless others are given
+    pub candidates: [BaseColorKind; 2],
+}
+
+impl BaseColorKind {
+    /// Returns the palette colors that the color refers to.
+    pub fn references(&self) -> Vec<&ColorName> {
+        match self {
+            Self::PaletteReference(name) => vec![name],
+            Self::Hex(_) | Self::DisplayP3(_) => vec![],
+            Self::On(on) => std::iter::once(&on.background)
+                .chain(&on.candidates)
+                .flat_map(Self::references)
+                .collect(),
+        }
+    }
+
+    /// Returns the palette colors that the color refers to, to be renamed.
+    pub fn references_mut(&mut self) -> Vec<&mut ColorName> {
+        match self {
+            Self::PaletteReference(name) => vec![name],
+            Self::Hex(_) | Self::DisplayP3(_) => vec![],
+            Self::On(on) => {
+                let OnColor {
+                    background,
+                    candidates,
+                } = &mut **on;
+                std::iter::once(background)
+                    .chain(candidates)
+                    .flat_map(Self::references_mut)
+                    .collect()
+            }
+        }
+    }
+
+    /// Replaces every reference to the palette color `name` with `base`.
+    pub fn replace_reference(&mut self, name: &str, base: &BaseColorKind) {
+        match self {
+            Self::PaletteReference(reference) if &**reference == name => self.clone_from(base),
+            Self::PaletteReference(_) | Self::Hex(_) | Self::DisplayP3(_) => {}
+            Self::On(on) => {
+                on.background.replace_reference(name, base);
+                for candidate in &mut on.candidates {
+                    candidate.replace_reference(name, base);
+                }
+            }
+        }
+    }
+
+    /// Returns the color without modifiers, getting the palette colors that it refers to from
+    /// `lookup`.
+    pub fn resolve(
+        &self,
+        lookup: &mut impl FnMut(&ColorName) -> anyhow::Result<HexColor>,
+    ) -> anyhow::Result<HexColor> {
+        match self {
+            Self::PaletteReference(name) => lookup(name),
+            Self::Hex(hex) => Ok(*hex),
+            Self::DisplayP3(color) => Ok(color.to_srgb()),
+            Self::On(on) => {
+                let background = on.background.resolve(lookup)?;
+                let [first, second] = &on.candidates;
+                let (first, second) = (first.resolve(lookup)?, second.resolve(lookup)?);
+                Ok(
+                    if second.over(background).contrast_ratio(background)
+                        > first.over(background).contrast_ratio(background)
+                    {
+                        second
+                    } else {
+                        first
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Writes the color the way that it is written in theme files.
+impl Display for BaseColorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PaletteReference(name) => write!(f, "{name}"),
+            Self::Hex(hex) => write!(f, "{hex}"),
+            Self::DisplayP3(color) => write!(f, "{color}"),
+            Self::On(on) => match &on.candidates {
+                [Self::Hex(first), Self::Hex(second)] if [*first, *second] == ON_CANDIDATES => {
+                    write!(f, "on({})", on.background)
+                }
+                [first, second] => write!(f, "on({}, {first}, {second})", on.background),
+            },
+        }
+    }
+}
+
 impl FromStr for BaseColorKind {
-    type Err = Infallible;
+    type Err = anyhow::Error;
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         if let Some(hex) = parse_hex_color(input) {
-            Ok(BaseColorKind::Hex(hex))
-        } else {
-            Ok(Self::PaletteReference(input.to_owned()))
+            return Ok(BaseColorKind::Hex(hex));
         }
+        if input.starts_with("color(") {
+            return input.parse().map(Self::DisplayP3);
+        }
+        let Some(args) = input
+            .strip_prefix("on(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        else {
+            return Ok(Self::PaletteReference(input.into()));
+        };
+        let args = args
+            .split(',')
+            .map(|arg| match arg.trim() {
+                "" => Err(anyhow!("{input:?} has an empty argument")),
+                arg if arg.contains(['(', ')']) => {
+                    Err(anyhow!("the arguments of {input:?} cannot be functions"))
+                }
+                arg => arg.parse(),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (background, candidates) = match <[_; 3]>::try_from(args) {
+            Ok([background, first, second]) => (background, [first, second]),
+            Err(args) => match <[_; 1]>::try_from(args) {
+                Ok([background]) => (background, ON_CANDIDATES.map(Self::Hex)),
+                Err(_) => {
+                    return Err(anyhow!(
+                        "{input:?} needs a color to be drawn on, followed by either no colors or two colors to pick from"
+                    ))
+                }
+            },
+        };
+        Ok(Self::On(Box::new(OnColor {
+            background,
+            candidates,
+        })))
     }
 }
 impl<S: ErrorSpan> DecodeScalar<S> for BaseColorKind {
@@ -102,6 +331,7 @@ impl<S: ErrorSpan> DecodeScalar<S> for BaseColorKind {
 
 /// Parses a hex color in the form of `#rrggbb(aa)` where `aa` is optional.
 /// Letters are case insensitive. Returns `None` on invalid inputs.
+#[cfg(not(feature = "safe"))]
 pub fn parse_hex_color(input: &str) -> Option<HexColor> {
     const QUARTER_HEXY_DEVIL: u64 = 0x6666_0000_0000_0000u64;
     const ZERO: u64 = 0x3030_3030_3030_3030;
@@ -158,12 +388,146 @@ pub fn parse_hex_color(input: &str) -> Option<HexColor> {
     Some(HexColor((data as u32).to_le_bytes()))
 }
 
+/// Parses a hex color in the form of `#rrggbb(aa)` where `aa` is optional.
+/// Letters are case insensitive. Returns `None` on invalid inputs.
+///
+/// This is the safe version that the `safe` feature selects, which goes through the digits one
+/// by one instead of all at once.
+#[cfg(feature = "safe")]
+pub fn parse_hex_color(input: &str) -> Option<HexColor> {
+    let digits = input.strip_prefix('#')?.as_bytes();
+    if digits.len() != 6 && digits.len() != 8 {
+        return None;
+    }
+    let value = |digit: u8| match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    };
+    let mut channels = [255; 4];
+    for (channel, pair) in channels.iter_mut().zip(digits.chunks_exact(2)) {
+        *channel = value(pair[0])? << 4 | value(pair[1])?;
+    }
+    Some(HexColor(channels))
+}
+
 /// A hex color input
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, DeserializeFromStr, SerializeDisplay)]
 pub struct HexColor(pub [u8; 4]);
 
+/// How colors that modifiers take outside of sRGB are brought back into it.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GamutMap {
+    /// Clip every channel on its own, which can shift the hue
+    #[default]
+    Clip,
+    /// Lower the chroma until the color fits, which keeps its lightness and hue
+    ChromaReduce,
+}
+
+/// The gamut that colors are written in, for outputs that can hold more than sRGB.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Gamut {
+    /// sRGB, which is all that Zed's theme JSON holds
+    #[default]
+    Srgb,
+    /// Display P3, which keeps Display P3 colors as they are written
+    P3,
+}
+
+static GAMUT_MAP: OnceLock<GamutMap> = OnceLock::new();
+
+/// Returns the gamut mapping chosen with [`set_gamut_map`], or clipping.
+pub fn gamut_map() -> GamutMap {
+    GAMUT_MAP.get().copied().unwrap_or_default()
+}
+
+pub fn set_gamut_map(map: GamutMap) {
+    // the mapping is only set once at startup
+    let _ = GAMUT_MAP.set(map);
+}
+
+/// How far outside of sRGB a channel may be and still count as inside, which is less than
+/// rounding to 8 bits can show and keeps rounding errors of the conversions from counting.
+const GAMUT_TOLERANCE: f32 = 0.5 / 255.0;
+
+fn in_srgb(color: Srgba) -> bool {
+    [color.red, color.green, color.blue]
+        .iter()
+        .all(|channel| (-GAMUT_TOLERANCE..=1.0 + GAMUT_TOLERANCE).contains(channel))
+}
+
+/// Returns the highest chroma up to `chroma` that `fits`, by bisection.
+fn fit_chroma(chroma: f32, fits: impl Fn(f32) -> bool) -> f32 {
+    if fits(chroma) {
+        return chroma;
+    }
+    let (mut low, mut high) = (0.0, chroma);
+    for _ in 0..24 {
+        let middle = f32::midpoint(low, high);
+        if fits(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Raises a lightness that goes up to `max` to the power of `gamma`.
+fn apply_gamma(lightness: f32, max: f32, gamma: Gamma) -> f32 {
+    max * (lightness.max(0.0) / max).powf(gamma.get()).min(1.0)
+}
+
+/// Lightens, darkens, and applies the gamma to a color in `OKLCH`, keeping the hue that is seen.
+/// Saturated colors that no longer fit in sRGB at their new lightness lose chroma instead of being
+/// clipped, since clipping would shift their hue after all.
+fn change_lightness_in_oklch(lcha: Lcha, modifiers: ColorModifiers) -> Lcha {
+    let mut oklcha: Oklcha = lcha.into_color_unclamped();
+    if let Some(multiplier) = modifiers.darken {
+        oklcha.darken_assign(multiplier);
+    }
+    if let Some(multiplier) = modifiers.lighten {
+        oklcha.lighten_assign(multiplier);
+    }
+    if let Some(gamma) = modifiers.gamma {
+        oklcha.l = apply_gamma(oklcha.l, 1.0, gamma);
+    }
+    let target = oklcha;
+    oklcha.chroma = fit_chroma(target.chroma, |chroma| {
+        let mut fitted = target;
+        fitted.chroma = chroma;
+        in_srgb(fitted.into_color_unclamped())
+    });
+    oklcha.into_color_unclamped()
+}
+
 impl HexColor {
-    pub(crate) fn apply_modifiers(self, modifiers: ColorModifiers) -> Self {
+    pub fn apply_modifiers(self, modifiers: ColorModifiers) -> Self {
+        let mut lcha = self.modified_lcha(modifiers);
+        let fits = |lcha: Lcha| in_srgb(lcha.into_color_unclamped());
+        if gamut_map() == GamutMap::ChromaReduce && !fits(lcha) {
+            lcha.chroma = fit_chroma(lcha.chroma, |chroma| {
+                let mut fitted = lcha;
+                fitted.chroma = chroma;
+                fits(fitted)
+            });
+        }
+
+        let srgba: Srgba = lcha.into_color();
+        let rgba = srgba.clamp().into_format();
+
+        HexColor([rgba.red, rgba.green, rgba.blue, rgba.alpha])
+    }
+
+    /// Returns whether `modifiers` take the color outside of sRGB, so that it has to be mapped
+    /// back into it.
+    pub fn leaves_gamut(self, modifiers: ColorModifiers) -> bool {
+        !in_srgb(self.modified_lcha(modifiers).into_color_unclamped())
+    }
+
+    fn modified_lcha(self, modifiers: ColorModifiers) -> Lcha {
         let HexColor([r, g, b, a]) = self;
         let rgba = Srgba::from((r, g, b, a)).into_format();
         let mut lcha: Lcha = rgba.into_color();
@@ -172,12 +536,20 @@ impl HexColor {
             lcha.alpha *= alpha;
         }
 
-        if let Some(multiplier) = modifiers.darken {
-            lcha.darken_assign(multiplier);
-        }
+        if modifiers.preserve_hue == Some(true) && modifiers.changes_lightness() {
+            lcha = change_lightness_in_oklch(lcha, modifiers);
+        } else {
+            if let Some(multiplier) = modifiers.darken {
+                lcha.darken_assign(multiplier);
+            }
+
+            if let Some(multiplier) = modifiers.lighten {
+                lcha.lighten_assign(multiplier);
+            }
 
-        if let Some(multiplier) = modifiers.lighten {
-            lcha.lighten_assign(multiplier);
+            if let Some(gamma) = modifiers.gamma {
+                lcha.l = apply_gamma(lcha.l, 100.0, gamma);
+            }
         }
 
         if let Some(multiplier) = modifiers.desaturate {
@@ -192,11 +564,93 @@ impl HexColor {
             lcha.shift_hue_assign(offset);
         }
 
-        let srgba: Srgba = lcha.into_color();
-        let rgba = srgba.into_format();
+        if let Some(shift) = modifiers.temperature {
+            let mut laba: Laba = lcha.into_color_unclamped();
+            laba.b += shift;
+            lcha = laba.into_color_unclamped();
+        }
+
+        lcha
+    }
+
+    /// Mirrors the lightness of the color in `OKLCH`, keeping its hue and chroma, so that dark
+    /// colors become light and the other way around.
+    pub fn invert_lightness(self) -> Self {
+        let HexColor([r, g, b, a]) = self;
+        let rgba = Srgba::from((r, g, b, a)).into_format();
+        let mut oklcha: Oklcha = rgba.into_color();
+        oklcha.l = 1.0 - oklcha.l;
+
+        // inverted colors are not always representable in sRGB
+        let srgba: Srgba = oklcha.into_color();
+        let rgba = srgba.clamp().into_format();
+
+        HexColor([rgba.red, rgba.green, rgba.blue, rgba.alpha])
+    }
 
+    /// Returns the color in `OKLCH`.
+    pub fn to_oklcha(self) -> Oklcha {
+        let HexColor([r, g, b, a]) = self;
+        Srgba::from((r, g, b, a)).into_format().into_color()
+    }
+
+    /// Converts a color in `OKLCH` to sRGB. Colors that are outside of sRGB lose chroma until
+    /// they fit, so that their lightness and hue stay the same.
+    pub fn from_oklcha(color: Oklcha) -> Self {
+        let mut fitted = color;
+        fitted.chroma = fit_chroma(color.chroma, |chroma| {
+            let mut fitted = color;
+            fitted.chroma = chroma;
+            in_srgb(fitted.into_color_unclamped())
+        });
+        let srgba: Srgba = fitted.into_color();
+        let rgba = srgba.clamp().into_format();
         HexColor([rgba.red, rgba.green, rgba.blue, rgba.alpha])
     }
+
+    /// Returns the opaque color that is seen when the color is drawn over `background`, whose
+    /// alpha is ignored.
+    pub fn over(self, background: Self) -> Self {
+        let HexColor([r, g, b, a]) = self;
+        let HexColor([br, bg, bb, _]) = background;
+        let foreground: Srgb = Srgb::new(r, g, b).into_format();
+        let background: Srgb = Srgb::new(br, bg, bb).into_format();
+        let blended = background.mix(foreground, f32::from(a) / 255.0);
+        let Srgb {
+            red, green, blue, ..
+        } = blended.into_format::<u8>();
+        HexColor([red, green, blue, 255])
+    }
+
+    /// Returns the WCAG 2.1 contrast ratio between the color drawn over `background` and the
+    /// background, from 1 to 21. The background is treated as opaque.
+    pub fn contrast(self, background: Self) -> f32 {
+        self.over(background).contrast_ratio(background)
+    }
+
+    /// Returns the WCAG 2.1 contrast ratio between the color and `other`, from 1 to 21. Both
+    /// colors are treated as opaque, so the order of the colors does not matter.
+    pub fn contrast_ratio(self, other: Self) -> f32 {
+        self.to_srgb().relative_contrast(other.to_srgb())
+    }
+
+    /// Returns the relative luminance of the color as defined by WCAG 2.1, from 0 for black to 1
+    /// for white. The alpha is ignored.
+    pub fn luminance(self) -> f32 {
+        self.to_srgb().relative_luminance().luma
+    }
+
+    /// Returns the CIEDE2000 difference between the color and `other` in Lab, where a
+    /// difference of about 2.3 is just noticeable. The alpha is ignored.
+    pub fn delta_e(self, other: Self) -> f32 {
+        let to_lab = |color: Self| -> Lab { color.to_srgb().into_color() };
+        to_lab(self).difference(to_lab(other))
+    }
+
+    fn to_srgb(self) -> Srgb {
+        let HexColor([r, g, b, _]) = self;
+        Srgb::new(r, g, b).into_format()
+    }
 }
 
 impl Display for HexColor {
@@ -237,3 +691,135 @@ impl FromStr for HexColor {
         parse_hex_color(s).ok_or_else(|| anyhow!("Expected hex color"))
     }
 }
+
+/// A color in Display P3, the gamut of most phones and recent Apple screens, which holds more
+/// vivid colors than sRGB. The channels go from 0 to 1 and use the transfer function of sRGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayP3([f32; 4]);
+
+/// The linear sRGB channels of the red, green, and blue of Display P3, which has the same white
+/// point as sRGB.
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.224_94, -0.224_94, 0.0],
+    [-0.042_056_9, 1.042_057, 0.0],
+    [-0.019_637_6, -0.078_636_1, 1.098_274],
+];
+
+// channels are never NaN, since they are checked by `new` and when parsed
+impl Eq for DisplayP3 {}
+impl Hash for DisplayP3 {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // adding 0 turns -0 into 0, which are equal
+        self.0.map(|channel| (channel + 0.0).to_bits()).hash(state);
+    }
+}
+
+impl DisplayP3 {
+    /// Makes a color from its red, green, blue, and alpha channels, which have to go from 0 to 1.
+    pub fn new(channels: [f32; 4]) -> anyhow::Result<Self> {
+        if channels.iter().all(|channel| (0.0..=1.0).contains(channel)) {
+            Ok(Self(channels))
+        } else {
+            Err(anyhow!(
+                "the channels of a display-p3 color go from 0 to 1, found {channels:?}"
+            ))
+        }
+    }
+
+    /// Returns the red, green, blue, and alpha channels.
+    pub fn channels(self) -> [f32; 4] {
+        self.0
+    }
+
+    fn to_linear_srgb(self) -> LinSrgba {
+        let [r, g, b, alpha] = self.0;
+        let linear: LinSrgb = Srgb::new(r, g, b).into_linear();
+        let [red, green, blue] =
+            P3_TO_SRGB.map(|[x, y, z]| x * linear.red + y * linear.green + z * linear.blue);
+        LinSrgba::new(red, green, blue, alpha)
+    }
+
+    /// Returns the color in `OKLCH`, where it can be outside of sRGB.
+    pub fn to_oklcha(self) -> Oklcha {
+        self.to_linear_srgb().into_color_unclamped()
+    }
+
+    /// Returns whether the color is outside of sRGB, so that it has to be compressed into it.
+    pub fn leaves_srgb(self) -> bool {
+        !in_srgb(self.to_linear_srgb().into_color_unclamped())
+    }
+
+    /// Maps the color into sRGB by lowering its chroma in `OKLCH` until it fits, which keeps its
+    /// lightness and hue. Colors that are inside of sRGB only change by rounding.
+    pub fn to_srgb(self) -> HexColor {
+        if self.leaves_srgb() {
+            return HexColor::from_oklcha(self.to_oklcha());
+        }
+        let srgba: Srgba = self.to_linear_srgb().into_color_unclamped();
+        let rgba = srgba.clamp().into_format();
+        HexColor([rgba.red, rgba.green, rgba.blue, rgba.alpha])
+    }
+}
+
+/// Writes the color the way that CSS and theme files write it, like `color(display-p3 1 0.5 0)`.
+impl Display for DisplayP3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b, alpha] = self.0;
+        write!(f, "color(display-p3 {r} {g} {b}")?;
+        if alpha < 1.0 {
+            write!(f, " / {alpha}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl FromStr for DisplayP3 {
+    type Err = anyhow::Error;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let args = input
+            .strip_prefix("color(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| {
+                anyhow!("expected a color like color(display-p3 1 0.5 0), found {input:?}")
+            })?;
+        let (channels, alpha) = match args.split_once('/') {
+            Some((channels, alpha)) => (channels, Some(alpha.trim())),
+            None => (args, None),
+        };
+        let mut channels = channels.split_whitespace();
+        match channels.next() {
+            Some("display-p3") => {}
+            Some(space) => {
+                return Err(anyhow!(
+                    "the color space {space:?} of {input:?} is not supported, only display-p3 is"
+                ))
+            }
+            None => return Err(anyhow!("{input:?} needs a color space")),
+        }
+        let channels = channels
+            .map(|channel| parse_channel(channel, input))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let Ok([r, g, b]) = <[_; 3]>::try_from(channels) else {
+            return Err(anyhow!(
+                "{input:?} needs a red, a green, and a blue channel"
+            ));
+        };
+        let alpha = alpha.map_or(Ok(1.0), |alpha| parse_channel(alpha, input))?;
+        Ok(Self([r, g, b, alpha]))
+    }
+}
+
+/// Parses a channel of a `color()`, which is a number from 0 to 1 or a percentage.
+fn parse_channel(channel: &str, input: &str) -> anyhow::Result<f32> {
+    let value = match channel.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().map(|value| value / 100.0),
+        None => channel.parse(),
+    }
+    .map_err(|_| anyhow!("{channel:?} in {input:?} is not a number"))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(anyhow!(
+            "the channels of {input:?} go from 0 to 1, or from 0% to 100%"
+        ));
+    }
+    Ok(value)
+}
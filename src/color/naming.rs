@@ -0,0 +1,139 @@
+//! Names colors after the closest color of a list of named colors, as measured by the CIEDE2000
+//! color difference in Lab space. This is what `migrate` picks palette color names with.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+
+use crate::color::HexColor;
+use crate::util::read_input;
+
+const CSS: &str = include_str!("dictionaries/css.csv");
+const X11: &str = include_str!("dictionaries/x11.csv");
+
+/// A list of color names that comes with zeddy.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuiltinDictionary {
+    /// The colors of X11's `rgb.txt`, which include a hundred shades of gray
+    #[default]
+    X11,
+    /// The 148 named colors of CSS, which CSS took from X11
+    Css,
+}
+
+/// How palette colors are named when a theme is migrated.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Naming {
+    /// After the closest color of the dictionary
+    #[default]
+    Nearest,
+    /// After what the colors are used for: `surface-*` for backgrounds, `border-*` for borders,
+    /// and `syntax-*` for colors that only appear in syntax highlighting. Other colors are
+    /// named after the closest color of the dictionary
+    Semantic,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// The color, made opaque
+    color: HexColor,
+    name: String,
+}
+
+/// A list of named colors, read from CSV lines of `hex,name` such as `#ff7733,orange`.
+#[derive(Debug, Clone)]
+pub struct Namer {
+    entries: Vec<Entry>,
+}
+
+impl Namer {
+    pub fn builtin(dictionary: BuiltinDictionary) -> Self {
+        let text = match dictionary {
+            BuiltinDictionary::X11 => X11,
+            BuiltinDictionary::Css => CSS,
+        };
+        Self::parse(text).expect("the built-in dictionaries are valid")
+    }
+
+    /// Reads a dictionary from a CSV file. `-` reads it from standard input.
+    pub fn read(path: &Path) -> Res<Self> {
+        Self::parse(&read_input(path)?)
+            .map_err(|e| anyhow!("invalid color dictionary {}: {e}", path.display()))
+    }
+
+    /// Parses CSV lines of `hex,name`. The `#` of the hex color is optional, the alpha of the
+    /// color is ignored, and blank lines are skipped. A first line that does not start with a
+    /// color is taken to be a header.
+    pub fn parse(text: &str) -> Res<Self> {
+        let mut colors = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (hex, name) = line
+                .split_once(',')
+                .ok_or_else(|| anyhow!("line {} is not of the form `hex,name`", idx + 1))?;
+            let hex = hex.trim();
+            let color = if hex.starts_with('#') {
+                hex.parse::<HexColor>()
+            } else {
+                format!("#{hex}").parse()
+            };
+            let name = name.trim().trim_matches('"');
+            match color {
+                Ok(color) if !name.is_empty() => colors.push((color, name)),
+                Ok(_) => return Err(anyhow!("line {} has no name", idx + 1)),
+                Err(_) if idx == 0 => {}
+                Err(_) => return Err(anyhow!("line {} has an invalid color {hex:?}", idx + 1)),
+            }
+        }
+        Self::new(colors)
+    }
+
+    /// Creates a dictionary from named colors, with names written in lowercase with dashes
+    /// instead of spaces. A color that is already in the list under another name is skipped.
+    pub fn new<'a>(colors: impl IntoIterator<Item = (HexColor, &'a str)>) -> Res<Self> {
+        let mut entries = Vec::<Entry>::new();
+        for (HexColor([r, g, b, _]), name) in colors {
+            let color = HexColor([r, g, b, u8::MAX]);
+            if entries.iter().any(|entry| entry.color == color) {
+                continue;
+            }
+            entries.push(Entry {
+                color,
+                name: name
+                    .to_lowercase()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join("-"),
+            });
+        }
+        if entries.is_empty() {
+            return Err(anyhow!("there are no colors in the dictionary"));
+        }
+        Ok(Self { entries })
+    }
+
+    /// Returns every name in the list with its color difference from `rgb`, from the closest to
+    /// the furthest. Equally close colors keep their order in the list, so the same color always
+    /// gets the same names.
+    pub fn ranked(&self, rgb: [u8; 3]) -> Vec<(f32, &str)> {
+        let [r, g, b] = rgb;
+        let color = HexColor([r, g, b, u8::MAX]);
+        let mut ranked = self
+            .entries
+            .iter()
+            .map(|entry| (color.delta_e(entry.color), entry.name.as_str()))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(left, _), (right, _)| left.total_cmp(right));
+        ranked
+    }
+}
+
+impl Default for Namer {
+    fn default() -> Self {
+        Self::builtin(BuiltinDictionary::default())
+    }
+}
@@ -1,23 +1,82 @@
 use std::{collections::HashMap, fmt::Debug};
 
-use crate::color::{BaseColorKind, Color, ColorModifiers, HexColor};
-use anyhow::anyhow;
+use crate::color::naming::Namer;
+use crate::color::{BaseColorKind, Color, ColorModifiers, ColorName, DisplayP3, HexColor};
+use crate::schema::location::Location;
 use bimap::BiMap;
-use colornamer::{ColorNamer, Colors};
 use knus::Decode;
+use tracing::info_span;
 
 /// The raw, unsanitized palette input directly from the theme file.
 /// This then needs to converted to a `Palette`.
 #[derive(Debug, Clone, Decode, Default)]
 pub struct RawPalette {
+    /// Makes lighten and darken keep the hue of every color of the family that does not set
+    /// `preserve-hue` itself
+    #[knus(property, default)]
+    pub preserve_hue: bool,
     #[knus(children)]
-    pub(crate) colors: Vec<ColorNode>,
+    pub colors: Vec<ColorNode>,
 }
 
 impl RawPalette {
     pub fn into_palette(self) -> Palette {
         Palette {
             colors: self.colors.into_iter().map(ColorNode::into_tuple).collect(),
+            preserve_hue: self.preserve_hue,
+        }
+    }
+
+    /// Writes the `preserve-hue` of the palette onto its colors and `colors` when they lighten
+    /// or darken without setting it themselves, and clears it from the palette, so that the
+    /// colors look the same when they are moved into another family.
+    pub fn pin_preserve_hue<'a>(&mut self, colors: impl IntoIterator<Item = &'a mut Color>) {
+        if !std::mem::take(&mut self.preserve_hue) {
+            return;
+        }
+        let palette = self.colors.iter_mut().map(|node| &mut node.modifiers);
+        let colors = colors.into_iter().map(|color| &mut color.modifiers);
+        for modifiers in palette.chain(colors) {
+            if modifiers.changes_lightness() {
+                modifiers.preserve_hue.get_or_insert(true);
+            }
+        }
+    }
+
+    /// Returns the Display P3 colors of the palette colors that are one, or that refer to one
+    /// without any modifiers, so that they can be written without mapping them into sRGB.
+    pub fn display_p3_colors(&self) -> HashMap<ColorName, DisplayP3> {
+        let nodes = self
+            .colors
+            .iter()
+            .map(|node| (node.name.as_str(), node))
+            .collect::<HashMap<_, _>>();
+        let exact = |name: &str| {
+            let mut node = *nodes.get(name)?;
+            // cycles are reported when the palette is resolved, so they are only cut short here
+            for _ in 0..nodes.len() {
+                if node.modifiers != ColorModifiers::default() {
+                    return None;
+                }
+                match &node.base {
+                    BaseColorKind::DisplayP3(color) => return Some(*color),
+                    BaseColorKind::PaletteReference(name) => node = nodes.get(&**name)?,
+                    BaseColorKind::Hex(_) | BaseColorKind::On(_) => return None,
+                }
+            }
+            None
+        };
+        self.colors
+            .iter()
+            .filter_map(|node| Some((node.name.as_str().into(), exact(&node.name)?)))
+            .collect()
+    }
+
+    /// Replaces the color with the same name as `node`, or adds it if there is none.
+    pub fn insert(&mut self, node: ColorNode) {
+        match self.colors.iter_mut().find(|x| x.name == node.name) {
+            Some(existing) => *existing = node,
+            None => self.colors.push(node),
         }
     }
 }
@@ -30,30 +89,46 @@ pub struct ColorNode {
     pub base: BaseColorKind,
     #[knus(flatten(property))]
     pub modifiers: ColorModifiers,
+    /// Keeps `harmonize` from adjusting the color
+    #[knus(property, default)]
+    pub lock: bool,
+    #[knus(span)]
+    pub location: Location,
 }
 impl ColorNode {
-    pub fn into_tuple(self) -> (String, Color) {
+    pub fn into_tuple(self) -> (ColorName, Color) {
         let ColorNode {
             name,
             base,
             modifiers,
+            location,
+            ..
         } = self;
-        (name, Color { base, modifiers })
+        (
+            name.into(),
+            Color {
+                base,
+                modifiers,
+                location,
+            },
+        )
     }
 }
 
 /// The raw, unsanitized, unresolved input from the theme file, but as a mapping instead of a sequence.
 /// Has information about both modifiers and color names.
 pub struct Palette {
-    pub colors: HashMap<String, Color>,
+    pub colors: HashMap<ColorName, Color>,
+    /// Whether colors that do not set `preserve-hue` keep their hue when lightened or darkened
+    pub preserve_hue: bool,
 }
 
 impl Palette {
     fn resolve_color<'a>(
         &'a self,
-        name: &'a str,
+        name: &'a ColorName,
         color: &'a Color,
-        partial_resolutions: &mut HashMap<String, HexColor>,
+        partial_resolutions: &mut HashMap<ColorName, HexColor>,
         deps: &mut Vec<&'a str>,
     ) -> anyhow::Result<HexColor> {
         if let Some(color) = partial_resolutions.get(name) {
@@ -64,13 +139,13 @@ impl Palette {
         if let Some(idx) = deps
             .iter()
             .enumerate()
-            .find_map(|x| (*x.1 == name).then_some(x.0))
+            .find_map(|x| (*x.1 == &**name).then_some(x.0))
         {
             let deps = &deps[idx..];
             if deps.len() <= 1 {
-                return Err(anyhow!(
+                return Err(color.location.error(format!(
                     "cyclic dependency in palette: {name} directly depends on itself!"
-                ));
+                )));
             }
             let mut iter = deps.iter();
             let mut msg = String::with_capacity(1024)
@@ -85,25 +160,25 @@ impl Palette {
                 msg += i;
             }
             msg += "\n        which depends on ";
-            msg += name;
+            msg += &**name;
 
-            return Err(anyhow!(msg));
+            return Err(color.location.error(msg));
         }
-        deps.push(name);
-        let resolved = match color.base {
-            BaseColorKind::Hex(hex) => hex,
-            BaseColorKind::PaletteReference(ref reference) => {
-                let Some(dep_color) = self.colors.get(reference) else {
-                    return Err(anyhow!("could not find color {reference} in the palette"));
-                };
-                self.resolve_color(reference, dep_color, partial_resolutions, deps)?
-            }
-        };
-        let modified = resolved.apply_modifiers(color.modifiers);
-        partial_resolutions.insert(name.to_owned(), modified);
+        deps.push(&**name);
+        let resolved = color.base.resolve(&mut |reference| {
+            let Some((reference, dep_color)) = self.colors.get_key_value(reference) else {
+                return Err(color
+                    .location
+                    .error(format!("could not find color {reference} in the palette")));
+            };
+            self.resolve_color(reference, dep_color, partial_resolutions, deps)
+        })?;
+        let modified = resolved.apply_modifiers(color.modifiers.or_preserve_hue(self.preserve_hue));
+        partial_resolutions.insert(name.clone(), modified);
         Ok(modified)
     }
     pub fn resolve(self) -> anyhow::Result<ResolvedPalette> {
+        let _span = info_span!("resolve", colors = self.colors.len()).entered();
         let mut resolutions = HashMap::with_capacity(self.colors.len());
         let mut deps = Vec::with_capacity(self.colors.len());
         for (name, color) in &self.colors {
@@ -112,6 +187,7 @@ impl Palette {
         }
         Ok(ResolvedPalette {
             colors: resolutions,
+            preserve_hue: self.preserve_hue,
         })
     }
 }
@@ -119,7 +195,10 @@ impl Palette {
 /// The final resolved palette of colors.
 #[derive(Debug, Clone)]
 pub struct ResolvedPalette {
-    pub colors: HashMap<String, HexColor>,
+    pub colors: HashMap<ColorName, HexColor>,
+    /// Whether colors that are looked up and do not set `preserve-hue` keep their hue when
+    /// lightened or darkened
+    pub preserve_hue: bool,
 }
 impl ResolvedPalette {
     pub fn into_raw_palette(self) -> RawPalette {
@@ -127,33 +206,47 @@ impl ResolvedPalette {
             .colors
             .into_iter()
             .map(|(name, color)| ColorNode {
-                name,
+                name: name.to_string(),
                 base: BaseColorKind::Hex(color),
                 modifiers: <_>::default(),
+                lock: false,
+                location: Location::default(),
             })
             .collect::<Vec<_>>();
         // we have to do it like this or else we get a lifetime error
         colors.sort_unstable_by(|x, y| x.name.cmp(&y.name));
-        RawPalette { colors }
+        RawPalette {
+            preserve_hue: self.preserve_hue,
+            colors,
+        }
     }
     pub fn lookup(&self, color: &Color) -> anyhow::Result<HexColor> {
-        let hex = match color.base {
-            BaseColorKind::Hex(hex) => hex,
-            BaseColorKind::PaletteReference(ref pal_ref) => *self
-                .colors
-                .get(pal_ref)
-                .ok_or_else(|| anyhow!("could not find color {pal_ref:?} in the palette"))?,
-        };
-        Ok(hex.apply_modifiers(color.modifiers))
+        let hex = color.base.resolve(&mut |pal_ref| {
+            self.colors.get(pal_ref).copied().ok_or_else(|| {
+                color
+                    .location
+                    .error(format!("could not find color {pal_ref:?} in the palette"))
+            })
+        })?;
+        Ok(hex.apply_modifiers(color.modifiers.or_preserve_hue(self.preserve_hue)))
     }
 }
 fn alpha_to_modifier(alpha: u8) -> f32 {
     f32::from(alpha) / 255.0
 }
+/// How much further from a color, in CIEDE2000 units, another name may be than the closest one
+/// to be used when the closest name is taken. Past this, the closest name is numbered instead.
+const ALTERNATIVE_NAME_TOLERANCE: f32 = 3.0;
+
 /// Generates a palette based on input colors, attempting to simplify repeated and similar colors, and assigning colors names
 pub struct PaletteGenerator {
-    rgb_to_name: BiMap<[u8; 3], String>,
-    namer: ColorNamer,
+    /// The palette colors by their RGBA channels. The alpha is always 255 unless `keep_alpha` is
+    /// set, in which case translucent colors get their own entries.
+    colors: BiMap<[u8; 4], ColorName>,
+    namer: Namer,
+    /// The last number given to a copy of each name
+    numbered: HashMap<String, usize>,
+    keep_alpha: bool,
 }
 impl Default for PaletteGenerator {
     fn default() -> Self {
@@ -164,61 +257,115 @@ impl Default for PaletteGenerator {
 impl Debug for PaletteGenerator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PaletteGenerator")
-            .field("rgb_to_names", &self.rgb_to_name)
+            .field("colors", &self.colors)
+            .field("keep_alpha", &self.keep_alpha)
             .finish_non_exhaustive()
     }
 }
 impl PaletteGenerator {
     pub fn new() -> Self {
+        Self::with_namer(Namer::default())
+    }
+
+    /// Creates a generator that names colors with `namer`.
+    pub fn with_namer(namer: Namer) -> Self {
         Self {
-            rgb_to_name: <_>::default(),
-            namer: ColorNamer::new(Colors::all()),
+            colors: <_>::default(),
+            namer,
+            numbered: HashMap::new(),
+            keep_alpha: false,
         }
     }
 
+    /// Gives colors that only differ in alpha their own palette entries, instead of referring to
+    /// the opaque color with an `alpha` modifier.
+    #[must_use]
+    pub fn keep_alpha(self, keep_alpha: bool) -> Self {
+        Self { keep_alpha, ..self }
+    }
+
+    /// The channels of the palette entry that `color` belongs to.
+    pub fn key(&self, color: HexColor) -> [u8; 4] {
+        let HexColor([r, g, b, a]) = color;
+        [r, g, b, if self.keep_alpha { a } else { 255 }]
+    }
+
     /// Feeds a single color into the generator
     pub fn feed(&mut self, color: HexColor) {
-        let HexColor([r, g, b, _]) = color;
-        let rgb = [r, g, b];
-        if self.rgb_to_name.contains_left(&rgb) {
+        let key = self.key(color);
+        if self.colors.contains_left(&key) {
             return;
         }
-        // This api is so bad... why do I need a hex string to name the damn color?
-        // I should probably fork the colornamer crate one day...
-        // You don't understand how bad their hex parser implementation is.
-        let name = self
-            .namer
-            .name_hex_color(&format!("#{r:02x}{g:02x}{b:02x}"))
-            .unwrap() // can only error on invalid hex
-            .to_lowercase()
-            .trim()
-            .replace(' ', "-");
-        let mut name2 = name.clone();
-        let mut idx = 1;
-        // todo: make this more efficient. this is extremely bad because of constant
-        // allocations
-        while let Some(&right) = self.rgb_to_name.get_by_right(&name2) {
-            if right == rgb {
-                break;
+        let [r, g, b, a] = key;
+        // translucent entries are named after the opaque color and their opacity
+        let suffix = if a == 255 {
+            String::new()
+        } else {
+            format!("-alpha-{:.0}", alpha_to_modifier(a) * 100.0)
+        };
+        let ranked = self.namer.ranked([r, g, b]);
+        let (closest_difference, closest) = ranked[0];
+        // a slightly worse name reads better than a numbered copy of the closest one
+        let alternative = ranked
+            .iter()
+            .take_while(|(difference, _)| {
+                *difference <= closest_difference + ALTERNATIVE_NAME_TOLERANCE
+            })
+            .map(|(_, name)| format!("{name}{suffix}"))
+            .find(|name| !self.colors.contains_right(name.as_str()));
+        let name = if let Some(name) = alternative {
+            name
+        } else {
+            let closest = format!("{closest}{suffix}");
+            self.numbered_name(&closest)
+        };
+        self.colors.insert(key, name.into());
+    }
+
+    /// Renames the palette entry of a color that was fed into the generator, numbering the name
+    /// if another entry already has it.
+    pub fn rename(&mut self, color: HexColor, name: &str) {
+        let key = self.key(color);
+        if self
+            .colors
+            .get_by_left(&key)
+            .is_some_and(|old| **old == *name)
+        {
+            return;
+        }
+        let name = if self.colors.contains_right(name) {
+            self.numbered_name(name)
+        } else {
+            name.to_owned()
+        };
+        self.colors.insert(key, name.into());
+    }
+
+    /// Returns the first numbered copy of `name`, such as `gray47-2`, that no color has.
+    fn numbered_name(&mut self, name: &str) -> String {
+        let idx = self.numbered.entry(name.to_owned()).or_insert(1);
+        loop {
+            *idx += 1;
+            let numbered = format!("{name}-{idx}");
+            if !self.colors.contains_right(numbered.as_str()) {
+                break numbered;
             }
-            name2 = format!("{name}-{idx}");
-            idx += 1;
         }
-        self.rgb_to_name.insert(rgb, name2);
     }
 
     pub fn lookup(&self, color: HexColor) -> Color {
-        let HexColor([r, g, b, a]) = color;
-        let rgb = [r, g, b];
-        if let Some(name) = self.rgb_to_name.get_by_left(&rgb) {
+        let key = self.key(color);
+        if let Some(name) = self.colors.get_by_left(&key) {
             let base = BaseColorKind::PaletteReference(name.clone());
-            let alpha = (a != 255).then(|| alpha_to_modifier(a));
+            let HexColor([.., a]) = color;
+            let alpha = (a != key[3]).then(|| alpha_to_modifier(a));
             Color {
                 base,
                 modifiers: ColorModifiers {
                     alpha,
                     ..<_>::default()
                 },
+                location: Location::default(),
             }
         } else {
             Color {
@@ -230,10 +377,11 @@ impl PaletteGenerator {
     pub fn into_resolved_palette(self) -> ResolvedPalette {
         ResolvedPalette {
             colors: self
-                .rgb_to_name
+                .colors
                 .into_iter()
-                .map(|([r, g, b], name)| (name, HexColor([r, g, b, 255])))
+                .map(|(key, name)| (name, HexColor(key)))
                 .collect(),
+            preserve_hue: false,
         }
     }
 }
@@ -0,0 +1,149 @@
+//! Compares two generated theme families style by style, so that changes to a theme can be
+//! reviewed without reading through the whole JSON file.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::color::parse_hex_color;
+use crate::schema::json::ThemeFamily as JsonThemeFamily;
+
+/// A single value that differs between two versions of a theme.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StyleChange {
+    /// The style key, with syntax and player entries written as `syntax.comment.color` and
+    /// `players[0].cursor`.
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// The changes to one theme, matched between both families by name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThemeDiff {
+    pub name: String,
+    pub changes: Vec<StyleChange>,
+}
+
+/// Diffs every theme of two families. Themes that only exist on one side show up with all of
+/// their values added or removed, and themes without changes are left out.
+pub fn diff_families(
+    before: &JsonThemeFamily,
+    after: &JsonThemeFamily,
+) -> anyhow::Result<Vec<ThemeDiff>> {
+    let before = flatten_themes(before)?;
+    let after = flatten_themes(after)?;
+    // keep the order of the themes, with new themes at the end
+    let mut names = before.iter().map(|(name, _)| name).collect::<Vec<_>>();
+    names.extend(
+        after
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| !before.iter().any(|(theme, _)| theme == *name)),
+    );
+
+    Ok(names
+        .into_iter()
+        .map(|name| ThemeDiff {
+            name: name.clone(),
+            changes: diff_values(values_of(&before, name), values_of(&after, name)),
+        })
+        .filter(|theme| !theme.changes.is_empty())
+        .collect())
+}
+
+type ThemeValues = (String, BTreeMap<String, Value>);
+
+/// Finds the flattened values of the theme called `name`, which are empty if there is no such theme.
+fn values_of<'a>(themes: &'a [ThemeValues], name: &str) -> &'a BTreeMap<String, Value> {
+    static EMPTY: BTreeMap<String, Value> = BTreeMap::new();
+    themes
+        .iter()
+        .find(|(theme, _)| theme == name)
+        .map_or(&EMPTY, |(_, values)| values)
+}
+
+fn diff_values(
+    before: &BTreeMap<String, Value>,
+    after: &BTreeMap<String, Value>,
+) -> Vec<StyleChange> {
+    let mut keys = before.keys().chain(after.keys()).collect::<Vec<_>>();
+    keys.sort_unstable();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| StyleChange {
+            key: key.clone(),
+            before: before.get(key).cloned(),
+            after: after.get(key).cloned(),
+        })
+        .collect()
+}
+
+/// Flattens the appearance and style of every theme into a map from keys to leaf values.
+fn flatten_themes(family: &JsonThemeFamily) -> anyhow::Result<Vec<ThemeValues>> {
+    family
+        .themes
+        .iter()
+        .map(|theme| {
+            let mut values = BTreeMap::new();
+            values.insert(
+                "appearance".to_owned(),
+                serde_json::to_value(&theme.appearance)?,
+            );
+            flatten("", &serde_json::to_value(&theme.style)?, &mut values);
+            Ok((theme.name.clone(), values))
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, value) in items.iter().enumerate() {
+                flatten(&format!("{prefix}[{i}]"), value, out);
+            }
+        }
+        // Zed treats `null` the same as a missing key
+        Value::Null => {}
+        leaf => {
+            out.insert(prefix.to_owned(), leaf.clone());
+        }
+    }
+}
+
+/// Renders a change as a line of a human readable diff. With `swatches`, hex colors are
+/// preceded by a block of that color using 24-bit terminal colors.
+pub fn render_change(change: &StyleChange, swatches: bool) -> String {
+    let render = |value: &Value| {
+        let text = value
+            .as_str()
+            .map_or_else(|| value.to_string(), str::to_owned);
+        match value.as_str().and_then(parse_hex_color) {
+            Some(color) if swatches => {
+                let [r, g, b, _] = color.0;
+                format!("\x1b[48;2;{r};{g};{b}m  \x1b[0m {text}")
+            }
+            _ => text,
+        }
+    };
+    match (&change.before, &change.after) {
+        (Some(before), Some(after)) => {
+            format!("~ {}: {} -> {}", change.key, render(before), render(after))
+        }
+        (None, Some(after)) => format!("+ {}: {}", change.key, render(after)),
+        (Some(before), None) => format!("- {}: {}", change.key, render(before)),
+        (None, None) => unreachable!("unchanged values are not part of the diff"),
+    }
+}
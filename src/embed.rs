@@ -0,0 +1,71 @@
+//! The conversion core as functions from text to text, which the bindings to other languages
+//! wrap. Versions are passed by name, and errors point into the text like they do on the command
+//! line.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result as Res};
+
+use crate::color::HexColor;
+use crate::schema::include::resolve_includes;
+use crate::schema::json::SchemaVersion;
+use crate::schema::location::with_source_text;
+use crate::schema::{KdlThemeFamily, KdlVersion};
+
+/// The name that errors refer to the theme by when it was not read from a file.
+const SOURCE_NAME: &str = "theme.kdl";
+
+/// Generates the JSON theme family of a KDL theme file, written in version `kdl_version` of KDL
+/// (`1` or `2`), for version `schema_version` of Zed's theme schema, such as `0.1.0`. Includes
+/// are resolved relative to `path`, the file that the text was read from, and rejected without it.
+pub(crate) fn kdl_to_json(
+    source: &str,
+    path: Option<&Path>,
+    kdl_version: &str,
+    schema_version: &str,
+) -> Res<String> {
+    let kdl_version = kdl_version.parse()?;
+    let res = schema_version.parse::<SchemaVersion>().and_then(|version| {
+        let json = crate::generate::generate_json(parse(source, path, kdl_version)?, version)?;
+        Ok(serde_json::to_string_pretty(&json)?)
+    });
+    res.map_err(|e| with_source_text(e, &name(path), source, kdl_version))
+}
+
+/// Resolves the palette of a KDL theme file into a JSON object of the hex color of every palette
+/// entry by name. The arguments are the same as those of [`kdl_to_json`].
+pub(crate) fn resolve_palette(source: &str, path: Option<&Path>, kdl_version: &str) -> Res<String> {
+    let kdl_version = kdl_version.parse()?;
+    let res = parse(source, path, kdl_version).and_then(|mut family| {
+        family.expand_templates()?;
+        let resolved = family.palette.into_palette().resolve()?;
+        let colors = resolved
+            .colors
+            .iter()
+            .map(|(name, color)| (&**name, *color))
+            .collect::<BTreeMap<_, HexColor>>();
+        Ok(serde_json::to_string_pretty(&colors)?)
+    });
+    res.map_err(|e| with_source_text(e, &name(path), source, kdl_version))
+}
+
+fn parse(source: &str, path: Option<&Path>, version: KdlVersion) -> Res<KdlThemeFamily> {
+    let mut family = KdlThemeFamily::parse(&name(path), source, version)?;
+    match path {
+        Some(path) => {
+            resolve_includes(&mut family, path, version)?;
+        }
+        None if !family.includes.is_empty() => {
+            return Err(anyhow!(
+                "the theme includes other files, but it was not read from a file that their paths are relative to"
+            ));
+        }
+        None => {}
+    }
+    Ok(family)
+}
+
+fn name(path: Option<&Path>) -> String {
+    path.map_or_else(|| SOURCE_NAME.to_owned(), |path| path.display().to_string())
+}
@@ -0,0 +1,417 @@
+//! Explains where the value of a key in a generated theme comes from, so that a surprising
+//! color can be traced back to the modifiers and palette entries that produced it.
+
+use std::fmt::{Display, Write as _};
+use std::io::Write;
+
+use anyhow::{anyhow, Result as Res};
+
+use crate::color::palette::{Palette, ResolvedPalette};
+use crate::color::{BaseColorKind, Color, ColorModifiers, Gamma, HexColor};
+use crate::schema::json::SchemaVersion;
+use crate::schema::kdl::{ActionField, Modifier, ModifierPath, TerminalColors, Theme, ThemeFamily};
+use crate::schema::location::Location;
+use crate::schema::{FontStyle, FontWeight};
+
+/// The fields of a syntax entry, with the name they have in the generated JSON.
+pub(crate) const SYNTAX_FIELDS: [(&str, ActionField); 4] = [
+    ("color", ActionField::Color),
+    ("background", ActionField::Background),
+    ("font_weight", ActionField::FontWeight),
+    ("font_style", ActionField::FontStyle),
+];
+
+/// The theme that a step of an explanation was written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Common,
+    Theme,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Common => "common",
+            Self::Theme => "theme",
+        })
+    }
+}
+
+/// A value that a step gave to a field.
+#[derive(Clone, Debug)]
+pub enum Setting {
+    Color(Color),
+    FontWeight(FontWeight),
+    FontStyle(FontStyle),
+    /// A style entry that was kept as JSON when the theme was migrated.
+    Json(String),
+}
+
+/// Something in the KDL source that set or cleared a field.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub origin: Origin,
+    /// What kind of node the step is, such as `modifier`.
+    pub kind: &'static str,
+    pub location: Location,
+    /// The new value of the field, or `None` if the step cleared it.
+    pub setting: Option<Setting>,
+}
+
+/// A palette entry that a color was resolved through.
+#[derive(Clone, Debug)]
+pub struct ChainLink {
+    /// The name of the palette entry, or `None` for the color written in the step itself.
+    pub name: Option<String>,
+    pub color: Color,
+    /// The color after the modifiers of this link have been applied.
+    pub resolved: HexColor,
+}
+
+/// The history of a single field, in the order the steps are applied.
+#[derive(Clone, Debug)]
+pub struct FieldExplanation {
+    pub key: String,
+    pub steps: Vec<Step>,
+    /// How the final color was resolved, if the field ends up with a color.
+    pub chain: Vec<ChainLink>,
+}
+
+/// Where the value of a key in a theme comes from. A syntax scope is explained field by field.
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub theme: String,
+    pub fields: Vec<FieldExplanation>,
+}
+
+/// The field of a generated theme that a key refers to.
+enum Target<'a> {
+    Style(&'a str),
+    Syntax(&'a str, ActionField),
+}
+
+/// A theme family with its templates expanded and its palette resolved, which can explain any
+/// number of keys without doing that again.
+pub struct Explainer {
+    palette: Palette,
+    resolved: ResolvedPalette,
+    common: Option<Theme>,
+    themes: Vec<Theme>,
+}
+
+impl Explainer {
+    pub fn new(mut family: ThemeFamily) -> Res<Self> {
+        family.expand_templates()?;
+        let palette = family.palette.into_palette();
+        let resolved = Palette {
+            colors: palette.colors.clone(),
+            preserve_hue: palette.preserve_hue,
+        }
+        .resolve()?;
+        Ok(Self {
+            palette,
+            resolved,
+            common: family.common,
+            themes: family.themes,
+        })
+    }
+
+    /// Explains the `key` of the theme called `theme`, where `key` is written like the keys
+    /// printed by `diff`, such as `editor.background`, `syntax.comment` or `syntax.comment.color`.
+    pub fn explain(&self, theme: &str, key: &str, version: SchemaVersion) -> Res<Explanation> {
+        let theme = self
+            .themes
+            .iter()
+            .find(|x| x.name == theme)
+            .ok_or_else(|| {
+                let names = self.themes.iter().map(|theme| format!("{:?}", theme.name));
+                anyhow!(
+                    "there is no theme called {theme:?}, expected one of {}",
+                    names.collect::<Vec<_>>().join(", ")
+                )
+            })?;
+
+        let layers = self
+            .common
+            .iter()
+            .map(|common| (Origin::Common, common))
+            .chain([(Origin::Theme, theme)])
+            .collect::<Vec<_>>();
+
+        let mut fields = Vec::new();
+        for (key, target) in targets(key, version)? {
+            let steps = steps(&layers, &target, theme);
+            let chain = match steps.last().and_then(|step| step.setting.as_ref()) {
+                Some(Setting::Color(color)) => color_chain(color, &self.palette, &self.resolved)?,
+                _ => Vec::new(),
+            };
+            fields.push(FieldExplanation { key, steps, chain });
+        }
+        Ok(Explanation {
+            theme: theme.name.clone(),
+            fields,
+        })
+    }
+}
+
+/// Explains the `key` of the theme called `theme` in `family`, like [`Explainer::explain`].
+pub fn explain(
+    family: ThemeFamily,
+    theme: &str,
+    key: &str,
+    version: SchemaVersion,
+) -> Res<Explanation> {
+    Explainer::new(family)?.explain(theme, key, version)
+}
+
+/// Splits a key into the fields it refers to, undoing the renames of the schema version.
+fn targets(key: &str, version: SchemaVersion) -> Res<Vec<(String, Target<'_>)>> {
+    if key == "players" || key.starts_with("players[") {
+        return Err(anyhow!(
+            "players are set directly by the `player` nodes of a theme and cannot be explained"
+        ));
+    }
+    if key == "accents" || key.starts_with("accents[") {
+        return Err(anyhow!(
+            "accents are set directly by the `accent` nodes of a theme and cannot be explained"
+        ));
+    }
+    if let Some(scope) = key.strip_prefix("syntax.") {
+        let field = SYNTAX_FIELDS.iter().find_map(|&(name, field)| {
+            scope
+                .strip_suffix(name)
+                .and_then(|scope| scope.strip_suffix('.'))
+                .map(|scope| (name, scope, field))
+        });
+        return Ok(match field {
+            Some((_, scope, field)) => vec![(key.to_owned(), Target::Syntax(scope, field))],
+            None => SYNTAX_FIELDS
+                .iter()
+                .map(|&(name, field)| (format!("{key}.{name}"), Target::Syntax(scope, field)))
+                .collect(),
+        });
+    }
+    let style = version
+        .renamed_style_keys()
+        .find_map(|(from, to)| (to == key).then_some(from))
+        .unwrap_or(key);
+    Ok(vec![(key.to_owned(), Target::Style(style))])
+}
+
+/// Collects every step that touches `target`, in the order that generating the theme applies them.
+fn steps(layers: &[(Origin, &Theme)], target: &Target, theme: &Theme) -> Vec<Step> {
+    let mut steps = Vec::new();
+    if let Target::Style(key) = *target {
+        // terminal colors of the theme win over the ones of the common theme
+        for &(origin, layer) in layers.iter().rev() {
+            let color = layer.terminal.as_ref().and_then(|terminal| {
+                terminal
+                    .entries()
+                    .into_iter()
+                    .find(|(name, _)| TerminalColors::style_key(name) == key)
+                    .and_then(|(_, color)| color.clone())
+            });
+            if let Some(color) = color {
+                steps.push(Step {
+                    origin,
+                    kind: "terminal color",
+                    location: color.location,
+                    setting: Some(Setting::Color(color)),
+                });
+                break;
+            }
+        }
+        for &(origin, layer) in layers {
+            for entry in layer.passthrough.style.iter().filter(|x| x.key == key) {
+                steps.push(Step {
+                    origin,
+                    kind: "passthrough entry",
+                    location: Location::default(),
+                    setting: Some(Setting::Json(entry.value.clone())),
+                });
+            }
+        }
+    }
+    for &(origin, layer) in layers {
+        let modifiers = layer.modifiers.iter().filter(|modifier| {
+            modifier
+                .if_appearance
+                .as_ref()
+                .is_none_or(|appearance| *appearance == theme.appearance)
+        });
+        for modifier in modifiers {
+            for path in &modifier.apply {
+                modifier_steps(origin, modifier, path, target, &mut steps);
+            }
+        }
+    }
+    steps
+}
+
+fn modifier_steps(
+    origin: Origin,
+    modifier: &Modifier,
+    path: &ModifierPath,
+    target: &Target,
+    steps: &mut Vec<Step>,
+) {
+    let action = &modifier.action;
+    let (field, setting) = match (path, target) {
+        (ModifierPath::Style(path), Target::Style(key)) if path == key => {
+            (ActionField::Color, action.color.clone().map(Setting::Color))
+        }
+        (ModifierPath::Syntax(path), &Target::Syntax(scope, field)) if path == scope => {
+            let setting = match field {
+                ActionField::Color => action.color.clone().map(Setting::Color),
+                ActionField::Background => action.background.clone().map(Setting::Color),
+                ActionField::FontWeight => action.font_weight.map(Setting::FontWeight),
+                ActionField::FontStyle => action.font_style.map(Setting::FontStyle),
+            };
+            (field, setting)
+        }
+        _ => return,
+    };
+    let mut step = |setting| {
+        steps.push(Step {
+            origin,
+            kind: "modifier",
+            location: modifier.location,
+            setting,
+        });
+    };
+    if action.clears(field) {
+        step(None);
+    }
+    if setting.is_some() {
+        step(setting);
+    }
+}
+
+/// Follows a color through the palette entries it refers to.
+fn color_chain(
+    color: &Color,
+    palette: &Palette,
+    resolved: &ResolvedPalette,
+) -> Res<Vec<ChainLink>> {
+    let mut chain = vec![ChainLink {
+        name: None,
+        color: color.clone(),
+        resolved: resolved.lookup(color)?,
+    }];
+    let mut current = color;
+    // the palette has already been resolved, so it has no cycles
+    while let BaseColorKind::PaletteReference(name) = &current.base {
+        let Some(next) = palette.colors.get(name) else {
+            break;
+        };
+        chain.push(ChainLink {
+            name: Some(name.to_string()),
+            color: next.clone(),
+            resolved: resolved.lookup(next)?,
+        });
+        current = next;
+    }
+    Ok(chain)
+}
+
+impl Explanation {
+    /// Writes the explanation to `out`, with locations written as lines of `source`.
+    pub fn render(&self, source: &str, out: &mut impl Write) -> std::io::Result<()> {
+        let at = |location: Location| {
+            location
+                .line(source)
+                .map_or_else(String::new, |line| format!(" at line {line}"))
+        };
+        for field in &self.fields {
+            let value = match (field.chain.first(), field.steps.last()) {
+                (Some(link), _) => link.resolved.to_string(),
+                (
+                    None,
+                    Some(Step {
+                        setting: Some(setting),
+                        ..
+                    }),
+                ) => describe_setting(setting),
+                _ => "not set".to_owned(),
+            };
+            writeln!(out, "{} in theme {:?}: {value}", field.key, self.theme)?;
+            if field.steps.is_empty() {
+                writeln!(out, "  nothing in the theme sets this key")?;
+            }
+            for (i, step) in field.steps.iter().enumerate() {
+                let action = match &step.setting {
+                    Some(setting) => format!("sets {}", describe_setting(setting)),
+                    None => "clears it".to_owned(),
+                };
+                let last = if i + 1 == field.steps.len() {
+                    " (final)"
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "  {} {}{} {action}{last}",
+                    step.origin,
+                    step.kind,
+                    at(step.location)
+                )?;
+            }
+            for link in &field.chain {
+                let name = link.name.as_ref().map_or_else(String::new, |name| {
+                    format!("palette {name:?}{}: ", at(link.color.location))
+                });
+                writeln!(
+                    out,
+                    "    {name}{} -> {}",
+                    describe_color(&link.color),
+                    link.resolved
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn describe_setting(setting: &Setting) -> String {
+    match setting {
+        Setting::Color(color) => describe_color(color),
+        Setting::FontWeight(weight) => weight.to_string(),
+        Setting::FontStyle(style) => format!("{style:?}").to_lowercase(),
+        Setting::Json(json) => json.clone(),
+    }
+}
+
+/// Writes a color the way it is written in KDL, such as `purple lighten=0.2`.
+pub fn describe_color(color: &Color) -> String {
+    let mut out = color.base.to_string();
+    let ColorModifiers {
+        alpha,
+        lighten,
+        darken,
+        saturate,
+        desaturate,
+        hue_shift,
+        gamma,
+        temperature,
+        preserve_hue,
+    } = color.modifiers;
+    let modifiers = [
+        ("alpha", alpha),
+        ("lighten", lighten),
+        ("darken", darken),
+        ("saturate", saturate),
+        ("desaturate", desaturate),
+        ("hue-shift", hue_shift),
+        ("gamma", gamma.map(Gamma::get)),
+        ("temperature", temperature),
+    ];
+    for (name, value) in modifiers {
+        if let Some(value) = value {
+            write!(out, " {name}={value}").unwrap();
+        }
+    }
+    if let Some(preserve_hue) = preserve_hue {
+        write!(out, " preserve-hue={preserve_hue}").unwrap();
+    }
+    out
+}
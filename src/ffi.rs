@@ -0,0 +1,129 @@
+//! Bindings to the conversion core over the C ABI, so that editor plugins and programs in other
+//! languages can embed zeddy instead of running the command line interface. Strings are passed
+//! as NUL-terminated UTF-8, and every string that zeddy returns, including error messages, must
+//! be freed with [`zeddy_string_free`]. The declarations are in `include/zeddy.h`.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use anyhow::{anyhow, Result as Res};
+
+use crate::embed;
+
+/// Generates the JSON theme family of the KDL theme file `source`, written in version
+/// `kdl_version` of KDL (`"1"` or `"2"`), for version `schema_version` of Zed's theme schema,
+/// such as `"0.1.0"`. Includes are resolved relative to `path`, the file that the text was read
+/// from, which can be null if the theme includes nothing.
+///
+/// Returns the JSON, or null on failure. If `error` is not null, it is set to the error message
+/// on failure and to null on success.
+///
+/// # Safety
+///
+/// `source`, `kdl_version`, and `schema_version` must be valid NUL-terminated strings, `path`
+/// must be null or one, and `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn zeddy_kdl_to_json(
+    source: *const c_char,
+    path: *const c_char,
+    kdl_version: *const c_char,
+    schema_version: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let res = catch(|| {
+        let path = optional_arg(path, "path")?.map(Path::new);
+        embed::kdl_to_json(
+            arg(source, "source")?,
+            path,
+            arg(kdl_version, "kdl_version")?,
+            arg(schema_version, "schema_version")?,
+        )
+    });
+    finish(res, error)
+}
+
+/// Resolves the palette of the KDL theme file `source` into a JSON object of the hex color of
+/// every palette entry by name. The arguments and the result are the same as those of
+/// [`zeddy_kdl_to_json`].
+///
+/// # Safety
+///
+/// `source` and `kdl_version` must be valid NUL-terminated strings, `path` must be null or one,
+/// and `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn zeddy_resolve_palette(
+    source: *const c_char,
+    path: *const c_char,
+    kdl_version: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let res = catch(|| {
+        let path = optional_arg(path, "path")?.map(Path::new);
+        embed::resolve_palette(
+            arg(source, "source")?,
+            path,
+            arg(kdl_version, "kdl_version")?,
+        )
+    });
+    finish(res, error)
+}
+
+/// Frees a string returned by zeddy. Does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by zeddy that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn zeddy_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn arg<'a>(ptr: *const c_char, name: &str) -> Res<&'a str> {
+    optional_arg(ptr, name)?.ok_or_else(|| anyhow!("{name} is null"))
+}
+
+unsafe fn optional_arg<'a>(ptr: *const c_char, name: &str) -> Res<Option<&'a str>> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    let text = CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|e| anyhow!("{name} is not UTF-8: {e}"))?;
+    Ok(Some(text))
+}
+
+/// Turns a panic into an error, since unwinding into C is undefined behavior.
+fn catch(f: impl FnOnce() -> Res<String>) -> Res<String> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown error");
+        Err(anyhow!("zeddy panicked: {message}"))
+    })
+}
+
+unsafe fn finish(res: Res<String>, error: *mut *mut c_char) -> *mut c_char {
+    let (value, message) = match res {
+        Ok(value) => (c_string(&value), ptr::null_mut()),
+        Err(e) => (ptr::null_mut(), c_string(&format!("{e:?}"))),
+    };
+    if error.is_null() {
+        zeddy_string_free(message);
+    } else {
+        *error = message;
+    }
+    value
+}
+
+fn c_string(text: &str) -> *mut c_char {
+    // JSON and error messages never contain NUL, but C cannot be handed one either way
+    CString::new(text.replace('\0', ""))
+        .unwrap_or_default()
+        .into_raw()
+}
@@ -1,6 +1,45 @@
+mod derive;
+mod extension;
+mod format_kdl;
+mod gallery;
+mod harmonize;
+mod helix;
+mod image;
 mod json;
 mod kdl;
+mod kdl_schema;
+mod merge;
+mod nvim;
+mod patch;
+mod resolve;
+mod scheme;
+mod semantic;
 mod serialize_kdl;
-pub use json::generate_json;
+mod split;
+mod tailwind;
+mod terminal;
+mod tokens;
+mod vscode;
+pub use derive::derive_light;
+pub use extension::{bump_version, extension_manifest, VersionPart};
+pub use format_kdl::{format_kdl, set_meta_version, set_palette_colors};
+pub use gallery::{gallery_markdown, GalleryFamily};
+pub use harmonize::{harmonize_palette, Harmony};
+pub use helix::export_helix;
+pub use image::{editor_svg, palette_svg, rasterize, swatch_svg};
+pub use json::{generate_json, GenerationCache};
 pub use kdl::generate_kdl;
-pub use serialize_kdl::serialize_kdl;
+pub use kdl_schema::kdl_schema;
+pub use merge::{merge_families, ColorConflict};
+pub use nvim::export_nvim;
+pub use patch::make_overlay;
+pub use resolve::resolve_theme;
+pub use scheme::{scheme_palette, ColorScheme};
+pub use serialize_kdl::{
+    serialize_kdl, serialize_overlay_kdl, serialize_theme_kdl, Indent, KdlFormat,
+};
+pub use split::split_family;
+pub use tailwind::tailwind_colors;
+pub use terminal::{export_terminal, terminal_scheme, TerminalFormat};
+pub use tokens::design_tokens;
+pub use vscode::vscode_theme;
@@ -0,0 +1,48 @@
+//! Derives new themes from existing ones, giving authors a starting point to refine by hand.
+
+use anyhow::{anyhow, Result as Res};
+use tracing::{info, warn};
+
+use crate::color::{BaseColorKind, Color, ColorModifiers};
+use crate::schema::kdl::{Theme, ThemeFamily};
+use crate::schema::Appearance;
+
+/// Creates a light theme from the theme called `name` by inverting the lightness of every color
+/// it uses. The theme is merged with the `common` theme first and all colors are written out as
+/// hex colors, since the palette colors they came from are meant for the dark theme.
+pub fn derive_light(mut family: ThemeFamily, name: &str) -> Res<Theme> {
+    info!("Deriving a light theme from {name:?}");
+    family.expand_templates()?;
+    let resolved = family.palette.into_palette().resolve()?;
+    let mut theme = family
+        .themes
+        .into_iter()
+        .find(|theme| theme.name == name)
+        .ok_or_else(|| anyhow!("there is no theme named {name:?}"))?;
+    if let Some(common) = &family.common {
+        theme.merge(common);
+    }
+    if theme.appearance == Appearance::Light {
+        warn!("{name:?} is already a light theme");
+    }
+
+    for color in theme.colors_mut() {
+        *color = Color {
+            base: BaseColorKind::Hex(resolved.lookup(color)?.invert_lightness()),
+            modifiers: ColorModifiers::default(),
+            location: color.location,
+        };
+    }
+    theme.name = light_name(&theme.name);
+    theme.appearance = Appearance::Light;
+    Ok(theme)
+}
+
+/// Names the light variant of a theme, e.g. `Foo Dark` becomes `Foo Light`.
+fn light_name(name: &str) -> String {
+    if name.contains("Dark") {
+        name.replace("Dark", "Light")
+    } else {
+        format!("{name} Light")
+    }
+}
@@ -0,0 +1,97 @@
+//! Writes the `extension.toml` of a Zed extension that publishes a theme family, and bumps
+//! the version that it is released under.
+
+use anyhow::{anyhow, bail, Result as Res};
+use clap::ValueEnum;
+use toml_edit::{value, Array, DocumentMut, Item, Value};
+
+use crate::schema::Meta;
+use crate::util::slug;
+
+/// The version of the format of `extension.toml` that Zed reads.
+const SCHEMA_VERSION: i64 = 1;
+
+/// A part of a `major.minor.patch` version.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionPart {
+    /// Fixes that don't change how the themes look much
+    Patch,
+    /// New themes or noticeable changes to colors
+    Minor,
+    /// Changes that users of the themes would not expect, such as renamed themes
+    Major,
+}
+
+/// Increments the `part` of `version`, resetting the parts after it to zero. A missing version
+/// counts as `0.0.0`, so the first bump releases `0.0.1`, `0.1.0`, or `1.0.0`.
+pub fn bump_version(version: Option<&str>, part: VersionPart) -> Res<String> {
+    let version = version.unwrap_or("0.0.0");
+    let parts = version
+        .split('.')
+        .map(str::parse::<u64>)
+        .collect::<Result<Vec<_>, _>>();
+    let Ok([major, minor, patch]) = parts.as_deref() else {
+        bail!("{version:?} is not a version of the form `major.minor.patch`");
+    };
+    Ok(match part {
+        VersionPart::Patch => format!("{major}.{minor}.{}", patch + 1),
+        VersionPart::Minor => format!("{major}.{}.0", minor + 1),
+        VersionPart::Major => format!("{}.0.0", major + 1),
+    })
+}
+
+/// Updates the `extension.toml` text `existing`, or writes a new one, with the fields that come
+/// from `meta`: the name, version, authors, description, and the homepage as the repository.
+/// Other fields, such as the id of an existing extension, are kept along with the comments and
+/// the order of the keys. The license is left out, since Zed reads it from the `LICENSE` file of
+/// the extension.
+pub fn extension_manifest(meta: &Meta, existing: Option<&str>) -> Res<String> {
+    let version = meta.version.as_deref().ok_or_else(|| {
+        anyhow!(
+            "the `meta` of {:?} has no `version`, which Zed extensions need",
+            meta.name
+        )
+    })?;
+    let mut manifest = match existing {
+        Some(text) => text
+            .parse::<DocumentMut>()
+            .map_err(|e| anyhow!("invalid extension.toml: {e}"))?,
+        None => DocumentMut::new(),
+    };
+    manifest
+        .entry("id")
+        .or_insert_with(|| value(slug(&meta.name)));
+    set(&mut manifest, "name", meta.name.as_str().into());
+    set(&mut manifest, "version", version.into());
+    manifest
+        .entry("schema_version")
+        .or_insert(value(SCHEMA_VERSION));
+    // `import extension` joins the authors of the manifest the same way
+    let authors = meta
+        .author
+        .split(',')
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .collect::<Array>();
+    set(&mut manifest, "authors", authors.into());
+    for (key, value) in [
+        ("description", &meta.description),
+        ("repository", &meta.homepage),
+    ] {
+        if let Some(value) = value {
+            set(&mut manifest, key, value.as_str().into());
+        }
+    }
+    Ok(manifest.to_string())
+}
+
+/// Sets `key` of `manifest` to `new`, keeping the comments around an existing value.
+fn set(manifest: &mut DocumentMut, key: &str, mut new: Value) {
+    match manifest.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => {
+            *new.decor_mut() = old.decor().clone();
+            *old = new;
+        }
+        None => manifest[key] = Item::Value(new),
+    }
+}
@@ -0,0 +1,153 @@
+//! Formats KDL theme files by editing the parsed document instead of serializing decoded
+//! themes, so that comments and blank lines between nodes survive.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result as Res};
+use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+use tracing::debug;
+
+use crate::color::{parse_hex_color, HexColor};
+use crate::generate::KdlFormat;
+use crate::schema::KdlVersion;
+use crate::util::ToAnyhow;
+
+/// Formats a KDL theme file written in the `version` of KDL canonically: nodes are laid out
+/// according to `format`, the palette is sorted by name, and hex colors are lowercase. Comments
+/// are kept, and runs of blank lines between nodes are collapsed into a single one.
+pub fn format_kdl(source: &str, version: KdlVersion, format: KdlFormat) -> Res<String> {
+    debug!(
+        "Formatting KDL v{version} document as KDL v{}",
+        format.version
+    );
+    let mut doc: KdlDocument = version.to_v1(source)?.parse().to_anyhow()?;
+    normalize_nodes(&mut doc);
+
+    // `fmt` drops blank lines, so remember where they were to put them back afterwards
+    let mut blank_lines = vec![];
+    find_blank_lines(&doc, &mut blank_lines);
+    doc.fmt();
+    restore_blank_lines(&mut doc, &mut blank_lines.into_iter());
+
+    if let Some(palette) = doc
+        .get_mut("palette")
+        .and_then(|palette| palette.children_mut().as_mut())
+    {
+        palette
+            .nodes_mut()
+            .sort_by(|x, y| x.name().value().cmp(y.name().value()));
+        // groups of colors no longer make sense after sorting
+        for node in palette.nodes_mut() {
+            let leading = node.leading().unwrap_or_default().trim_start_matches('\n');
+            node.set_leading(leading.to_owned());
+        }
+    }
+
+    format.apply(&mut doc);
+
+    let mut formatted = format.version.render(doc)?;
+    if !formatted.ends_with('\n') {
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
+/// Replaces the colors of palette entries in a KDL theme file written in the `version` of KDL,
+/// keeping everything else as it was. The modifiers of the replaced entries are removed, since
+/// the new colors are final.
+pub fn set_palette_colors(
+    source: &str,
+    version: KdlVersion,
+    colors: &BTreeMap<String, HexColor>,
+) -> Res<String> {
+    let mut doc: KdlDocument = version.to_v1(source)?.parse().to_anyhow()?;
+    let palette = doc
+        .get_mut("palette")
+        .and_then(|palette| palette.children_mut().as_mut())
+        .ok_or_else(|| anyhow!("the file has no palette"))?;
+    for (name, &color) in colors {
+        let node = palette
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("{name:?} is not defined in the palette of this file"))?;
+        let HexColor([.., alpha]) = color;
+        let mut hex = color.to_string();
+        if alpha == u8::MAX {
+            hex.truncate("#rrggbb".len());
+        }
+        node.entries_mut().clear();
+        node.push(KdlEntry::new(hex));
+    }
+    version.render(doc)
+}
+
+/// Sets the `version` of the `meta` node in a KDL theme file written in the `version` of KDL,
+/// keeping everything else as it was. A missing `version` node is added after the other
+/// children of `meta`, indented like them.
+pub fn set_meta_version(source: &str, version: KdlVersion, meta_version: &str) -> Res<String> {
+    let mut doc: KdlDocument = version.to_v1(source)?.parse().to_anyhow()?;
+    let meta = doc
+        .get_mut("meta")
+        .ok_or_else(|| anyhow!("the file has no meta"))?
+        .ensure_children();
+    if meta.get("version").is_none() {
+        let mut node = KdlNode::new("version");
+        if let Some(last) = meta.nodes().last() {
+            node.set_leading(last.leading().unwrap_or_default());
+            node.set_trailing(last.trailing().unwrap_or_default());
+        }
+        meta.nodes_mut().push(node);
+    }
+    let node = meta
+        .get_mut("version")
+        .expect("the version node was just added");
+    node.entries_mut().clear();
+    node.push(KdlEntry::new(meta_version));
+    version.render(doc)
+}
+
+/// Lowercases hex colors and makes sure that every node gets indented by `fmt`, which skips
+/// nodes without any leading text, such as a node following another one on the same line.
+fn normalize_nodes(doc: &mut KdlDocument) {
+    for node in doc.nodes_mut() {
+        if node.leading().unwrap_or_default().is_empty() {
+            node.set_leading(" ");
+        }
+        for entry in node.entries_mut() {
+            if let KdlValue::String(value) = entry.value() {
+                if parse_hex_color(value).is_some() {
+                    let lowercase = value.to_lowercase();
+                    entry.set_value(KdlValue::String(lowercase));
+                }
+            }
+        }
+        if let Some(children) = node.children_mut() {
+            normalize_nodes(children);
+        }
+    }
+}
+
+/// Records, for every node in document order, whether it is preceded by a blank line. The
+/// first node in a block never is.
+fn find_blank_lines(doc: &KdlDocument, out: &mut Vec<bool>) {
+    for (i, node) in doc.nodes().iter().enumerate() {
+        // the last line of the leading text is the indentation of the node itself
+        let mut lines = node.leading().unwrap_or_default().split('\n');
+        lines.next_back();
+        out.push(i > 0 && lines.any(|line| line.trim().is_empty()));
+        if let Some(children) = node.children() {
+            find_blank_lines(children, out);
+        }
+    }
+}
+
+fn restore_blank_lines(doc: &mut KdlDocument, blank_lines: &mut impl Iterator<Item = bool>) {
+    for node in doc.nodes_mut() {
+        if blank_lines.next() == Some(true) {
+            let leading = format!("\n{}", node.leading().unwrap_or_default());
+            node.set_leading(leading);
+        }
+        if let Some(children) = node.children_mut() {
+            restore_blank_lines(children, blank_lines);
+        }
+    }
+}
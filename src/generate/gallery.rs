@@ -0,0 +1,62 @@
+//! Writes a Markdown gallery of theme families, with a section for every theme that shows its
+//! appearance and the palette colors it uses.
+
+use std::fmt::Write;
+
+use anyhow::Result as Res;
+
+use crate::color::HexColor;
+use crate::schema::json::ThemeFamily as JsonThemeFamily;
+use crate::schema::Appearance;
+
+/// A theme family to show in the gallery.
+pub struct GalleryFamily {
+    /// The path of the theme file, relative to the gallery
+    pub path: String,
+    pub family: JsonThemeFamily,
+    /// The palette colors used by each theme of `family`, in the same order as its themes
+    pub palettes: Vec<Vec<(String, HexColor)>>,
+}
+
+/// Renders the gallery of `families`, whose swatches are images in `swatch_dir` named after the
+/// hex code of their color without the `#`. The output only depends on the families, so that
+/// regenerating an unchanged gallery does not change it.
+pub fn gallery_markdown(families: &[GalleryFamily], swatch_dir: &str) -> Res<String> {
+    let mut out = String::from(
+        "# Themes\n\n<!-- generated by `zeddy gallery`, changes to this file will be overwritten -->\n",
+    );
+    for GalleryFamily {
+        path,
+        family,
+        palettes,
+    } in families
+    {
+        write!(
+            out,
+            "\n## {}\n\nBy {}, from [`{path}`]({})\n",
+            family.meta.name,
+            family.meta.author,
+            path.replace(' ', "%20")
+        )?;
+        for (theme, palette) in family.themes.iter().zip(palettes) {
+            let appearance = match theme.appearance {
+                Appearance::Light => "Light",
+                Appearance::Dark => "Dark",
+            };
+            write!(out, "\n### {}\n\n**{appearance}**\n", theme.name)?;
+            if palette.is_empty() {
+                continue;
+            }
+            out.push_str("\n| | Name | Hex |\n| --- | --- | --- |\n");
+            for (name, color) in palette {
+                let hex = color.to_string();
+                writeln!(
+                    out,
+                    "| ![{hex}]({swatch_dir}/{}.svg) | `{name}` | `{hex}` |",
+                    &hex[1..]
+                )?;
+            }
+        }
+    }
+    Ok(out)
+}
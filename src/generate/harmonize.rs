@@ -0,0 +1,70 @@
+//! Snaps the colors of a palette to a few lightness levels and a range of chroma in `OKLCH`, so
+//! that colors which are meant to stand out as much as each other actually do.
+
+use std::collections::BTreeMap;
+
+use crate::color::palette::RawPalette;
+use crate::color::{BaseColorKind, HexColor};
+
+/// Colors with less chroma than this are grays, such as backgrounds and comments, whose
+/// lightness is picked on its own and which would only get tinted by a minimum chroma.
+const NEUTRAL_CHROMA: f32 = 0.02;
+
+/// How `harmonize_palette` adjusts colors, all in `OKLCH`. Colors keep their lightness when there
+/// are no `levels`, and their chroma when there is no bound for it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Harmony {
+    /// The lightness levels from 0 to 1, the closest of which every color is moved to
+    pub levels: Vec<f32>,
+    /// The chroma that duller colors are raised to
+    pub min_chroma: Option<f32>,
+    /// The chroma that more vivid colors are lowered to
+    pub max_chroma: Option<f32>,
+}
+
+impl Harmony {
+    /// Adjusts a single color, keeping its hue and alpha.
+    pub fn apply(&self, color: HexColor) -> HexColor {
+        let mut oklcha = color.to_oklcha();
+        if let Some(&level) = self
+            .levels
+            .iter()
+            .min_by(|x, y| (*x - oklcha.l).abs().total_cmp(&(*y - oklcha.l).abs()))
+        {
+            oklcha.l = level;
+        }
+        if let Some(min) = self.min_chroma {
+            oklcha.chroma = oklcha.chroma.max(min);
+        }
+        if let Some(max) = self.max_chroma {
+            oklcha.chroma = oklcha.chroma.min(max);
+        }
+        HexColor::from_oklcha(oklcha)
+    }
+}
+
+/// Returns the new colors of the palette entries that `harmony` changes, with their modifiers
+/// applied first. Only entries with a hex color are adjusted, since entries that refer to other
+/// colors follow them and Display P3 colors would be written back as sRGB, and entries with
+/// `lock` and grays are left alone.
+pub fn harmonize_palette(palette: &RawPalette, harmony: &Harmony) -> BTreeMap<String, HexColor> {
+    palette
+        .colors
+        .iter()
+        .filter(|node| !node.lock)
+        .filter_map(|node| match node.base {
+            BaseColorKind::Hex(hex) => {
+                let modifiers = node.modifiers.or_preserve_hue(palette.preserve_hue);
+                Some((node, hex.apply_modifiers(modifiers)))
+            }
+            BaseColorKind::PaletteReference(_)
+            | BaseColorKind::On(_)
+            | BaseColorKind::DisplayP3(_) => None,
+        })
+        .filter(|(_, color)| color.to_oklcha().chroma >= NEUTRAL_CHROMA)
+        .filter_map(|(node, color)| {
+            let harmonized = harmony.apply(color);
+            (harmonized != color).then(|| (node.name.clone(), harmonized))
+        })
+        .collect()
+}
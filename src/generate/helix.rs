@@ -0,0 +1,207 @@
+//! Writes a generated theme as a Helix `theme.toml`. Colors that are in the palette of the theme
+//! family are written as references to a `[palette]` table, so that Helix themes keep the names.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result as Res};
+
+use crate::color::palette::ResolvedPalette;
+use crate::color::HexColor;
+use crate::import::{HelixField, HELIX_SCOPES, HELIX_UI};
+use crate::schema::json::{JsonTheme, StyleEntry};
+use crate::schema::FontStyle;
+
+/// The scopes that Helix underlines diagnostics with, and the style keys of their colors.
+const DIAGNOSTICS: [(&str, &str); 4] = [
+    ("diagnostic.error", "error"),
+    ("diagnostic.warning", "warning"),
+    ("diagnostic.info", "info"),
+    ("diagnostic.hint", "hint"),
+];
+
+/// A Helix style, with its colors already written as palette names or hex colors.
+#[derive(Default)]
+struct Style {
+    fg: Option<String>,
+    bg: Option<String>,
+    /// The color of a curly underline
+    underline: Option<String>,
+    modifiers: Vec<&'static str>,
+}
+
+impl Style {
+    /// Writes the style as a TOML value: a plain string for a style with only a foreground, or
+    /// else an inline table.
+    fn to_toml(&self) -> String {
+        if let (Some(fg), None, None, []) =
+            (&self.fg, &self.bg, &self.underline, &self.modifiers[..])
+        {
+            return quote(fg);
+        }
+        let mut fields = Vec::new();
+        if let Some(fg) = &self.fg {
+            fields.push(format!("fg = {}", quote(fg)));
+        }
+        if let Some(bg) = &self.bg {
+            fields.push(format!("bg = {}", quote(bg)));
+        }
+        if let Some(color) = &self.underline {
+            fields.push(format!(
+                "underline = {{ color = {}, style = \"curl\" }}",
+                quote(color)
+            ));
+        }
+        if !self.modifiers.is_empty() {
+            let modifiers = self.modifiers.iter().map(|x| quote(x)).collect::<Vec<_>>();
+            fields.push(format!("modifiers = [{}]", modifiers.join(", ")));
+        }
+        format!("{{ {} }}", fields.join(", "))
+    }
+}
+
+/// Names colors after the palette, keeping track of the palette colors that were used.
+struct Colors<'a> {
+    /// Helix has no transparency, so colors are drawn over the background first
+    background: HexColor,
+    names: HashMap<HexColor, &'a str>,
+    used: BTreeMap<&'a str, HexColor>,
+}
+
+impl Colors<'_> {
+    fn name(&mut self, color: HexColor) -> String {
+        let color = color.over(self.background);
+        if let Some(name) = self.names.get(&color) {
+            self.used.insert(name, color);
+            (*name).to_owned()
+        } else {
+            let HexColor([r, g, b, _]) = color;
+            format!("#{r:02x}{g:02x}{b:02x}")
+        }
+    }
+}
+
+fn quote(text: &str) -> String {
+    toml::Value::String(text.to_owned()).to_string()
+}
+
+/// Writes a key bare when TOML allows it. Scopes have dots, which would make them nested tables.
+fn key(name: &str) -> String {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        name.to_owned()
+    } else {
+        quote(name)
+    }
+}
+
+/// Writes a theme as a Helix `theme.toml`, with the UI colors of [`HELIX_UI`], the cursor and
+/// selection of the first player, and the syntax scopes of [`HELIX_SCOPES`]. Colors that match
+/// a palette color are referred to by its name, the first one in order when several match.
+pub fn export_helix(theme: &JsonTheme, palette: &ResolvedPalette) -> Res<String> {
+    let background = ["editor.background", "background"]
+        .iter()
+        .find_map(|key| theme.color(key))
+        .ok_or_else(|| anyhow!("the theme {:?} has no background color", theme.name))?
+        .over(HexColor([0, 0, 0, 255]));
+    let mut names = palette
+        .colors
+        .iter()
+        .filter(|(_, color)| color.0[3] == 255)
+        .collect::<Vec<_>>();
+    names.sort_unstable_by(|x, y| x.0.cmp(y.0));
+    let mut colors = Colors {
+        background,
+        names: HashMap::new(),
+        used: BTreeMap::new(),
+    };
+    for (name, color) in names {
+        colors.names.entry(*color).or_insert(name);
+    }
+
+    let mut styles = BTreeMap::<&str, Style>::new();
+    for (scope, field, key) in HELIX_UI {
+        let Some(color) = theme.color(key) else {
+            continue;
+        };
+        let style = styles.entry(scope).or_default();
+        let slot = match field {
+            HelixField::Fg => &mut style.fg,
+            HelixField::Bg => &mut style.bg,
+        };
+        slot.get_or_insert_with(|| colors.name(color));
+    }
+    for (scope, key) in DIAGNOSTICS {
+        if let Some(color) = theme.color(key) {
+            styles.entry(scope).or_default().underline = Some(colors.name(color));
+        }
+    }
+    player_styles(theme, &mut colors, &mut styles);
+    syntax_styles(theme, &mut colors, &mut styles);
+
+    let mut out = format!("# {}\n\n", theme.name);
+    for (scope, style) in &styles {
+        writeln!(out, "{} = {}", key(scope), style.to_toml())?;
+    }
+    if !colors.used.is_empty() {
+        writeln!(out, "\n[palette]")?;
+        for (name, color) in &colors.used {
+            let HexColor([r, g, b, _]) = color;
+            writeln!(out, "{} = \"#{r:02x}{g:02x}{b:02x}\"", key(name))?;
+        }
+    }
+    Ok(out)
+}
+
+/// Styles the cursor and selection after the first player, with the background as the color of
+/// the text under the cursor.
+fn player_styles(theme: &JsonTheme, colors: &mut Colors, styles: &mut BTreeMap<&str, Style>) {
+    let Some(StyleEntry::Players(players)) = theme.style.get("players") else {
+        return;
+    };
+    let Some(player) = players.first() else {
+        return;
+    };
+    if let Some(cursor) = player.cursor {
+        for scope in ["ui.cursor", "ui.cursor.primary"] {
+            let style = styles.entry(scope).or_default();
+            style.fg = Some(colors.name(colors.background));
+            style.bg = Some(colors.name(cursor));
+        }
+    }
+    if let Some(selection) = player.selection {
+        for scope in ["ui.selection", "ui.selection.primary"] {
+            styles.entry(scope).or_default().bg = Some(colors.name(selection));
+        }
+    }
+}
+
+fn syntax_styles(theme: &JsonTheme, colors: &mut Colors, styles: &mut BTreeMap<&str, Style>) {
+    let Some(StyleEntry::Syntax(syntax)) = theme.style.get("syntax") else {
+        return;
+    };
+    for (key, scope) in HELIX_SCOPES {
+        let Some(entry) = syntax.get(key) else {
+            continue;
+        };
+        let mut modifiers = Vec::new();
+        if entry.font_weight.is_some_and(|weight| weight.get() >= 600) {
+            modifiers.push("bold");
+        }
+        if entry.font_style == Some(FontStyle::Italic) {
+            modifiers.push("italic");
+        }
+        let style = Style {
+            fg: entry.color.map(|color| colors.name(color)),
+            bg: entry.background.map(|color| colors.name(color)),
+            underline: None,
+            modifiers,
+        };
+        if style.fg.is_some() || style.bg.is_some() || !style.modifiers.is_empty() {
+            styles.insert(scope, style);
+        }
+    }
+}
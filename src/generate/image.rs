@@ -0,0 +1,439 @@
+//! Renders themes to SVG images: a strip of palette swatches, and a mock editor window that shows
+//! the theme on a short piece of code, sized for READMEs and extension galleries.
+
+use std::fmt::{Arguments, Write};
+
+use anyhow::Result as Res;
+
+use crate::color::HexColor;
+use crate::schema::json::JsonTheme;
+
+/// The size of a single swatch of a palette strip.
+const SWATCH_SIZE: u32 = 32;
+
+const WIDTH: u32 = 720;
+const TITLE_BAR_HEIGHT: u32 = 32;
+const TAB_BAR_HEIGHT: u32 = 32;
+const STATUS_BAR_HEIGHT: u32 = 28;
+const PANEL_WIDTH: u32 = 160;
+const TAB_WIDTH: u32 = 110;
+const GUTTER_WIDTH: u32 = 48;
+const LINE_HEIGHT: u32 = 20;
+const EDITOR_PADDING: u32 = 12;
+/// The 1-based line of [`CODE`] that the cursor is on.
+const ACTIVE_LINE: usize = 5;
+
+/// The code shown in the editor, as a list of lines made of a syntax scope and the text that is
+/// highlighted with it. An empty scope uses the editor's foreground color.
+const CODE: &[&[(&str, &str)]] = &[
+    &[
+        ("keyword", "const "),
+        ("constant", "LOUD"),
+        ("punctuation", ": "),
+        ("type", "bool"),
+        ("operator", " = "),
+        ("boolean", "true"),
+        ("punctuation", ";"),
+    ],
+    &[],
+    &[("comment", "// Greets everyone on the list")],
+    &[
+        ("keyword", "fn "),
+        ("function", "greet"),
+        ("punctuation.bracket", "("),
+        ("variable", "names"),
+        ("punctuation", ": &["),
+        ("type", "&str"),
+        ("punctuation", "]) -> "),
+        ("type", "usize"),
+        ("punctuation.bracket", " {"),
+    ],
+    &[
+        ("", "    "),
+        ("keyword", "let mut "),
+        ("variable", "count"),
+        ("operator", " = "),
+        ("number", "0"),
+        ("punctuation", ";"),
+    ],
+    &[
+        ("", "    "),
+        ("keyword", "for "),
+        ("variable", "name "),
+        ("keyword", "in "),
+        ("variable", "names"),
+        ("punctuation.bracket", " {"),
+    ],
+    &[
+        ("", "        "),
+        ("function", "println!"),
+        ("punctuation.bracket", "("),
+        ("string", "\"Hello, {name}!\""),
+        ("punctuation.bracket", ")"),
+        ("punctuation", ";"),
+    ],
+    &[
+        ("", "        "),
+        ("variable", "count"),
+        ("operator", " += "),
+        ("number", "1"),
+        ("punctuation", ";"),
+    ],
+    &[("", "    "), ("punctuation.bracket", "}")],
+    &[("", "    "), ("variable", "count")],
+    &[("punctuation.bracket", "}")],
+];
+
+/// The entries of the project panel, with whether they are the open file.
+const PROJECT_ENTRIES: &[(&str, bool)] = &[
+    ("src", false),
+    ("    lib.rs", false),
+    ("    main.rs", true),
+    ("Cargo.toml", false),
+    ("README.md", false),
+];
+
+const STYLE: &str = ".ui{font-family:sans-serif;font-size:12px}\
+.code{font-family:'JetBrains Mono',Menlo,Consolas,monospace;font-size:13px;white-space:pre}\
+.middle{text-anchor:middle}.end{text-anchor:end}";
+
+/// Renders the colors of a palette as a row of square swatches, in the given order. Each swatch
+/// has a tooltip with the name and hex code of its color.
+pub fn palette_svg(colors: &[(String, HexColor)]) -> String {
+    let width = SWATCH_SIZE * index(colors.len()).max(1);
+    let mut svg = Svg::new(width, SWATCH_SIZE);
+    for (idx, (name, color)) in colors.iter().enumerate() {
+        let x = SWATCH_SIZE * index(idx);
+        svg.push(format_args!(
+            r#"<rect x="{x}" y="0" width="{SWATCH_SIZE}" height="{SWATCH_SIZE}" {}><title>{} {color}</title></rect>"#,
+            fill(*color),
+            escape(name)
+        ));
+    }
+    svg.finish()
+}
+
+/// Renders a single swatch of a color, to be shown next to its name.
+pub fn swatch_svg(color: HexColor) -> String {
+    let mut svg = Svg::new(SWATCH_SIZE / 2, SWATCH_SIZE / 2);
+    svg.rect(0, 0, SWATCH_SIZE / 2, SWATCH_SIZE / 2, color);
+    svg.finish()
+}
+
+/// Renders a mock editor window with a project panel, a tab bar, a status bar and some
+/// highlighted code, using the colors of `theme`. Keys that the theme does not set fall back to
+/// related keys, and finally to black and white.
+pub fn editor_svg(theme: &JsonTheme) -> String {
+    let editor_height = EDITOR_PADDING * 2 + LINE_HEIGHT * index(CODE.len());
+    let height = TITLE_BAR_HEIGHT + TAB_BAR_HEIGHT + editor_height + STATUS_BAR_HEIGHT;
+    let colors = Colors::new(theme);
+    let mut svg = Svg::new(WIDTH, height);
+    svg.rect(0, 0, WIDTH, height, colors.background);
+    title_bar(&mut svg, &colors, &theme.name);
+    project_panel(&mut svg, &colors, height - STATUS_BAR_HEIGHT);
+    tab_bar(&mut svg, &colors);
+    editor(&mut svg, &colors, editor_height);
+    status_bar(&mut svg, &colors, height - STATUS_BAR_HEIGHT);
+    borders(&mut svg, &colors, height - STATUS_BAR_HEIGHT);
+    svg.finish()
+}
+
+/// The title bar with window controls and the name of the theme.
+fn title_bar(svg: &mut Svg, colors: &Colors, name: &str) {
+    let background = colors.get(&["title_bar.background"], colors.background);
+    svg.rect(0, 0, WIDTH, TITLE_BAR_HEIGHT, background);
+    for (idx, [r, g, b]) in [[0xff, 0x5f, 0x57], [0xfe, 0xbc, 0x2e], [0x28, 0xc8, 0x40]]
+        .into_iter()
+        .enumerate()
+    {
+        svg.push(format_args!(
+            r#"<circle cx="{}" cy="{}" r="6" {}/>"#,
+            16 + 20 * index(idx),
+            TITLE_BAR_HEIGHT / 2,
+            fill(HexColor([r, g, b, u8::MAX]))
+        ));
+    }
+    svg.text(WIDTH / 2, 20, "ui middle", &[(colors.muted, name)]);
+}
+
+/// The project panel on the left, which ends at `bottom`.
+fn project_panel(svg: &mut Svg, colors: &Colors, bottom: u32) {
+    let background = colors.get(&["panel.background"], colors.background);
+    svg.rect(
+        0,
+        TITLE_BAR_HEIGHT,
+        PANEL_WIDTH,
+        bottom - TITLE_BAR_HEIGHT,
+        background,
+    );
+    for (idx, (name, open)) in PROJECT_ENTRIES.iter().enumerate() {
+        let y = TITLE_BAR_HEIGHT + 8 + 22 * index(idx);
+        let color = if *open {
+            let selected = colors.get(
+                &["element.selected", "ghost_element.selected"],
+                colors.border,
+            );
+            svg.rect(0, y, PANEL_WIDTH, 22, selected);
+            colors.text
+        } else {
+            colors.muted
+        };
+        svg.text(12, y + 15, "ui", &[(color, name)]);
+    }
+}
+
+/// The tab bar above the editor, with the open file as the active tab.
+fn tab_bar(svg: &mut Svg, colors: &Colors) {
+    let background = colors.get(&["tab_bar.background"], colors.background);
+    svg.rect(
+        PANEL_WIDTH,
+        TITLE_BAR_HEIGHT,
+        WIDTH - PANEL_WIDTH,
+        TAB_BAR_HEIGHT,
+        background,
+    );
+    for (idx, (name, active)) in [("main.rs", true), ("lib.rs", false)].iter().enumerate() {
+        let x = PANEL_WIDTH + TAB_WIDTH * index(idx);
+        let (key, color) = if *active {
+            ("tab.active_background", colors.text)
+        } else {
+            ("tab.inactive_background", colors.muted)
+        };
+        let tab = colors.get(&[key], background);
+        svg.rect(x, TITLE_BAR_HEIGHT, TAB_WIDTH, TAB_BAR_HEIGHT, tab);
+        svg.text(x + 16, TITLE_BAR_HEIGHT + 20, "ui", &[(color, name)]);
+    }
+}
+
+/// The editor with line numbers and highlighted code.
+fn editor(svg: &mut Svg, colors: &Colors, height: u32) {
+    let top = TITLE_BAR_HEIGHT + TAB_BAR_HEIGHT;
+    let background = colors.get(&["editor.background"], colors.background);
+    let gutter = colors.get(&["editor.gutter.background"], background);
+    let foreground = colors.get(&["editor.foreground"], colors.text);
+    let line_number = colors.get(&["editor.line_number"], colors.muted);
+    let active_line_number = colors.get(&["editor.active_line_number"], colors.text);
+    svg.rect(PANEL_WIDTH, top, WIDTH - PANEL_WIDTH, height, background);
+    svg.rect(PANEL_WIDTH, top, GUTTER_WIDTH, height, gutter);
+    for (idx, tokens) in CODE.iter().enumerate() {
+        let y = top + EDITOR_PADDING + LINE_HEIGHT * index(idx);
+        let active = idx + 1 == ACTIVE_LINE;
+        if active {
+            if let Some(color) = colors.theme.color("editor.active_line.background") {
+                svg.rect(PANEL_WIDTH, y, WIDTH - PANEL_WIDTH, LINE_HEIGHT, color);
+            }
+        }
+        let number = (idx + 1).to_string();
+        let number_color = if active {
+            active_line_number
+        } else {
+            line_number
+        };
+        let baseline = y + 14;
+        let gutter_end = PANEL_WIDTH + GUTTER_WIDTH;
+        svg.text(
+            gutter_end - 12,
+            baseline,
+            "code end",
+            &[(number_color, &number)],
+        );
+        let spans = tokens
+            .iter()
+            .map(|(scope, text)| (colors.syntax(scope, foreground), *text))
+            .collect::<Vec<_>>();
+        svg.text(gutter_end + 12, baseline, "code", &spans);
+    }
+}
+
+/// The borders between the parts of the window, which are drawn over them.
+fn borders(svg: &mut Svg, colors: &Colors, status_bar_top: u32) {
+    let tab_bar_bottom = TITLE_BAR_HEIGHT + TAB_BAR_HEIGHT;
+    svg.line(0, TITLE_BAR_HEIGHT, WIDTH, TITLE_BAR_HEIGHT, colors.border);
+    svg.line(
+        PANEL_WIDTH,
+        tab_bar_bottom,
+        WIDTH,
+        tab_bar_bottom,
+        colors.border,
+    );
+    svg.line(
+        PANEL_WIDTH,
+        TITLE_BAR_HEIGHT,
+        PANEL_WIDTH,
+        status_bar_top,
+        colors.border,
+    );
+    svg.line(0, status_bar_top, WIDTH, status_bar_top, colors.border);
+}
+
+/// The status bar at the bottom of the window, which starts at `top`.
+fn status_bar(svg: &mut Svg, colors: &Colors, top: u32) {
+    let background = colors.get(&["status_bar.background"], colors.background);
+    svg.rect(0, top, WIDTH, STATUS_BAR_HEIGHT, background);
+    let status = format!("main.rs  Rust  Ln {ACTIVE_LINE}, Col 9");
+    svg.text(12, top + 18, "ui", &[(colors.muted, &status)]);
+}
+
+/// Rasterizes an SVG image to PNG, using the fonts installed on the system for text.
+#[cfg(feature = "png")]
+pub fn rasterize(svg: &str) -> Res<Vec<u8>> {
+    use resvg::{tiny_skia, usvg};
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| anyhow::anyhow!("the image is empty"))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap.encode_png()?)
+}
+
+/// Rasterizes an SVG image to PNG, which needs the `png` feature.
+#[cfg(not(feature = "png"))]
+pub fn rasterize(_svg: &str) -> Res<Vec<u8>> {
+    Err(anyhow::anyhow!(
+        "zeddy was built without PNG support, reinstall it with `--features png`"
+    ))
+}
+
+/// Looks up the colors of a theme, falling back to other keys when a key is not set.
+struct Colors<'a> {
+    theme: &'a JsonTheme,
+    background: HexColor,
+    text: HexColor,
+    muted: HexColor,
+    border: HexColor,
+}
+
+impl<'a> Colors<'a> {
+    fn new(theme: &'a JsonTheme) -> Self {
+        let background = theme
+            .color("background")
+            .unwrap_or(HexColor([0, 0, 0, u8::MAX]));
+        let text = theme
+            .color("text")
+            .or_else(|| theme.color("editor.foreground"))
+            .unwrap_or(HexColor([u8::MAX; 4]));
+        let muted = theme.color("text.muted").unwrap_or(text);
+        let border = theme.color("border").unwrap_or(muted);
+        Self {
+            theme,
+            background,
+            text,
+            muted,
+            border,
+        }
+    }
+
+    /// Returns the color of the first of `keys` that the theme sets, or `default`.
+    fn get(&self, keys: &[&str], default: HexColor) -> HexColor {
+        keys.iter()
+            .find_map(|key| self.theme.color(key))
+            .unwrap_or(default)
+    }
+
+    /// Returns the color of a syntax scope, falling back to its parent scopes the way Zed does,
+    /// so that `punctuation.bracket` uses the color of `punctuation` if it has none.
+    fn syntax(&self, scope: &str, default: HexColor) -> HexColor {
+        let mut scope = scope;
+        while !scope.is_empty() {
+            if let Some(color) = self.theme.syntax_color(scope) {
+                return color;
+            }
+            scope = scope.rsplit_once('.').map_or("", |(parent, _)| parent);
+        }
+        default
+    }
+}
+
+struct Svg {
+    width: u32,
+    height: u32,
+    body: String,
+}
+
+impl Svg {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            body: String::new(),
+        }
+    }
+
+    fn push(&mut self, element: Arguments<'_>) {
+        // writing to a `String` cannot fail
+        let _ = self.body.write_fmt(element);
+    }
+
+    fn rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: HexColor) {
+        self.push(format_args!(
+            r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" {}/>"#,
+            fill(color)
+        ));
+    }
+
+    fn line(&mut self, x1: u32, y1: u32, x2: u32, y2: u32, color: HexColor) {
+        self.push(format_args!(
+            r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" {}/>"#,
+            paint("stroke", color)
+        ));
+    }
+
+    /// Adds a line of text made of spans with their own colors.
+    fn text(&mut self, x: u32, y: u32, class: &str, spans: &[(HexColor, &str)]) {
+        self.push(format_args!(
+            r#"<text x="{x}" y="{y}" class="{class}" xml:space="preserve">"#
+        ));
+        for (color, text) in spans {
+            self.push(format_args!(
+                "<tspan {}>{}</tspan>",
+                fill(*color),
+                escape(text)
+            ));
+        }
+        self.body.push_str("</text>");
+    }
+
+    fn finish(self) -> String {
+        let Self {
+            width,
+            height,
+            body,
+        } = self;
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}"><style>{STYLE}</style>{body}</svg>
+"#
+        )
+    }
+}
+
+fn index(idx: usize) -> u32 {
+    u32::try_from(idx).unwrap_or(u32::MAX)
+}
+
+/// Returns the `fill` attributes for a color. SVG 1.1 does not have hex colors with an alpha
+/// channel, so it is given as a separate opacity.
+fn fill(color: HexColor) -> String {
+    paint("fill", color)
+}
+
+fn paint(attribute: &str, HexColor([r, g, b, a]): HexColor) -> String {
+    let rgb = format!(r##"{attribute}="#{r:02x}{g:02x}{b:02x}""##);
+    if a == u8::MAX {
+        rgb
+    } else {
+        format!(
+            r#"{rgb} {attribute}-opacity="{:.3}""#,
+            f32::from(a) / f32::from(u8::MAX)
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,59 +1,265 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 use anyhow::{anyhow, Result as Res};
-use log::info;
+use rayon::prelude::*;
+use serde_json::Value;
+use tracing::{debug, info, info_span};
 
-use crate::color::palette::ResolvedPalette;
+use crate::color::palette::{RawPalette, ResolvedPalette};
 use crate::color::Color;
-use crate::schema::json::{JsonTheme, Player, StyleEntry, Syntax, ThemeFamily as JsonThemeFamily};
-use crate::schema::kdl::{Action, Modifier, ModifierPath, ThemeFamily};
+use crate::schema::json::{
+    JsonTheme, Player, SchemaVersion, StyleEntry, ThemeFamily as JsonThemeFamily,
+};
+use crate::schema::kdl::{
+    Action, ActionField, Modifier, ModifierPath, Passthrough, TerminalColors, Theme, ThemeFamily,
+};
+use crate::schema::location::Location;
 
-pub fn generate_json(family: ThemeFamily) -> Res<JsonThemeFamily> {
+pub fn generate_json(family: ThemeFamily, version: SchemaVersion) -> Res<JsonThemeFamily> {
+    generate_family(family, version, None)
+}
+
+/// The palette and the themes that were generated the last time, so that a theme family that is
+/// generated again, like when it is watched, can skip what did not change. Inputs are compared by
+/// a fingerprint of their nodes, which includes their locations, so a theme whose nodes moved in
+/// the file is generated again.
+#[derive(Default)]
+pub struct GenerationCache {
+    palette: Option<(u64, ResolvedPalette)>,
+    /// The fingerprint of every theme with its palette and what it generated, by name
+    themes: HashMap<String, (u64, JsonTheme)>,
+}
+
+impl GenerationCache {
+    /// Generates a theme family like [`generate_json`], reusing the resolved palette and the
+    /// themes whose inputs did not change since the last time.
+    pub fn generate(
+        &mut self,
+        family: ThemeFamily,
+        version: SchemaVersion,
+    ) -> Res<JsonThemeFamily> {
+        generate_family(family, version, Some(self))
+    }
+
+    /// Resolves `palette`, or returns what it resolved to the last time if it did not change.
+    fn resolve(&mut self, palette: RawPalette) -> Res<(u64, ResolvedPalette)> {
+        let key = fingerprint(&palette);
+        if let Some((_, resolved)) = self.palette.as_ref().filter(|(old, _)| *old == key) {
+            debug!("Reusing the resolved palette");
+            return Ok((key, resolved.clone()));
+        }
+        let resolved = palette.into_palette().resolve()?;
+        self.palette = Some((key, resolved.clone()));
+        Ok((key, resolved))
+    }
+}
+
+fn fingerprint(value: &impl Debug) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{value:?}").hash(&mut hasher);
+    hasher.finish()
+}
+
+fn generate_family(
+    mut family: ThemeFamily,
+    version: SchemaVersion,
+    mut cache: Option<&mut GenerationCache>,
+) -> Res<JsonThemeFamily> {
+    let _span = info_span!("generate", family = %family.meta.name).entered();
     info!("Generating JSON file from KDL");
+    family.expand_templates()?;
 
     let ThemeFamily {
         meta,
         palette,
-        mut themes,
+        themes,
         common,
+        ..
     } = family;
-    let resolved = palette.into_palette().resolve()?;
-    let mut base_theme_file = JsonThemeFamily {
-        schema: "https://zed.dev/schema/themes/v0.1.0.json".to_owned(),
-        meta,
-        themes: Vec::with_capacity(themes.len()),
+    let (palette_key, resolved) = match cache.as_deref_mut() {
+        Some(cache) => {
+            let (key, resolved) = cache.resolve(palette)?;
+            (Some(key), resolved)
+        }
+        None => (None, palette.into_palette().resolve()?),
     };
-    // merge all themes with the `common` theme if it exists
-    if let Some(common) = common {
-        themes.iter_mut().for_each(|x| x.merge(&common));
+    let previous = cache.as_deref().map(|cache| &cache.themes);
+    // themes do not depend on each other, so they are generated in parallel. Every result is
+    // collected before looking at them, so that the order of the themes and the error that is
+    // reported are the same as when generating them one by one
+    let themes = themes
+        .into_par_iter()
+        .map(|mut theme| {
+            // merge all themes with the `common` theme if it exists
+            if let Some(common) = &common {
+                theme.merge(common);
+            }
+            let key = palette_key.map(|palette| fingerprint(&(palette, version, &theme)));
+            let cached = previous
+                .and_then(|previous| previous.get(&theme.name))
+                .filter(|(old, _)| Some(*old) == key);
+            if let Some((_, json)) = cached {
+                debug!("Reusing theme {:?}", theme.name);
+                return (key, Ok(json.clone()));
+            }
+            (key, generate_theme(theme, &resolved, version))
+        })
+        .collect::<Vec<_>>();
+    if let Some(cache) = cache {
+        // themes that failed or were removed are dropped
+        cache.themes = themes
+            .iter()
+            .filter_map(|(key, json)| {
+                let json = json.as_ref().ok()?;
+                Some((json.name.clone(), ((*key)?, json.clone())))
+            })
+            .collect();
     }
+    Ok(JsonThemeFamily {
+        schema: version.url(),
+        meta,
+        themes: themes
+            .into_iter()
+            .map(|(_, json)| json)
+            .collect::<Res<_>>()?,
+    })
+}
+
+fn generate_theme(
+    theme: Theme,
+    resolved: &ResolvedPalette,
+    version: SchemaVersion,
+) -> Res<JsonTheme> {
     let process = |v: Option<Color>| v.map(|x| resolved.lookup(&x)).transpose();
-    for theme in themes {
-        let mut players = Vec::with_capacity(theme.players.len());
-        for player in theme.players {
-            players.push(Player {
-                cursor: process(player.cursor)?,
-                selection: process(player.selection)?,
-                background: process(player.background)?,
-            });
-        }
+    let mut players = Vec::with_capacity(theme.players.len());
+    for player in theme.players {
+        players.push(Player {
+            cursor: process(player.cursor)?,
+            selection: process(player.selection)?,
+            background: process(player.background)?,
+            extra: parse_json_fields(player.extra)?,
+        });
+    }
 
-        let mut base_json_theme = JsonTheme {
-            name: theme.name,
-            style: HashMap::from_iter([
-                ("players".to_owned(), StyleEntry::Players(players)),
-                ("syntax".to_owned(), StyleEntry::Syntax(HashMap::default())),
-            ]),
-            appearance: theme.appearance,
-        };
-        for Modifier { action, apply } in theme.modifiers {
-            for target in apply {
-                apply_action(&mut base_json_theme, &action, &resolved, &target)?;
+    let mut accents = Vec::with_capacity(theme.accents.len());
+    for accent in &theme.accents {
+        accents.push(resolved.lookup(accent)?);
+    }
+
+    let mut base_json_theme = JsonTheme {
+        name: theme.name,
+        style: BTreeMap::from_iter([
+            ("players".to_owned(), StyleEntry::Players(players)),
+            ("syntax".to_owned(), StyleEntry::Syntax(BTreeMap::default())),
+        ]),
+        appearance: theme.appearance,
+    };
+    if !accents.is_empty() {
+        base_json_theme
+            .style
+            .insert("accents".to_owned(), StyleEntry::Accents(accents));
+    }
+    if let Some(appearance) = theme.window_appearance {
+        base_json_theme.style.insert(
+            "background.appearance".to_owned(),
+            StyleEntry::WindowAppearance(appearance),
+        );
+    }
+    if let Some(terminal) = &theme.terminal {
+        let missing = terminal.missing();
+        if !missing.is_empty() {
+            return Err(theme.location.error(format!(
+                "the terminal colors of theme {:?} are missing {}",
+                base_json_theme.name,
+                missing.join(", ")
+            )));
+        }
+        // inserted before the modifiers are applied so that explicit modifiers still win
+        for (name, color) in terminal.entries() {
+            if let Some(color) = color {
+                base_json_theme.style.insert(
+                    TerminalColors::style_key(name),
+                    StyleEntry::Normal(Some(resolved.lookup(color)?)),
+                );
             }
         }
-        base_theme_file.themes.push(base_json_theme);
     }
-    Ok(base_theme_file)
+    // inserted before the modifiers too, so that a modifier for a key that zeddy learns about
+    // later wins over the copy that was kept when the theme was migrated
+    insert_passthrough_style(&mut base_json_theme, &theme.passthrough)?;
+    for Modifier {
+        if_appearance,
+        action,
+        apply,
+        location,
+    } in theme.modifiers
+    {
+        if if_appearance.is_some_and(|appearance| appearance != base_json_theme.appearance) {
+            continue;
+        }
+        for target in apply {
+            apply_action(&mut base_json_theme, &action, resolved, &target, location)?;
+        }
+    }
+    insert_passthrough_syntax(&mut base_json_theme, theme.passthrough)?;
+    for (from, to) in version.renamed_style_keys() {
+        if let Some(entry) = base_json_theme.style.remove(from) {
+            base_json_theme.style.insert(to.to_owned(), entry);
+        }
+    }
+    if let Some(key) = version
+        .required_style_keys()
+        .find(|key| !base_json_theme.style.contains_key(*key))
+    {
+        return Err(theme.location.error(format!(
+            "theme {:?} does not set `{key}`, which schema version {version} requires",
+            base_json_theme.name
+        )));
+    }
+    Ok(base_json_theme)
+}
+
+fn parse_json_fields(fields: BTreeMap<String, String>) -> Res<BTreeMap<String, Value>> {
+    fields
+        .into_iter()
+        .map(|(name, value)| {
+            let value = serde_json::from_str(&value)
+                .map_err(|e| anyhow!("field {name:?} is not valid JSON: {e}"))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn insert_passthrough_style(base: &mut JsonTheme, passthrough: &Passthrough) -> Res<()> {
+    for entry in &passthrough.style {
+        let value = serde_json::from_str(&entry.value)
+            .map_err(|e| anyhow!("style entry {:?} is not valid JSON: {e}", entry.key))?;
+        base.style
+            .insert(entry.key.clone(), StyleEntry::Other(value));
+    }
+    Ok(())
+}
+
+/// Adds the extra fields of syntax entries, after the modifiers have created the entries.
+fn insert_passthrough_syntax(base: &mut JsonTheme, passthrough: Passthrough) -> Res<()> {
+    if passthrough.syntax.is_empty() {
+        return Ok(());
+    }
+    // a bare `unset` on `style "syntax"` removes the map
+    let Some(StyleEntry::Syntax(syntax_map)) = base.style.get_mut("syntax") else {
+        return Err(anyhow!("Could not get syntax map"));
+    };
+    for entry in passthrough.syntax {
+        let fields = parse_json_fields(entry.fields)?;
+        syntax_map
+            .entry(entry.key)
+            .or_default()
+            .extra
+            .extend(fields);
+    }
+    Ok(())
 }
 
 fn apply_action(
@@ -61,11 +267,16 @@ fn apply_action(
     action: &Action,
     palette: &ResolvedPalette,
     to: &ModifierPath,
+    location: Location,
 ) -> Res<()> {
     match to {
         ModifierPath::Style(path) => {
             if path.starts_with("player") {
-                return Err(anyhow!("`style.player` cannot be modified with modifiers. Use the `theme.players` list instead."));
+                return Err(location.error("`style.player` cannot be modified with modifiers. Use the `theme.players` list instead."));
+            }
+            // `style` items only have a color, so clearing it removes the item
+            if action.clears(ActionField::Color) {
+                base.style.remove(path);
             }
             // Can only apply `color` to `style` items.
             if let Some(color) = &action.color {
@@ -91,19 +302,36 @@ fn process_syntax_path(
     let StyleEntry::Syntax(syntax_map) = base.style.get_mut("syntax").unwrap() else {
         return Err(anyhow!("Could not get syntax map"));
     };
-    let syntax_entry = syntax_map.entry(path.to_owned()).or_insert_with(|| Syntax {
-        color: None,
-        background: None,
-        font_weight: None,
-        font_style: None,
-    });
+    if action.removes_target() {
+        syntax_map.remove(path);
+    }
+    let syntax_entry = if action.sets_any() {
+        syntax_map.entry(path.to_owned()).or_default()
+    } else if let Some(syntax_entry) = syntax_map.get_mut(path) {
+        syntax_entry
+    } else {
+        // there is nothing to clear
+        return Ok(());
+    };
+    for field in action.unset.iter().flatten() {
+        match field {
+            ActionField::Color => syntax_entry.color = None,
+            ActionField::Background => syntax_entry.background = None,
+            ActionField::FontWeight => syntax_entry.font_weight = None,
+            ActionField::FontStyle => syntax_entry.font_style = None,
+        }
+    }
     if let Some(color) = &action.color {
         syntax_entry.color = Some(palette.lookup(color)?);
     }
     if let Some(color) = &action.background {
         syntax_entry.background = Some(palette.lookup(color)?);
     }
-    syntax_entry.font_style.clone_from(&action.font_style);
-    syntax_entry.font_weight = action.font_weight;
+    if let Some(style) = action.font_style {
+        syntax_entry.font_style = Some(style);
+    }
+    if let Some(weight) = action.font_weight {
+        syntax_entry.font_weight = Some(weight);
+    }
     Ok(())
 }
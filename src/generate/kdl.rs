@@ -1,25 +1,30 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, hash::Hash};
 
-use log::{debug, warn};
 use multimap::MultiMap;
+use tracing::debug;
+
+use super::semantic::UsageVisitor;
 
 use crate::{
+    color::naming::Naming,
     color::palette::{PaletteGenerator, RawPalette},
     color::{Color, HexColor},
     schema::json::{StyleEntry, Syntax, ThemeFamily as JsonThemeFamily},
     schema::kdl::{
-        Action, BorrowedModifierPath, Modifier, ModifierPath, Player, Theme, ThemeFamily,
+        Action, BorrowedModifierPath, Modifier, ModifierPath, Passthrough, PassthroughStyle,
+        PassthroughSyntax, Player, TerminalColors, Theme, ThemeFamily,
     },
+    schema::{location::Location, FontStyle, FontWeight},
 };
 
 pub trait StyleVisitor {
     fn visit_syntax(&mut self, _path: BorrowedModifierPath<'_>, _syntax: &Syntax) {}
     fn visit_color(&mut self, _key: Option<BorrowedModifierPath<'_>>, _color: HexColor) {}
-    fn visit_font_weight(&mut self, _key: BorrowedModifierPath<'_>, _weight: u16) {}
-    fn visit_font_style(&mut self, _key: BorrowedModifierPath<'_>, _style: &str) {}
+    fn visit_font_weight(&mut self, _key: BorrowedModifierPath<'_>, _weight: FontWeight) {}
+    fn visit_font_style(&mut self, _key: BorrowedModifierPath<'_>, _style: FontStyle) {}
 }
 
-pub fn visit_styles<V: StyleVisitor>(visitor: &mut V, map: &HashMap<String, StyleEntry>) {
+pub fn visit_styles<V: StyleVisitor>(visitor: &mut V, map: &BTreeMap<String, StyleEntry>) {
     for (key, value) in map {
         match value {
             StyleEntry::Normal(Some(color)) => {
@@ -38,6 +43,11 @@ pub fn visit_styles<V: StyleVisitor>(visitor: &mut V, map: &HashMap<String, Styl
                     }
                 }
             }
+            StyleEntry::Accents(accents) => {
+                for &color in accents {
+                    visitor.visit_color(None, color);
+                }
+            }
             StyleEntry::Syntax(syntax_map) => {
                 for (name, syntax) in syntax_map {
                     let path = BorrowedModifierPath::Syntax(name);
@@ -48,7 +58,7 @@ pub fn visit_styles<V: StyleVisitor>(visitor: &mut V, map: &HashMap<String, Styl
                     if let Some(color) = syntax.background {
                         visitor.visit_color(Some(path), color);
                     }
-                    if let Some(style) = &syntax.font_style {
+                    if let Some(style) = syntax.font_style {
                         visitor.visit_font_style(path, style);
                     }
                     if let Some(weight) = syntax.font_weight {
@@ -56,7 +66,7 @@ pub fn visit_styles<V: StyleVisitor>(visitor: &mut V, map: &HashMap<String, Styl
                     }
                 }
             }
-            StyleEntry::Normal(None) => {}
+            StyleEntry::Normal(None) | StyleEntry::WindowAppearance(_) | StyleEntry::Other(_) => {}
         }
     }
 }
@@ -66,6 +76,9 @@ pub struct ColorVisitor {
     generator: PaletteGenerator,
 }
 impl ColorVisitor {
+    pub fn new(generator: PaletteGenerator) -> Self {
+        Self { generator }
+    }
     pub fn into_inner(self) -> PaletteGenerator {
         self.generator
     }
@@ -80,8 +93,8 @@ impl StyleVisitor for ColorVisitor {
 struct ModifierVisitor<'a> {
     colors: MultiMap<Color, ModifierPath>,
     background: MultiMap<Color, ModifierPath>,
-    font_weight: MultiMap<u16, ModifierPath>,
-    font_style: MultiMap<String, ModifierPath>,
+    font_weight: MultiMap<FontWeight, ModifierPath>,
+    font_style: MultiMap<FontStyle, ModifierPath>,
     palette: &'a PaletteGenerator,
 }
 
@@ -97,42 +110,86 @@ impl<'a> ModifierVisitor<'a> {
     }
     pub fn into_modifiers(self) -> Vec<Modifier> {
         let mut modifiers = vec![];
-        modifiers.extend(self.colors.into_iter().map(|(color, paths)| Modifier {
-            apply: paths.clone(),
-            action: Action {
-                color: Some(color),
-                ..<_>::default()
-            },
-        }));
-        modifiers.extend(self.background.into_iter().map(|(color, paths)| Modifier {
-            apply: paths.clone(),
-            action: Action {
-                background: Some(color),
-                ..<_>::default()
-            },
-        }));
-        modifiers.extend(self.font_style.into_iter().map(|(style, paths)| Modifier {
-            apply: paths.clone(),
-            action: Action {
-                font_style: Some(style.clone()),
-                ..<_>::default()
-            },
-        }));
-        modifiers.extend(
-            self.font_weight
-                .into_iter()
-                .map(|(weight, paths)| Modifier {
-                    apply: paths.clone(),
-                    action: Action {
-                        font_weight: Some(weight),
-                        ..<_>::default()
-                    },
-                }),
-        );
+        push_by_section(&mut modifiers, self.colors, |color| Action {
+            color: Some(color),
+            ..<_>::default()
+        });
+        push_by_section(&mut modifiers, self.background, |color| Action {
+            background: Some(color),
+            ..<_>::default()
+        });
+        push_by_section(&mut modifiers, self.font_style, |style| Action {
+            font_style: Some(style),
+            ..<_>::default()
+        });
+        push_by_section(&mut modifiers, self.font_weight, |weight| Action {
+            font_weight: Some(weight),
+            ..<_>::default()
+        });
+        // the maps are unordered, so order the modifiers by section and then by the first path
+        // they apply to in order for migrating the same file twice to give the same result
+        modifiers.sort_by(|x, y| {
+            section(&x.apply[0])
+                .cmp(&section(&y.apply[0]))
+                .then_with(|| action_kind(&x.action).cmp(&action_kind(&y.action)))
+                .then_with(|| x.apply[0].cmp(&y.apply[0]))
+        });
         modifiers
     }
 }
 
+/// The section of the theme that a path belongs to: the first segment of a `style` key, such
+/// as `editor` for `editor.background`. All `syntax` paths are in one section after the others.
+fn section(path: &ModifierPath) -> (bool, &str) {
+    match path {
+        ModifierPath::Style(key) => (false, key.split('.').next().unwrap_or(key)),
+        ModifierPath::Syntax(_) => (true, ""),
+    }
+}
+
+/// Turns every value of a visitor's map into one modifier per section of the theme that it is
+/// used in, so that modifiers for related keys end up next to each other.
+fn push_by_section<T: Clone + Eq + Hash>(
+    modifiers: &mut Vec<Modifier>,
+    map: MultiMap<T, ModifierPath>,
+    action: impl Fn(T) -> Action,
+) {
+    for (value, mut paths) in map {
+        // sorting keeps the paths of a section in order and lets each section be split off the
+        // end without copying the section names. The modifiers are sorted again afterwards
+        paths.sort_by(|x, y| section(x).cmp(&section(y)));
+        while let Some(last) = paths.last() {
+            let last = section(last);
+            let len = paths
+                .iter()
+                .rev()
+                .take_while(|x| section(x) == last)
+                .count();
+            let apply = paths.split_off(paths.len() - len);
+            modifiers.push(Modifier {
+                if_appearance: None,
+                apply,
+                action: action(value.clone()),
+                location: Location::default(),
+            });
+        }
+    }
+}
+
+/// The order that migrated modifiers in a section are written in: colors, then backgrounds,
+/// then font styles and weights.
+fn action_kind(action: &Action) -> u8 {
+    if action.color.is_some() {
+        0
+    } else if action.background.is_some() {
+        1
+    } else if action.font_style.is_some() {
+        2
+    } else {
+        3
+    }
+}
+
 impl StyleVisitor for ModifierVisitor<'_> {
     fn visit_color(&mut self, path: Option<BorrowedModifierPath<'_>>, color: HexColor) {
         let Some(path) = path else {
@@ -141,38 +198,140 @@ impl StyleVisitor for ModifierVisitor<'_> {
         let color = self.palette.lookup(color);
         self.colors.insert(color, path.into_owned());
     }
-    fn visit_font_style(&mut self, path: BorrowedModifierPath<'_>, style: &str) {
-        self.font_style.insert(style.to_owned(), path.into_owned());
+    fn visit_font_style(&mut self, path: BorrowedModifierPath<'_>, style: FontStyle) {
+        self.font_style.insert(style, path.into_owned());
     }
-    fn visit_font_weight(&mut self, path: BorrowedModifierPath<'_>, weight: u16) {
+    fn visit_font_weight(&mut self, path: BorrowedModifierPath<'_>, weight: FontWeight) {
         self.font_weight.insert(weight, path.into_owned());
     }
 }
 
-pub fn generate_kdl(theme_family: JsonThemeFamily) -> ThemeFamily {
+/// Moves the `terminal.ansi.*` colors out of the style map and into a terminal block
+/// if the theme defines every color that a terminal block requires.
+fn extract_terminal_colors(
+    style: &mut BTreeMap<String, StyleEntry>,
+    palette: &PaletteGenerator,
+) -> Option<TerminalColors> {
+    let mut terminal = TerminalColors::default();
+    for (name, color) in terminal.entries_mut() {
+        if let Some(StyleEntry::Normal(Some(hex))) = style.get(&TerminalColors::style_key(name)) {
+            *color = Some(palette.lookup(*hex));
+        }
+    }
+    if !terminal.missing().is_empty() {
+        return None;
+    }
+    debug!("Translating terminal colors");
+    for (name, color) in terminal.entries() {
+        if color.is_some() {
+            style.remove(&TerminalColors::style_key(name));
+        }
+    }
+    Some(terminal)
+}
+
+/// Whether a style entry has the shape that zeddy expects for its key. Since the entries are
+/// untagged, an entry that Zed added after zeddy was written can look like a known one.
+fn is_known_entry(key: &str, entry: &StyleEntry) -> bool {
+    match entry {
+        StyleEntry::Syntax(_) => key == "syntax",
+        StyleEntry::Players(_) => key == "players",
+        StyleEntry::Accents(_) => key == "accents",
+        StyleEntry::WindowAppearance(_) => key == "background.appearance",
+        StyleEntry::Normal(_) => true,
+        StyleEntry::Other(_) => false,
+    }
+}
+
+/// Moves the entries and fields that cannot be expressed in the KDL format out of the style map
+/// so that they are kept as JSON.
+fn extract_passthrough(style: &mut BTreeMap<String, StyleEntry>) -> Passthrough {
+    let mut passthrough = Passthrough::default();
+    let unknown = style
+        .iter()
+        .filter(|(key, entry)| !is_known_entry(key, entry))
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+    for key in unknown {
+        let entry = style.remove(&key).expect("the key is in the map");
+        debug!("Keeping unknown style entry {key:?} as JSON");
+        passthrough.style.push(PassthroughStyle {
+            key,
+            value: serde_json::to_string(&entry).expect("style entries are valid JSON"),
+        });
+    }
+    if let Some(StyleEntry::Syntax(syntax_map)) = style.get_mut("syntax") {
+        for (key, syntax) in syntax_map {
+            if !syntax.extra.is_empty() {
+                debug!("Keeping unknown fields of syntax entry {key:?} as JSON");
+                passthrough.syntax.push(PassthroughSyntax {
+                    key: key.clone(),
+                    fields: to_json_text(std::mem::take(&mut syntax.extra)),
+                });
+            }
+        }
+    }
+    passthrough
+}
+
+fn to_json_text(fields: BTreeMap<String, serde_json::Value>) -> BTreeMap<String, String> {
+    fields
+        .into_iter()
+        .map(|(name, value)| (name, value.to_string()))
+        .collect()
+}
+
+/// Converts a JSON theme family into the KDL format, building the palette with `generator` and
+/// renaming its colors after what they are used for with semantic naming.
+pub fn generate_kdl(
+    theme_family: JsonThemeFamily,
+    generator: PaletteGenerator,
+    naming: Naming,
+) -> ThemeFamily {
     debug!("Converting from JSON to KDL");
     let mut base_theme = ThemeFamily {
         meta: theme_family.meta,
         palette: RawPalette::default(),
         themes: vec![],
         common: None,
+        templates: vec![],
+        instances: vec![],
+        includes: vec![],
     };
-    let mut color_visitor = ColorVisitor::default();
+    let mut themes = theme_family.themes;
+    // taken out first so that colors in entries that are kept as JSON do not end up in the palette
+    let passthroughs = themes
+        .iter_mut()
+        .map(|theme| extract_passthrough(&mut theme.style))
+        .collect::<Vec<_>>();
+    let mut color_visitor = ColorVisitor::new(generator);
     debug!("Generating palettes");
-    for theme in &theme_family.themes {
+    for theme in &themes {
         visit_styles(&mut color_visitor, &theme.style);
     }
 
-    let palette_generator = color_visitor.into_inner();
-    debug!("Generated palette {:?}", palette_generator);
+    let mut palette_generator = color_visitor.into_inner();
+    if naming == Naming::Semantic {
+        let mut usage_visitor = UsageVisitor::default();
+        for theme in &themes {
+            visit_styles(&mut usage_visitor, &theme.style);
+        }
+        usage_visitor.rename(&mut palette_generator);
+    }
+    debug!("Generated palette {palette_generator:?}");
 
-    for theme in theme_family.themes {
+    for (theme, passthrough) in themes.into_iter().zip(passthroughs) {
         debug!("Translating theme {}", theme.name);
         let mut kdl_theme = Theme {
             appearance: theme.appearance.clone(),
+            window_appearance: None,
+            terminal: None,
             modifiers: vec![],
             players: vec![],
+            accents: vec![],
             name: theme.name.clone(),
+            passthrough,
+            location: Location::default(),
         };
         let mut modifier_visitor = ModifierVisitor::new(&palette_generator);
         if let Some(StyleEntry::Players(players)) = theme.style.get("players") {
@@ -182,11 +341,26 @@ pub fn generate_kdl(theme_family: JsonThemeFamily) -> ThemeFamily {
                     cursor: player.cursor.map(|x| palette_generator.lookup(x)),
                     selection: player.selection.map(|x| palette_generator.lookup(x)),
                     background: player.background.map(|x| palette_generator.lookup(x)),
+                    extra: to_json_text(player.extra.clone()),
                 });
             }
         }
+        if let Some(StyleEntry::Accents(accents)) = theme.style.get("accents") {
+            debug!("Translating accents");
+            kdl_theme.accents = accents
+                .iter()
+                .map(|&x| palette_generator.lookup(x))
+                .collect();
+        }
+        if let Some(StyleEntry::WindowAppearance(appearance)) =
+            theme.style.get("background.appearance")
+        {
+            kdl_theme.window_appearance = Some(*appearance);
+        }
+        let mut style = theme.style;
+        kdl_theme.terminal = extract_terminal_colors(&mut style, &palette_generator);
         debug!("Translating expressions to modifiers");
-        visit_styles(&mut modifier_visitor, &theme.style);
+        visit_styles(&mut modifier_visitor, &style);
         let modifiers = modifier_visitor.into_modifiers();
         debug!("Got modifiers {modifiers:?}");
         kdl_theme.modifiers = modifiers;
@@ -195,10 +369,6 @@ pub fn generate_kdl(theme_family: JsonThemeFamily) -> ThemeFamily {
     }
 
     base_theme.palette = palette_generator.into_resolved_palette().into_raw_palette();
-    if let [t1, t2] = base_theme.themes.as_mut_slice() {
-        base_theme.common = Some(t1.extract_common(t2));
-    } else if base_theme.themes.len() > 2 {
-        warn!("Extracting common attributes from more than 2 themes in a family is not supported yet. A `common` node will not be made.");
-    }
+    base_theme.common = Theme::extract_common_of(&mut base_theme.themes);
     base_theme
 }
@@ -0,0 +1,431 @@
+//! A description of the theme file format in the KDL Schema Language, so that KDL tooling can
+//! check and complete theme files without running zeddy. The decoder is derived from the types
+//! in [`crate::schema::kdl`], so this has to be kept in sync with them by hand.
+
+use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+
+use crate::schema::kdl::TerminalColors;
+use crate::schema::FontWeight;
+
+/// The id of the definition of a color node, such as `accent "#ff7733" alpha=0.5`.
+const COLOR: &str = "color";
+/// The id of the definition of a theme node, which is also used for `common` and templates.
+const THEME: &str = "theme";
+
+const APPEARANCES: &[&str] = &["light", "dark"];
+const WINDOW_APPEARANCES: &[&str] = &["opaque", "transparent", "blurred"];
+const FONT_STYLES: &[&str] = &["normal", "italic", "oblique"];
+const ACTION_FIELDS: &[&str] = &["color", "background", "font-weight", "font-style"];
+
+/// The properties that adjust a color, each with a description.
+const COLOR_MODIFIERS: &[(&str, &str)] = &[
+    ("alpha", "Multiplies the opacity of the color"),
+    ("lighten", "Lightens the color by this fraction"),
+    ("darken", "Darkens the color by this fraction"),
+    ("saturate", "Saturates the color by this fraction"),
+    ("desaturate", "Desaturates the color by this fraction"),
+    (
+        "hue-shift",
+        "Rotates the hue of the color by this many degrees",
+    ),
+    (
+        "gamma",
+        "Raises the lightness of the color to this power, darkening the midtones above 1 and lightening them below 1. It has to be above 0",
+    ),
+    (
+        "temperature",
+        "Shifts the color towards yellow when positive and towards blue when negative",
+    ),
+];
+
+/// Builds the schema of theme family files.
+pub fn kdl_schema() -> KdlDocument {
+    let info = block(
+        "info",
+        [
+            leaf("title", ["zeddy theme family"]),
+            leaf(
+                "description",
+                ["A family of themes for the Zed editor, which `zeddy generate` turns into JSON"],
+            ),
+            leaf("link", [env!("CARGO_PKG_REPOSITORY")]),
+        ],
+    );
+    let document = block(
+        "document",
+        [
+            info,
+            node(
+                "meta",
+                "The name and author of the theme family, and what its extension is published with",
+                [
+                    required(),
+                    once(),
+                    children([
+                        string_node("name", "The name of the theme family", true),
+                        string_node("author", "The author of the theme family", true),
+                        string_node("version", "The version of the theme family", false),
+                        string_node("license", "An SPDX license identifier", false),
+                        string_node("homepage", "A URL of the theme family", false),
+                        string_node("description", "What the theme family looks like", false),
+                    ]),
+                ],
+            ),
+            node(
+                "palette",
+                "Named colors that the themes refer to by name",
+                [
+                    required(),
+                    once(),
+                    prop(
+                        "preserve-hue",
+                        "Lightens, darkens, and applies the gamma to every color that does not set `preserve-hue` in OKLCH, keeping its hue",
+                        leaf("type", ["boolean"]),
+                    ),
+                    children([palette_entry()]),
+                ],
+            ),
+            reference(THEME, Some("theme")),
+            with_rules(
+                reference(THEME, Some("common")),
+                [
+                    description("A theme that every other theme is merged with"),
+                    once(),
+                ],
+            ),
+            template(),
+            instance(),
+            node(
+                "include",
+                "Adds the palette, themes, and templates of another file, relative to this one",
+                [string_value()],
+            ),
+            block("definitions", [theme(), color()]),
+        ],
+    );
+    let mut schema = KdlDocument::new();
+    schema.nodes_mut().push(document);
+    schema
+}
+
+fn theme() -> KdlNode {
+    definition(
+        THEME,
+        "A theme of the family",
+        [children([
+            string_node("name", "The name of the theme", true),
+            enum_node(
+                "appearance",
+                "Whether the theme is light or dark",
+                APPEARANCES,
+                true,
+            ),
+            enum_node(
+                "window-appearance",
+                "How the window background is drawn",
+                WINDOW_APPEARANCES,
+                false,
+            ),
+            node(
+                "player",
+                "The colors of a collaborator, in order",
+                [
+                    leaf("other-props-allowed", [true]),
+                    children([
+                        color_node("cursor", "The cursor of the player"),
+                        color_node("background", "The background of the player"),
+                        color_node("selection", "The selection of the player"),
+                    ]),
+                ],
+            ),
+            reference(COLOR, Some("accent")),
+            node(
+                "terminal",
+                "The ANSI colors of the terminal. The normal and bright colors are required, \
+                 either here or in `common`",
+                [
+                    once(),
+                    children(
+                        TerminalColors::default()
+                            .entries()
+                            .into_iter()
+                            .map(|(name, _)| color_node(name, "A terminal color")),
+                    ),
+                ],
+            ),
+            modifier(),
+            node(
+                "passthrough",
+                "Style keys that zeddy does not know about, as JSON text",
+                [
+                    once(),
+                    children([
+                        node(
+                            "style",
+                            "A style key and its JSON value",
+                            [value(leaf("type", ["string"]), 2)],
+                        ),
+                        node(
+                            "syntax",
+                            "A syntax scope, with its fields as properties of JSON text",
+                            [string_value(), leaf("other-props-allowed", [true])],
+                        ),
+                    ]),
+                ],
+            ),
+        ])],
+    )
+}
+
+fn modifier() -> KdlNode {
+    node(
+        "modifier",
+        "Sets or clears fields of style keys and syntax scopes. Later modifiers win",
+        [
+            prop(
+                "if-appearance",
+                "Only apply the modifier to themes with this appearance",
+                enum_rule(APPEARANCES),
+            ),
+            children([
+                node(
+                    "apply",
+                    "The style keys and syntax scopes to modify",
+                    [
+                        required(),
+                        once(),
+                        children([
+                            string_node(
+                                "style",
+                                "A style key, as listed by `zeddy list-styles`",
+                                false,
+                            ),
+                            string_node(
+                                "syntax",
+                                "A syntax scope, as listed by `zeddy list-syntax`",
+                                false,
+                            ),
+                        ]),
+                    ],
+                ),
+                color_node("color", "The color to set"),
+                color_node("background", "The background to set on syntax scopes"),
+                node(
+                    "font-weight",
+                    "The font weight to set on syntax scopes",
+                    [once(), value(font_weights(), 1)],
+                ),
+                enum_node(
+                    "font-style",
+                    "The font style to set on syntax scopes",
+                    FONT_STYLES,
+                    false,
+                ),
+                node(
+                    "unset",
+                    "The fields to clear, or the targets themselves without any fields",
+                    [once(), value(enum_rule(ACTION_FIELDS), 0)],
+                ),
+            ]),
+        ],
+    )
+}
+
+fn template() -> KdlNode {
+    node(
+        "template",
+        "A theme with color parameters that can be stamped out several times with `instantiate`",
+        [
+            string_value(),
+            children([
+                node(
+                    "param",
+                    "A parameter, which the theme refers to like a palette color",
+                    [string_value()],
+                ),
+                with_rules(reference(THEME, Some("theme")), [required(), once()]),
+            ]),
+        ],
+    )
+}
+
+fn instance() -> KdlNode {
+    node(
+        "instantiate",
+        "A theme created from a template, with a color node for each of its parameters",
+        [
+            string_value(),
+            schema_node(
+                "prop",
+                &["template"],
+                [
+                    leaf("required", [true]),
+                    description("The name of the template"),
+                    leaf("type", ["string"]),
+                ],
+            ),
+            children([palette_entry()]),
+        ],
+    )
+}
+
+/// A node with any name that defines a color, as found in the palette and in instances.
+fn palette_entry() -> KdlNode {
+    with_rules(
+        reference(COLOR, None),
+        [
+            description("A named color, which is defined by the name of the node"),
+            prop(
+                "lock",
+                "Keeps `zeddy harmonize` from adjusting the color",
+                leaf("type", ["boolean"]),
+            ),
+        ],
+    )
+}
+
+fn color() -> KdlNode {
+    definition(
+        COLOR,
+        "A color",
+        COLOR_MODIFIERS
+            .iter()
+            .map(|(name, text)| prop(name, text, leaf("type", ["number"])))
+            .chain([prop(
+                "preserve-hue",
+                "Lightens, darkens, and applies the gamma to the color in OKLCH, keeping its hue",
+                leaf("type", ["boolean"]),
+            )])
+            .chain([block(
+                "value",
+                [
+                    description(
+                        "A hex color such as #ff7733, a Display P3 color such as color(display-p3 1 0.5 0.2), the name of a palette color, or on(background) for whichever of black and white contrasts more with a color",
+                    ),
+                    leaf("type", ["string"]),
+                    leaf("min", [1]),
+                    leaf("max", [1]),
+                ],
+            )]),
+    )
+}
+
+/// The font weights as numbers and as CSS keywords.
+fn font_weights() -> KdlNode {
+    let mut rule = KdlNode::new("enum");
+    for weight in (100..=900_i64).step_by(100) {
+        rule.push(KdlEntry::new(weight));
+    }
+    for keyword in FontWeight::KEYWORDS {
+        rule.push(KdlEntry::new(keyword));
+    }
+    rule
+}
+
+/// A node that refers to a definition, optionally restricted to one name.
+fn reference(id: &str, name: Option<&str>) -> KdlNode {
+    let mut node = KdlNode::new("node");
+    if let Some(name) = name {
+        node.push(KdlEntry::new(name));
+    }
+    node.push(KdlEntry::new_prop("ref", format!("[id=\"{id}\"]")));
+    node
+}
+
+/// The rules for the nodes of a theme file called `name`.
+fn node(name: &str, text: &str, rules: impl IntoIterator<Item = KdlNode>) -> KdlNode {
+    schema_node(
+        "node",
+        &[name],
+        std::iter::once(description(text)).chain(rules),
+    )
+}
+
+/// Rules that nodes can refer to by `id`.
+fn definition(id: &str, text: &str, rules: impl IntoIterator<Item = KdlNode>) -> KdlNode {
+    let mut node = schema_node("node", &[], std::iter::once(description(text)).chain(rules));
+    node.push(KdlEntry::new_prop("id", id));
+    node
+}
+
+/// A node with a single string argument, which has to appear exactly once if it is `required`.
+fn string_node(name: &str, text: &str, required: bool) -> KdlNode {
+    if required {
+        node(name, text, [self::required(), once(), string_value()])
+    } else {
+        node(name, text, [string_value()])
+    }
+}
+
+/// A node with a single argument out of `options`.
+fn enum_node(name: &str, text: &str, options: &[&str], required: bool) -> KdlNode {
+    let mut rules = Vec::new();
+    if required {
+        rules.push(self::required());
+    }
+    rules.extend([once(), value(enum_rule(options), 1)]);
+    node(name, text, rules)
+}
+
+/// A node that defines a color and appears at most once.
+fn color_node(name: &str, text: &str) -> KdlNode {
+    with_rules(reference(COLOR, Some(name)), [description(text), once()])
+}
+
+fn prop(name: &str, text: &str, rule: KdlNode) -> KdlNode {
+    schema_node("prop", &[name], [description(text), rule])
+}
+
+/// The arguments of a node, which all have to follow `rule`.
+fn value(rule: KdlNode, count: i64) -> KdlNode {
+    let mut rules = vec![rule];
+    if count > 0 {
+        rules.extend([leaf("min", [count]), leaf("max", [count])]);
+    }
+    block("value", rules)
+}
+
+fn string_value() -> KdlNode {
+    value(leaf("type", ["string"]), 1)
+}
+
+fn enum_rule(options: &[&str]) -> KdlNode {
+    leaf("enum", options.iter().copied())
+}
+
+fn once() -> KdlNode {
+    leaf("max", [1])
+}
+
+fn required() -> KdlNode {
+    leaf("min", [1])
+}
+
+fn description(text: &str) -> KdlNode {
+    leaf("description", [text])
+}
+
+fn children(nodes: impl IntoIterator<Item = KdlNode>) -> KdlNode {
+    block("children", nodes)
+}
+
+fn leaf<T: Into<KdlValue>>(name: &str, args: impl IntoIterator<Item = T>) -> KdlNode {
+    let mut node = KdlNode::new(name);
+    for arg in args {
+        node.push(KdlEntry::new(arg));
+    }
+    node
+}
+
+fn block(name: &str, nodes: impl IntoIterator<Item = KdlNode>) -> KdlNode {
+    schema_node(name, &[], nodes)
+}
+
+fn schema_node(name: &str, args: &[&str], nodes: impl IntoIterator<Item = KdlNode>) -> KdlNode {
+    with_rules(leaf(name, args.iter().copied()), nodes)
+}
+
+fn with_rules(mut node: KdlNode, rules: impl IntoIterator<Item = KdlNode>) -> KdlNode {
+    node.ensure_children().nodes_mut().extend(rules);
+    node
+}
@@ -0,0 +1,157 @@
+//! Combines several theme families into one.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+use tracing::{debug, info, warn};
+
+use crate::color::palette::{ColorNode, RawPalette};
+use crate::color::Color;
+use crate::schema::kdl::{Theme, ThemeFamily};
+
+/// What to do when two families have palette colors with the same name but different colors.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorConflict {
+    /// Rename the color of the later family to `<label>-<name>`, where the label is the name of
+    /// the file it came from
+    #[default]
+    Prefix,
+    /// Keep the color of the earlier family, which changes the later family's themes
+    KeepFirst,
+    /// Keep the color of the later family, which changes the earlier family's themes
+    KeepLast,
+    /// Stop with an error
+    Error,
+}
+
+/// Merges theme families into one, with the meta of the first family. Each family is given with
+/// a label that is used to rename conflicting palette colors. Templates are instantiated and
+/// every theme is merged with the `common` theme of its family before a new `common` theme is
+/// extracted from all of them.
+pub fn merge_families(
+    families: Vec<(String, ThemeFamily)>,
+    on_conflict: ColorConflict,
+) -> Res<ThemeFamily> {
+    info!("Merging {} theme families", families.len());
+    let (_, first) = families
+        .first()
+        .ok_or_else(|| anyhow!("there are no theme families to merge"))?;
+    let mut merged = ThemeFamily {
+        meta: first.meta.clone(),
+        palette: RawPalette::default(),
+        themes: Vec::new(),
+        common: None,
+        templates: Vec::new(),
+        instances: Vec::new(),
+        includes: Vec::new(),
+    };
+    for (label, mut family) in families {
+        debug!("Merging family {:?}", family.meta.name);
+        family.expand_templates()?;
+        family.palette.pin_preserve_hue(
+            family
+                .themes
+                .iter_mut()
+                .chain(&mut family.common)
+                .flat_map(Theme::colors_mut),
+        );
+        let renames = merge_palette(&mut merged.palette, family.palette, &label, on_conflict)?;
+        for mut theme in family.themes {
+            if merged.themes.iter().any(|x| x.name == theme.name) {
+                return Err(anyhow!(
+                    "there are two themes called {:?}, which Zed cannot tell apart",
+                    theme.name
+                ));
+            }
+            if let Some(common) = &family.common {
+                theme.merge(common);
+            }
+            rename_colors(theme.colors_mut(), &renames);
+            merged.themes.push(theme);
+        }
+    }
+    merged.common = Theme::extract_common_of(&mut merged.themes);
+    Ok(merged)
+}
+
+/// Adds the colors of `palette` to `merged`, returning the colors that had to be renamed.
+fn merge_palette(
+    merged: &mut RawPalette,
+    palette: RawPalette,
+    label: &str,
+    on_conflict: ColorConflict,
+) -> Res<HashMap<String, String>> {
+    // colors are compared by what they resolve to, since the same color can be written
+    // differently in two families
+    let before = merged.clone().into_palette().resolve()?;
+    let resolved = palette.clone().into_palette().resolve()?;
+    let mut renames = HashMap::new();
+    let mut added = Vec::with_capacity(palette.colors.len());
+    for node in palette.colors {
+        let Some(existing) = before.colors.get(node.name.as_str()) else {
+            added.push(node);
+            continue;
+        };
+        if resolved.colors.get(node.name.as_str()) == Some(existing) {
+            continue;
+        }
+        match on_conflict {
+            ColorConflict::Prefix => {
+                let name = format!("{label}-{}", node.name);
+                if before.colors.contains_key(name.as_str())
+                    || resolved.colors.contains_key(name.as_str())
+                {
+                    return Err(anyhow!(
+                        "could not rename the conflicting color {:?} to {name:?} because that name is taken",
+                        node.name
+                    ));
+                }
+                debug!("Renaming color {:?} from {label} to {name:?}", node.name);
+                renames.insert(node.name.clone(), name.clone());
+                added.push(ColorNode { name, ..node });
+            }
+            ColorConflict::KeepFirst => {
+                warn!("Keeping the first definition of color {:?}", node.name);
+            }
+            ColorConflict::KeepLast => {
+                warn!(
+                    "Replacing the color {:?} with the one from {label}",
+                    node.name
+                );
+                added.push(node);
+            }
+            ColorConflict::Error => {
+                return Err(anyhow!(
+                    "the color {:?} of {label} is different from the one of an earlier family",
+                    node.name
+                ));
+            }
+        }
+    }
+    let added = added.into_iter().map(|mut node| {
+        for reference in node.base.references_mut() {
+            if let Some(renamed) = renames.get(&**reference) {
+                *reference = renamed.as_str().into();
+            }
+        }
+        node
+    });
+    for node in added {
+        merged.insert(node);
+    }
+    Ok(renames)
+}
+
+fn rename_colors<'a>(
+    colors: impl Iterator<Item = &'a mut Color>,
+    renames: &HashMap<String, String>,
+) {
+    for color in colors {
+        for reference in color.base.references_mut() {
+            if let Some(renamed) = renames.get(&**reference) {
+                *reference = renamed.as_str().into();
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Writes a generated theme as a Neovim colorscheme in Lua, so that Neovim can mirror the editor.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result as Res};
+
+use super::terminal::terminal_scheme;
+use crate::color::HexColor;
+use crate::schema::json::{JsonTheme, StyleEntry, Syntax};
+use crate::schema::{Appearance, FontStyle};
+use crate::util::slug;
+
+/// The highlight groups of Neovim's UI, with the style keys of their foreground and background.
+const UI_GROUPS: [(&str, Option<&str>, Option<&str>); 33] = [
+    ("Normal", Some("text"), Some("editor.background")),
+    (
+        "NormalFloat",
+        Some("text"),
+        Some("elevated_surface.background"),
+    ),
+    (
+        "FloatBorder",
+        Some("border"),
+        Some("elevated_surface.background"),
+    ),
+    ("Pmenu", Some("text"), Some("elevated_surface.background")),
+    ("PmenuSel", None, Some("element.selected")),
+    ("PmenuSbar", None, Some("scrollbar.track.background")),
+    ("PmenuThumb", None, Some("scrollbar.thumb.background")),
+    ("CursorLine", None, Some("editor.active_line.background")),
+    ("CursorColumn", None, Some("editor.active_line.background")),
+    ("ColorColumn", None, Some("editor.active_line.background")),
+    (
+        "LineNr",
+        Some("editor.line_number"),
+        Some("editor.gutter.background"),
+    ),
+    ("CursorLineNr", Some("editor.active_line_number"), None),
+    ("SignColumn", None, Some("editor.gutter.background")),
+    ("Search", None, Some("search.match_background")),
+    ("IncSearch", None, Some("search.match_background")),
+    (
+        "MatchParen",
+        None,
+        Some("editor.document_highlight.bracket_background"),
+    ),
+    ("StatusLine", Some("text"), Some("status_bar.background")),
+    (
+        "StatusLineNC",
+        Some("text.muted"),
+        Some("status_bar.background"),
+    ),
+    (
+        "TabLine",
+        Some("text.muted"),
+        Some("tab.inactive_background"),
+    ),
+    ("TabLineSel", Some("text"), Some("tab.active_background")),
+    ("TabLineFill", None, Some("tab_bar.background")),
+    ("WinSeparator", Some("border"), None),
+    ("NonText", Some("editor.invisible"), None),
+    ("Whitespace", Some("editor.invisible"), None),
+    ("Directory", Some("text.accent"), None),
+    ("ErrorMsg", Some("error"), None),
+    ("WarningMsg", Some("warning"), None),
+    ("DiffAdd", None, Some("created.background")),
+    ("DiffDelete", None, Some("deleted.background")),
+    ("DiffChange", None, Some("modified.background")),
+    ("Added", Some("created"), None),
+    ("Removed", Some("deleted"), None),
+    ("Changed", Some("modified"), None),
+];
+
+/// Neovim's diagnostic severities and the style keys of their colors.
+const DIAGNOSTICS: [(&str, &str); 4] = [
+    ("Error", "error"),
+    ("Warn", "warning"),
+    ("Info", "info"),
+    ("Hint", "hint"),
+];
+
+/// The standard syntax groups of Vim and the syntax scopes that color them.
+const SYNTAX_GROUPS: [(&str, &str); 23] = [
+    ("Comment", "comment"),
+    ("Constant", "constant"),
+    ("String", "string"),
+    ("Character", "string"),
+    ("Number", "number"),
+    ("Float", "number"),
+    ("Boolean", "boolean"),
+    ("Identifier", "variable"),
+    ("Function", "function"),
+    ("Statement", "keyword"),
+    ("Keyword", "keyword"),
+    ("Conditional", "keyword"),
+    ("Repeat", "keyword"),
+    ("Label", "label"),
+    ("Operator", "operator"),
+    ("PreProc", "preproc"),
+    ("Type", "type"),
+    ("Special", "string.special"),
+    ("SpecialChar", "string.escape"),
+    ("Delimiter", "punctuation.delimiter"),
+    ("Tag", "tag"),
+    ("Title", "title"),
+    ("Underlined", "link_uri"),
+];
+
+/// The treesitter captures of Neovim and the syntax scopes of the same tokens. Neovim falls back
+/// from a capture to the captures it is nested in, so only the scopes that the theme sets are
+/// written.
+const CAPTURES: [(&str, &str); 33] = [
+    ("@attribute", "attribute"),
+    ("@boolean", "boolean"),
+    ("@comment", "comment"),
+    ("@comment.documentation", "comment.doc"),
+    ("@constant", "constant"),
+    ("@constructor", "constructor"),
+    ("@function", "function"),
+    ("@keyword", "keyword"),
+    ("@keyword.directive", "preproc"),
+    ("@label", "label"),
+    ("@markup.heading", "title"),
+    ("@markup.italic", "emphasis"),
+    ("@markup.link.label", "link_text"),
+    ("@markup.link.url", "link_uri"),
+    ("@markup.list", "punctuation.list_marker"),
+    ("@markup.raw", "text.literal"),
+    ("@markup.strong", "emphasis.strong"),
+    ("@number", "number"),
+    ("@operator", "operator"),
+    ("@property", "property"),
+    ("@punctuation", "punctuation"),
+    ("@punctuation.bracket", "punctuation.bracket"),
+    ("@punctuation.delimiter", "punctuation.delimiter"),
+    ("@punctuation.special", "punctuation.special"),
+    ("@string", "string"),
+    ("@string.escape", "string.escape"),
+    ("@string.regexp", "string.regex"),
+    ("@string.special", "string.special"),
+    ("@string.special.symbol", "string.special.symbol"),
+    ("@tag", "tag"),
+    ("@type", "type"),
+    ("@variable", "variable"),
+    ("@variable.builtin", "variable.special"),
+];
+
+/// The fields of a highlight definition, written as a Lua table.
+#[derive(Default)]
+struct Highlight {
+    fg: Option<HexColor>,
+    bg: Option<HexColor>,
+    sp: Option<HexColor>,
+    bold: bool,
+    italic: bool,
+    undercurl: bool,
+}
+
+impl Highlight {
+    fn from_syntax(syntax: &Syntax) -> Self {
+        Self {
+            fg: syntax.color,
+            bg: syntax.background,
+            bold: syntax.font_weight.is_some_and(|weight| weight.get() >= 600),
+            italic: syntax.font_style == Some(FontStyle::Italic),
+            ..Self::default()
+        }
+    }
+
+    /// Writes the definition, with colors drawn over `background` since Neovim has no
+    /// transparency. Returns `None` when it would be empty.
+    fn to_lua(&self, background: HexColor) -> Option<String> {
+        let mut fields = Vec::new();
+        for (name, color) in [("fg", self.fg), ("bg", self.bg), ("sp", self.sp)] {
+            if let Some(color) = color {
+                let HexColor([r, g, b, _]) = color.over(background);
+                fields.push(format!("{name} = \"#{r:02x}{g:02x}{b:02x}\""));
+            }
+        }
+        for (name, set) in [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("undercurl", self.undercurl),
+        ] {
+            if set {
+                fields.push(format!("{name} = true"));
+            }
+        }
+        (!fields.is_empty()).then(|| format!("{{ {} }}", fields.join(", ")))
+    }
+}
+
+/// Returns the entry of a syntax scope, falling back to the scopes it is nested in like Zed does.
+fn syntax_entry<'a>(theme: &'a JsonTheme, scope: &str) -> Option<&'a Syntax> {
+    let Some(StyleEntry::Syntax(syntax)) = theme.style.get("syntax") else {
+        return None;
+    };
+    let mut scope = scope;
+    loop {
+        if let Some(entry) = syntax.get(scope) {
+            return Some(entry);
+        }
+        scope = scope.rsplit_once('.')?.0;
+    }
+}
+
+/// Writes a theme as a Neovim colorscheme in Lua, which sets the UI highlight groups of
+/// [`UI_GROUPS`], the cursor and selection of the first player, diagnostics, the standard syntax
+/// groups, the treesitter captures, and the terminal colors when the theme has all of them. The
+/// colorscheme is named after the theme, like `foo-dark` for "Foo Dark".
+pub fn export_nvim(theme: &JsonTheme) -> Res<String> {
+    let background = ["editor.background", "background"]
+        .iter()
+        .find_map(|key| theme.color(key))
+        .ok_or_else(|| anyhow!("the theme {:?} has no background color", theme.name))?
+        .over(HexColor([0, 0, 0, 255]));
+    let mut groups = Vec::new();
+    for (group, fg, bg) in UI_GROUPS {
+        let highlight = Highlight {
+            fg: fg.and_then(|key| theme.color(key)),
+            bg: bg.and_then(|key| theme.color(key)),
+            ..Highlight::default()
+        };
+        groups.push((group.to_owned(), highlight));
+    }
+    let player = match theme.style.get("players") {
+        Some(StyleEntry::Players(players)) => players.first(),
+        _ => None,
+    };
+    let cursor = player.and_then(|player| player.cursor);
+    let cursor = Highlight {
+        fg: cursor.map(|_| background),
+        bg: cursor,
+        ..Highlight::default()
+    };
+    groups.push(("Cursor".to_owned(), cursor));
+    let visual = Highlight {
+        bg: player.and_then(|player| player.selection),
+        ..Highlight::default()
+    };
+    groups.push(("Visual".to_owned(), visual));
+    for (severity, key) in DIAGNOSTICS {
+        let color = theme.color(key);
+        let text = Highlight {
+            fg: color,
+            ..Highlight::default()
+        };
+        groups.push((format!("Diagnostic{severity}"), text));
+        let underline = Highlight {
+            sp: color,
+            undercurl: true,
+            ..Highlight::default()
+        };
+        groups.push((format!("DiagnosticUnderline{severity}"), underline));
+    }
+    for (group, scope) in SYNTAX_GROUPS {
+        if let Some(entry) = syntax_entry(theme, scope) {
+            groups.push((group.to_owned(), Highlight::from_syntax(entry)));
+        }
+    }
+    if let Some(StyleEntry::Syntax(syntax)) = theme.style.get("syntax") {
+        for (capture, scope) in CAPTURES {
+            if let Some(entry) = syntax.get(scope) {
+                groups.push((capture.to_owned(), Highlight::from_syntax(entry)));
+            }
+        }
+    }
+
+    let mut out = format!("-- {}\n\n", theme.name);
+    writeln!(out, "vim.cmd(\"highlight clear\")")?;
+    let appearance = match theme.appearance {
+        Appearance::Light => "light",
+        Appearance::Dark => "dark",
+    };
+    writeln!(out, "vim.o.background = \"{appearance}\"")?;
+    writeln!(out, "vim.g.colors_name = \"{}\"\n", slug(&theme.name))?;
+    writeln!(out, "local hl = function(group, spec)")?;
+    writeln!(out, "  vim.api.nvim_set_hl(0, group, spec)")?;
+    writeln!(out, "end\n")?;
+    for (group, highlight) in &groups {
+        // a diagnostic underline without a color would still be curly
+        if highlight.undercurl && highlight.sp.is_none() {
+            continue;
+        }
+        if let Some(spec) = highlight.to_lua(background) {
+            writeln!(out, "hl(\"{group}\", {spec})")?;
+        }
+    }
+    if let Ok(scheme) = terminal_scheme(theme) {
+        out.push('\n');
+        for (idx, color) in scheme.ansi.iter().enumerate() {
+            let HexColor([r, g, b, _]) = color;
+            writeln!(
+                out,
+                "vim.g.terminal_color_{idx} = \"#{r:02x}{g:02x}{b:02x}\""
+            )?;
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,122 @@
+//! Creates overlays from the differences between two versions of a theme family, so that
+//! customizations of an upstream theme can be kept apart from it.
+
+use anyhow::Result as Res;
+use tracing::{info, warn};
+
+use crate::schema::kdl::{Modifier, Theme, ThemeFamily};
+use crate::schema::overlay::{Overlay, OverlayTheme};
+
+/// Creates the smallest overlay that turns `original` into `modified`. Overlays can only replace
+/// palette colors and add modifiers, so other changes are reported as warnings and left out.
+/// Changed modifiers are added again at the end of the theme, so they win over every other one.
+pub fn make_overlay(mut original: ThemeFamily, mut modified: ThemeFamily) -> Res<Overlay> {
+    info!("Creating an overlay from the differences between two theme families");
+    original.expand_templates()?;
+    modified.expand_templates()?;
+
+    let mut overlay = Overlay::default();
+    for node in &original.palette.colors {
+        if !modified.palette.colors.iter().any(|x| x.name == node.name) {
+            warn!(
+                "palette color {:?} was removed, which an overlay cannot express",
+                node.name
+            );
+        }
+    }
+    overlay.palette.colors = modified
+        .palette
+        .colors
+        .into_iter()
+        .filter(|node| {
+            !original.palette.colors.iter().any(|x| {
+                x.name == node.name && x.base == node.base && x.modifiers == node.modifiers
+            })
+        })
+        .collect();
+
+    let common = new_modifiers(
+        original
+            .common
+            .as_ref()
+            .map_or(&[], |theme| &theme.modifiers),
+        modified
+            .common
+            .as_ref()
+            .map_or(&[], |theme| &theme.modifiers),
+        "common",
+    );
+    if !common.is_empty() {
+        overlay.common = Some(OverlayTheme {
+            name: None,
+            modifiers: common,
+        });
+    }
+
+    for theme in &modified.themes {
+        let Some(before) = original.themes.iter().find(|x| x.name == theme.name) else {
+            warn!(
+                "theme {:?} is new, which an overlay cannot express",
+                theme.name
+            );
+            continue;
+        };
+        warn_unexpressible(before, theme);
+        let modifiers = new_modifiers(&before.modifiers, &theme.modifiers, &theme.name);
+        if !modifiers.is_empty() {
+            overlay.themes.push(OverlayTheme {
+                name: Some(theme.name.clone()),
+                modifiers,
+            });
+        }
+    }
+    for theme in &original.themes {
+        if !modified.themes.iter().any(|x| x.name == theme.name) {
+            warn!(
+                "theme {:?} was removed, which an overlay cannot express",
+                theme.name
+            );
+        }
+    }
+    Ok(overlay)
+}
+
+/// Returns the modifiers of `after` that are not in `before`.
+fn new_modifiers(before: &[Modifier], after: &[Modifier], theme: &str) -> Vec<Modifier> {
+    if before
+        .iter()
+        .any(|x| !after.iter().any(|y| same_modifier(x, y)))
+    {
+        warn!("modifiers were removed from {theme:?}, which an overlay cannot express");
+    }
+    after
+        .iter()
+        .filter(|x| !before.iter().any(|y| same_modifier(x, y)))
+        .cloned()
+        .collect()
+}
+
+fn same_modifier(x: &Modifier, y: &Modifier) -> bool {
+    x.if_appearance == y.if_appearance && x.apply == y.apply && x.action == y.action
+}
+
+/// Warns about changes to the parts of a theme that are not modifiers.
+fn warn_unexpressible(before: &Theme, after: &Theme) {
+    let changed = [
+        ("appearance", before.appearance != after.appearance),
+        (
+            "window appearance",
+            before.window_appearance != after.window_appearance,
+        ),
+        ("players", before.players != after.players),
+        ("accents", before.accents != after.accents),
+        ("terminal colors", before.terminal != after.terminal),
+        ("passthrough", before.passthrough != after.passthrough),
+    ];
+    for (part, _) in changed.iter().filter(|(_, changed)| *changed) {
+        warn!(
+            "the {part} of theme {:?} changed, which an overlay cannot express",
+            after.name
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Flattens a single theme of a family into the form that generation sees it in.
+
+use anyhow::{anyhow, Result as Res};
+use tracing::info;
+
+use crate::color::{BaseColorKind, Color, ColorModifiers};
+use crate::schema::kdl::{Theme, ThemeFamily};
+
+/// Returns the theme called `name` after templates are expanded and the theme is merged with
+/// the `common` theme. Every color is written out as a hex color, and modifiers that do not
+/// apply to the appearance of the theme are left out.
+pub fn resolve_theme(mut family: ThemeFamily, name: &str) -> Res<Theme> {
+    info!("Resolving theme {name:?}");
+    family.expand_templates()?;
+    let resolved = family.palette.into_palette().resolve()?;
+    let mut theme = family
+        .themes
+        .into_iter()
+        .find(|theme| theme.name == name)
+        .ok_or_else(|| anyhow!("there is no theme named {name:?}"))?;
+    if let Some(common) = &family.common {
+        theme.merge(common);
+    }
+
+    let appearance = theme.appearance.clone();
+    theme.modifiers.retain(|modifier| {
+        modifier
+            .if_appearance
+            .as_ref()
+            .is_none_or(|x| *x == appearance)
+    });
+    for modifier in &mut theme.modifiers {
+        modifier.if_appearance = None;
+    }
+    for color in theme.colors_mut() {
+        *color = Color {
+            base: BaseColorKind::Hex(resolved.lookup(color)?),
+            modifiers: ColorModifiers::default(),
+            location: color.location,
+        };
+    }
+    Ok(theme)
+}
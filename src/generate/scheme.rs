@@ -0,0 +1,102 @@
+//! Generates starter palettes from a single color with the classic rules of color harmony, so
+//! that a new theme has something better than a blank palette to start from.
+
+use anyhow::{bail, Result as Res};
+use clap::ValueEnum;
+
+use crate::color::palette::{ColorNode, RawPalette};
+use crate::color::{BaseColorKind, ColorModifiers, HexColor};
+use crate::schema::location::Location;
+
+/// The names of the hues of a scheme, in the order that they are used in.
+const ROLES: &[&str] = &["primary", "secondary", "tertiary"];
+/// How the lightness of the hues changes every time that they are used again, with the suffix
+/// of the names of those colors.
+const VARIANTS: &[(f32, &str)] = &[
+    (0.0, ""),
+    (0.1, "-light"),
+    (-0.1, "-dark"),
+    (0.2, "-lighter"),
+    (-0.2, "-darker"),
+];
+/// The chroma of the neutrals, which is just enough to tint them with the hue of the base.
+const NEUTRAL_CHROMA: f32 = 0.015;
+/// The lightness of the darkest and the lightest neutral.
+const NEUTRAL_LIGHTNESS: (f32, f32) = (0.18, 0.95);
+
+/// A rule of color harmony that picks hues that go with the hue of a base color.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// The base and the hue opposite of it
+    Complementary,
+    /// The base and its neighbors 30 degrees away on both sides
+    Analogous,
+    /// Three hues evenly spread around the color wheel
+    Triadic,
+}
+
+impl ColorScheme {
+    /// How far the hues of the scheme are from the hue of the base, in degrees.
+    fn hue_offsets(self) -> &'static [f32] {
+        match self {
+            Self::Complementary => &[0.0, 180.0],
+            Self::Analogous => &[0.0, 30.0, -30.0],
+            Self::Triadic => &[0.0, 120.0, 240.0],
+        }
+    }
+}
+
+/// Generates `count` colors with the hues of `scheme`, which keep the lightness and chroma of
+/// `base` in `OKLCH`, followed by `neutrals` grays that are tinted with its hue, from dark to
+/// light. Once every hue is used, the hues are used again a little lighter or darker, so the
+/// colors are named like `primary`, `secondary-light`, and `neutral-1`. Every hue can be used up
+/// to five times.
+pub fn scheme_palette(
+    base: HexColor,
+    scheme: ColorScheme,
+    count: usize,
+    neutrals: usize,
+) -> Res<RawPalette> {
+    let base = base.to_oklcha();
+    let offsets = scheme.hue_offsets();
+    let max = offsets.len() * VARIANTS.len();
+    if count > max {
+        bail!("the scheme has at most {max} colors, but {count} were asked for");
+    }
+    let mut palette = RawPalette::default();
+    let mut add = |name: String, color| {
+        palette.insert(ColorNode {
+            name,
+            base: BaseColorKind::Hex(HexColor::from_oklcha(color)),
+            modifiers: ColorModifiers::default(),
+            lock: false,
+            location: Location::default(),
+        });
+    };
+    let variants = VARIANTS.iter().flat_map(|&variant| {
+        offsets
+            .iter()
+            .zip(ROLES)
+            .map(move |(&offset, &role)| (offset, role, variant))
+    });
+    for (offset, role, (lightness, suffix)) in variants.take(count) {
+        let mut color = base;
+        color.hue += offset;
+        color.l = (color.l + lightness).clamp(0.0, 1.0);
+        add(format!("{role}{suffix}"), color);
+    }
+    let (darkest, lightest) = NEUTRAL_LIGHTNESS;
+    for i in 0..neutrals {
+        let mut color = base;
+        color.chroma = color.chroma.min(NEUTRAL_CHROMA);
+        #[allow(clippy::cast_precision_loss, reason = "palettes are small")]
+        let step = if neutrals > 1 {
+            i as f32 / (neutrals - 1) as f32
+        } else {
+            0.5
+        };
+        color.l = darkest + (lightest - darkest) * step;
+        add(format!("neutral-{}", i + 1), color);
+    }
+    Ok(palette)
+}
@@ -0,0 +1,136 @@
+//! Names palette colors after what the migrated themes use them for instead of after their hue:
+//! `surface-*` for backgrounds, `border-*` for borders, and `syntax-*` for colors that only
+//! appear in syntax highlighting.
+
+use std::collections::{BTreeMap, HashMap};
+
+use palette::{IntoColor, Lab, Srgb};
+use tracing::debug;
+
+use crate::color::palette::PaletteGenerator;
+use crate::color::HexColor;
+use crate::schema::kdl::BorrowedModifierPath;
+
+use super::kdl::StyleVisitor;
+
+/// How often a color is used for each purpose.
+#[derive(Default)]
+struct Uses {
+    surface: usize,
+    border: usize,
+    /// How often the color is used for each syntax scope
+    syntax: BTreeMap<String, usize>,
+    other: usize,
+}
+
+impl Uses {
+    fn total(&self) -> usize {
+        self.surface + self.border + self.syntax.values().sum::<usize>() + self.other
+    }
+
+    fn add(&mut self, other: Self) {
+        self.surface += other.surface;
+        self.border += other.border;
+        for (scope, count) in other.syntax {
+            *self.syntax.entry(scope).or_default() += count;
+        }
+        self.other += other.other;
+    }
+
+    /// The most common syntax scope, preferring the first in alphabetical order on ties.
+    fn main_scope(&self) -> Option<&str> {
+        let most = *self.syntax.values().max()?;
+        self.syntax
+            .iter()
+            .find(|(_, &count)| count == most)
+            .map(|(scope, _)| scope.as_str())
+    }
+}
+
+/// Collects what every color of the themes is used for.
+#[derive(Default)]
+pub struct UsageVisitor {
+    uses: HashMap<HexColor, Uses>,
+}
+
+impl StyleVisitor for UsageVisitor {
+    fn visit_color(&mut self, key: Option<BorrowedModifierPath<'_>>, color: HexColor) {
+        let uses = self.uses.entry(color).or_default();
+        match key {
+            Some(BorrowedModifierPath::Syntax(scope)) => {
+                *uses.syntax.entry(scope.to_owned()).or_default() += 1;
+            }
+            Some(BorrowedModifierPath::Style(key)) => {
+                // any segment counts, since `border.variant` is a border and
+                // `editor.background` a surface
+                let mut segments = key.split('.');
+                if segments.clone().any(|segment| segment.contains("border")) {
+                    uses.border += 1;
+                } else if segments.any(|segment| segment.contains("background")) {
+                    uses.surface += 1;
+                } else {
+                    uses.other += 1;
+                }
+            }
+            // players, accents, and terminal colors
+            None => uses.other += 1,
+        }
+    }
+}
+
+impl UsageVisitor {
+    /// Renames the colors that are mostly used for backgrounds or borders, or only for syntax
+    /// highlighting. The other colors keep the names they got from the generator.
+    pub fn rename(self, generator: &mut PaletteGenerator) {
+        // colors that share a palette entry share their uses
+        let mut entries = HashMap::<[u8; 4], Uses>::new();
+        for (color, uses) in self.uses {
+            entries.entry(generator.key(color)).or_default().add(uses);
+        }
+        let mut surfaces = Vec::new();
+        let mut borders = Vec::new();
+        let mut syntax = Vec::new();
+        for (key, uses) in &entries {
+            let total = uses.total();
+            if uses.surface * 2 > total {
+                surfaces.push(*key);
+            } else if uses.border * 2 > total {
+                borders.push(*key);
+            } else if uses.surface + uses.border + uses.other == 0 {
+                if let Some(scope) = uses.main_scope() {
+                    syntax.push((*key, scope));
+                }
+            }
+        }
+        for (prefix, mut colors) in [("surface", surfaces), ("border", borders)] {
+            // darkest first, like the layers of a dark theme
+            colors.sort_by(|left, right| {
+                lightness(*left)
+                    .total_cmp(&lightness(*right))
+                    .then(left.cmp(right))
+            });
+            for (idx, key) in colors.into_iter().enumerate() {
+                generator.rename(HexColor(key), &format!("{prefix}-{idx}"));
+            }
+        }
+        // the most used color of a scope gets its name without a number
+        syntax.sort_by(|(left, left_scope), (right, right_scope)| {
+            left_scope
+                .cmp(right_scope)
+                .then(entries[right].total().cmp(&entries[left].total()))
+                .then(left.cmp(right))
+        });
+        for (key, scope) in syntax {
+            generator.rename(
+                HexColor(key),
+                &format!("syntax-{}", scope.replace('.', "-")),
+            );
+        }
+        debug!("Named colors by usage: {generator:?}");
+    }
+}
+
+fn lightness([r, g, b, _]: [u8; 4]) -> f32 {
+    let lab: Lab = Srgb::new(r, g, b).into_format::<f32>().into_color();
+    lab.l
+}
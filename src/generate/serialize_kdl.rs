@@ -1,52 +1,261 @@
-//! A flawed implementation of KDL serialization that only works in our specific cases.
+//! KDL serialization for our specific file format, built on top of the `kdl` crate's document
+//! model so that strings and identifiers are always escaped and quoted correctly.
 //! I had to do all this because there is no good serde implementation for KDL (that isn't wildly broken)
 
 use std::{fmt::Display, io::Write};
 
-use log::debug;
+use kdl::{KdlDocument, KdlEntry, KdlIdentifier, KdlNode, KdlValue};
+use tracing::debug;
 
 use crate::{
-    color::{palette::RawPalette, BaseColorKind, Color},
-    schema::kdl::{Action, Modifier, ModifierPath, Player, Theme, ThemeFamily},
-    schema::{Appearance, Meta},
+    color::{
+        palette::{ColorNode, RawPalette, ResolvedPalette},
+        BaseColorKind, Color, Gamma, HexColor,
+    },
+    schema::include::Include,
+    schema::kdl::{
+        Action, ActionField, Instance, Modifier, ModifierPath, Passthrough, PassthroughStyle,
+        PassthroughSyntax, Player, Template, TerminalColors, Theme, ThemeFamily,
+    },
+    schema::overlay::{Overlay, OverlayTheme},
+    schema::{Appearance, FontStyle, FontWeight, KdlVersion, Meta, WindowAppearance},
 };
 
+/// The number of columns a tab is assumed to take up when measuring lines.
+const TAB_WIDTH: usize = 4;
+
+/// How a block is indented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+impl Indent {
+    fn at(self, depth: usize) -> String {
+        match self {
+            Self::Spaces(width) => " ".repeat(width * depth),
+            Self::Tabs => "\t".repeat(depth),
+        }
+    }
+    fn columns(self, depth: usize) -> usize {
+        match self {
+            Self::Spaces(width) => width * depth,
+            Self::Tabs => TAB_WIDTH * depth,
+        }
+    }
+}
+
+/// How serialized and formatted KDL documents are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdlFormat {
+    pub indent: Indent,
+    /// Sort the properties of each node by name instead of keeping them in the order they
+    /// were written in.
+    pub sort_properties: bool,
+    /// Write `apply` blocks on a single line if it fits within this many columns.
+    pub max_width: Option<usize>,
+    /// The version of KDL to write.
+    pub version: KdlVersion,
+}
+
+impl Default for KdlFormat {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(4),
+            sort_properties: false,
+            max_width: None,
+            version: KdlVersion::default(),
+        }
+    }
+}
+
+impl KdlFormat {
+    /// Lays out a document in this format. Comments before nodes are kept and re-indented.
+    pub fn apply(&self, doc: &mut KdlDocument) {
+        self.apply_at(doc, 0);
+    }
+
+    fn apply_at(&self, doc: &mut KdlDocument, depth: usize) {
+        for node in doc.nodes_mut() {
+            let leading = reindent(node.leading().unwrap_or_default(), self.indent, depth);
+            node.set_leading(leading);
+            if self.sort_properties {
+                // arguments have no name, so they stay in front
+                node.entries_mut().sort_by(|x, y| {
+                    x.name()
+                        .map(KdlIdentifier::value)
+                        .cmp(&y.name().map(KdlIdentifier::value))
+                });
+            }
+            if let Some(children) = node.children_mut() {
+                self.apply_at(children, depth + 1);
+                // the trailing text of a block ends with the indentation of its closing brace
+                let trailing =
+                    reindent(children.trailing().unwrap_or_default(), self.indent, depth);
+                children.set_trailing(trailing);
+            }
+            if node.name().value() == "apply" {
+                self.inline_apply(node, depth);
+            }
+        }
+    }
+
+    /// Puts an `apply` block on a single line if it fits and has no comments that would be lost.
+    fn inline_apply(&self, node: &mut KdlNode, depth: usize) {
+        let Some(max_width) = self.max_width else {
+            return;
+        };
+        let Some(children) = node.children_mut() else {
+            return;
+        };
+        let is_bare = |node: &KdlNode| {
+            node.children().is_none()
+                && node.leading().unwrap_or_default().trim().is_empty()
+                && node
+                    .trailing()
+                    .unwrap_or_default()
+                    .trim()
+                    .trim_start_matches(';')
+                    .is_empty()
+        };
+        if !children.nodes().iter().all(is_bare) {
+            return;
+        }
+        for child in children.nodes_mut() {
+            child.set_leading(" ");
+            child.set_trailing(";");
+        }
+        let mut header = node.clone();
+        header.clear_children();
+        header.set_leading("");
+        header.set_trailing("");
+        let Some(children) = node.children_mut() else {
+            return;
+        };
+        // `<indent><header> {<children> }`
+        let width = self.indent.columns(depth)
+            + header.to_string().len()
+            + 2
+            + children
+                .nodes()
+                .iter()
+                .map(|child| child.to_string().len())
+                .sum::<usize>()
+            + 2;
+        if width <= max_width {
+            children.set_leading("");
+            children.set_trailing(" ");
+        } else {
+            // undo the changes to the children
+            for child in children.nodes_mut() {
+                child.set_leading(self.indent.at(depth + 1));
+                child.set_trailing("\n");
+            }
+        }
+    }
+}
+
+/// Re-indents the whitespace and comments before a node, keeping blank lines empty.
+fn reindent(text: &str, indent: Indent, depth: usize) -> String {
+    let indent = indent.at(depth);
+    let lines = text.split('\n').collect::<Vec<_>>();
+    let last = lines.len() - 1;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line.trim_start();
+            if line.is_empty() && i != last {
+                String::new()
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub struct KdlSerializer<W: Write> {
-    indent: usize,
+    /// The documents of the children blocks that are currently open, starting with the root.
+    documents: Vec<KdlDocument>,
     writer: W,
+    format: KdlFormat,
+    /// The palette used to annotate colors that refer to it with their hex color.
+    annotations: Option<ResolvedPalette>,
 }
 impl<W: Write> KdlSerializer<W> {
     pub fn new(writer: W) -> Self {
-        Self { indent: 0, writer }
+        Self {
+            documents: vec![KdlDocument::new()],
+            writer,
+            format: KdlFormat::default(),
+            annotations: None,
+        }
     }
-    fn indent(&mut self) {
-        self.indent += 1;
+    #[must_use]
+    pub fn with_format(self, format: KdlFormat) -> Self {
+        Self { format, ..self }
     }
-    fn dedent(&mut self) {
-        self.indent -= 1;
+    /// Writes the hex color that every palette reference resolves to in a comment after it.
+    #[must_use]
+    pub fn with_annotations(self, palette: ResolvedPalette) -> Self {
+        Self {
+            annotations: Some(palette),
+            ..self
+        }
     }
-    fn write_indent(&mut self) -> std::io::Result<()> {
-        for _ in 0..self.indent {
-            self.writer.write_all("    ".as_bytes())?;
+    /// The hex color to annotate a color with, if it refers to the palette or is not a hex color.
+    fn annotation(&self, color: &Color) -> Option<HexColor> {
+        let palette = self.annotations.as_ref()?;
+        match color.base {
+            BaseColorKind::PaletteReference(_)
+            | BaseColorKind::On(_)
+            | BaseColorKind::DisplayP3(_) => palette.lookup(color).ok(),
+            BaseColorKind::Hex(_) => None,
         }
-        Ok(())
+    }
+    fn document(&mut self) -> &mut KdlDocument {
+        self.documents
+            .last_mut()
+            .expect("the root document is never closed")
+    }
+    fn node(&mut self) -> &mut KdlNode {
+        self.document()
+            .nodes_mut()
+            .last_mut()
+            .expect("nodes are started before they are written to")
     }
     pub fn children_block(
         &mut self,
         node_name: impl Display,
     ) -> std::io::Result<ChildrenBlock<'_, W>> {
-        self.writer.write_all(b"\n")?;
-        self.write_indent()?;
-        self.writer.write_fmt(format_args!("{node_name} {{"))?;
-        Ok(ChildrenBlock { inner: self })
+        self.inline_node(node_name)?.children_block()
     }
 
+    #[allow(clippy::unnecessary_wraps)]
     pub fn inline_node(&mut self, node_name: impl Display) -> std::io::Result<InlineNode<'_, W>> {
-        self.writer.write_all(b"\n")?;
-        self.write_indent()?;
-        self.writer.write_fmt(format_args!("{node_name}"))?;
+        self.document()
+            .nodes_mut()
+            .push(KdlNode::new(node_name.to_string()));
         Ok(InlineNode { inner: self })
     }
+
+    /// Writes out everything that has been serialized.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        debug_assert_eq!(self.documents.len(), 1, "a children block was not finished");
+        let format = self.format;
+        let mut document = self
+            .documents
+            .pop()
+            .expect("the root document is never closed");
+        format.apply(&mut document);
+        let document = format
+            .version
+            .render(document)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        self.writer.write_all(document.as_bytes())
+    }
 }
 
 pub struct ChildrenBlock<'a, W: Write> {
@@ -55,9 +264,7 @@ pub struct ChildrenBlock<'a, W: Write> {
 
 impl<'a, W: Write> ChildrenBlock<'a, W> {
     pub fn child(self, name: impl Display, child: impl SerializeKdl) -> std::io::Result<Self> {
-        self.inner.indent();
         child.serialize(name, self.inner)?;
-        self.inner.dedent();
         Ok(self)
     }
     pub fn children(
@@ -69,10 +276,14 @@ impl<'a, W: Write> ChildrenBlock<'a, W> {
         }
         Ok(self)
     }
+    #[allow(clippy::unnecessary_wraps)]
     pub fn finish(self) -> std::io::Result<&'a mut KdlSerializer<W>> {
-        self.inner.writer.write_all(b"\n")?;
-        self.inner.write_indent()?;
-        self.inner.writer.write_all(b"}")?;
+        let children = self
+            .inner
+            .documents
+            .pop()
+            .expect("the children block was opened");
+        self.inner.node().set_children(children);
         Ok(self.inner)
     }
 }
@@ -81,10 +292,10 @@ pub struct InlineNode<'a, W: Write> {
     inner: &'a mut KdlSerializer<W>,
 }
 
+#[allow(clippy::unnecessary_wraps)]
 impl<'a, W: Write> InlineNode<'a, W> {
     pub fn arg(self, arg: impl SerializeKdlScalar) -> std::io::Result<Self> {
-        self.inner.writer.write_all(b" ")?;
-        arg.serialize_scalar(self.inner)?;
+        self.inner.node().push(KdlEntry::new(arg.to_kdl_value()));
         Ok(self)
     }
     pub fn property(
@@ -93,12 +304,12 @@ impl<'a, W: Write> InlineNode<'a, W> {
         value: Option<impl SerializeKdlScalar>,
     ) -> std::io::Result<Self> {
         if let Some(value) = value {
-            self.inner.writer.write_fmt(format_args!(" {prop}="))?;
-            value.serialize_scalar(self.inner)?;
+            self.inner
+                .node()
+                .push(KdlEntry::new_prop(prop.to_string(), value.to_kdl_value()));
         }
         Ok(self)
     }
-    #[allow(dead_code)]
     pub fn properties(
         mut self,
         props: impl IntoIterator<Item = (impl Display, Option<impl SerializeKdlScalar>)>,
@@ -108,9 +319,17 @@ impl<'a, W: Write> InlineNode<'a, W> {
         }
         Ok(self)
     }
-    #[allow(clippy::unnecessary_wraps)]
+    /// Adds a comment at the end of the line of this node.
+    pub fn comment(self, text: impl Display) -> std::io::Result<Self> {
+        self.inner.node().set_trailing(format!(" // {text}\n"));
+        Ok(self)
+    }
+    /// Opens a children block on this node, after any arguments and properties written so far.
+    pub fn children_block(self) -> std::io::Result<ChildrenBlock<'a, W>> {
+        self.inner.documents.push(KdlDocument::new());
+        Ok(ChildrenBlock { inner: self.inner })
+    }
     pub fn finish(self) -> std::io::Result<&'a mut KdlSerializer<W>> {
-        self.inner.dedent();
         Ok(self.inner)
     }
 }
@@ -125,19 +344,20 @@ pub trait SerializeKdl {
     ) -> std::io::Result<()>;
 }
 
+/// Data that can be written as an argument or property value.
 pub trait SerializeKdlScalar {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()>;
+    fn to_kdl_value(&self) -> KdlValue;
 }
 
 impl SerializeKdlScalar for String {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        serializer.writer.write_fmt(format_args!("{self:?}"))
+    fn to_kdl_value(&self) -> KdlValue {
+        KdlValue::String(self.clone())
     }
 }
 
 impl SerializeKdlScalar for &'_ str {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        serializer.writer.write_fmt(format_args!("{self:?}"))
+    fn to_kdl_value(&self) -> KdlValue {
+        KdlValue::String((*self).to_owned())
     }
 }
 
@@ -153,8 +373,8 @@ impl SerializeKdl for String {
 }
 
 impl<T: SerializeKdlScalar> SerializeKdlScalar for &'_ T {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        SerializeKdlScalar::serialize_scalar(*self, serializer)
+    fn to_kdl_value(&self) -> KdlValue {
+        SerializeKdlScalar::to_kdl_value(*self)
     }
 }
 
@@ -167,27 +387,20 @@ impl<T: SerializeKdl> SerializeKdl for &'_ T {
         SerializeKdl::serialize(*self, node_name, serializer)
     }
 }
-impl<T: SerializeKdlScalar> SerializeKdlScalar for Option<T> {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        if let Some(scalar) = self {
-            scalar.serialize_scalar(serializer)
-        } else {
-            Ok(())
-        }
-    }
-}
-
 impl SerializeKdlScalar for f32 {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        serializer.writer.write_fmt(format_args!("{self:?}"))
+    fn to_kdl_value(&self) -> KdlValue {
+        // go through the shortest representation of the `f32` so that `0.1` does not turn
+        // into `0.10000000149011612`
+        KdlValue::Base10Float(
+            self.to_string()
+                .parse()
+                .expect("an f32 is always a valid f64"),
+        )
     }
 }
 impl SerializeKdlScalar for BaseColorKind {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        match self {
-            Self::Hex(color) => color.to_string().serialize_scalar(serializer),
-            Self::PaletteReference(reference) => reference.serialize_scalar(serializer),
-        }
+    fn to_kdl_value(&self) -> KdlValue {
+        self.to_string().to_kdl_value()
     }
 }
 impl<T: SerializeKdl> SerializeKdl for Vec<T> {
@@ -214,9 +427,39 @@ impl<T: SerializeKdl> SerializeKdl for Option<T> {
         Ok(())
     }
 }
+impl SerializeKdlScalar for bool {
+    fn to_kdl_value(&self) -> KdlValue {
+        KdlValue::Bool(*self)
+    }
+}
 impl SerializeKdlScalar for u16 {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
-        serializer.writer.write_fmt(format_args!("{self:?}"))
+    fn to_kdl_value(&self) -> KdlValue {
+        KdlValue::Base10(i64::from(*self))
+    }
+}
+impl SerializeKdlScalar for FontWeight {
+    fn to_kdl_value(&self) -> KdlValue {
+        self.keyword().to_kdl_value()
+    }
+}
+impl SerializeKdlScalar for FontStyle {
+    fn to_kdl_value(&self) -> KdlValue {
+        let s = match self {
+            Self::Normal => "normal",
+            Self::Italic => "italic",
+            Self::Oblique => "oblique",
+        };
+        s.to_kdl_value()
+    }
+}
+impl SerializeKdl for FontStyle {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer.inline_node(node_name)?.arg(self)?.finish()?;
+        Ok(())
     }
 }
 impl SerializeKdl for ThemeFamily {
@@ -229,12 +472,30 @@ impl SerializeKdl for ThemeFamily {
         self.palette.serialize("palette", serializer)?;
         self.common.serialize("common", serializer)?;
         self.themes.serialize("theme", serializer)?;
+        self.templates.serialize("template", serializer)?;
+        self.instances.serialize("instantiate", serializer)?;
+        self.includes.serialize("include", serializer)?;
 
         Ok(())
     }
 }
 
-impl SerializeKdl for RawPalette {
+impl SerializeKdl for Overlay {
+    fn serialize<W: Write>(
+        &self,
+        _node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        if !self.palette.colors.is_empty() {
+            self.palette.serialize("palette", serializer)?;
+        }
+        self.common.serialize("common", serializer)?;
+        self.themes.serialize("theme", serializer)?;
+        Ok(())
+    }
+}
+
+impl SerializeKdl for OverlayTheme {
     fn serialize<W: Write>(
         &self,
         node_name: impl Display,
@@ -242,13 +503,62 @@ impl SerializeKdl for RawPalette {
     ) -> std::io::Result<()> {
         serializer
             .children_block(node_name)?
-            .children(self.colors.iter().map(|node| node.clone().into_tuple()))?
+            .child("name", &self.name)?
+            .child("modifier", &self.modifiers)?
             .finish()?;
         Ok(())
     }
 }
 
-impl SerializeKdl for Color {
+impl SerializeKdl for Include {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer
+            .inline_node(node_name)?
+            .arg(&self.path)?
+            .finish()?;
+        Ok(())
+    }
+}
+
+impl SerializeKdl for Template {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer
+            .inline_node(node_name)?
+            .arg(&self.name)?
+            .children_block()?
+            .children(self.params.iter().map(|param| ("param", &param.name)))?
+            .child("theme", &self.theme)?
+            .finish()?;
+        Ok(())
+    }
+}
+
+impl SerializeKdl for Instance {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer
+            .inline_node(node_name)?
+            .arg(&self.name)?
+            .property("template", Some(&self.template))?
+            .children_block()?
+            .children(self.args.iter().map(|node| (&node.name, node)))?
+            .finish()?;
+        Ok(())
+    }
+}
+
+impl SerializeKdl for RawPalette {
     fn serialize<W: Write>(
         &self,
         node_name: impl Display,
@@ -256,18 +566,63 @@ impl SerializeKdl for Color {
     ) -> std::io::Result<()> {
         serializer
             .inline_node(node_name)?
-            .arg(&self.base)?
-            .property("alpha", self.modifiers.alpha)?
-            .property("lighten", self.modifiers.lighten)?
-            .property("darken", self.modifiers.darken)?
-            .property("saturate", self.modifiers.saturate)?
-            .property("desaturate", self.modifiers.desaturate)?
-            .property("hue-shift", self.modifiers.hue_shift)?
+            .property("preserve-hue", self.preserve_hue.then_some(true))?
+            .children_block()?
+            .children(self.colors.iter().map(|node| (&node.name, node)))?
             .finish()?;
         Ok(())
     }
 }
 
+impl SerializeKdl for Color {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serialize_color(self, false, node_name, serializer)
+    }
+}
+
+impl SerializeKdl for ColorNode {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        let (_, color) = self.clone().into_tuple();
+        serialize_color(&color, self.lock, node_name, serializer)
+    }
+}
+
+/// Writes a color with its modifiers, followed by `lock` for locked palette colors.
+fn serialize_color<W: Write>(
+    color: &Color,
+    lock: bool,
+    node_name: impl Display,
+    serializer: &mut KdlSerializer<W>,
+) -> std::io::Result<()> {
+    let annotation = serializer.annotation(color);
+    let mut node = serializer
+        .inline_node(node_name)?
+        .arg(&color.base)?
+        .property("alpha", color.modifiers.alpha)?
+        .property("lighten", color.modifiers.lighten)?
+        .property("darken", color.modifiers.darken)?
+        .property("saturate", color.modifiers.saturate)?
+        .property("desaturate", color.modifiers.desaturate)?
+        .property("hue-shift", color.modifiers.hue_shift)?
+        .property("gamma", color.modifiers.gamma.map(Gamma::get))?
+        .property("temperature", color.modifiers.temperature)?
+        .property("preserve-hue", color.modifiers.preserve_hue)?
+        .property("lock", lock.then_some(true))?;
+    if let Some(hex) = annotation {
+        node = node.comment(hex)?;
+    }
+    node.finish()?;
+    Ok(())
+}
+
 impl SerializeKdl for Meta {
     fn serialize<W: Write>(
         &self,
@@ -278,6 +633,10 @@ impl SerializeKdl for Meta {
             .children_block(node_name)?
             .child("name", &self.name)?
             .child("author", &self.author)?
+            .child("version", &self.version)?
+            .child("license", &self.license)?
+            .child("homepage", &self.homepage)?
+            .child("description", &self.description)?
             .finish()?;
         Ok(())
     }
@@ -293,19 +652,72 @@ impl SerializeKdl for Theme {
             .children_block(node_name)?
             .child("name", &self.name)?
             .child("appearance", &self.appearance)?
+            .child("window-appearance", self.window_appearance)?
             .child("modifier", &self.modifiers)?
             .child("player", &self.players)?
+            .child("accent", &self.accents)?
+            .child("terminal", &self.terminal)?
+            .child("passthrough", &self.passthrough)?
+            .finish()?;
+        Ok(())
+    }
+}
+
+impl SerializeKdl for Passthrough {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        serializer
+            .children_block(node_name)?
+            .child("style", &self.style)?
+            .child("syntax", &self.syntax)?
+            .finish()?;
+        Ok(())
+    }
+}
+
+impl SerializeKdl for PassthroughStyle {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer
+            .inline_node(node_name)?
+            .arg(&self.key)?
+            .arg(&self.value)?
             .finish()?;
         Ok(())
     }
 }
+
+impl SerializeKdl for PassthroughSyntax {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer
+            .inline_node(node_name)?
+            .arg(&self.key)?
+            .properties(self.fields.iter().map(|(name, value)| (name, Some(value))))?
+            .finish()?;
+        Ok(())
+    }
+}
+
 impl SerializeKdlScalar for Appearance {
-    fn serialize_scalar<W: Write>(&self, serializer: &mut KdlSerializer<W>) -> std::io::Result<()> {
+    fn to_kdl_value(&self) -> KdlValue {
         let s = match self {
             Self::Dark => "dark",
             Self::Light => "light",
         };
-        s.serialize_scalar(serializer)
+        s.to_kdl_value()
     }
 }
 
@@ -320,6 +732,28 @@ impl SerializeKdl for Appearance {
     }
 }
 
+impl SerializeKdlScalar for WindowAppearance {
+    fn to_kdl_value(&self) -> KdlValue {
+        let s = match self {
+            Self::Opaque => "opaque",
+            Self::Transparent => "transparent",
+            Self::Blurred => "blurred",
+        };
+        s.to_kdl_value()
+    }
+}
+
+impl SerializeKdl for WindowAppearance {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer.inline_node(node_name)?.arg(self)?.finish()?;
+        Ok(())
+    }
+}
+
 impl SerializeKdl for Modifier {
     fn serialize<W: Write>(
         &self,
@@ -327,7 +761,9 @@ impl SerializeKdl for Modifier {
         serializer: &mut KdlSerializer<W>,
     ) -> std::io::Result<()> {
         serializer
-            .children_block(node_name)?
+            .inline_node(node_name)?
+            .property("if-appearance", self.if_appearance.as_ref())?
+            .children_block()?
             .child("action", &self.action)?
             .child("apply", ApplyBlock(&self.apply))?
             .finish()?;
@@ -378,11 +814,18 @@ impl SerializeKdl for Action {
                 .arg(font_weight)?
                 .finish()?;
         }
+        if let Some(fields) = &self.unset {
+            let mut node = serializer.inline_node("unset")?;
+            for field in fields {
+                node = node.arg(field)?;
+            }
+            node.finish()?;
+        }
 
         Ok(())
     }
 }
-impl SerializeKdl for Player {
+impl SerializeKdl for TerminalColors {
     fn serialize<W: Write>(
         &self,
         node_name: impl Display,
@@ -390,6 +833,37 @@ impl SerializeKdl for Player {
     ) -> std::io::Result<()> {
         serializer
             .children_block(node_name)?
+            .children(
+                self.entries()
+                    .into_iter()
+                    .filter_map(|(name, color)| color.as_ref().map(|color| (name, color))),
+            )?
+            .finish()?;
+        Ok(())
+    }
+}
+
+impl SerializeKdlScalar for ActionField {
+    fn to_kdl_value(&self) -> KdlValue {
+        let s = match self {
+            Self::Color => "color",
+            Self::Background => "background",
+            Self::FontWeight => "font-weight",
+            Self::FontStyle => "font-style",
+        };
+        s.to_kdl_value()
+    }
+}
+impl SerializeKdl for Player {
+    fn serialize<W: Write>(
+        &self,
+        node_name: impl Display,
+        serializer: &mut KdlSerializer<W>,
+    ) -> std::io::Result<()> {
+        serializer
+            .inline_node(node_name)?
+            .properties(self.extra.iter().map(|(name, value)| (name, Some(value))))?
+            .children_block()?
             .child("cursor", &self.cursor)?
             .child("selection", &self.selection)?
             .child("background", &self.background)?
@@ -397,7 +871,43 @@ impl SerializeKdl for Player {
         Ok(())
     }
 }
-pub fn serialize_kdl<W: Write>(writer: W, family: &ThemeFamily) -> std::io::Result<()> {
+/// Serializes a theme family. If a resolved palette is given, colors that refer to the palette
+/// are followed by a comment with their hex color.
+pub fn serialize_kdl<W: Write>(
+    writer: W,
+    family: &ThemeFamily,
+    format: KdlFormat,
+    annotations: Option<ResolvedPalette>,
+) -> std::io::Result<()> {
     debug!("Serializing to KDL");
-    family.serialize("", &mut KdlSerializer::new(writer))
+    let mut serializer = KdlSerializer::new(writer).with_format(format);
+    if let Some(palette) = annotations {
+        serializer = serializer.with_annotations(palette);
+    }
+    family.serialize("", &mut serializer)?;
+    serializer.finish()
+}
+
+/// Serializes a single `theme` node, for commands that produce a theme to paste into a file.
+pub fn serialize_theme_kdl<W: Write>(
+    writer: W,
+    theme: &Theme,
+    format: KdlFormat,
+) -> std::io::Result<()> {
+    debug!("Serializing theme {:?} to KDL", theme.name);
+    let mut serializer = KdlSerializer::new(writer).with_format(format);
+    theme.serialize("theme", &mut serializer)?;
+    serializer.finish()
+}
+
+/// Serializes an overlay, which is written like a partial theme family.
+pub fn serialize_overlay_kdl<W: Write>(
+    writer: W,
+    overlay: &Overlay,
+    format: KdlFormat,
+) -> std::io::Result<()> {
+    debug!("Serializing overlay to KDL");
+    let mut serializer = KdlSerializer::new(writer).with_format(format);
+    overlay.serialize("", &mut serializer)?;
+    serializer.finish()
 }
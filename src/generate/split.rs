@@ -0,0 +1,30 @@
+//! Splits a theme family into several files that are connected with `include` nodes.
+
+use tracing::info;
+
+use crate::schema::include::Include;
+use crate::schema::kdl::{Theme, ThemeFamily};
+use crate::util::slug;
+
+/// Moves every theme of `family` into its own file in `dir`, which is relative to the file of the
+/// family. The family is left with everything that its themes share and an `include` node for
+/// each theme file. Returns the themes with the paths of their files.
+pub fn split_family(family: &mut ThemeFamily, dir: &str) -> Vec<(String, Theme)> {
+    info!(
+        "Splitting {} themes into their own files",
+        family.themes.len()
+    );
+    let mut files: Vec<(String, Theme)> = Vec::with_capacity(family.themes.len());
+    for theme in std::mem::take(&mut family.themes) {
+        let slug = slug(&theme.name);
+        let mut path = format!("{dir}/{slug}.kdl");
+        let mut idx = 1;
+        while files.iter().any(|(x, _)| *x == path) {
+            idx += 1;
+            path = format!("{dir}/{slug}-{idx}.kdl");
+        }
+        family.includes.push(Include { path: path.clone() });
+        files.push((path, theme));
+    }
+    files
+}
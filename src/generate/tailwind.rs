@@ -0,0 +1,68 @@
+//! Writes the palette as the `colors` of a Tailwind config.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use anyhow::Result as Res;
+
+use super::tokens::hex;
+use crate::color::palette::RawPalette;
+use crate::color::{ColorName, Gamut};
+
+/// Writes the resolved palette, or only the colors in `used`, as a JSON object that a
+/// `tailwind.config` can use as its `colors`. Colors named like `blue-500` are grouped into a
+/// `blue` scale, with `blue` itself as the `DEFAULT` shade. Shades are sorted by their number
+/// rather than alphabetically, so that `50` comes before `100`. Display P3 colors are written as
+/// CSS `color(display-p3 ...)` when the `gamut` is P3.
+pub fn tailwind_colors(
+    palette: RawPalette,
+    used: Option<&BTreeSet<ColorName>>,
+    gamut: Gamut,
+) -> Res<String> {
+    let display_p3 = match gamut {
+        Gamut::Srgb => HashMap::new(),
+        Gamut::P3 => palette.display_p3_colors(),
+    };
+    let resolved = palette.into_palette().resolve()?;
+    let mut scales = BTreeMap::<String, BTreeMap<(u32, String), String>>::new();
+    for (name, color) in resolved.colors {
+        if used.is_some_and(|used| !used.contains(&name)) {
+            continue;
+        }
+        let (scale, shade) = match name.rsplit_once('-') {
+            Some((scale, shade)) if shade.parse::<u32>().is_ok() => {
+                (scale.to_owned(), shade.to_owned())
+            }
+            _ => (name.to_string(), "DEFAULT".to_owned()),
+        };
+        let order = shade.parse().unwrap_or(0);
+        let color = display_p3
+            .get(&name)
+            .map_or_else(|| hex(color), ToString::to_string);
+        scales
+            .entry(scale)
+            .or_default()
+            .insert((order, shade), color);
+    }
+
+    let quote = |text: &str| serde_json::Value::from(text).to_string();
+    let mut out = String::from("{\n");
+    for (idx, (scale, shades)) in scales.iter().enumerate() {
+        let comma = if idx + 1 < scales.len() { "," } else { "" };
+        match shades.iter().next() {
+            Some(((_, shade), color)) if shades.len() == 1 && shade == "DEFAULT" => {
+                writeln!(out, "  {}: {}{comma}", quote(scale), quote(color))?;
+            }
+            _ => {
+                writeln!(out, "  {}: {{", quote(scale))?;
+                for (idx, ((_, shade), color)) in shades.iter().enumerate() {
+                    let comma = if idx + 1 < shades.len() { "," } else { "" };
+                    writeln!(out, "    {}: {}{comma}", quote(shade), quote(color))?;
+                }
+                writeln!(out, "  }}{comma}")?;
+            }
+        }
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
@@ -0,0 +1,183 @@
+//! Writes the terminal colors of a generated theme as the color scheme of a terminal emulator,
+//! so that the terminal can match the editor.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+
+use crate::color::HexColor;
+use crate::import::{TerminalScheme, ANSI_NAMES};
+use crate::schema::json::{JsonTheme, StyleEntry};
+
+/// A terminal emulator to write a color scheme for.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalFormat {
+    /// An Alacritty config in TOML, to be imported from `alacritty.toml`
+    Alacritty,
+    /// A kitty config, to be included from `kitty.conf`
+    Kitty,
+    /// A wezterm color scheme in TOML, to be put in a `colors` directory
+    Wezterm,
+    /// A scheme object for the `schemes` list of Windows Terminal's `settings.json`
+    WindowsTerminal,
+}
+
+impl TerminalFormat {
+    /// The extension of the files that the terminal emulator reads the scheme from.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Alacritty | Self::Wezterm => "toml",
+            Self::Kitty => "conf",
+            Self::WindowsTerminal => "json",
+        }
+    }
+}
+
+/// Takes the terminal colors of a generated theme. Missing colors fall back to the ones that Zed
+/// uses in their place, such as the editor background for the terminal background, and colors
+/// that are not opaque are drawn over the background, since terminals have no transparency.
+pub fn terminal_scheme(theme: &JsonTheme) -> Res<TerminalScheme> {
+    let color = |keys: &[&str]| keys.iter().find_map(|key| theme.color(key));
+    let background = color(&["terminal.background", "editor.background", "background"])
+        .ok_or_else(|| anyhow!("the theme {:?} has no background color", theme.name))?;
+    let background = background.over(HexColor([0, 0, 0, 255]));
+    let foreground = color(&["terminal.foreground", "editor.foreground", "text"])
+        .ok_or_else(|| anyhow!("the theme {:?} has no foreground color", theme.name))?;
+
+    let mut ansi = [background; 16];
+    for (idx, slot) in ansi.iter_mut().enumerate() {
+        let key = format!("terminal.ansi.{}", ANSI_NAMES[idx]);
+        let normal = format!("terminal.ansi.{}", ANSI_NAMES[idx % 8]);
+        *slot = color(&[&key, &normal])
+            .ok_or_else(|| anyhow!("the theme {:?} has no {normal} color", theme.name))?
+            .over(background);
+    }
+    let player = match theme.style.get("players") {
+        Some(StyleEntry::Players(players)) => players.first(),
+        _ => None,
+    };
+    Ok(TerminalScheme {
+        name: theme.name.clone(),
+        author: String::new(),
+        ansi,
+        background,
+        foreground: foreground.over(background),
+        cursor: player
+            .and_then(|player| player.cursor)
+            .map(|cursor| cursor.over(background)),
+        selection: player
+            .and_then(|player| player.selection)
+            .map(|selection| selection.over(background)),
+    })
+}
+
+/// Writes a color scheme in the format of a terminal emulator.
+pub fn export_terminal(scheme: &TerminalScheme, format: TerminalFormat) -> Res<String> {
+    match format {
+        TerminalFormat::Alacritty => alacritty(scheme),
+        TerminalFormat::Kitty => kitty(scheme),
+        TerminalFormat::Wezterm => wezterm(scheme),
+        TerminalFormat::WindowsTerminal => windows_terminal(scheme),
+    }
+}
+
+/// Writes a color as `#rrggbb`, which every terminal emulator reads.
+fn hex(color: HexColor) -> String {
+    let HexColor([r, g, b, _]) = color;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn alacritty(scheme: &TerminalScheme) -> Res<String> {
+    let mut out = format!("# {}\n\n", scheme.name);
+    writeln!(out, "[colors.primary]")?;
+    writeln!(out, "background = \"{}\"", hex(scheme.background))?;
+    writeln!(out, "foreground = \"{}\"", hex(scheme.foreground))?;
+    if let Some(cursor) = scheme.cursor {
+        writeln!(out, "\n[colors.cursor]")?;
+        writeln!(out, "text = \"{}\"", hex(scheme.background))?;
+        writeln!(out, "cursor = \"{}\"", hex(cursor))?;
+    }
+    if let Some(selection) = scheme.selection {
+        writeln!(out, "\n[colors.selection]")?;
+        writeln!(out, "text = \"CellForeground\"")?;
+        writeln!(out, "background = \"{}\"", hex(selection))?;
+    }
+    for (table, colors) in [("normal", &scheme.ansi[..8]), ("bright", &scheme.ansi[8..])] {
+        writeln!(out, "\n[colors.{table}]")?;
+        for (name, color) in ANSI_NAMES.iter().zip(colors) {
+            writeln!(out, "{name} = \"{}\"", hex(*color))?;
+        }
+    }
+    Ok(out)
+}
+
+fn kitty(scheme: &TerminalScheme) -> Res<String> {
+    let mut out = format!("## name: {}\n\n", scheme.name);
+    writeln!(out, "background {}", hex(scheme.background))?;
+    writeln!(out, "foreground {}", hex(scheme.foreground))?;
+    if let Some(cursor) = scheme.cursor {
+        writeln!(out, "cursor {}", hex(cursor))?;
+        writeln!(out, "cursor_text_color {}", hex(scheme.background))?;
+    }
+    if let Some(selection) = scheme.selection {
+        writeln!(out, "selection_background {}", hex(selection))?;
+        writeln!(out, "selection_foreground none")?;
+    }
+    out.push('\n');
+    for (idx, color) in scheme.ansi.iter().enumerate() {
+        writeln!(out, "color{idx} {}", hex(*color))?;
+    }
+    Ok(out)
+}
+
+fn wezterm(scheme: &TerminalScheme) -> Res<String> {
+    let list = |colors: &[HexColor]| {
+        colors
+            .iter()
+            .map(|color| format!("\"{}\"", hex(*color)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut out = String::from("[colors]\n");
+    writeln!(out, "ansi = [{}]", list(&scheme.ansi[..8]))?;
+    writeln!(out, "brights = [{}]", list(&scheme.ansi[8..]))?;
+    writeln!(out, "background = \"{}\"", hex(scheme.background))?;
+    writeln!(out, "foreground = \"{}\"", hex(scheme.foreground))?;
+    if let Some(cursor) = scheme.cursor {
+        writeln!(out, "cursor_bg = \"{}\"", hex(cursor))?;
+        writeln!(out, "cursor_border = \"{}\"", hex(cursor))?;
+        writeln!(out, "cursor_fg = \"{}\"", hex(scheme.background))?;
+    }
+    if let Some(selection) = scheme.selection {
+        writeln!(out, "selection_bg = \"{}\"", hex(selection))?;
+    }
+    writeln!(out, "\n[metadata]")?;
+    writeln!(out, "name = {}", toml::Value::String(scheme.name.clone()))?;
+    Ok(out)
+}
+
+fn windows_terminal(scheme: &TerminalScheme) -> Res<String> {
+    // Windows Terminal calls magenta purple
+    let names = ANSI_NAMES.map(|name| {
+        let name = name.replace("magenta", "purple");
+        match name.strip_prefix("bright_") {
+            Some(rest) => format!("bright{}{}", rest[..1].to_uppercase(), &rest[1..]),
+            None => name,
+        }
+    });
+    let mut object = serde_json::Map::new();
+    object.insert("name".to_owned(), scheme.name.clone().into());
+    object.insert("background".to_owned(), hex(scheme.background).into());
+    object.insert("foreground".to_owned(), hex(scheme.foreground).into());
+    if let Some(cursor) = scheme.cursor {
+        object.insert("cursorColor".to_owned(), hex(cursor).into());
+    }
+    if let Some(selection) = scheme.selection {
+        object.insert("selectionBackground".to_owned(), hex(selection).into());
+    }
+    for (name, color) in names.into_iter().zip(scheme.ansi) {
+        object.insert(name, hex(color).into());
+    }
+    Ok(serde_json::to_string_pretty(&object)? + "\n")
+}
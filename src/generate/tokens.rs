@@ -0,0 +1,80 @@
+//! Writes the palette as design tokens in the format of the Design Tokens Community Group, which
+//! Style Dictionary and Tokens Studio for Figma read.
+
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result as Res};
+use serde_json::{json, Map, Value};
+
+use crate::color::palette::RawPalette;
+use crate::color::{BaseColorKind, ColorModifiers, ColorName, DisplayP3, Gamut, HexColor};
+
+/// Writes a color token for every palette entry, or only for the ones in `used`. Entries that
+/// only refer to another entry become aliases of its token, so that changing one color in a
+/// design tool changes everything derived from it. Display P3 colors are written with their
+/// `display-p3` components when the `gamut` is P3, with the sRGB color as their `hex` fallback.
+pub fn design_tokens(
+    palette: &RawPalette,
+    used: Option<&BTreeSet<ColorName>>,
+    gamut: Gamut,
+) -> Res<String> {
+    let kept = |name: &str| used.is_none_or(|used| used.contains(name));
+    let display_p3 = match gamut {
+        Gamut::Srgb => HashMap::new(),
+        Gamut::P3 => palette.display_p3_colors(),
+    };
+    let resolved = palette.clone().into_palette().resolve()?;
+    let mut tokens = Map::new();
+    for node in palette.colors.iter().filter(|node| kept(&node.name)) {
+        let value = match &node.base {
+            BaseColorKind::PaletteReference(name)
+                if node.modifiers == ColorModifiers::default() && kept(name) =>
+            {
+                Value::from(format!("{{{name}}}"))
+            }
+            _ => {
+                let color = *resolved
+                    .colors
+                    .get(node.name.as_str())
+                    .ok_or_else(|| anyhow!("the palette color {:?} was not resolved", node.name))?;
+                match display_p3.get(node.name.as_str()) {
+                    Some(&p3) => components(p3, color),
+                    None => Value::from(hex(color)),
+                }
+            }
+        };
+        tokens.insert(
+            node.name.clone(),
+            json!({ "$type": "color", "$value": value }),
+        );
+    }
+    Ok(serde_json::to_string_pretty(&Value::Object(tokens))? + "\n")
+}
+
+/// Writes a Display P3 color as a color object of the 2025 format, with `fallback` as its `hex`.
+fn components(color: DisplayP3, fallback: HexColor) -> Value {
+    // channels are rounded, since widening them as they are writes 0.2 as 0.20000000298023224
+    let [r, g, b, alpha] = color
+        .channels()
+        .map(|channel| (f64::from(channel) * 1e6).round() / 1e6);
+    let HexColor([red, green, blue, _]) = fallback;
+    let mut object = json!({
+        "colorSpace": "display-p3",
+        "components": [r, g, b],
+        "hex": format!("#{red:02x}{green:02x}{blue:02x}"),
+    });
+    if alpha < 1.0 {
+        object["alpha"] = json!(alpha);
+    }
+    object
+}
+
+/// Writes a color as `#rrggbb`, or as `#rrggbbaa` if it is not opaque.
+pub(super) fn hex(color: HexColor) -> String {
+    let HexColor([r, g, b, a]) = color;
+    if a == 255 {
+        format!("#{r:02x}{g:02x}{b:02x}")
+    } else {
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
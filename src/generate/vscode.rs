@@ -0,0 +1,208 @@
+//! Writes a generated theme as a VS Code color theme, so that the same KDL source can be shipped
+//! as a Zed extension and as a VS Code extension.
+
+use anyhow::Result as Res;
+use serde_json::{json, Map, Value};
+
+use super::tokens::hex;
+use crate::import::{ANSI_NAMES, TEXTMATE_SCOPES};
+use crate::schema::json::{JsonTheme, StyleEntry};
+use crate::schema::{Appearance, FontStyle};
+
+/// Zed's style keys and the workbench colors of VS Code that they set.
+const WORKBENCH_COLORS: [(&str, &[&str]); 41] = [
+    ("text", &["foreground"]),
+    (
+        "text.muted",
+        &["descriptionForeground", "tab.inactiveForeground"],
+    ),
+    ("text.accent", &["textLink.foreground"]),
+    (
+        "border",
+        &[
+            "panel.border",
+            "sideBar.border",
+            "editorGroup.border",
+            "tab.border",
+        ],
+    ),
+    ("border.focused", &["focusBorder"]),
+    ("editor.background", &["editor.background"]),
+    ("editor.foreground", &["editor.foreground"]),
+    ("editor.gutter.background", &["editorGutter.background"]),
+    ("editor.line_number", &["editorLineNumber.foreground"]),
+    (
+        "editor.active_line_number",
+        &["editorLineNumber.activeForeground"],
+    ),
+    (
+        "editor.active_line.background",
+        &["editor.lineHighlightBackground"],
+    ),
+    ("editor.invisible", &["editorWhitespace.foreground"]),
+    ("editor.indent_guide", &["editorIndentGuide.background1"]),
+    (
+        "editor.indent_guide_active",
+        &["editorIndentGuide.activeBackground1"],
+    ),
+    ("editor.wrap_guide", &["editorRuler.foreground"]),
+    (
+        "editor.document_highlight.bracket_background",
+        &["editorBracketMatch.background"],
+    ),
+    (
+        "search.match_background",
+        &["editor.findMatchHighlightBackground"],
+    ),
+    (
+        "panel.background",
+        &["panel.background", "sideBar.background"],
+    ),
+    ("surface.background", &["activityBar.background"]),
+    (
+        "elevated_surface.background",
+        &[
+            "editorWidget.background",
+            "editorSuggestWidget.background",
+            "quickInput.background",
+            "dropdown.background",
+            "menu.background",
+        ],
+    ),
+    ("status_bar.background", &["statusBar.background"]),
+    ("title_bar.background", &["titleBar.activeBackground"]),
+    (
+        "title_bar.inactive_background",
+        &["titleBar.inactiveBackground"],
+    ),
+    ("tab_bar.background", &["editorGroupHeader.tabsBackground"]),
+    ("tab.active_background", &["tab.activeBackground"]),
+    ("tab.inactive_background", &["tab.inactiveBackground"]),
+    ("element.hover", &["list.hoverBackground"]),
+    (
+        "element.selected",
+        &[
+            "list.activeSelectionBackground",
+            "editorSuggestWidget.selectedBackground",
+        ],
+    ),
+    (
+        "scrollbar.thumb.background",
+        &["scrollbarSlider.background"],
+    ),
+    (
+        "scrollbar.thumb.hover_background",
+        &["scrollbarSlider.hoverBackground"],
+    ),
+    ("error", &["errorForeground", "editorError.foreground"]),
+    ("warning", &["editorWarning.foreground"]),
+    ("info", &["editorInfo.foreground"]),
+    ("hint", &["editorHint.foreground"]),
+    (
+        "created",
+        &[
+            "gitDecoration.addedResourceForeground",
+            "editorGutter.addedBackground",
+        ],
+    ),
+    (
+        "modified",
+        &[
+            "gitDecoration.modifiedResourceForeground",
+            "editorGutter.modifiedBackground",
+        ],
+    ),
+    (
+        "deleted",
+        &[
+            "gitDecoration.deletedResourceForeground",
+            "editorGutter.deletedBackground",
+        ],
+    ),
+    ("ignored", &["gitDecoration.ignoredResourceForeground"]),
+    ("conflict", &["gitDecoration.conflictingResourceForeground"]),
+    ("terminal.background", &["terminal.background"]),
+    ("terminal.foreground", &["terminal.foreground"]),
+];
+
+/// Writes a theme as the JSON of a VS Code color theme. The style keys of [`WORKBENCH_COLORS`],
+/// the terminal colors, and the cursor and selection of the first player become workbench
+/// colors, and every syntax scope that the theme sets becomes a token color rule for the grammar
+/// scopes of [`TEXTMATE_SCOPES`].
+pub fn vscode_theme(theme: &JsonTheme) -> Res<String> {
+    let mut colors = Map::new();
+    for (key, names) in WORKBENCH_COLORS {
+        if let Some(color) = theme.color(key) {
+            for name in names {
+                colors.insert((*name).to_owned(), hex(color).into());
+            }
+        }
+    }
+    for name in ANSI_NAMES {
+        if let Some(color) = theme.color(&format!("terminal.ansi.{name}")) {
+            // `bright_black` is `terminal.ansiBrightBlack`
+            let camel = name
+                .split('_')
+                .map(|word| word[..1].to_uppercase() + &word[1..])
+                .collect::<String>();
+            colors.insert(format!("terminal.ansi{camel}"), hex(color).into());
+        }
+    }
+    if let Some(StyleEntry::Players(players)) = theme.style.get("players") {
+        if let Some(player) = players.first() {
+            if let Some(cursor) = player.cursor {
+                colors.insert("editorCursor.foreground".to_owned(), hex(cursor).into());
+            }
+            if let Some(selection) = player.selection {
+                colors.insert(
+                    "editor.selectionBackground".to_owned(),
+                    hex(selection).into(),
+                );
+            }
+        }
+    }
+
+    let mut token_colors = Vec::new();
+    if let Some(StyleEntry::Syntax(syntax)) = theme.style.get("syntax") {
+        for (key, scopes) in TEXTMATE_SCOPES {
+            let Some(entry) = syntax.get(*key) else {
+                continue;
+            };
+            let mut settings = Map::new();
+            if let Some(color) = entry.color {
+                settings.insert("foreground".to_owned(), hex(color).into());
+            }
+            let mut font_style = Vec::new();
+            if entry.font_weight.is_some_and(|weight| weight.get() >= 600) {
+                font_style.push("bold");
+            }
+            if entry.font_style == Some(FontStyle::Italic) {
+                font_style.push("italic");
+            }
+            if !font_style.is_empty() {
+                settings.insert("fontStyle".to_owned(), font_style.join(" ").into());
+            }
+            if settings.is_empty() {
+                continue;
+            }
+            token_colors.push(json!({
+                "name": key,
+                "scope": scopes,
+                "settings": settings,
+            }));
+        }
+    }
+
+    let kind = match theme.appearance {
+        Appearance::Light => "light",
+        Appearance::Dark => "dark",
+    };
+    let object = json!({
+        "$schema": "vscode://schemas/color-theme",
+        "name": theme.name,
+        "type": kind,
+        "colors": Value::Object(colors),
+        "tokenColors": token_colors,
+    });
+    Ok(serde_json::to_string_pretty(&object)? + "\n")
+}
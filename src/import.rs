@@ -0,0 +1,22 @@
+//! Reads themes that were made elsewhere, so that they can be migrated to theme files.
+
+mod extension;
+mod helix;
+mod jetbrains;
+mod plist;
+mod style;
+mod tailwind;
+mod terminal;
+mod textmate;
+mod tokens;
+mod xml;
+mod yaml;
+mod zip;
+
+pub use extension::read_extension;
+pub use helix::{read_helix_theme, HelixField, HELIX_SCOPES, HELIX_UI};
+pub use jetbrains::read_jetbrains_scheme;
+pub use tailwind::read_tailwind_colors;
+pub use terminal::{read_terminal_schemes, read_wal_colors, TerminalScheme, ANSI_NAMES};
+pub use textmate::{read_tm_theme, TEXTMATE_SCOPES};
+pub use tokens::read_design_tokens;
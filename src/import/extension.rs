@@ -0,0 +1,204 @@
+//! Finds the theme families of a Zed extension, which can be a directory, a zip archive such as
+//! the ones GitHub makes of repositories, or a git URL that is cloned.
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Result as Res};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use super::zip::read_zip;
+use crate::schema::JsonThemeFamily;
+use crate::util::{download, is_url, slug};
+
+/// The largest archive that is downloaded.
+const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The fields of `extension.toml` that are kept in the theme files.
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    id: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+    repository: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+}
+
+/// The theme families of an extension.
+pub struct Extension {
+    /// The id of the extension, or the name of its directory or archive if it has no manifest
+    pub id: String,
+    /// Every family with the name of its file in `themes`, without the extension
+    pub families: Vec<(String, JsonThemeFamily)>,
+}
+
+/// The files of an extension that are needed to import it.
+struct Files {
+    manifest: Option<String>,
+    /// The name and contents of every file in `themes`
+    themes: Vec<(String, String)>,
+}
+
+/// Reads the theme families of an extension. The authors of `extension.toml` replace the author
+/// of every family, since the manifest is the one that Zed shows. Its version, description, and
+/// repository fill in the ones that a family does not have.
+pub fn read_extension(source: &str) -> Res<Extension> {
+    let is_http = is_url(source);
+    let has_extension = |extension: &str| {
+        Path::new(source)
+            .extension()
+            .is_some_and(|x| x.eq_ignore_ascii_case(extension))
+    };
+    let is_git = ["ssh://", "git@"]
+        .iter()
+        .any(|scheme| source.starts_with(scheme))
+        || has_extension("git");
+    let files = if is_http && has_extension("zip") {
+        info!("Downloading {source}");
+        archive_files(&download(source, MAX_ARCHIVE_SIZE)?)?
+    } else if is_http || is_git {
+        cloned_files(source)?
+    } else if Path::new(source).is_dir() {
+        directory_files(Path::new(source))?
+    } else {
+        archive_files(&std::fs::read(source).map_err(|e| anyhow!("could not read {source}: {e}"))?)?
+    };
+
+    let manifest = match &files.manifest {
+        Some(text) => {
+            toml::from_str::<Manifest>(text).map_err(|e| anyhow!("invalid extension.toml: {e}"))?
+        }
+        None => Manifest::default(),
+    };
+    let authors = manifest
+        .authors
+        .iter()
+        // authors are usually written as `Name <email>`
+        .map(|author| author.split('<').next().unwrap_or(author).trim())
+        .filter(|author| !author.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut families = Vec::with_capacity(files.themes.len());
+    for (name, text) in files.themes {
+        let mut family: JsonThemeFamily = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("could not read themes/{name}.json: {e}"))?;
+        if !authors.is_empty() {
+            family.meta.author.clone_from(&authors);
+        }
+        if family.meta.name.is_empty() {
+            if let Some(extension_name) = &manifest.name {
+                family.meta.name.clone_from(extension_name);
+            }
+        }
+        let meta = &mut family.meta;
+        for (field, value) in [
+            (&mut meta.version, &manifest.version),
+            (&mut meta.description, &manifest.description),
+            (&mut meta.homepage, &manifest.repository),
+        ] {
+            if field.is_none() {
+                field.clone_from(value);
+            }
+        }
+        families.push((name, family));
+    }
+    if families.is_empty() {
+        return Err(anyhow!("there are no themes in {source}"));
+    }
+    let id = manifest.id.unwrap_or_else(|| {
+        let name = source.trim_end_matches('/').rsplit(['/', '\\', ':']).next();
+        let name = name.unwrap_or(source);
+        slug(name.trim_end_matches(".git").trim_end_matches(".zip"))
+    });
+    Ok(Extension { id, families })
+}
+
+fn directory_files(dir: &Path) -> Res<Files> {
+    let manifest = std::fs::read_to_string(dir.join("extension.toml")).ok();
+    let pattern = dir.join("themes").join("*.json");
+    let mut paths = glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+    // sorted so that the files do not depend on the order of the directory entries
+    paths.sort();
+    let mut themes = Vec::with_capacity(paths.len());
+    for path in paths {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        themes.push((name, std::fs::read_to_string(&path)?));
+    }
+    Ok(Files { manifest, themes })
+}
+
+fn cloned_files(url: &str) -> Res<Files> {
+    let dir = std::env::temp_dir().join(format!("zeddy-import-{}", std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    info!("Cloning {url}");
+    let status = Command::new("git")
+        // `--` keeps a URL that starts with `-` from being read as an option
+        .args(["clone", "--depth", "1", "--quiet", "--", url])
+        .arg(&dir)
+        .status()
+        .map_err(|e| anyhow!("could not run git: {e}"))?;
+    let files = if status.success() {
+        directory_files(&dir)
+    } else {
+        Err(anyhow!("could not clone {url}"))
+    };
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        debug!("Failed to remove {}: {e}", dir.display());
+    }
+    files
+}
+
+/// Reads the files of an archive, which usually has the extension in a top-level directory
+/// named after the repository and branch.
+fn archive_files(archive: &[u8]) -> Res<Files> {
+    let entries = read_zip(archive, MAX_ARCHIVE_SIZE, |name| {
+        name.ends_with("extension.toml")
+            || (name.contains("themes/")
+                && Path::new(name).extension().is_some_and(|x| x == "json"))
+    })?;
+    let depth = |prefix: &&str| prefix.matches('/').count();
+    let is_directory = |prefix: &&str| prefix.is_empty() || prefix.ends_with('/');
+    let manifest_root = entries
+        .iter()
+        .filter_map(|(name, _)| name.strip_suffix("extension.toml"))
+        .filter(is_directory)
+        .min_by_key(depth);
+    let root = manifest_root
+        .or_else(|| {
+            entries
+                .iter()
+                .filter_map(|(name, _)| Some(&name[..name.find("themes/")?]))
+                .filter(is_directory)
+                .min_by_key(depth)
+        })
+        .unwrap_or_default();
+
+    let mut manifest = None;
+    let mut themes = Vec::new();
+    for (name, contents) in &entries {
+        let Some(path) = name.strip_prefix(root) else {
+            continue;
+        };
+        let text =
+            || String::from_utf8(contents.clone()).map_err(|_| anyhow!("{name} is not UTF-8"));
+        if path == "extension.toml" {
+            manifest = Some(text()?);
+        } else if let Some(stem) = path
+            .strip_prefix("themes/")
+            .and_then(|file| file.strip_suffix(".json"))
+            .filter(|stem| !stem.contains('/'))
+        {
+            themes.push((stem.to_owned(), text()?));
+        }
+    }
+    themes.sort();
+    Ok(Files { manifest, themes })
+}
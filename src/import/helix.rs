@@ -0,0 +1,299 @@
+//! Reads Helix themes, which give a style to every scope in a `theme.toml` and can name their
+//! colors in a `[palette]` table, much like the palette of a KDL theme family.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result as Res};
+use toml::{Table, Value};
+
+use super::style::{appearance, base_style, insert_syntax};
+use super::tokens::parse_hex;
+use crate::color::HexColor;
+use crate::schema::json::{JsonTheme, SchemaVersion, StyleEntry, Syntax};
+use crate::schema::{FontStyle, FontWeight, JsonThemeFamily, Meta};
+
+/// The color of a Helix style that a style key of Zed corresponds to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HelixField {
+    Fg,
+    Bg,
+}
+
+/// The scopes of Helix's UI and the style keys of Zed that they correspond to. When several
+/// scopes correspond to the same style key, importing takes the first one that the theme has.
+pub const HELIX_UI: [(&str, HelixField, &str); 32] = [
+    ("ui.background", HelixField::Bg, "editor.background"),
+    ("ui.text", HelixField::Fg, "text"),
+    ("ui.text.inactive", HelixField::Fg, "text.muted"),
+    ("ui.linenr", HelixField::Fg, "editor.line_number"),
+    (
+        "ui.linenr.selected",
+        HelixField::Fg,
+        "editor.active_line_number",
+    ),
+    (
+        "ui.cursorline.primary",
+        HelixField::Bg,
+        "editor.active_line.background",
+    ),
+    (
+        "ui.cursor.match",
+        HelixField::Bg,
+        "editor.document_highlight.bracket_background",
+    ),
+    ("ui.gutter", HelixField::Bg, "editor.gutter.background"),
+    ("ui.statusline", HelixField::Fg, "text"),
+    ("ui.statusline", HelixField::Bg, "status_bar.background"),
+    ("ui.statusline.inactive", HelixField::Fg, "text.muted"),
+    (
+        "ui.statusline.inactive",
+        HelixField::Bg,
+        "status_bar.background",
+    ),
+    ("ui.popup", HelixField::Fg, "text"),
+    ("ui.popup", HelixField::Bg, "elevated_surface.background"),
+    ("ui.menu", HelixField::Fg, "text"),
+    ("ui.menu", HelixField::Bg, "elevated_surface.background"),
+    ("ui.menu.selected", HelixField::Bg, "element.selected"),
+    ("ui.help", HelixField::Bg, "elevated_surface.background"),
+    ("ui.window", HelixField::Fg, "border"),
+    ("ui.bufferline", HelixField::Bg, "tab_bar.background"),
+    (
+        "ui.bufferline.active",
+        HelixField::Bg,
+        "tab.active_background",
+    ),
+    ("ui.virtual.whitespace", HelixField::Fg, "editor.invisible"),
+    (
+        "ui.virtual.indent-guide",
+        HelixField::Fg,
+        "editor.indent_guide",
+    ),
+    ("ui.virtual.ruler", HelixField::Bg, "editor.wrap_guide"),
+    ("ui.virtual.inlay-hint", HelixField::Fg, "hint"),
+    ("error", HelixField::Fg, "error"),
+    ("warning", HelixField::Fg, "warning"),
+    ("info", HelixField::Fg, "info"),
+    ("hint", HelixField::Fg, "hint"),
+    ("diff.plus", HelixField::Fg, "created"),
+    ("diff.minus", HelixField::Fg, "deleted"),
+    ("diff.delta", HelixField::Fg, "modified"),
+];
+
+/// Zed's syntax scopes and the Helix scopes of the same tokens.
+pub const HELIX_SCOPES: [(&str, &str); 35] = [
+    ("attribute", "attribute"),
+    ("boolean", "constant.builtin.boolean"),
+    ("comment", "comment"),
+    ("comment.doc", "comment.block.documentation"),
+    ("constant", "constant"),
+    ("constructor", "constructor"),
+    ("emphasis", "markup.italic"),
+    ("emphasis.strong", "markup.bold"),
+    ("enum", "type.enum"),
+    ("function", "function"),
+    ("keyword", "keyword"),
+    ("label", "label"),
+    ("link_text", "markup.link.text"),
+    ("link_uri", "markup.link.url"),
+    ("number", "constant.numeric"),
+    ("operator", "operator"),
+    ("preproc", "keyword.directive"),
+    ("property", "variable.other.member"),
+    ("punctuation", "punctuation"),
+    ("punctuation.bracket", "punctuation.bracket"),
+    ("punctuation.delimiter", "punctuation.delimiter"),
+    ("punctuation.list_marker", "markup.list"),
+    ("punctuation.special", "punctuation.special"),
+    ("string", "string"),
+    ("string.escape", "constant.character.escape"),
+    ("string.regex", "string.regexp"),
+    ("string.special", "string.special"),
+    ("string.special.symbol", "string.special.symbol"),
+    ("tag", "tag"),
+    ("text.literal", "markup.raw"),
+    ("title", "markup.heading"),
+    ("type", "type"),
+    ("variable", "variable"),
+    ("variable.special", "variable.builtin"),
+    ("variant", "type.enum.variant"),
+];
+
+/// A Helix theme read into a theme family.
+pub struct HelixTheme {
+    pub family: JsonThemeFamily,
+    /// The colors of the `[palette]` table by name
+    pub palette: Vec<(String, HexColor)>,
+    /// The theme that this one `inherits` from, whose styles are not included
+    pub inherits: Option<String>,
+    /// The colors that are neither hex colors nor in the palette, such as the colors of the
+    /// terminal, which were left out
+    pub unknown: Vec<String>,
+}
+
+/// A style of the theme with its colors looked up.
+#[derive(Default)]
+struct Style {
+    fg: Option<HexColor>,
+    bg: Option<HexColor>,
+    modifiers: Vec<String>,
+}
+
+struct Reader<'a> {
+    table: &'a Table,
+    palette: HashMap<&'a str, HexColor>,
+    unknown: BTreeSet<String>,
+}
+
+impl Reader<'_> {
+    fn color(&mut self, value: Option<&Value>) -> Option<HexColor> {
+        let text = value?.as_str()?;
+        let color = if text.starts_with('#') {
+            parse_hex(text).ok()
+        } else {
+            self.palette.get(text).copied()
+        };
+        if color.is_none() {
+            self.unknown.insert(text.to_owned());
+        }
+        color
+    }
+
+    /// Looks up the style of a scope, falling back to the scopes it is nested in like Helix does.
+    fn style(&mut self, scope: &str) -> Option<Style> {
+        let mut scope = scope;
+        let value = loop {
+            if let Some(value) = self.table.get(scope) {
+                break value;
+            }
+            scope = scope.rsplit_once('.')?.0;
+        };
+        Some(match value {
+            Value::Table(style) => Style {
+                fg: self.color(style.get("fg")),
+                bg: self.color(style.get("bg")),
+                modifiers: style
+                    .get("modifiers")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(str::to_owned)
+                    .collect(),
+            },
+            value => Style {
+                fg: self.color(Some(value)),
+                ..Style::default()
+            },
+        })
+    }
+}
+
+/// Reads a Helix `theme.toml` into a theme family with a single theme named `name`, since Helix
+/// names themes after their file.
+pub fn read_helix_theme(text: &str, name: &str) -> Res<HelixTheme> {
+    let table: Table = text.parse()?;
+    let palette = match table.get("palette") {
+        Some(Value::Table(palette)) => palette
+            .iter()
+            .map(|(name, value)| {
+                let color = value
+                    .as_str()
+                    .and_then(|text| parse_hex(text).ok())
+                    .ok_or_else(|| anyhow!("the palette color {name:?} is not a hex color"))?;
+                Ok((name.clone(), color))
+            })
+            .collect::<Res<Vec<_>>>()?,
+        Some(_) => return Err(anyhow!("`palette` is not a table")),
+        None => Vec::new(),
+    };
+    let mut reader = Reader {
+        table: &table,
+        palette: palette
+            .iter()
+            .map(|(name, color)| (name.as_str(), *color))
+            .collect(),
+        unknown: BTreeSet::new(),
+    };
+    let background = reader
+        .style("ui.background")
+        .and_then(|style| style.bg)
+        .ok_or_else(|| anyhow!("the theme has no `ui.background` color"))?;
+    let foreground = reader
+        .style("ui.text")
+        .and_then(|style| style.fg)
+        .ok_or_else(|| anyhow!("the theme has no `ui.text` color"))?;
+    let mut style = base_style(background, foreground);
+    let mut taken = BTreeSet::new();
+    for (scope, field, key) in HELIX_UI {
+        let color = reader.style(scope).and_then(|style| match field {
+            HelixField::Fg => style.fg,
+            HelixField::Bg => style.bg,
+        });
+        if let Some(color) = color.filter(|_| taken.insert(key)) {
+            style.insert(key.to_owned(), StyleEntry::Normal(Some(color)));
+        }
+    }
+    let cursor = reader.style("ui.cursor.primary").and_then(|style| style.bg);
+    let selection = reader
+        .style("ui.selection.primary")
+        .and_then(|style| style.bg);
+    if let Some(StyleEntry::Players(players)) = style.get_mut("players") {
+        if let Some(cursor) = cursor {
+            players[0].cursor = Some(cursor);
+            players[0].background = Some(cursor);
+        }
+        if let Some(selection) = selection {
+            players[0].selection = Some(selection);
+        }
+    }
+    let syntax = syntax(&mut reader);
+    style.insert("syntax".to_owned(), StyleEntry::Syntax(syntax));
+
+    Ok(HelixTheme {
+        family: JsonThemeFamily {
+            schema: SchemaVersion::default().url(),
+            meta: Meta {
+                name: name.to_owned(),
+                author: String::new(),
+                ..Meta::default()
+            },
+            themes: vec![JsonTheme {
+                name: name.to_owned(),
+                appearance: appearance(background),
+                style,
+            }],
+        },
+        inherits: table
+            .get("inherits")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        unknown: reader.unknown.into_iter().collect(),
+        palette,
+    })
+}
+
+/// Colors Zed's syntax scopes like the scopes of [`HELIX_SCOPES`].
+fn syntax(reader: &mut Reader) -> BTreeMap<String, Syntax> {
+    let mut syntax = BTreeMap::new();
+    for (key, scope) in HELIX_SCOPES {
+        let Some(style) = reader.style(scope) else {
+            continue;
+        };
+        let has = |modifier: &str| style.modifiers.iter().any(|x| x == modifier);
+        let font_weight = if has("bold") {
+            FontWeight::try_from(700).ok()
+        } else {
+            None
+        };
+        let entry = Syntax {
+            color: style.fg,
+            background: style.bg,
+            font_weight,
+            font_style: has("italic").then_some(FontStyle::Italic),
+            extra: BTreeMap::new(),
+        };
+        insert_syntax(&mut syntax, key, entry);
+    }
+    syntax
+}
@@ -0,0 +1,467 @@
+//! Reads the editor color schemes of IDEA and the other IDEs built on it, which are exported as
+//! `.icls` files and shipped as `.xml` files in theme plugins. A scheme has a list of editor colors and a list of
+//! text attributes for highlighting, and both are mapped to Zed's style keys and syntax scopes
+//! where there is an equivalent. Attributes for a single language, such as `KOTLIN_KEYWORD`,
+//! have none, since Zed's scopes are the same for every language.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result as Res};
+
+use super::style::{appearance, base_style, player};
+use super::terminal::ANSI_NAMES;
+use super::xml::{parse_xml, Element};
+use crate::color::HexColor;
+use crate::schema::json::{JsonTheme, SchemaVersion, StyleEntry, Syntax};
+use crate::schema::{FontStyle, FontWeight, JsonThemeFamily, Meta};
+
+/// How deeply attributes can inherit from each other through `baseAttributes`, which guards
+/// against cycles.
+const MAX_DEPTH: usize = 16;
+
+/// The bundled schemes that a scheme can name as its `parent_scheme`, with their background and
+/// foreground, which a scheme leaves out when it does not change them.
+const PARENT_SCHEMES: [(&str, u32, u32); 5] = [
+    ("Default", 0xff_ffff, 0x00_0000),
+    ("Darcula", 0x2b_2b2b, 0xa9_b7c6),
+    ("Light", 0xff_ffff, 0x08_0808),
+    ("Dark", 0x1e_1f22, 0xbc_bec4),
+    ("High contrast", 0x00_0000, 0xff_ffff),
+];
+
+/// The editor colors and the style keys that they set.
+const COLORS: [(&str, &[&str]); 14] = [
+    ("CARET_ROW_COLOR", &["editor.active_line.background"]),
+    ("GUTTER_BACKGROUND", &["editor.gutter.background"]),
+    ("LINE_NUMBERS_COLOR", &["editor.line_number"]),
+    (
+        "LINE_NUMBER_ON_CARET_ROW_COLOR",
+        &["editor.active_line_number"],
+    ),
+    ("INDENT_GUIDE", &["editor.indent_guide"]),
+    ("SELECTED_INDENT_GUIDE", &["editor.indent_guide_active"]),
+    ("RIGHT_MARGIN_COLOR", &["editor.wrap_guide"]),
+    ("WHITESPACES", &["editor.invisible"]),
+    ("TEARLINE_COLOR", &["border.variant"]),
+    ("DOCUMENTATION_COLOR", &["elevated_surface.background"]),
+    ("CONSOLE_BACKGROUND_KEY", &["terminal.background"]),
+    ("ADDED_LINES_COLOR", &["created"]),
+    ("MODIFIED_LINES_COLOR", &["modified"]),
+    ("DELETED_LINES_COLOR", &["deleted"]),
+];
+
+/// The editor colors of the local player.
+const CARET_COLOR: &str = "CARET_COLOR";
+const SELECTION_BACKGROUND: &str = "SELECTION_BACKGROUND";
+
+/// The attribute with the default text color and editor background.
+const TEXT: &str = "TEXT";
+
+/// Which color of a text attribute sets a style key.
+#[derive(Clone, Copy)]
+enum Field {
+    /// The text color, or else the color of the underline or error stripe, which is all that
+    /// some attributes for diagnostics have
+    Foreground,
+    Background,
+}
+
+/// The text attributes whose colors set style keys.
+const ATTRIBUTE_COLORS: [(&str, Field, &[&str]); 9] = [
+    ("ERRORS_ATTRIBUTES", Field::Foreground, &["error"]),
+    ("WARNING_ATTRIBUTES", Field::Foreground, &["warning"]),
+    ("INFO_ATTRIBUTES", Field::Foreground, &["info"]),
+    ("INLINE_PARAMETER_HINT", Field::Foreground, &["hint"]),
+    (
+        "SEARCH_RESULT_ATTRIBUTES",
+        Field::Background,
+        &["search.match_background"],
+    ),
+    (
+        "IDENTIFIER_UNDER_CARET_ATTRIBUTES",
+        Field::Background,
+        &["editor.document_highlight.read_background"],
+    ),
+    (
+        "WRITE_IDENTIFIER_UNDER_CARET_ATTRIBUTES",
+        Field::Background,
+        &["editor.document_highlight.write_background"],
+    ),
+    (
+        "MATCHED_BRACE_ATTRIBUTES",
+        Field::Background,
+        &["editor.document_highlight.bracket_background"],
+    ),
+    (
+        "CONSOLE_NORMAL_OUTPUT",
+        Field::Foreground,
+        &["terminal.foreground"],
+    ),
+];
+
+/// The text attributes of the console output in each ANSI color, in the order of
+/// [`ANSI_NAMES`].
+const CONSOLE_COLORS: [&str; 16] = [
+    "CONSOLE_BLACK_OUTPUT",
+    "CONSOLE_RED_OUTPUT",
+    "CONSOLE_GREEN_OUTPUT",
+    "CONSOLE_YELLOW_OUTPUT",
+    "CONSOLE_BLUE_OUTPUT",
+    "CONSOLE_MAGENTA_OUTPUT",
+    "CONSOLE_CYAN_OUTPUT",
+    "CONSOLE_GRAY_OUTPUT",
+    "CONSOLE_DARKGRAY_OUTPUT",
+    "CONSOLE_RED_BRIGHT_OUTPUT",
+    "CONSOLE_GREEN_BRIGHT_OUTPUT",
+    "CONSOLE_YELLOW_BRIGHT_OUTPUT",
+    "CONSOLE_BLUE_BRIGHT_OUTPUT",
+    "CONSOLE_MAGENTA_BRIGHT_OUTPUT",
+    "CONSOLE_CYAN_BRIGHT_OUTPUT",
+    "CONSOLE_WHITE_OUTPUT",
+];
+
+/// The text attributes that color each of Zed's syntax scopes, in order of preference.
+const SYNTAX_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("attribute", &["DEFAULT_ATTRIBUTE", "DEFAULT_METADATA"]),
+    (
+        "comment",
+        &["DEFAULT_LINE_COMMENT", "DEFAULT_BLOCK_COMMENT"],
+    ),
+    ("comment.doc", &["DEFAULT_DOC_COMMENT"]),
+    ("constant", &["DEFAULT_CONSTANT"]),
+    ("embedded", &["DEFAULT_TEMPLATE_LANGUAGE_COLOR"]),
+    ("emphasis", &["MARKDOWN_ITALIC"]),
+    ("emphasis.strong", &["MARKDOWN_BOLD"]),
+    (
+        "function",
+        &[
+            "DEFAULT_FUNCTION_DECLARATION",
+            "DEFAULT_FUNCTION_CALL",
+            "DEFAULT_INSTANCE_METHOD",
+        ],
+    ),
+    ("keyword", &["DEFAULT_KEYWORD"]),
+    ("label", &["DEFAULT_LABEL"]),
+    ("link_text", &["MARKDOWN_LINK_TEXT"]),
+    ("link_uri", &["MARKDOWN_LINK_DESTINATION"]),
+    ("number", &["DEFAULT_NUMBER"]),
+    ("operator", &["DEFAULT_OPERATION_SIGN"]),
+    (
+        "property",
+        &["DEFAULT_INSTANCE_FIELD", "DEFAULT_STATIC_FIELD"],
+    ),
+    (
+        "punctuation.bracket",
+        &["DEFAULT_BRACES", "DEFAULT_BRACKETS", "DEFAULT_PARENTHS"],
+    ),
+    (
+        "punctuation.delimiter",
+        &["DEFAULT_COMMA", "DEFAULT_SEMICOLON", "DEFAULT_DOT"],
+    ),
+    ("punctuation.list_marker", &["MARKDOWN_LIST_ITEM"]),
+    ("string", &["DEFAULT_STRING"]),
+    ("string.escape", &["DEFAULT_VALID_STRING_ESCAPE"]),
+    ("tag", &["DEFAULT_TAG"]),
+    ("text.literal", &["MARKDOWN_CODE_SPAN"]),
+    ("title", &["MARKDOWN_HEADER_LEVEL_1"]),
+    (
+        "type",
+        &[
+            "DEFAULT_CLASS_NAME",
+            "DEFAULT_INTERFACE_NAME",
+            "DEFAULT_CLASS_REFERENCE",
+        ],
+    ),
+    (
+        "variable",
+        &["DEFAULT_LOCAL_VARIABLE", "DEFAULT_IDENTIFIER"],
+    ),
+    ("variable.special", &["DEFAULT_PREDEFINED_SYMBOL"]),
+    ("variant", &["ENUM_CONST"]),
+];
+
+/// The colors and font of a text attribute.
+#[derive(Debug, Clone, Default)]
+struct Attribute {
+    foreground: Option<HexColor>,
+    background: Option<HexColor>,
+    /// The color of the underline, wave, or box that the attribute draws
+    effect: Option<HexColor>,
+    error_stripe: Option<HexColor>,
+    bold: bool,
+    italic: bool,
+}
+
+impl Attribute {
+    fn color(&self, field: Field) -> Option<HexColor> {
+        match field {
+            Field::Foreground => self.foreground.or(self.effect).or(self.error_stripe),
+            Field::Background => self.background,
+        }
+    }
+}
+
+/// A text attribute as it is written, which either has its own value or inherits the one of
+/// another attribute.
+enum RawAttribute {
+    Value(Attribute),
+    Base(String),
+}
+
+/// A scheme that was read, and the keys of it that have no equivalent in Zed, sorted by name.
+pub struct JetbrainsScheme {
+    pub family: JsonThemeFamily,
+    pub unmapped: Vec<String>,
+}
+
+/// Reads an `.icls` scheme into a theme family with a single theme. The background and
+/// foreground fall back to the ones of the bundled scheme that the scheme is based on.
+pub fn read_jetbrains_scheme(text: &str) -> Res<JetbrainsScheme> {
+    let root = parse_xml(text)?;
+    if root.name != "scheme" {
+        return Err(anyhow!(
+            "expected a <scheme> document, found <{}>",
+            root.name
+        ));
+    }
+    // IntelliJ prefixes the names of schemes that were copied from a bundled one
+    let name = root
+        .attribute("name")
+        .unwrap_or_default()
+        .trim_start_matches("_@user_")
+        .to_owned();
+    let colors = read_colors(&root)?;
+    let raw = read_attributes(&root)?;
+    let attributes = raw
+        .keys()
+        .filter_map(|key| Some((key.clone(), resolve(&raw, key, 0)?)))
+        .collect::<HashMap<_, _>>();
+
+    let parent = root.attribute("parent_scheme").unwrap_or("Default");
+    let defaults = PARENT_SCHEMES
+        .iter()
+        .find(|(name, ..)| *name == parent)
+        .map(|&(_, background, foreground)| (from_rgb(background), from_rgb(foreground)));
+    let text = attributes.get(TEXT);
+    let missing = |what: &str| {
+        anyhow!("the scheme has no {what} color, and its parent scheme {parent:?} is not known")
+    };
+    let background = text
+        .and_then(|text| text.background)
+        .or(defaults.map(|(background, _)| background))
+        .ok_or_else(|| missing("background"))?;
+    let foreground = text
+        .and_then(|text| text.foreground)
+        .or(defaults.map(|(_, foreground)| foreground))
+        .ok_or_else(|| missing("foreground"))?;
+
+    let style = style(&colors, &attributes, background, foreground);
+    let unmapped = unmapped(&colors, &raw);
+    Ok(JetbrainsScheme {
+        family: JsonThemeFamily {
+            schema: SchemaVersion::default().url(),
+            meta: Meta {
+                name: name.clone(),
+                author: String::new(),
+                ..Meta::default()
+            },
+            themes: vec![JsonTheme {
+                name,
+                appearance: appearance(background),
+                style,
+            }],
+        },
+        unmapped,
+    })
+}
+
+/// Maps the editor colors and the text attributes to Zed's style keys, leaving the ones that the
+/// scheme does not have to [`base_style`].
+fn style(
+    colors: &HashMap<String, HexColor>,
+    attributes: &HashMap<String, Attribute>,
+    background: HexColor,
+    foreground: HexColor,
+) -> BTreeMap<String, StyleEntry> {
+    let mut style = base_style(background, foreground);
+    for (key, keys) in COLORS {
+        if let Some(color) = colors.get(key) {
+            for key in keys {
+                style.insert((*key).to_owned(), StyleEntry::Normal(Some(*color)));
+            }
+        }
+    }
+    let caret = colors.get(CARET_COLOR).copied().unwrap_or(foreground);
+    let HexColor([r, g, b, _]) = foreground;
+    let selection = colors
+        .get(SELECTION_BACKGROUND)
+        .copied()
+        .unwrap_or(HexColor([r, g, b, 0x33]));
+    style.insert("players".to_owned(), player(caret, selection));
+    for key in ["text.accent", "border.focused"] {
+        style.insert(key.to_owned(), StyleEntry::Normal(Some(caret)));
+    }
+    for (attribute, field, keys) in ATTRIBUTE_COLORS {
+        if let Some(color) = attributes.get(attribute).and_then(|x| x.color(field)) {
+            for key in keys {
+                style.insert((*key).to_owned(), StyleEntry::Normal(Some(color)));
+            }
+        }
+    }
+    for (attribute, name) in CONSOLE_COLORS.iter().zip(ANSI_NAMES) {
+        if let Some(color) = attributes.get(*attribute).and_then(|x| x.foreground) {
+            style.insert(
+                format!("terminal.ansi.{name}"),
+                StyleEntry::Normal(Some(color)),
+            );
+        }
+    }
+    style.insert("syntax".to_owned(), StyleEntry::Syntax(syntax(attributes)));
+    style
+}
+
+/// Returns the keys of the scheme that are in none of the tables, sorted by name.
+fn unmapped(
+    colors: &HashMap<String, HexColor>,
+    raw: &BTreeMap<String, RawAttribute>,
+) -> Vec<String> {
+    let known = COLORS
+        .iter()
+        .map(|(key, _)| *key)
+        .chain([CARET_COLOR, SELECTION_BACKGROUND, TEXT])
+        .chain(ATTRIBUTE_COLORS.iter().map(|(key, ..)| *key))
+        .chain(CONSOLE_COLORS)
+        .chain(
+            SYNTAX_ATTRIBUTES
+                .iter()
+                .flat_map(|(_, keys)| keys.iter().copied()),
+        )
+        .collect::<BTreeSet<_>>();
+    colors
+        .keys()
+        .chain(raw.keys())
+        .filter(|key| !known.contains(key.as_str()))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Reads the `option`s of `<colors>`. Options without a value are left out, since they reset a
+/// color to the one of the IDE's theme.
+fn read_colors(root: &Element) -> Res<HashMap<String, HexColor>> {
+    let mut colors = HashMap::new();
+    for option in root.child("colors").into_iter().flat_map(Element::elements) {
+        let (Some(name), Some(value)) = (option.attribute("name"), option.attribute("value"))
+        else {
+            continue;
+        };
+        if let Some(color) = parse_color(value).map_err(|e| anyhow!("invalid {name}: {e}"))? {
+            colors.insert(name.to_owned(), color);
+        }
+    }
+    Ok(colors)
+}
+
+fn read_attributes(root: &Element) -> Res<BTreeMap<String, RawAttribute>> {
+    let mut attributes = BTreeMap::new();
+    for option in root
+        .child("attributes")
+        .into_iter()
+        .flat_map(Element::elements)
+    {
+        let Some(name) = option.attribute("name") else {
+            continue;
+        };
+        let attribute = match (option.attribute("baseAttributes"), option.child("value")) {
+            (_, Some(value)) => RawAttribute::Value(
+                read_attribute(value).map_err(|e| anyhow!("invalid {name}: {e}"))?,
+            ),
+            (Some(base), None) => RawAttribute::Base(base.to_owned()),
+            (None, None) => continue,
+        };
+        attributes.insert(name.to_owned(), attribute);
+    }
+    Ok(attributes)
+}
+
+/// Reads the `<value>` of a text attribute, whose `FONT_TYPE` is 1 for bold, 2 for italic, and 3
+/// for both.
+fn read_attribute(value: &Element) -> Res<Attribute> {
+    let mut attribute = Attribute::default();
+    for option in value.elements() {
+        let (Some(name), Some(value)) = (option.attribute("name"), option.attribute("value"))
+        else {
+            continue;
+        };
+        match name {
+            "FOREGROUND" => attribute.foreground = parse_color(value)?,
+            "BACKGROUND" => attribute.background = parse_color(value)?,
+            "EFFECT_COLOR" => attribute.effect = parse_color(value)?,
+            "ERROR_STRIPE_COLOR" => attribute.error_stripe = parse_color(value)?,
+            "FONT_TYPE" => {
+                let font_type = value.trim().parse::<u8>().unwrap_or(0);
+                attribute.bold = font_type & 1 != 0;
+                attribute.italic = font_type & 2 != 0;
+            }
+            _ => {}
+        }
+    }
+    Ok(attribute)
+}
+
+/// Follows `baseAttributes` to the attribute that has a value.
+fn resolve(raw: &BTreeMap<String, RawAttribute>, key: &str, depth: usize) -> Option<Attribute> {
+    match raw.get(key)? {
+        RawAttribute::Value(attribute) => Some(attribute.clone()),
+        RawAttribute::Base(base) if depth < MAX_DEPTH => resolve(raw, base, depth + 1),
+        RawAttribute::Base(_) => None,
+    }
+}
+
+fn syntax(attributes: &HashMap<String, Attribute>) -> BTreeMap<String, Syntax> {
+    let mut syntax = BTreeMap::new();
+    for (scope, keys) in SYNTAX_ATTRIBUTES {
+        let Some(attribute) = keys.iter().find_map(|key| attributes.get(*key)) else {
+            continue;
+        };
+        let font_weight = if attribute.bold {
+            FontWeight::try_from(700).ok()
+        } else {
+            None
+        };
+        syntax.insert(
+            (*scope).to_owned(),
+            Syntax {
+                color: attribute.foreground,
+                background: attribute.background,
+                font_weight,
+                font_style: attribute.italic.then_some(FontStyle::Italic),
+                extra: BTreeMap::new(),
+            },
+        );
+    }
+    syntax
+}
+
+/// Parses a color, which is written as hex digits without a `#` and without leading zeros, like
+/// `ff` for blue. Eight digits have an alpha channel at the end. Empty values are `None`.
+fn parse_color(text: &str) -> Res<Option<HexColor>> {
+    let text = text.trim().trim_start_matches('#');
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let value = u32::from_str_radix(text, 16)
+        .map_err(|_| anyhow!("expected a hex color, found {text:?}"))?;
+    let color = match text.len() {
+        ..=6 => from_rgb(value),
+        8 => HexColor(value.to_be_bytes()),
+        _ => return Err(anyhow!("expected a hex color, found {text:?}")),
+    };
+    Ok(Some(color))
+}
+
+fn from_rgb(rgb: u32) -> HexColor {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    HexColor([r, g, b, 255])
+}
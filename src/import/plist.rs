@@ -0,0 +1,105 @@
+//! Reads property lists, the format that macOS applications such as iTerm2 and Terminal.app save
+//! their color schemes in. Both the XML and the binary (`bplist00`) encodings are supported.
+
+use std::io::Cursor;
+
+use anyhow::{anyhow, Result as Res};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Plist {
+    /// The entries of a dictionary in the order they were written
+    Dict(Vec<(String, Plist)>),
+    Array(Vec<Plist>),
+    String(String),
+    Real(f64),
+    Integer(i64),
+    Bool(bool),
+    Data(Vec<u8>),
+    /// A reference to an object of a keyed archive, which only appears in binary property lists
+    Uid(u64),
+    /// A date, which no importer needs, in the ISO 8601 format of XML property lists
+    Date(String),
+}
+
+impl Plist {
+    /// Returns the value of `key` if this is a dictionary that has it.
+    pub fn get(&self, key: &str) -> Option<&Plist> {
+        match self {
+            Plist::Dict(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Plist::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the number, converting integers and booleans.
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "numbers in color schemes are far below 2^52"
+    )]
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Plist::Real(x) => Some(x),
+            Plist::Integer(x) => Some(x as f64),
+            Plist::Bool(x) => Some(f64::from(u8::from(x))),
+            _ => None,
+        }
+    }
+
+    pub fn as_data(&self) -> Option<&[u8]> {
+        match self {
+            Plist::Data(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Plist]> {
+        match self {
+            Plist::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_uid(&self) -> Option<u64> {
+        match *self {
+            Plist::Uid(uid) => Some(uid),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a property list in either encoding. The `plist` crate does the parsing, which also
+/// guards against truncated files and objects of binary property lists that refer to themselves.
+pub fn parse_plist(bytes: &[u8]) -> Res<Plist> {
+    let value = ::plist::Value::from_reader(Cursor::new(bytes))
+        .map_err(|e| anyhow!("invalid property list: {e}"))?;
+    convert(value)
+}
+
+fn convert(value: ::plist::Value) -> Res<Plist> {
+    use ::plist::Value;
+    Ok(match value {
+        Value::Dictionary(dict) => Plist::Dict(
+            dict.into_iter()
+                .map(|(key, value)| Ok((key, convert(value)?)))
+                .collect::<Res<_>>()?,
+        ),
+        Value::Array(items) => Plist::Array(items.into_iter().map(convert).collect::<Res<_>>()?),
+        Value::String(text) => Plist::String(text),
+        Value::Real(x) => Plist::Real(x),
+        Value::Integer(x) => Plist::Integer(
+            x.as_signed()
+                .ok_or_else(|| anyhow!("the property list has an integer that is too large"))?,
+        ),
+        Value::Boolean(x) => Plist::Bool(x),
+        Value::Data(data) => Plist::Data(data),
+        Value::Uid(uid) => Plist::Uid(uid.get()),
+        Value::Date(date) => Plist::Date(date.to_xml_format()),
+        value => return Err(anyhow!("unsupported property list value {value:?}")),
+    })
+}
@@ -0,0 +1,104 @@
+//! The parts of an imported theme that formats without Zed's many UI colors fill in the same way.
+
+use std::collections::BTreeMap;
+
+use crate::color::HexColor;
+use crate::schema::json::{Player, StyleEntry, Syntax};
+use crate::schema::Appearance;
+
+/// The style keys of the surfaces that share the editor background when a format has no colors
+/// for the rest of the UI.
+const SURFACES: [&str; 16] = [
+    "background",
+    "editor.background",
+    "editor.gutter.background",
+    "editor.subheader.background",
+    "terminal.background",
+    "panel.background",
+    "status_bar.background",
+    "title_bar.background",
+    "title_bar.inactive_background",
+    "tab_bar.background",
+    "tab.active_background",
+    "tab.inactive_background",
+    "toolbar.background",
+    "surface.background",
+    "elevated_surface.background",
+    "scrollbar.track.background",
+];
+
+/// Returns whether text on `background` reads better in black, which makes it a light theme.
+pub fn appearance(background: HexColor) -> Appearance {
+    let black = HexColor([0, 0, 0, 255]);
+    let white = HexColor([255, 255, 255, 255]);
+    if background.contrast_ratio(black) > background.contrast_ratio(white) {
+        Appearance::Light
+    } else {
+        Appearance::Dark
+    }
+}
+
+/// Builds the style of a theme from just its background and foreground: every surface gets the
+/// background, and muted text, borders, guides, and hover states get translucent tints of the
+/// foreground. Importers then replace the keys that their format has colors for.
+pub fn base_style(background: HexColor, foreground: HexColor) -> BTreeMap<String, StyleEntry> {
+    let HexColor([r, g, b, _]) = foreground;
+    let tint = |alpha: u8| HexColor([r, g, b, alpha]);
+    let muted = tint(0x80);
+    let border = tint(0x26);
+    let colors = [
+        ("text", foreground),
+        ("editor.foreground", foreground),
+        ("editor.active_line_number", foreground),
+        ("text.muted", muted),
+        ("text.placeholder", muted),
+        ("text.disabled", muted),
+        ("editor.line_number", muted),
+        ("editor.invisible", muted),
+        ("editor.indent_guide_active", muted),
+        ("border", border),
+        ("border.variant", border),
+        ("editor.indent_guide", border),
+        ("editor.wrap_guide", border),
+        ("editor.active_line.background", tint(0x0f)),
+        ("element.hover", tint(0x1a)),
+        ("ghost_element.hover", tint(0x1a)),
+        ("scrollbar.thumb.background", tint(0x33)),
+    ];
+    let mut style = colors
+        .into_iter()
+        .chain(SURFACES.map(|key| (key, background)))
+        .map(|(key, color)| (key.to_owned(), StyleEntry::Normal(Some(color))))
+        .collect::<BTreeMap<_, _>>();
+    style.insert("players".to_owned(), player(foreground, tint(0x33)));
+    style
+}
+
+/// The `players` entry for the local player, with the cursor color also used for their avatar.
+pub fn player(cursor: HexColor, selection: HexColor) -> StyleEntry {
+    StyleEntry::Players(vec![Player {
+        cursor: Some(cursor),
+        background: Some(cursor),
+        selection: Some(selection),
+        extra: BTreeMap::new(),
+    }])
+}
+
+/// Adds `entry` for the syntax scope `key`, unless it is empty or the same as the closest scope
+/// that `key` is nested in and that was added before it, since Zed falls back to that one.
+pub fn insert_syntax(syntax: &mut BTreeMap<String, Syntax>, key: &str, entry: Syntax) {
+    let fields = |x: &Syntax| (x.color, x.background, x.font_weight, x.font_style);
+    let mut parent = None;
+    let mut rest = key;
+    while let Some((prefix, _)) = rest.rsplit_once('.') {
+        parent = syntax.get(prefix);
+        if parent.is_some() {
+            break;
+        }
+        rest = prefix;
+    }
+    let is_empty = fields(&entry) == (None, None, None, None);
+    if !is_empty && parent.is_none_or(|parent| fields(parent) != fields(&entry)) {
+        syntax.insert(key.to_owned(), entry);
+    }
+}
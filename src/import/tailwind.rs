@@ -0,0 +1,110 @@
+//! Reads Tailwind color scales, such as the `colors` of a `tailwind.config`, saved as JSON.
+
+use anyhow::{anyhow, Result as Res};
+use palette::{Clamp, IntoColor, Oklcha, Srgba};
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use super::tokens::parse_hex;
+use crate::color::palette::{ColorNode, RawPalette};
+use crate::color::{BaseColorKind, ColorModifiers, HexColor};
+use crate::schema::location::Location;
+
+/// The values of Tailwind colors that are not colors of their own.
+const KEYWORDS: [&str; 4] = ["inherit", "current", "currentColor", "transparent"];
+
+/// Reads every color into a palette entry named after its scale and shade, like `blue-500`.
+/// Nested objects are joined with dashes and `DEFAULT` shades are named after their scale. The
+/// colors can be the whole object, or the `colors` of it or of its `theme` or `theme.extend`,
+/// so that a config that was exported to JSON can be read as it is.
+pub fn read_tailwind_colors(text: &str) -> Res<RawPalette> {
+    let root: Value = serde_json::from_str(text)?;
+    let theme = root.get("theme");
+    let colors = [
+        theme
+            .and_then(|x| x.get("extend"))
+            .and_then(|x| x.get("colors")),
+        theme.and_then(|x| x.get("colors")),
+        root.get("colors"),
+    ]
+    .into_iter()
+    .flatten()
+    .next()
+    .unwrap_or(&root)
+    .as_object()
+    .ok_or_else(|| anyhow!("expected an object of Tailwind colors"))?;
+
+    let mut palette = RawPalette::default();
+    collect(colors, "", &mut palette)?;
+    if palette.colors.is_empty() {
+        return Err(anyhow!("there are no colors"));
+    }
+    Ok(palette)
+}
+
+fn collect(colors: &Map<String, Value>, prefix: &str, palette: &mut RawPalette) -> Res<()> {
+    for (key, value) in colors {
+        let name = match (prefix, key.as_str()) {
+            ("", _) => key.clone(),
+            (_, "DEFAULT") => prefix.to_owned(),
+            _ => format!("{prefix}-{key}"),
+        };
+        match value {
+            Value::Object(shades) => collect(shades, &name, palette)?,
+            Value::String(text) if KEYWORDS.contains(&text.as_str()) => {
+                debug!("Skipping {name}, which is {text}");
+            }
+            Value::String(text) => {
+                let color = parse_css_color(text).map_err(|e| anyhow!("invalid {name}: {e}"))?;
+                palette.insert(ColorNode {
+                    name,
+                    base: BaseColorKind::Hex(color),
+                    modifiers: ColorModifiers::default(),
+                    lock: false,
+                    location: Location::default(),
+                });
+            }
+            _ => return Err(anyhow!("expected a color for {name}, found {value}")),
+        }
+    }
+    Ok(())
+}
+
+/// Parses a hex color or an `oklch()` color, which is what Tailwind's own palette has used
+/// since version 4.
+fn parse_css_color(text: &str) -> Res<HexColor> {
+    let text = text.trim();
+    let Some(args) = text
+        .strip_prefix("oklch(")
+        .and_then(|x| x.strip_suffix(')'))
+    else {
+        return parse_hex(text);
+    };
+    let number = |arg: &str, percent: f32| -> Res<f32> {
+        let value = match arg.strip_suffix('%') {
+            // a missing component, such as the hue of a gray
+            _ if arg == "none" => Ok(0.0),
+            Some(arg) => arg.parse::<f32>().map(|x| x / 100.0 * percent),
+            None => arg.trim_end_matches("deg").parse(),
+        };
+        value.map_err(|_| anyhow!("invalid number {arg:?} in {text:?}"))
+    };
+    let args = args
+        .split([' ', '/'])
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    let (l, c, h, alpha) = match args[..] {
+        [l, c, h] => (l, c, h, None),
+        [l, c, h, alpha] => (l, c, h, Some(alpha)),
+        _ => return Err(anyhow!("expected 3 or 4 components in {text:?}")),
+    };
+    let oklch = Oklcha::new(
+        number(l, 1.0)?,
+        number(c, 0.4)?,
+        number(h, 360.0)?,
+        alpha.map_or(Ok(1.0), |alpha| number(alpha, 1.0))?,
+    );
+    let rgba: Srgba = oklch.into_color();
+    let rgba = rgba.clamp().into_format::<u8, u8>();
+    Ok(HexColor([rgba.red, rgba.green, rgba.blue, rgba.alpha]))
+}
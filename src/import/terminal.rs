@@ -0,0 +1,420 @@
+//! Turns the color schemes of terminal emulators into theme families. A terminal scheme only has
+//! the 16 ANSI colors and a few others, so the rest of the theme is filled in from those.
+//! iTerm2, Terminal.app, Alacritty, kitty, wezterm, Gogh, and terminal.sexy schemes and the
+//! colors of pywal are supported.
+
+mod alacritty;
+mod gogh;
+mod kitty;
+mod macos;
+mod terminal_sexy;
+mod wal;
+mod wezterm;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result as Res};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::color::naming::{Namer, Naming};
+use crate::color::palette::PaletteGenerator;
+use crate::color::HexColor;
+use crate::generate::generate_kdl;
+use crate::import::yaml::yaml_values;
+use crate::schema::json::{JsonTheme, Player, SchemaVersion, StyleEntry, Syntax};
+use crate::schema::{Appearance, JsonThemeFamily, KdlThemeFamily, Meta};
+
+/// The names of the ANSI colors in order, as used in `terminal.ansi.*`.
+pub const ANSI_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+const RED: usize = 1;
+const GREEN: usize = 2;
+const YELLOW: usize = 3;
+const BLUE: usize = 4;
+const MAGENTA: usize = 5;
+const CYAN: usize = 6;
+const BRIGHT_BLACK: usize = 8;
+
+/// The colors of a terminal emulator's color scheme.
+#[derive(Debug, Clone)]
+pub struct TerminalScheme {
+    pub name: String,
+    /// The author, which most formats do not have
+    pub author: String,
+    /// The ANSI colors in the order of [`ANSI_NAMES`]
+    pub ansi: [HexColor; 16],
+    pub background: HexColor,
+    pub foreground: HexColor,
+    pub cursor: Option<HexColor>,
+    pub selection: Option<HexColor>,
+}
+
+/// Reads the color schemes of terminal emulators in a file or a directory. The format of a file
+/// is detected from its extension, or from its contents if the extension is not one that is
+/// known. Most files have a single scheme, but a JSON list such as Gogh's `themes.json` has
+/// many, and so does a directory such as a clone of Gogh, whose `themes` directory is read if it
+/// has one. Files in a directory that are not color schemes are skipped.
+pub fn read_terminal_schemes(path: &Path) -> Res<Vec<TerminalScheme>> {
+    if !path.is_dir() {
+        return read_file(path);
+    }
+    let themes = path.join("themes");
+    let dir = if themes.is_dir() { &themes } else { path };
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Res<Vec<_>>>()?;
+    // sorted so that the schemes do not depend on the order of the directory entries
+    paths.sort();
+    let mut schemes = Vec::new();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        match read_file(path) {
+            Ok(found) => schemes.extend(found),
+            Err(e) => debug!("Skipping {}: {e}", path.display()),
+        }
+    }
+    if schemes.is_empty() {
+        return Err(anyhow!("there are no color schemes in {}", dir.display()));
+    }
+    Ok(schemes)
+}
+
+/// Reads the `colors.json` of pywal, which is named `wal` so that a theme generated from it keeps
+/// its name when the wallpaper changes.
+pub fn read_wal_colors(path: &Path) -> Res<TerminalScheme> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("could not read {}: {e}", path.display()))?;
+    serde_json::from_str(&text)
+        .map_err(anyhow::Error::from)
+        .and_then(|value| wal::read_wal(&value))
+        .map_err(|e| anyhow!("invalid wal colors {}: {e}", path.display()))
+}
+
+fn read_file(path: &Path) -> Res<Vec<TerminalScheme>> {
+    let extension = path
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let bytes =
+        std::fs::read(path).map_err(|e| anyhow!("could not read {}: {e}", path.display()))?;
+    let text = std::str::from_utf8(&bytes).ok();
+    let is_json = match extension.as_str() {
+        "json" => true,
+        "toml" | "yml" | "yaml" | "conf" | "itermcolors" | "terminal" => false,
+        // TOML is tried first, since Alacritty and WezTerm configs start with a `[colors]` table
+        // that looks like the start of a JSON list
+        _ => text.is_some_and(|text| {
+            toml::from_str::<toml::Value>(text).is_err()
+                && text.trim_start().starts_with(['{', '['])
+        }),
+    };
+    let schemes = match text {
+        Some(text) if is_json => read_json(text),
+        _ => read_scheme(&extension, &bytes).map(|scheme| vec![scheme]),
+    };
+    let mut schemes =
+        schemes.map_err(|e| anyhow!("invalid color scheme {}: {e}", path.display()))?;
+    for scheme in &mut schemes {
+        if scheme.name.is_empty() {
+            scheme.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+    }
+    Ok(schemes)
+}
+
+/// Reads a terminal.sexy export, a Gogh scheme, or a list of either.
+fn read_json(text: &str) -> Res<Vec<TerminalScheme>> {
+    let value = serde_json::from_str::<Value>(text)?;
+    let schemes = match &value {
+        Value::Array(schemes) => schemes.iter().collect(),
+        scheme => vec![scheme],
+    };
+    schemes
+        .into_iter()
+        .map(|scheme| {
+            if scheme.get("color").is_some_and(Value::is_array) {
+                terminal_sexy::read_terminal_sexy(scheme)
+            } else if scheme.get("color_01").is_some() {
+                gogh::read_gogh(|key| Some(scheme.get(key)?.as_str()?.to_owned()))
+            } else if scheme.get("special").is_some() || scheme.get("color0").is_some() {
+                wal::read_wal(scheme)
+            } else {
+                Err(anyhow!(
+                    "the format is not one of Gogh, terminal.sexy, or pywal"
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Reads a Gogh scheme or an Alacritty config in YAML.
+fn read_yaml(text: &str) -> Res<TerminalScheme> {
+    let values = yaml_values(text);
+    if values.contains_key("color_01") {
+        gogh::read_gogh(|key| values.get(key).cloned())
+    } else {
+        alacritty::read_yaml(&values)
+    }
+}
+
+fn read_scheme(extension: &str, bytes: &[u8]) -> Res<TerminalScheme> {
+    let is_plist = bytes.starts_with(b"bplist") || bytes.starts_with(b"<?xml");
+    match extension {
+        "itermcolors" => return macos::read_iterm(bytes),
+        "terminal" => return macos::read_terminal_app(bytes),
+        // property lists of iTerm2 have a key for every ANSI color, unlike Terminal.app's
+        _ if is_plist && contains(bytes, b"Ansi 0 Color") => return macos::read_iterm(bytes),
+        _ if is_plist => return macos::read_terminal_app(bytes),
+        _ => {}
+    }
+
+    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("the file is not UTF-8"))?;
+    match extension {
+        "yml" | "yaml" => return read_yaml(text),
+        "conf" => return kitty::read_kitty(text),
+        _ => {}
+    }
+    if let Ok(config) = toml::from_str::<toml::Value>(text) {
+        // WezTerm lists the ANSI colors, while Alacritty has a table for each half
+        let colors = config.get("colors");
+        return if colors.is_some_and(|colors| colors.get("ansi").is_some()) {
+            wezterm::read_wezterm(&config)
+        } else {
+            alacritty::read_toml(&config)
+        };
+    }
+    if extension == "toml" {
+        return Err(anyhow!("the file is not valid TOML"));
+    }
+    let is_kitty = text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("color0") || line.starts_with("background ")
+    });
+    if is_kitty {
+        kitty::read_kitty(text)
+    } else if text
+        .lines()
+        .any(|line| line.trim_end() == "colors:" || line.starts_with("color_01:"))
+    {
+        read_yaml(text)
+    } else {
+        Err(anyhow!(
+            "the format is not one of iTerm2, Terminal.app, Alacritty, kitty, WezTerm, or Gogh"
+        ))
+    }
+}
+
+fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+    bytes.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Parses a color as terminal emulators write them: `#rrggbb`, `#rgb`, or `0xrrggbb`, with or
+/// without quotes.
+fn parse_color(text: &str) -> Res<HexColor> {
+    let text = text.trim().trim_matches(['"', '\'']);
+    let digits = text
+        .strip_prefix('#')
+        .or_else(|| text.strip_prefix("0x"))
+        .ok_or_else(|| anyhow!("expected a color such as #ff8800, found {text:?}"))?;
+    let hex = if digits.len() == 3 {
+        digits.chars().flat_map(|c| [c, c]).collect()
+    } else {
+        digits.to_owned()
+    };
+    format!("#{hex}")
+        .parse()
+        .map_err(|_| anyhow!("expected a color such as #ff8800, found {text:?}"))
+}
+
+/// Fills in the bright colors that a scheme leaves out with the normal ones. Every normal color
+/// is required.
+fn complete_ansi(ansi: [Option<HexColor>; 16]) -> Res<[HexColor; 16]> {
+    let mut complete = [HexColor([0, 0, 0, 255]); 16];
+    for (idx, slot) in complete.iter_mut().enumerate() {
+        *slot = ansi[idx]
+            .or(ansi[idx % 8])
+            .ok_or_else(|| anyhow!("there is no {} color", ANSI_NAMES[idx % 8]))?;
+    }
+    Ok(complete)
+}
+
+impl TerminalScheme {
+    /// Builds a theme family with a single theme from the scheme. The palette colors are named
+    /// after their role in the terminal, such as `background` and `bright-red`.
+    pub fn into_kdl_family(self) -> Res<KdlThemeFamily> {
+        let cursor = self.cursor.unwrap_or(self.foreground);
+        let roles = [
+            ("background", self.background),
+            ("foreground", self.foreground),
+            ("cursor", cursor),
+        ]
+        .into_iter()
+        .chain(self.selection.map(|selection| ("selection", selection)))
+        .chain(ANSI_NAMES.into_iter().zip(self.ansi))
+        .map(|(name, color)| (color, name.replace('_', "-")))
+        .collect::<Vec<_>>();
+        let namer = Namer::new(roles.iter().map(|(color, name)| (*color, name.as_str())))?;
+
+        let family = JsonThemeFamily {
+            schema: SchemaVersion::default().url(),
+            meta: Meta {
+                name: self.name.clone(),
+                author: self.author.clone(),
+                ..Meta::default()
+            },
+            themes: vec![JsonTheme {
+                name: self.name.clone(),
+                appearance: self.appearance(),
+                style: self.style(),
+            }],
+        };
+        Ok(generate_kdl(
+            family,
+            PaletteGenerator::with_namer(namer),
+            Naming::Nearest,
+        ))
+    }
+
+    fn appearance(&self) -> Appearance {
+        let black = HexColor([0, 0, 0, 255]);
+        let white = HexColor([255, 255, 255, 255]);
+        if self.background.contrast_ratio(black) > self.background.contrast_ratio(white) {
+            Appearance::Light
+        } else {
+            Appearance::Dark
+        }
+    }
+
+    fn style(&self) -> BTreeMap<String, StyleEntry> {
+        let HexColor([r, g, b, _]) = self.foreground;
+        let tint = |alpha: u8| HexColor([r, g, b, alpha]);
+        let ansi = |idx: usize| self.ansi[idx];
+        let cursor = self.cursor.unwrap_or(self.foreground);
+
+        let mut colors = vec![
+            ("editor.active_line.background", tint(0x0f)),
+            ("element.hover", tint(0x1a)),
+            ("ghost_element.hover", tint(0x1a)),
+            ("scrollbar.thumb.background", tint(0x33)),
+            ("text", self.foreground),
+            ("editor.foreground", self.foreground),
+            ("editor.active_line_number", self.foreground),
+            ("terminal.foreground", self.foreground),
+            ("terminal.ansi.background", self.background),
+            ("text.accent", ansi(BLUE)),
+            ("border.focused", ansi(BLUE)),
+            ("error", ansi(RED)),
+            ("deleted", ansi(RED)),
+            ("warning", ansi(YELLOW)),
+            ("modified", ansi(YELLOW)),
+            ("success", ansi(GREEN)),
+            ("created", ansi(GREEN)),
+            ("info", ansi(BLUE)),
+            ("hint", ansi(CYAN)),
+        ];
+        for key in [
+            "background",
+            "editor.background",
+            "editor.gutter.background",
+            "editor.subheader.background",
+            "terminal.background",
+            "panel.background",
+            "status_bar.background",
+            "title_bar.background",
+            "title_bar.inactive_background",
+            "tab_bar.background",
+            "tab.active_background",
+            "tab.inactive_background",
+            "toolbar.background",
+            "surface.background",
+            "elevated_surface.background",
+            "scrollbar.track.background",
+        ] {
+            colors.push((key, self.background));
+        }
+        for key in [
+            "text.muted",
+            "text.placeholder",
+            "text.disabled",
+            "editor.line_number",
+            "editor.indent_guide",
+            "editor.wrap_guide",
+            "editor.invisible",
+            "border",
+            "border.variant",
+        ] {
+            colors.push((key, ansi(BRIGHT_BLACK)));
+        }
+        let mut style = colors
+            .into_iter()
+            .map(|(key, color)| (key.to_owned(), StyleEntry::Normal(Some(color))))
+            .collect::<BTreeMap<_, _>>();
+        for (name, color) in ANSI_NAMES.iter().zip(self.ansi) {
+            style.insert(
+                format!("terminal.ansi.{name}"),
+                StyleEntry::Normal(Some(color)),
+            );
+        }
+
+        style.insert(
+            "players".to_owned(),
+            StyleEntry::Players(vec![Player {
+                cursor: Some(cursor),
+                background: Some(cursor),
+                selection: Some(self.selection.unwrap_or(tint(0x33))),
+                extra: BTreeMap::new(),
+            }]),
+        );
+        style.insert("syntax".to_owned(), StyleEntry::Syntax(self.syntax()));
+        style
+    }
+
+    /// Colors the syntax with the ANSI colors that most terminal programs use for the same kinds
+    /// of tokens.
+    fn syntax(&self) -> BTreeMap<String, Syntax> {
+        [
+            ("comment", BRIGHT_BLACK),
+            ("string", GREEN),
+            ("keyword", MAGENTA),
+            ("function", BLUE),
+            ("type", YELLOW),
+            ("number", CYAN),
+            ("constant", CYAN),
+            ("boolean", CYAN),
+            ("tag", RED),
+            ("attribute", YELLOW),
+            ("property", RED),
+            ("variable.special", RED),
+        ]
+        .into_iter()
+        .map(|(scope, idx)| {
+            let syntax = Syntax {
+                color: Some(self.ansi[idx]),
+                ..Syntax::default()
+            };
+            (scope.to_owned(), syntax)
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,47 @@
+//! Reads Alacritty color schemes, which are written in TOML since Alacritty 0.13 and in YAML
+//! before that. Both put the colors in the same tables, such as `colors.normal.red`.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result as Res};
+
+use super::{complete_ansi, parse_color, TerminalScheme, ANSI_NAMES};
+
+pub fn read_toml(config: &toml::Value) -> Res<TerminalScheme> {
+    read(|path| {
+        path.split('.')
+            .try_fold(config, |value, key| value.get(key))
+            .and_then(toml::Value::as_str)
+            .map(str::to_owned)
+    })
+}
+
+/// Reads a YAML config that was flattened with [`crate::import::yaml::yaml_values`].
+pub fn read_yaml(values: &HashMap<String, String>) -> Res<TerminalScheme> {
+    read(|path| values.get(path).cloned())
+}
+
+fn read(get: impl Fn(&str) -> Option<String>) -> Res<TerminalScheme> {
+    // the cursor and selection can also be `CellForeground` or `CellBackground`, which have no
+    // color of their own
+    let color = |path: &str| get(path).and_then(|text| parse_color(&text).ok());
+    let required = |path: &str| {
+        let text = get(path).ok_or_else(|| anyhow!("there is no {path}"))?;
+        parse_color(&text).map_err(|e| anyhow!("invalid {path}: {e}"))
+    };
+
+    let mut ansi = [None; 16];
+    for (idx, name) in ANSI_NAMES.iter().take(8).enumerate() {
+        ansi[idx] = Some(required(&format!("colors.normal.{name}"))?);
+        ansi[idx + 8] = color(&format!("colors.bright.{name}"));
+    }
+    Ok(TerminalScheme {
+        name: String::new(),
+        author: String::new(),
+        ansi: complete_ansi(ansi)?,
+        background: required("colors.primary.background")?,
+        foreground: required("colors.primary.foreground")?,
+        cursor: color("colors.cursor.cursor"),
+        selection: color("colors.selection.background"),
+    })
+}
@@ -0,0 +1,30 @@
+//! Reads the color schemes of Gogh, which are YAML files or entries of its `themes.json` with the
+//! ANSI colors in `color_01` to `color_16`.
+
+use anyhow::{anyhow, Result as Res};
+
+use super::{complete_ansi, parse_color, TerminalScheme};
+
+/// Reads a scheme whose values are looked up with `get`, which works for both YAML and JSON.
+pub fn read_gogh(get: impl Fn(&str) -> Option<String>) -> Res<TerminalScheme> {
+    let color = |key: &str| {
+        get(key)
+            .map(|text| parse_color(&text).map_err(|e| anyhow!("invalid {key}: {e}")))
+            .transpose()
+    };
+    let required = |key: &str| color(key)?.ok_or_else(|| anyhow!("there is no {key}"));
+
+    let mut ansi = [None; 16];
+    for (idx, slot) in ansi.iter_mut().enumerate() {
+        *slot = color(&format!("color_{:02}", idx + 1))?;
+    }
+    Ok(TerminalScheme {
+        name: get("name").unwrap_or_default(),
+        author: get("author").unwrap_or_default(),
+        ansi: complete_ansi(ansi)?,
+        background: required("background")?,
+        foreground: required("foreground")?,
+        cursor: color("cursor")?,
+        selection: None,
+    })
+}
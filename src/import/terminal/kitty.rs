@@ -0,0 +1,53 @@
+//! Reads kitty color schemes, which are `kitty.conf` files with lines such as `color1 #cc6666`.
+
+use anyhow::{anyhow, Result as Res};
+
+use super::{complete_ansi, parse_color, TerminalScheme};
+
+/// Reads the colors of a kitty config. The name comes from the `## name:` comment that the
+/// themes of kitty-themes start with.
+pub fn read_kitty(text: &str) -> Res<TerminalScheme> {
+    let mut name = String::new();
+    let mut ansi = [None; 16];
+    let mut background = None;
+    let mut foreground = None;
+    let mut cursor = None;
+    let mut selection = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(theme_name) = comment.trim_start_matches('#').trim().strip_prefix("name:") {
+                theme_name.trim().clone_into(&mut name);
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let value = value.trim();
+        // the cursor and selection can be `none`, which uses the colors of the cell
+        let slot = match key {
+            "background" => &mut background,
+            "foreground" => &mut foreground,
+            "cursor" => &mut cursor,
+            "selection_background" => &mut selection,
+            _ => match key
+                .strip_prefix("color")
+                .and_then(|idx| idx.parse::<usize>().ok())
+            {
+                Some(idx) if idx < 16 => &mut ansi[idx],
+                _ => continue,
+            },
+        };
+        *slot = parse_color(value).ok();
+    }
+    Ok(TerminalScheme {
+        name,
+        author: String::new(),
+        ansi: complete_ansi(ansi)?,
+        background: background.ok_or_else(|| anyhow!("there is no background"))?,
+        foreground: foreground.ok_or_else(|| anyhow!("there is no foreground"))?,
+        cursor,
+        selection,
+    })
+}
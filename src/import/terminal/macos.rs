@@ -0,0 +1,165 @@
+//! Reads the color schemes of iTerm2 (`.itermcolors`) and Terminal.app (`.terminal`), which are
+//! both property lists.
+
+use anyhow::{anyhow, Result as Res};
+use palette::{Clamp, Srgba};
+
+use super::TerminalScheme;
+use crate::color::HexColor;
+use crate::import::plist::{parse_plist, Plist};
+
+/// The keys of the ANSI colors in a Terminal.app profile, in order.
+const TERMINAL_APP_ANSI_KEYS: [&str; 16] = [
+    "ANSIBlackColor",
+    "ANSIRedColor",
+    "ANSIGreenColor",
+    "ANSIYellowColor",
+    "ANSIBlueColor",
+    "ANSIMagentaColor",
+    "ANSICyanColor",
+    "ANSIWhiteColor",
+    "ANSIBrightBlackColor",
+    "ANSIBrightRedColor",
+    "ANSIBrightGreenColor",
+    "ANSIBrightYellowColor",
+    "ANSIBrightBlueColor",
+    "ANSIBrightMagentaColor",
+    "ANSIBrightCyanColor",
+    "ANSIBrightWhiteColor",
+];
+
+/// The colors of Terminal.app's Basic profile, which profiles leave out when they do not change
+/// them.
+const TERMINAL_APP_ANSI: [u32; 16] = [
+    0x00_0000, 0x99_0000, 0x00_a600, 0x99_9900, 0x00_00b2, 0xb2_00b2, 0x00_a6b2, 0xbf_bfbf,
+    0x66_6666, 0xe5_0000, 0x00_d900, 0xe5_e500, 0x00_00ff, 0xe5_00e5, 0x00_e5e5, 0xe5_e5e5,
+];
+const TERMINAL_APP_BACKGROUND: u32 = 0xff_ffff;
+const TERMINAL_APP_TEXT: u32 = 0x00_0000;
+
+/// Reads an iTerm2 color preset, which stores every color as a dictionary of components between
+/// 0 and 1.
+pub fn read_iterm(bytes: &[u8]) -> Res<TerminalScheme> {
+    let plist = parse_plist(bytes)?;
+    let color = |key: &str| -> Res<Option<HexColor>> {
+        let Some(color) = plist.get(key) else {
+            return Ok(None);
+        };
+        let component = |name: &str| {
+            color
+                .get(&format!("{name} Component"))
+                .and_then(Plist::as_f64)
+        };
+        let channels = [component("Red"), component("Green"), component("Blue")];
+        let [Some(r), Some(g), Some(b)] = channels else {
+            return Err(anyhow!("{key} is missing a color component"));
+        };
+        Ok(Some(from_components(
+            r,
+            g,
+            b,
+            component("Alpha").unwrap_or(1.0),
+        )))
+    };
+    let required = |key: &str| color(key)?.ok_or_else(|| anyhow!("there is no {key}"));
+
+    let mut ansi = [HexColor([0, 0, 0, 255]); 16];
+    for (idx, slot) in ansi.iter_mut().enumerate() {
+        *slot = required(&format!("Ansi {idx} Color"))?;
+    }
+    Ok(TerminalScheme {
+        name: String::new(),
+        author: String::new(),
+        ansi,
+        background: required("Background Color")?,
+        foreground: required("Foreground Color")?,
+        cursor: color("Cursor Color")?,
+        selection: color("Selection Color")?,
+    })
+}
+
+/// Reads a Terminal.app profile, which stores every color as an archived `NSColor`. Colors that
+/// the profile leaves out are the ones of the Basic profile.
+pub fn read_terminal_app(bytes: &[u8]) -> Res<TerminalScheme> {
+    let plist = parse_plist(bytes)?;
+    let color = |key: &str| -> Res<Option<HexColor>> {
+        plist
+            .get(key)
+            .map(|value| {
+                value
+                    .as_data()
+                    .ok_or_else(|| anyhow!("{key} is not an archived color"))
+                    .and_then(unarchive_color)
+                    .map_err(|e| anyhow!("invalid {key}: {e}"))
+            })
+            .transpose()
+    };
+
+    let mut ansi = TERMINAL_APP_ANSI.map(from_rgb);
+    for (slot, key) in ansi.iter_mut().zip(TERMINAL_APP_ANSI_KEYS) {
+        if let Some(color) = color(key)? {
+            *slot = color;
+        }
+    }
+    Ok(TerminalScheme {
+        name: plist
+            .get("name")
+            .and_then(Plist::as_str)
+            .unwrap_or_default()
+            .to_owned(),
+        author: String::new(),
+        ansi,
+        background: color("BackgroundColor")?.unwrap_or(from_rgb(TERMINAL_APP_BACKGROUND)),
+        foreground: color("TextColor")?.unwrap_or(from_rgb(TERMINAL_APP_TEXT)),
+        cursor: color("CursorColor")?,
+        selection: color("SelectionColor")?,
+    })
+}
+
+/// Reads a color that was archived with `NSKeyedArchiver`. The components are written as text,
+/// such as `0.5 0.25 1`, under `NSRGB` for RGB colors and `NSWhite` for grays.
+fn unarchive_color(data: &[u8]) -> Res<HexColor> {
+    let archive = parse_plist(data)?;
+    let objects = archive
+        .get("$objects")
+        .and_then(Plist::as_array)
+        .ok_or_else(|| anyhow!("not a keyed archive"))?;
+    let root = archive
+        .get("$top")
+        .and_then(|top| top.get("root"))
+        .and_then(Plist::as_uid)
+        .and_then(|uid| objects.get(usize::try_from(uid).ok()?))
+        .ok_or_else(|| anyhow!("the archive has no root object"))?;
+    let components = |key: &str| {
+        root.get(key).and_then(Plist::as_data).map(|text| {
+            String::from_utf8_lossy(text)
+                .trim_end_matches('\0')
+                .split_whitespace()
+                .map(str::parse::<f64>)
+                .collect::<Result<Vec<_>, _>>()
+        })
+    };
+    match (components("NSRGB"), components("NSWhite")) {
+        (Some(Ok(rgb)), _) => match rgb[..] {
+            [r, g, b] => Ok(from_components(r, g, b, 1.0)),
+            [r, g, b, a] => Ok(from_components(r, g, b, a)),
+            _ => Err(anyhow!("NSRGB does not have 3 or 4 components")),
+        },
+        (_, Some(Ok(white))) => match white[..] {
+            [w] => Ok(from_components(w, w, w, 1.0)),
+            [w, a] => Ok(from_components(w, w, w, a)),
+            _ => Err(anyhow!("NSWhite does not have 1 or 2 components")),
+        },
+        _ => Err(anyhow!("only RGB and gray colors are supported")),
+    }
+}
+
+fn from_components(r: f64, g: f64, b: f64, a: f64) -> HexColor {
+    let rgba = Srgba::new(r, g, b, a).clamp().into_format::<u8, u8>();
+    HexColor([rgba.red, rgba.green, rgba.blue, rgba.alpha])
+}
+
+fn from_rgb(rgb: u32) -> HexColor {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    HexColor([r, g, b, 255])
+}
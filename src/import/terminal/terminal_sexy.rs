@@ -0,0 +1,33 @@
+//! Reads the JSON that terminal.sexy exports, which has the ANSI colors in a `color` list.
+
+use anyhow::{anyhow, Result as Res};
+use serde_json::Value;
+
+use super::{complete_ansi, parse_color, TerminalScheme};
+
+pub fn read_terminal_sexy(scheme: &Value) -> Res<TerminalScheme> {
+    let text = |key: &str| scheme.get(key).and_then(Value::as_str);
+    let required = |key: &str| {
+        let color = text(key).ok_or_else(|| anyhow!("there is no {key}"))?;
+        parse_color(color).map_err(|e| anyhow!("invalid {key}: {e}"))
+    };
+
+    let mut ansi = [None; 16];
+    let colors = scheme.get("color").and_then(Value::as_array);
+    for (slot, value) in ansi.iter_mut().zip(colors.into_iter().flatten()) {
+        let color = value
+            .as_str()
+            .ok_or_else(|| anyhow!("color has to be a list of colors"))?;
+        *slot =
+            Some(parse_color(color).map_err(|e| anyhow!("invalid color in the color list: {e}"))?);
+    }
+    Ok(TerminalScheme {
+        name: text("name").unwrap_or_default().to_owned(),
+        author: text("author").unwrap_or_default().to_owned(),
+        ansi: complete_ansi(ansi)?,
+        background: required("background")?,
+        foreground: required("foreground")?,
+        cursor: None,
+        selection: None,
+    })
+}
@@ -0,0 +1,41 @@
+//! Reads the colors that pywal writes to `colors.json` in its cache, which wallust can also
+//! write with a pywal template.
+
+use anyhow::{anyhow, Result as Res};
+use serde_json::Value;
+
+use super::{complete_ansi, parse_color, TerminalScheme};
+
+/// Reads the `special` colors and the `colors` from `color0` to `color15`. Templates that write
+/// every color at the top level of the object are read as well.
+pub fn read_wal(scheme: &Value) -> Res<TerminalScheme> {
+    let special = scheme.get("special").unwrap_or(scheme);
+    let colors = scheme.get("colors").unwrap_or(scheme);
+    let color = |group: &Value, key: &str| {
+        group
+            .get(key)
+            .map(|value| {
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("{key} is not a color"))?;
+                parse_color(text).map_err(|e| anyhow!("invalid {key}: {e}"))
+            })
+            .transpose()
+    };
+    let required =
+        |group: &Value, key: &str| color(group, key)?.ok_or_else(|| anyhow!("there is no {key}"));
+
+    let mut ansi = [None; 16];
+    for (idx, slot) in ansi.iter_mut().enumerate() {
+        *slot = color(colors, &format!("color{idx}"))?;
+    }
+    Ok(TerminalScheme {
+        name: "wal".to_owned(),
+        author: String::new(),
+        ansi: complete_ansi(ansi)?,
+        background: required(special, "background")?,
+        foreground: required(special, "foreground")?,
+        cursor: color(special, "cursor")?,
+        selection: None,
+    })
+}
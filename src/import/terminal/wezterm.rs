@@ -0,0 +1,49 @@
+//! Reads the color schemes of wezterm, which are TOML files with the ANSI colors in the `ansi`
+//! and `brights` arrays of the `colors` table.
+
+use anyhow::{anyhow, Result as Res};
+
+use super::{complete_ansi, parse_color, TerminalScheme};
+
+pub fn read_wezterm(config: &toml::Value) -> Res<TerminalScheme> {
+    let colors = config
+        .get("colors")
+        .ok_or_else(|| anyhow!("there is no colors table"))?;
+    let color = |key: &str| -> Res<Option<_>> {
+        colors
+            .get(key)
+            .and_then(toml::Value::as_str)
+            .map(|text| parse_color(text).map_err(|e| anyhow!("invalid {key}: {e}")))
+            .transpose()
+    };
+    let required = |key: &str| color(key)?.ok_or_else(|| anyhow!("there is no {key}"));
+
+    let mut ansi = [None; 16];
+    for (key, offset) in [("ansi", 0), ("brights", 8)] {
+        let list = colors.get(key).and_then(toml::Value::as_array);
+        for (idx, value) in list.into_iter().flatten().take(8).enumerate() {
+            let text = value
+                .as_str()
+                .ok_or_else(|| anyhow!("{key} has to be a list of colors"))?;
+            ansi[offset + idx] =
+                Some(parse_color(text).map_err(|e| anyhow!("invalid color in {key}: {e}"))?);
+        }
+    }
+    let metadata = |key: &str| {
+        config
+            .get("metadata")
+            .and_then(|metadata| metadata.get(key))
+            .and_then(toml::Value::as_str)
+            .unwrap_or_default()
+            .to_owned()
+    };
+    Ok(TerminalScheme {
+        name: metadata("name"),
+        author: metadata("author"),
+        ansi: complete_ansi(ansi)?,
+        background: required("background")?,
+        foreground: required("foreground")?,
+        cursor: color("cursor_bg")?,
+        selection: color("selection_bg")?,
+    })
+}
@@ -0,0 +1,320 @@
+//! Reads `.tmTheme` color schemes, the format of Sublime Text and the editors before it. They are
+//! property lists with the colors of the editor and a list of rules that color scope selectors.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result as Res};
+
+use super::plist::{parse_plist, Plist};
+use super::style::{appearance, base_style, insert_syntax, player};
+use super::tokens::parse_hex;
+use crate::color::HexColor;
+use crate::schema::json::{JsonTheme, SchemaVersion, StyleEntry, Syntax};
+use crate::schema::{FontStyle, FontWeight, JsonThemeFamily, Meta};
+
+/// The scopes that `.tmTheme` grammars give to the tokens of each of Zed's syntax scopes, in
+/// order of preference. A Zed scope gets the color that the theme gives to the first of these
+/// scopes that it colors.
+pub const TEXTMATE_SCOPES: &[(&str, &[&str])] = &[
+    ("attribute", &["entity.other.attribute-name"]),
+    (
+        "boolean",
+        &["constant.language.boolean", "constant.language"],
+    ),
+    ("comment", &["comment"]),
+    ("comment.doc", &["comment.block.documentation"]),
+    (
+        "constant",
+        &["constant.other", "variable.other.constant", "constant"],
+    ),
+    (
+        "constructor",
+        &["entity.name.function.constructor", "entity.name.type.class"],
+    ),
+    ("emphasis", &["markup.italic"]),
+    ("emphasis.strong", &["markup.bold"]),
+    ("enum", &["entity.name.type.enum", "entity.name.type"]),
+    (
+        "function",
+        &[
+            "entity.name.function",
+            "support.function",
+            "meta.function-call",
+        ],
+    ),
+    ("keyword", &["keyword.control", "keyword", "storage"]),
+    ("label", &["entity.name.label"]),
+    ("link_text", &["string.other.link", "markup.link"]),
+    ("link_uri", &["markup.underline.link"]),
+    ("number", &["constant.numeric"]),
+    ("operator", &["keyword.operator"]),
+    (
+        "preproc",
+        &["meta.preprocessor", "keyword.control.directive"],
+    ),
+    (
+        "property",
+        &[
+            "variable.other.property",
+            "variable.other.member",
+            "support.type.property-name",
+        ],
+    ),
+    ("punctuation", &["punctuation"]),
+    (
+        "punctuation.bracket",
+        &["punctuation.section", "meta.brace"],
+    ),
+    (
+        "punctuation.delimiter",
+        &["punctuation.separator", "punctuation.terminator"],
+    ),
+    (
+        "punctuation.list_marker",
+        &["punctuation.definition.list", "markup.list"],
+    ),
+    (
+        "punctuation.special",
+        &[
+            "punctuation.section.embedded",
+            "punctuation.definition.template-expression",
+        ],
+    ),
+    ("string", &["string"]),
+    ("string.escape", &["constant.character.escape"]),
+    ("string.regex", &["string.regexp"]),
+    (
+        "string.special",
+        &["constant.other.placeholder", "string.other"],
+    ),
+    ("string.special.symbol", &["constant.other.symbol"]),
+    ("tag", &["entity.name.tag"]),
+    ("text.literal", &["markup.inline.raw", "markup.raw"]),
+    ("title", &["markup.heading", "entity.name.section"]),
+    (
+        "type",
+        &[
+            "entity.name.type",
+            "support.type",
+            "support.class",
+            "storage.type",
+        ],
+    ),
+    ("variable", &["variable"]),
+    ("variable.special", &["variable.language"]),
+    (
+        "variant",
+        &["variable.other.enummember", "constant.other.enum"],
+    ),
+];
+
+/// The settings of the editor colors and the style keys that they set.
+const EDITOR_COLORS: [(&str, &[&str]); 9] = [
+    ("gutter", &["editor.gutter.background"]),
+    ("gutterForeground", &["editor.line_number"]),
+    ("gutterForegroundHighlight", &["editor.active_line_number"]),
+    ("lineHighlight", &["editor.active_line.background"]),
+    ("invisibles", &["editor.invisible"]),
+    ("guide", &["editor.indent_guide", "editor.wrap_guide"]),
+    ("activeGuide", &["editor.indent_guide_active"]),
+    ("findHighlight", &["search.match_background"]),
+    ("accent", &["text.accent", "border.focused"]),
+];
+
+/// A rule of the color scheme, which applies its settings to the scopes that its selector
+/// matches.
+struct Rule {
+    /// The scopes of the selector. Selectors that only apply inside of other scopes, like
+    /// `text.html string`, are left out, since Zed's scopes have no context
+    scopes: Vec<String>,
+    foreground: Option<HexColor>,
+    background: Option<HexColor>,
+    font_style: Option<String>,
+}
+
+impl Rule {
+    fn read(rule: &Plist) -> Res<Self> {
+        let settings = rule
+            .get("settings")
+            .ok_or_else(|| anyhow!("a rule has no settings"))?;
+        let scopes = rule
+            .get("scope")
+            .and_then(Plist::as_str)
+            .unwrap_or_default()
+            .split(',')
+            // exclusions like `string - string.unquoted` only narrow the selector down
+            .map(|selector| selector.split(" -").next().unwrap_or_default().trim())
+            .filter(|selector| !selector.is_empty() && !selector.contains(char::is_whitespace))
+            .map(str::to_owned)
+            .collect();
+        Ok(Self {
+            scopes,
+            foreground: color(settings, "foreground")?,
+            background: color(settings, "background")?,
+            font_style: settings
+                .get("fontStyle")
+                .and_then(Plist::as_str)
+                .map(str::to_owned),
+        })
+    }
+
+    /// How closely the rule matches `scope`, as the number of parts of its most specific
+    /// selector that is a prefix of the scope.
+    fn score(&self, scope: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .filter(|selector| {
+                scope
+                    .strip_prefix(selector.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+            .map(|selector| selector.split('.').count())
+            .max()
+    }
+}
+
+/// Reads a `.tmTheme` into a theme family with a single theme. The theme is named after the
+/// `name` of the scheme, or `fallback_name` if it has none.
+pub fn read_tm_theme(bytes: &[u8], fallback_name: &str) -> Res<JsonThemeFamily> {
+    let plist = parse_plist(bytes)?;
+    let settings = plist
+        .get("settings")
+        .and_then(Plist::as_array)
+        .ok_or_else(|| anyhow!("the color scheme has no settings"))?;
+    // the rule without a scope holds the colors of the editor
+    let (globals, rules): (Vec<_>, Vec<_>) = settings
+        .iter()
+        .partition(|rule| rule.get("scope").is_none());
+    let globals = globals
+        .first()
+        .and_then(|rule| rule.get("settings"))
+        .ok_or_else(|| anyhow!("the color scheme has no editor colors"))?;
+    let rules = rules.into_iter().map(Rule::read).collect::<Res<Vec<_>>>()?;
+
+    let name = plist
+        .get("name")
+        .and_then(Plist::as_str)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(fallback_name)
+        .to_owned();
+    let background =
+        color(globals, "background")?.ok_or_else(|| anyhow!("there is no background color"))?;
+    let mut style = editor_style(globals, background)?;
+    status_style(&mut style, &rules);
+    style.insert("syntax".to_owned(), StyleEntry::Syntax(syntax(&rules)));
+    Ok(JsonThemeFamily {
+        schema: SchemaVersion::default().url(),
+        meta: Meta {
+            name: name.clone(),
+            author: plist
+                .get("author")
+                .and_then(Plist::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            ..Meta::default()
+        },
+        themes: vec![JsonTheme {
+            name,
+            appearance: appearance(background),
+            style,
+        }],
+    })
+}
+
+fn color(settings: &Plist, key: &str) -> Res<Option<HexColor>> {
+    settings
+        .get(key)
+        .and_then(Plist::as_str)
+        .map(|text| parse_hex(text.trim()).map_err(|e| anyhow!("invalid {key}: {e}")))
+        .transpose()
+}
+
+/// Maps the colors of the editor to Zed's, leaving the ones that the scheme does not have to
+/// [`base_style`].
+fn editor_style(globals: &Plist, background: HexColor) -> Res<BTreeMap<String, StyleEntry>> {
+    let foreground =
+        color(globals, "foreground")?.ok_or_else(|| anyhow!("there is no foreground color"))?;
+    let mut style = base_style(background, foreground);
+    for (setting, keys) in EDITOR_COLORS {
+        if let Some(color) = color(globals, setting)? {
+            for key in keys {
+                style.insert((*key).to_owned(), StyleEntry::Normal(Some(color)));
+            }
+        }
+    }
+    let caret = color(globals, "caret")?.unwrap_or(foreground);
+    if color(globals, "accent")?.is_none() {
+        for key in ["text.accent", "border.focused"] {
+            style.insert(key.to_owned(), StyleEntry::Normal(Some(caret)));
+        }
+    }
+    if let Some(selection) = color(globals, "selection")? {
+        style.insert("players".to_owned(), player(caret, selection));
+    } else if let Some(StyleEntry::Players(players)) = style.get_mut("players") {
+        players[0].cursor = Some(caret);
+        players[0].background = Some(caret);
+    }
+    Ok(style)
+}
+
+/// Takes the colors of errors and version control changes from the rules for invalid code and
+/// diffs, when the scheme has them.
+fn status_style(style: &mut BTreeMap<String, StyleEntry>, rules: &[Rule]) {
+    for (scope, keys) in [
+        ("invalid", &["error"][..]),
+        ("markup.deleted", &["deleted", "conflict"]),
+        ("markup.inserted", &["created", "success"]),
+        ("markup.changed", &["modified", "warning"]),
+    ] {
+        if let Some(color) = best_rule(rules, &[scope], |rule| rule.foreground) {
+            for key in keys {
+                style.insert((*key).to_owned(), StyleEntry::Normal(Some(color)));
+            }
+        }
+    }
+}
+
+/// Returns `get` of the rule that matches the first of `scopes` most closely among the rules
+/// that set it. Later rules win ties, like in Sublime Text.
+fn best_rule<T>(rules: &[Rule], scopes: &[&str], get: impl Fn(&Rule) -> Option<T>) -> Option<T> {
+    scopes.iter().find_map(|scope| {
+        let mut best = None;
+        for rule in rules {
+            let (Some(score), Some(value)) = (rule.score(scope), get(rule)) else {
+                continue;
+            };
+            if best.as_ref().is_none_or(|(best, _)| score >= *best) {
+                best = Some((score, value));
+            }
+        }
+        best.map(|(_, value)| value)
+    })
+}
+
+/// Colors Zed's syntax scopes like the scopes of [`TEXTMATE_SCOPES`]. Scopes that end up
+/// the same as the scope they are nested in are left out, since Zed falls back to that one.
+fn syntax(rules: &[Rule]) -> BTreeMap<String, Syntax> {
+    let mut syntax = BTreeMap::new();
+    for (key, scopes) in TEXTMATE_SCOPES {
+        let font_style = best_rule(rules, scopes, |rule| rule.font_style.clone());
+        let has = |word: &str| {
+            font_style
+                .as_deref()
+                .is_some_and(|style| style.split_whitespace().any(|x| x == word))
+        };
+        let font_weight = if has("bold") {
+            FontWeight::try_from(700).ok()
+        } else {
+            None
+        };
+        let entry = Syntax {
+            color: best_rule(rules, scopes, |rule| rule.foreground),
+            background: best_rule(rules, scopes, |rule| rule.background),
+            font_weight,
+            font_style: has("italic").then_some(FontStyle::Italic),
+            extra: BTreeMap::new(),
+        };
+        insert_syntax(&mut syntax, key, entry);
+    }
+    syntax
+}
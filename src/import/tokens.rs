@@ -0,0 +1,154 @@
+//! Reads the color tokens of a design tokens file in the format of the Design Tokens Community
+//! Group, which Style Dictionary and Tokens Studio for Figma write.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result as Res};
+use palette::{Clamp, Srgba};
+use serde_json::{Map, Value};
+
+use crate::color::palette::{ColorNode, RawPalette};
+use crate::color::{BaseColorKind, ColorModifiers, DisplayP3, HexColor};
+use crate::schema::location::Location;
+
+/// The top-level groups that only say that their tokens are colors, which are left out of the
+/// names of the palette entries.
+const COLOR_GROUPS: [&str; 2] = ["color", "colors"];
+
+/// Reads every color token into a palette entry. The names of nested groups are joined with
+/// dashes, `$type` is inherited from the enclosing groups, and aliases such as `{blue.500}`
+/// become references to other palette entries. Tokens of other types are ignored.
+pub fn read_design_tokens(text: &str) -> Res<RawPalette> {
+    let root: Value = serde_json::from_str(text)?;
+    let root = root
+        .as_object()
+        .ok_or_else(|| anyhow!("expected an object of design tokens"))?;
+    let mut tokens = Vec::new();
+    collect(root, &mut Vec::new(), None, &mut tokens);
+
+    let names = tokens
+        .iter()
+        .map(|(path, _)| (path.join("."), entry_name(path)))
+        .collect::<BTreeMap<_, _>>();
+    let mut palette = RawPalette::default();
+    for (path, value) in tokens {
+        let name = entry_name(&path);
+        let base = read_value(value, &names)
+            .map_err(|e| anyhow!("invalid token {:?}: {e}", path.join(".")))?;
+        palette.insert(ColorNode {
+            name,
+            base,
+            modifiers: ColorModifiers::default(),
+            lock: false,
+            location: Location::default(),
+        });
+    }
+    if palette.colors.is_empty() {
+        return Err(anyhow!("there are no color tokens"));
+    }
+    Ok(palette)
+}
+
+/// Collects the paths and values of the color tokens in a group. A token is an object with a
+/// `$value`, and the other objects are groups.
+fn collect<'a>(
+    group: &'a Map<String, Value>,
+    path: &mut Vec<String>,
+    inherited: Option<&'a str>,
+    tokens: &mut Vec<(Vec<String>, &'a Value)>,
+) {
+    let group_type = group.get("$type").and_then(Value::as_str).or(inherited);
+    for (key, value) in group {
+        let Some(object) = value.as_object().filter(|_| !key.starts_with('$')) else {
+            continue;
+        };
+        path.push(key.clone());
+        match object.get("$value") {
+            Some(value) => {
+                let token_type = object.get("$type").and_then(Value::as_str).or(group_type);
+                if token_type == Some("color") {
+                    tokens.push((path.clone(), value));
+                }
+            }
+            None => collect(object, path, group_type, tokens),
+        }
+        path.pop();
+    }
+}
+
+fn entry_name(path: &[String]) -> String {
+    let path = match path {
+        [group, rest @ ..] if !rest.is_empty() && COLOR_GROUPS.contains(&group.as_str()) => rest,
+        _ => path,
+    };
+    path.join("-")
+}
+
+/// Reads a color, which is either a hex string, an object with `sRGB` or Display P3 components,
+/// or an alias of another token. `names` maps the paths of the color tokens to their palette entries.
+fn read_value(value: &Value, names: &BTreeMap<String, String>) -> Res<BaseColorKind> {
+    if let Some(object) = value.as_object() {
+        return read_components(object);
+    }
+    let text = value
+        .as_str()
+        .ok_or_else(|| anyhow!("expected a color, found {value}"))?
+        .trim();
+    if let Some(alias) = text.strip_prefix('{').and_then(|x| x.strip_suffix('}')) {
+        return names
+            .get(alias)
+            .map(|name| BaseColorKind::PaletteReference(name.as_str().into()))
+            .ok_or_else(|| anyhow!("the alias {text} does not refer to a color token"));
+    }
+    parse_hex(text).map(BaseColorKind::Hex)
+}
+
+/// Reads a color object of the 2025 format, which has `components` between 0 and 1 in a
+/// `colorSpace` and an optional `alpha`. `display-p3` colors are kept as they are, and the `hex`
+/// fallback is used for other color spaces.
+fn read_components(object: &Map<String, Value>) -> Res<BaseColorKind> {
+    let alpha = object.get("alpha").and_then(Value::as_f64).unwrap_or(1.0);
+    let space = object.get("colorSpace").and_then(Value::as_str);
+    let components = object
+        .get("components")
+        .and_then(Value::as_array)
+        .map(|x| x.iter().map(Value::as_f64).collect::<Option<Vec<_>>>());
+    if let (Some("srgb"), Some(Some(components))) = (space, &components) {
+        let [r, g, b] = components[..] else {
+            return Err(anyhow!("an srgb color needs 3 components"));
+        };
+        let rgba = Srgba::new(r, g, b, alpha).clamp().into_format::<u8, u8>();
+        return Ok(BaseColorKind::Hex(HexColor([
+            rgba.red, rgba.green, rgba.blue, rgba.alpha,
+        ])));
+    }
+    if let (Some("display-p3"), Some(Some(components))) = (space, &components) {
+        let [r, g, b] = components[..] else {
+            return Err(anyhow!("a display-p3 color needs 3 components"));
+        };
+        #[allow(clippy::cast_possible_truncation, reason = "channels go from 0 to 1")]
+        let channels = [r, g, b, alpha].map(|channel| channel.clamp(0.0, 1.0) as f32);
+        return Ok(BaseColorKind::DisplayP3(DisplayP3::new(channels)?));
+    }
+    let hex = object.get("hex").and_then(Value::as_str).ok_or_else(|| {
+        anyhow!("only srgb colors are supported, unless the color has a hex fallback")
+    })?;
+    let HexColor([r, g, b, _]) = parse_hex(hex)?;
+    let alpha = Srgba::new(0.0, 0.0, 0.0, alpha)
+        .clamp()
+        .into_format::<u8, u8>()
+        .alpha;
+    Ok(BaseColorKind::Hex(HexColor([r, g, b, alpha])))
+}
+
+/// Parses `#rgb`, `#rgba`, `#rrggbb`, or `#rrggbbaa`.
+pub(super) fn parse_hex(text: &str) -> Res<HexColor> {
+    let error = || anyhow!("expected a color such as #ff8800, found {text:?}");
+    let digits = text.strip_prefix('#').ok_or_else(error)?;
+    let hex = if digits.len() == 3 || digits.len() == 4 {
+        digits.chars().flat_map(|c| [c, c]).collect()
+    } else {
+        digits.to_owned()
+    };
+    format!("#{hex}").parse().map_err(|_| error())
+}
@@ -0,0 +1,229 @@
+//! A small XML parser for the editor schemes that themes are imported from. It builds the whole
+//! tree of elements in memory and ignores text, namespaces, DTDs, and processing instructions.
+
+use anyhow::{anyhow, Result as Res};
+
+/// How deeply elements can be nested, so that a malicious document cannot overflow the stack.
+/// Editor schemes are only a few levels deep.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub name: String,
+    /// The attributes in the order they were written
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    /// Iterates over the child elements.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the first child element named `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+}
+
+/// Parses a document and returns its root element.
+pub fn parse_xml(text: &str) -> Res<Element> {
+    let mut parser = Parser { text, pos: 0 };
+    parser.skip_misc()?;
+    let root = parser.element(0)?;
+    parser.skip_misc()?;
+    if parser.pos < text.len() {
+        return Err(parser.error("content after the root element"));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        anyhow!("invalid XML on line {line}: {message}")
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Moves past `end`, which has to come later in the text.
+    fn skip_past(&mut self, end: &str) -> Res<()> {
+        let idx = self
+            .rest()
+            .find(end)
+            .ok_or_else(|| self.error(&format!("missing {end:?}")))?;
+        self.pos += idx + end.len();
+        Ok(())
+    }
+
+    /// Skips whitespace, comments, processing instructions, and the document type declaration.
+    fn skip_misc(&mut self) -> Res<()> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                // an internal subset in brackets can contain `>`
+                let end = self.rest().find(['[', '>']).unwrap_or(0);
+                if self.rest()[end..].starts_with('[') {
+                    self.skip_past("]")?;
+                }
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Res<String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = rest[..len].to_owned();
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn element(&mut self, depth: usize) -> Res<Element> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("elements are nested too deeply"));
+        }
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        self.pos += 1;
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(Element {
+                    name,
+                    attributes,
+                    children: Vec::new(),
+                });
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(&format!("expected a value for attribute {key:?}")));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = self
+                .rest()
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+                .ok_or_else(|| self.error("expected a quoted attribute value"))?;
+            self.pos += 1;
+            let len = self
+                .rest()
+                .find(quote)
+                .ok_or_else(|| self.error("unterminated attribute value"))?;
+            attributes.push((key, unescape(&self.rest()[..len])));
+            self.pos += len + 1;
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let end = self.name()?;
+                if end != name {
+                    return Err(self.error(&format!("expected </{name}>, found </{end}>")));
+                }
+                self.skip_whitespace();
+                self.skip_past(">")?;
+                return Ok(Element {
+                    name,
+                    attributes,
+                    children,
+                });
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                children.push(self.element(depth + 1)?);
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("missing </{name}>")));
+            } else {
+                self.pos += rest.find('<').unwrap_or(rest.len());
+            }
+        }
+    }
+}
+
+/// Replaces the predefined entities and character references. Unknown entities are kept as
+/// they are.
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        if let Some(c) = replacement {
+            result.push(c);
+        } else {
+            result.push_str(&rest[..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
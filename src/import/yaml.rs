@@ -0,0 +1,64 @@
+//! Just enough of a YAML reader for the color schemes of terminal emulators, which are flat or
+//! nested mappings of strings.
+
+use std::collections::HashMap;
+
+/// Flattens the mappings of a YAML document into dotted paths to their scalar values, such as
+/// `colors.primary.background`. Only block mappings are read, which is all that color schemes
+/// use; sequences, anchors, and multi-line scalars are skipped.
+pub fn yaml_values(text: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    // the indentation and key of every mapping that contains the current line
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    for line in text.lines() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') || content.starts_with('-') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        while parents.last().is_some_and(|(parent, _)| *parent >= indent) {
+            parents.pop();
+        }
+        let Some((key, value)) = content.split_once(':') else {
+            continue;
+        };
+        let key = unquote(key.trim());
+        let value = strip_comment(value).trim();
+        if value.is_empty() {
+            parents.push((indent, key.to_owned()));
+            continue;
+        }
+        let path = parents
+            .iter()
+            .map(|(_, parent)| parent.as_str())
+            .chain([key])
+            .collect::<Vec<_>>()
+            .join(".");
+        values.insert(path, unquote(value).to_owned());
+    }
+    values
+}
+
+/// Removes a comment after a value. A `#` only starts a comment after whitespace and outside of
+/// quotes, which is why colors such as `'#ff0000'` have to be quoted in YAML.
+fn strip_comment(value: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (idx, c) in value.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '#') if previous.is_whitespace() => return &value[..idx],
+            _ => {}
+        }
+        previous = c;
+    }
+    value
+}
+
+fn unquote(text: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|&quote| text.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(text)
+}
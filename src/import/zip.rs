@@ -0,0 +1,110 @@
+//! Just enough of a zip reader to get files out of the archives that GitHub makes of
+//! repositories. Only stored and deflated entries are supported, without zip64 or encryption.
+
+use std::io::Read;
+
+use anyhow::{anyhow, Result as Res};
+use flate2::read::DeflateDecoder;
+
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+/// The size of the end of central directory record without its comment.
+const END_OF_DIRECTORY_SIZE: usize = 22;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// Returns the files of a zip archive whose path is accepted by `wanted`, with their contents.
+/// Directories are skipped, and files that inflate to more than `max_size` bytes are an error, so
+/// that a small archive cannot claim or unpack gigabytes.
+pub fn read_zip(
+    archive: &[u8],
+    max_size: u64,
+    wanted: impl Fn(&str) -> bool,
+) -> Res<Vec<(String, Vec<u8>)>> {
+    // the record is at the end of the archive, followed by a comment of at most 64 KiB
+    let end = (0..=archive.len().saturating_sub(END_OF_DIRECTORY_SIZE))
+        .rev()
+        .take(usize::from(u16::MAX) + 1)
+        .find(|&offset| u32_at(archive, offset).is_ok_and(|x| x == END_OF_DIRECTORY))
+        .ok_or_else(|| anyhow!("not a zip archive"))?;
+    let entries = u16_at(archive, end + 10)?;
+    let mut offset = offset_at(archive, end + 16)?;
+
+    let mut files = Vec::new();
+    for _ in 0..entries {
+        if u32_at(archive, offset)? != DIRECTORY_ENTRY {
+            return Err(anyhow!("the zip archive is corrupted"));
+        }
+        let method = u16_at(archive, offset + 10)?;
+        let compressed_size = offset_at(archive, offset + 20)?;
+        let size = offset_at(archive, offset + 24)?;
+        let name_len = usize::from(u16_at(archive, offset + 28)?);
+        let extra_len = usize::from(u16_at(archive, offset + 30)?);
+        let comment_len = usize::from(u16_at(archive, offset + 32)?);
+        let header = offset_at(archive, offset + 42)?;
+        let name = String::from_utf8_lossy(slice(archive, offset + 46, name_len)?).into_owned();
+        offset += 46 + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') || !wanted(&name) {
+            continue;
+        }
+        if u32_at(archive, header)? != LOCAL_HEADER {
+            return Err(anyhow!("the zip archive is corrupted"));
+        }
+        // the local header can have a different extra field than the directory entry
+        let data_start = header
+            + 30
+            + usize::from(u16_at(archive, header + 26)?)
+            + usize::from(u16_at(archive, header + 28)?);
+        let data = slice(archive, data_start, compressed_size)?;
+        let contents = match method {
+            STORED => data.to_vec(),
+            DEFLATED => {
+                // the size in the directory is only a hint, since the data can inflate to more
+                let capacity = size.min(usize::try_from(max_size).unwrap_or(usize::MAX));
+                let mut contents = Vec::with_capacity(capacity);
+                DeflateDecoder::new(data)
+                    .take(max_size + 1)
+                    .read_to_end(&mut contents)?;
+                if contents.len() as u64 > max_size {
+                    return Err(anyhow!("{name} is larger than {max_size} bytes"));
+                }
+                contents
+            }
+            _ => {
+                return Err(anyhow!(
+                    "{name} is compressed with an unsupported method ({method})"
+                ))
+            }
+        };
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+fn slice(archive: &[u8], offset: usize, len: usize) -> Res<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| archive.get(offset..end))
+        .ok_or_else(|| anyhow!("the zip archive is truncated"))
+}
+
+fn u16_at(archive: &[u8], offset: usize) -> Res<u16> {
+    let bytes = slice(archive, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(archive: &[u8], offset: usize) -> Res<u32> {
+    let bytes = slice(archive, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a 32-bit size or offset, which is all ones in archives that need zip64.
+fn offset_at(archive: &[u8], offset: usize) -> Res<usize> {
+    match u32_at(archive, offset)? {
+        u32::MAX => Err(anyhow!("zip64 archives are not supported")),
+        value => Ok(usize::try_from(value)?),
+    }
+}
@@ -0,0 +1,35 @@
+//! The conversion core of zeddy: reading KDL theme files, resolving their palettes, generating
+//! Zed's JSON themes from them, and converting themes from and to other formats. The `zeddy`
+//! command line interface is built on top of it.
+#![deny(clippy::perf)]
+#![deny(clippy::pedantic)]
+#![cfg_attr(all(feature = "safe", not(feature = "ffi")), forbid(unsafe_code))]
+#![allow(clippy::module_name_repetitions)]
+// these only fire now that the modules are public, and the docs already say what fails
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate,
+    clippy::return_self_not_must_use
+)]
+pub mod color;
+pub mod diff;
+#[cfg(any(feature = "wasm", feature = "ffi"))]
+mod embed;
+pub mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod generate;
+pub mod import;
+pub mod lint;
+pub mod lsp;
+pub mod provenance;
+pub mod schema;
+pub mod util;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use color::{parse_hex_color, HexColor};
+pub use generate::generate_json;
+pub use schema::{JsonThemeFamily, KdlThemeFamily, KdlVersion};
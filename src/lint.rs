@@ -0,0 +1,493 @@
+//! Lints that point out likely mistakes in a theme family. Each lint can be allowed, reported as
+//! a warning, or turned into an error on the command line or in the configuration file.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result as Res};
+use clap::ValueEnum;
+use tracing::{error, warn};
+
+use crate::color::{gamut_map, BaseColorKind, ColorModifiers, DisplayP3, GamutMap, HexColor};
+use crate::schema::json::ThemeFamily as JsonThemeFamily;
+use crate::schema::kdl::{ActionField, ModifierPath, Theme, ThemeFamily};
+use crate::schema::location::Location;
+use crate::validate::unknown_style_keys;
+
+/// Pairs of foreground and background style keys whose contrast is checked by `low-contrast`.
+const CONTRAST_PAIRS: &[(&str, &str)] = &[
+    ("text", "background"),
+    ("editor.foreground", "editor.background"),
+    ("terminal.foreground", "terminal.background"),
+];
+
+/// The smallest contrast ratio that WCAG 2.1 level AA allows for normal text.
+const MIN_CONTRAST: f32 = 4.5;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lint {
+    /// A palette color that no theme uses, either directly or through other palette colors
+    UnusedPaletteColor,
+    /// A `style` path that is not in Zed's theme schema
+    UnknownStyleKey,
+    /// Text that is hard to read on its background
+    LowContrast,
+    /// Two modifiers of the same theme that set the same field of the same path to the same
+    /// value, so that one of them does nothing
+    DuplicateModifierTarget,
+    /// Two modifiers that set the same path to different colors once a theme is merged with
+    /// `common`, so that the later one silently wins
+    ConflictingModifiers,
+    /// A palette color whose modifiers take it outside of sRGB, so that it has to be mapped back
+    /// into it with `--gamut-map`
+    OutOfGamut,
+    /// A Display P3 color that is outside of sRGB, so that it is compressed into it in Zed's
+    /// theme JSON
+    WideGamut,
+}
+
+impl Lint {
+    pub fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_owned())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Level {
+    /// Do not report the lint
+    Allow,
+    /// Report the lint as a warning
+    #[default]
+    Warn,
+    /// Report the lint as an error and fail
+    Deny,
+}
+
+/// What a lint level is set for: a single lint, or `warnings` for every lint that would warn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintTarget {
+    Lint(Lint),
+    Warnings,
+}
+
+impl FromStr for LintTarget {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "warnings" {
+            return Ok(Self::Warnings);
+        }
+        Lint::from_str(s, false).map(Self::Lint).map_err(|_| {
+            let known = Lint::value_variants()
+                .iter()
+                .map(|lint| lint.name())
+                .collect::<Vec<_>>();
+            anyhow!(
+                "unknown lint {s:?}, expected `warnings` or one of {}",
+                known.join(", ")
+            )
+        })
+    }
+}
+
+/// The levels of every lint, built from the configuration file and the command line.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    levels: Vec<(Lint, Level)>,
+    warnings: Option<Level>,
+}
+
+impl LintLevels {
+    /// Sets the level of a lint. Later calls win over earlier ones.
+    pub fn set(&mut self, target: LintTarget, level: Level) {
+        match target {
+            LintTarget::Lint(lint) => {
+                self.levels.retain(|(x, _)| *x != lint);
+                self.levels.push((lint, level));
+            }
+            LintTarget::Warnings => self.warnings = Some(level),
+        }
+    }
+
+    /// Returns these levels with every lint that would warn denied, unless `warnings` was
+    /// given a level of its own.
+    pub fn strict(&self) -> Self {
+        Self {
+            levels: self.levels.clone(),
+            warnings: self.warnings.or(Some(Level::Deny)),
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        let level = self
+            .levels
+            .iter()
+            .find(|(x, _)| *x == lint)
+            .map_or(Level::Warn, |(_, level)| *level);
+        match (level, self.warnings) {
+            (Level::Warn, Some(warnings)) => warnings,
+            _ => level,
+        }
+    }
+}
+
+static LINT_LEVELS: OnceLock<LintLevels> = OnceLock::new();
+
+/// Returns the lint levels chosen with [`set_lint_levels`], or the default ones.
+pub fn lint_levels() -> &'static LintLevels {
+    LINT_LEVELS.get_or_init(LintLevels::default)
+}
+
+pub fn set_lint_levels(levels: LintLevels) {
+    // the levels are only set once at startup
+    let _ = LINT_LEVELS.set(levels);
+}
+
+/// Something that a lint found.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub lint: Lint,
+    pub message: String,
+    pub location: Location,
+    /// Another place that is involved, such as the modifier that was overridden
+    pub related: Location,
+}
+
+/// Runs the lints that work on the KDL source of a family.
+pub fn lint_source(family: &ThemeFamily) -> Vec<Finding> {
+    let mut findings = unknown_style_keys(family)
+        .into_iter()
+        .map(|key| Finding {
+            lint: Lint::UnknownStyleKey,
+            message: key.to_string(),
+            location: Location::default(),
+            related: Location::default(),
+        })
+        .collect::<Vec<_>>();
+    findings.extend(unused_palette_colors(family));
+    findings.extend(conflicting_modifiers(family));
+    findings.extend(out_of_gamut_colors(family));
+    findings.extend(wide_gamut_colors(family));
+    let themes = family
+        .themes
+        .iter()
+        .chain(&family.common)
+        .chain(family.templates.iter().map(|template| &template.theme));
+    for theme in themes {
+        findings.extend(duplicate_modifier_targets(theme));
+    }
+    findings
+}
+
+/// Runs the lints that work on the generated JSON of a family.
+pub fn lint_generated(family: &JsonThemeFamily) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for theme in &family.themes {
+        for (foreground, background) in CONTRAST_PAIRS {
+            let (Some(fg), Some(bg)) = (theme.color(foreground), theme.color(background)) else {
+                continue;
+            };
+            let contrast = fg.over(bg).contrast_ratio(bg);
+            if contrast < MIN_CONTRAST {
+                findings.push(Finding {
+                    lint: Lint::LowContrast,
+                    message: format!(
+                        "`{foreground}` {fg} has a contrast of {contrast:.2} against `{background}` {bg} in theme {:?}, below {MIN_CONTRAST}",
+                        theme.name
+                    ),
+                    location: Location::default(),
+                    related: Location::default(),
+                });
+            }
+        }
+    }
+    findings
+}
+
+fn unused_palette_colors(family: &ThemeFamily) -> Vec<Finding> {
+    // instances add their colors to the palette and templates refer to them under other names,
+    // so references are only collected after expanding templates
+    let mut expanded = family.clone();
+    if expanded.expand_templates().is_err() {
+        return Vec::new();
+    }
+    // a color that is only referred to by other unused colors is unused as well
+    let used = expanded.used_palette_colors(expanded.themes.iter().chain(&expanded.common));
+    family
+        .palette
+        .colors
+        .iter()
+        .filter(|node| !used.contains(node.name.as_str()))
+        .map(|node| Finding {
+            lint: Lint::UnusedPaletteColor,
+            message: format!("palette color {:?} is never used", node.name),
+            location: node.location,
+            related: Location::default(),
+        })
+        .collect()
+}
+
+fn duplicate_modifier_targets(theme: &Theme) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (idx, modifier) in theme.modifiers.iter().enumerate() {
+        let action = &modifier.action;
+        for (i, path) in modifier.apply.iter().enumerate() {
+            let earlier = match modifier.apply[..i].iter().filter(|x| *x == path).count() {
+                // a modifier without `if_appearance` applies to every appearance, so it overlaps
+                // with all of the others. Each earlier modifier is compared once, the first time
+                // that this one lists the path.
+                0 => theme.modifiers[..idx]
+                    .iter()
+                    .filter(|x| match (&x.if_appearance, &modifier.if_appearance) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => true,
+                    })
+                    .filter(|x| x.apply.contains(path))
+                    .map(|x| (&x.action, x.location))
+                    .collect(),
+                // listing the path again in the same modifier is reported once, however many
+                // times it is repeated
+                1 => vec![(action, modifier.location)],
+                _ => Vec::new(),
+            };
+            for (other, related) in earlier {
+                let same = [
+                    (
+                        "color",
+                        action.color.is_some() && action.color == other.color,
+                    ),
+                    (
+                        "background",
+                        action.background.is_some() && action.background == other.background,
+                    ),
+                    (
+                        "font_weight",
+                        action.font_weight.is_some() && action.font_weight == other.font_weight,
+                    ),
+                    (
+                        "font_style",
+                        action.font_style.is_some() && action.font_style == other.font_style,
+                    ),
+                ];
+                for (field, _) in same.iter().filter(|(_, same)| *same) {
+                    findings.push(Finding {
+                        lint: Lint::DuplicateModifierTarget,
+                        message: format!(
+                            "the {field} of `{path}` is set to the same value more than once in theme {:?}",
+                            theme.name
+                        ),
+                        location: modifier.location,
+                        related,
+                    });
+                }
+            }
+        }
+    }
+    findings
+}
+
+fn out_of_gamut_colors(family: &ThemeFamily) -> Vec<Finding> {
+    let mut expanded = family.clone();
+    if expanded.expand_templates().is_err() {
+        return Vec::new();
+    }
+    let Ok(palette) = expanded.palette.clone().into_palette().resolve() else {
+        return Vec::new();
+    };
+    let mapping = match gamut_map() {
+        GamutMap::Clip => "clipped, which can shift its hue",
+        GamutMap::ChromaReduce => "mapped back by lowering its chroma",
+    };
+    let mut lookup = |name: &_| {
+        palette
+            .colors
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("could not find color {name} in the palette"))
+    };
+    let mut findings = Vec::new();
+    for node in &expanded.palette.colors {
+        if node.modifiers == ColorModifiers::default() {
+            continue;
+        }
+        let Ok(base) = node.base.resolve(&mut lookup) else {
+            continue;
+        };
+        if base.leaves_gamut(
+            node.modifiers
+                .or_preserve_hue(expanded.palette.preserve_hue),
+        ) {
+            findings.push(Finding {
+                lint: Lint::OutOfGamut,
+                message: format!(
+                    "palette color {:?} is outside of sRGB after its modifiers and is {mapping}",
+                    node.name
+                ),
+                location: node.location,
+                related: Location::default(),
+            });
+        }
+    }
+    findings
+}
+
+fn wide_gamut_colors(family: &ThemeFamily) -> Vec<Finding> {
+    let compressed = |base: &BaseColorKind| match base {
+        BaseColorKind::DisplayP3(color) if color.leaves_srgb() => Some(*color),
+        _ => None,
+    };
+    let finding = |what: String, color: DisplayP3, location| Finding {
+        lint: Lint::WideGamut,
+        message: format!(
+            "{what} is {color}, which is outside of sRGB and is compressed to {}",
+            color.to_srgb()
+        ),
+        location,
+        related: Location::default(),
+    };
+    let mut findings = Vec::new();
+    for node in &family.palette.colors {
+        if let Some(color) = compressed(&node.base) {
+            let what = format!("palette color {:?}", node.name);
+            findings.push(finding(what, color, node.location));
+        }
+    }
+    let themes = family
+        .themes
+        .iter()
+        .chain(&family.common)
+        .chain(family.templates.iter().map(|template| &template.theme));
+    for theme in themes {
+        for color in theme.colors() {
+            if let Some(p3) = compressed(&color.base) {
+                let what = format!("a color of theme {:?}", theme.name);
+                findings.push(finding(what, p3, color.location));
+            }
+        }
+    }
+    findings
+}
+
+/// The modifier that last set a field of a path, while walking through the modifiers of a theme.
+struct Setter {
+    color: HexColor,
+    from_common: bool,
+    location: Location,
+}
+
+fn conflicting_modifiers(family: &ThemeFamily) -> Vec<Finding> {
+    let mut expanded = family.clone();
+    if expanded.expand_templates().is_err() {
+        return Vec::new();
+    }
+    let Ok(palette) = expanded.palette.clone().into_palette().resolve() else {
+        return Vec::new();
+    };
+    let common_len = expanded
+        .common
+        .as_ref()
+        .map_or(0, |common| common.modifiers.len());
+    let mut findings = Vec::new();
+    for mut theme in expanded.themes {
+        if let Some(common) = &expanded.common {
+            theme.merge(common);
+        }
+        let mut setters: HashMap<(&ModifierPath, ActionField), Setter> = HashMap::new();
+        let modifiers = theme.modifiers.iter().enumerate().filter(|(_, modifier)| {
+            modifier
+                .if_appearance
+                .as_ref()
+                .is_none_or(|appearance| *appearance == theme.appearance)
+        });
+        for (idx, modifier) in modifiers {
+            let action = &modifier.action;
+            let fields = [
+                (ActionField::Color, &action.color),
+                (ActionField::Background, &action.background),
+            ];
+            for path in &modifier.apply {
+                for (field, _) in fields {
+                    if action.clears(field) {
+                        setters.remove(&(path, field));
+                    }
+                }
+                for (field, color) in fields {
+                    let Some(Ok(color)) = color.as_ref().map(|color| palette.lookup(color)) else {
+                        continue;
+                    };
+                    let setter = Setter {
+                        color,
+                        from_common: idx < common_len,
+                        location: modifier.location,
+                    };
+                    let Some(earlier) = setters.insert((path, field), setter) else {
+                        continue;
+                    };
+                    if earlier.color == color {
+                        continue;
+                    }
+                    let origin = |from_common| {
+                        if from_common {
+                            "the `common` theme".to_owned()
+                        } else {
+                            format!("theme {:?}", theme.name)
+                        }
+                    };
+                    findings.push(Finding {
+                        lint: Lint::ConflictingModifiers,
+                        message: format!(
+                            "`{path}` {} is set to {} by {} and then to {color} by {}, which wins",
+                            field_name(field),
+                            earlier.color,
+                            origin(earlier.from_common),
+                            origin(idx < common_len),
+                        ),
+                        location: modifier.location,
+                        related: earlier.location,
+                    });
+                }
+            }
+        }
+    }
+    findings
+}
+
+fn field_name(field: ActionField) -> &'static str {
+    match field {
+        ActionField::Color => "color",
+        ActionField::Background => "background",
+        ActionField::FontWeight => "font_weight",
+        ActionField::FontStyle => "font_style",
+    }
+}
+
+/// Reports findings at the level of their lint. `source` is the KDL v1 source of the family,
+/// which is used to show line numbers. Fails if any finding was denied.
+pub fn report_findings(findings: &[Finding], levels: &LintLevels, source: Option<&str>) -> Res<()> {
+    let mut denied = 0;
+    for finding in findings {
+        let lines =
+            source.map(|source| (finding.location.line(source), finding.related.line(source)));
+        let line = match lines {
+            Some((Some(line), Some(related))) => format!(" (line {line}, see also line {related})"),
+            Some((Some(line), None)) => format!(" (line {line})"),
+            _ => String::new(),
+        };
+        let name = finding.lint.name();
+        match levels.level(finding.lint) {
+            Level::Allow => {}
+            Level::Warn => warn!("{}{line} [{name}]", finding.message),
+            Level::Deny => {
+                error!("{}{line} [{name}]", finding.message);
+                denied += 1;
+            }
+        }
+    }
+    if denied == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!("{denied} lint(s) were denied"))
+    }
+}
@@ -0,0 +1,564 @@
+//! A minimal language server for theme files, which talks JSON-RPC over standard input and
+//! output. It reports the same mistakes as `validate`, completes palette colors and style keys,
+//! shows the resolved color of a palette reference on hover, and jumps from a reference to its
+//! palette entry.
+//!
+//! Positions in KDL v2 files are found in the file after it has been converted to KDL v1, so
+//! they can be slightly off on lines that the conversion changes.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result as Res};
+use miette::{Diagnostic, SourceSpan};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::color::{parse_hex_color, ColorName, HexColor};
+use crate::generate::{generate_json, swatch_svg};
+use crate::lint::{lint_generated, lint_levels, lint_source, Finding, Level, Lint};
+use crate::schema::include::resolve_includes;
+use crate::schema::json::SchemaVersion;
+use crate::schema::location::LocatedError;
+use crate::schema::{zed, KdlThemeFamily, KdlVersion};
+use crate::validate::unknown_style_keys;
+
+/// The JSON-RPC error code for a method that the server does not implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The LSP severities of diagnostics.
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+/// The LSP kinds of completion items.
+const KIND_PROPERTY: u8 = 10;
+const KIND_COLOR: u8 = 16;
+
+/// Runs the language server until the client exits or closes standard input.
+pub fn run_server(version: SchemaVersion, kdl_version: KdlVersion) -> Res<()> {
+    info!("Starting the language server");
+    let mut server = Server {
+        version,
+        kdl_version,
+        documents: HashMap::new(),
+    };
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    while let Some(message) = read_message(&mut input)? {
+        // responses to requests of the server have no method, and it does not send any
+        let Some(method) = message["method"].as_str() else {
+            continue;
+        };
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": code, "message": message},
+                    }),
+                };
+                write_message(&mut output, &response)?;
+            }
+            None if method == "exit" => break,
+            None => {
+                for notification in server.notify(method, params) {
+                    write_message(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+    info!("Stopping the language server");
+    Ok(())
+}
+
+/// Reads a message with its `Content-Length` header, or returns `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Res<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("message without a Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Res<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()?;
+    Ok(())
+}
+
+/// An open theme file and what was learned about it the last time it changed.
+struct Document {
+    /// The text in the editor
+    text: String,
+    /// The text converted to KDL v1, which locations point into
+    source: String,
+    /// The resolved color of every palette entry
+    colors: HashMap<ColorName, HexColor>,
+    /// The location of every palette entry of the file itself
+    definitions: HashMap<String, SourceSpan>,
+}
+
+struct Server {
+    version: SchemaVersion,
+    kdl_version: KdlVersion,
+    documents: HashMap<String, Document>,
+}
+
+impl Server {
+    fn request(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        debug!("Request {method}");
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    // the whole text is sent on every change
+                    "textDocumentSync": 1,
+                    "completionProvider": {"triggerCharacters": ["\"", "."]},
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": {"name": "zeddy", "version": env!("CARGO_PKG_VERSION")},
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/completion" => {
+                self.at_cursor(params, |document, cursor| Some(complete(document, cursor)))
+            }
+            "textDocument/hover" => self.at_cursor(params, hover),
+            "textDocument/definition" => self.at_cursor(params, |document, cursor| {
+                let span = document.definitions.get(cursor.content)?;
+                Some(json!({
+                    "uri": params["textDocument"]["uri"],
+                    "range": range(&document.source, *span),
+                }))
+            }),
+            _ => return Err((METHOD_NOT_FOUND, format!("unknown method {method}"))),
+        };
+        Ok(result)
+    }
+
+    /// Handles a notification, returning the notifications to send back.
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        debug!("Notification {method}");
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, &[])];
+            }
+            _ => return Vec::new(),
+        };
+        let Some(text) = text else {
+            warn!("{method} without the text of {uri}");
+            return Vec::new();
+        };
+        let (document, diagnostics) = self.analyze(&uri, text.to_owned());
+        self.documents.insert(uri.clone(), document);
+        vec![publish_diagnostics(&uri, &diagnostics)]
+    }
+
+    /// Runs `f` with the string that the cursor of a request is in, if it is in one.
+    fn at_cursor(
+        &self,
+        params: &Value,
+        f: impl FnOnce(&Document, Cursor) -> Option<Value>,
+    ) -> Value {
+        let document = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri));
+        let position = &params["position"];
+        let (Some(document), Some(line), Some(character)) = (
+            document,
+            position["line"].as_u64(),
+            position["character"].as_u64(),
+        ) else {
+            return Value::Null;
+        };
+        let cursor = usize::try_from(line)
+            .ok()
+            .zip(usize::try_from(character).ok())
+            .and_then(|(line, character)| Cursor::new(&document.text, line, character));
+        cursor
+            .and_then(|cursor| f(document, cursor))
+            .unwrap_or(Value::Null)
+    }
+
+    /// Checks a theme file the same way as `validate`, and remembers its palette.
+    fn analyze(&self, uri: &str, text: String) -> (Document, Vec<Value>) {
+        let mut document = Document {
+            source: text.clone(),
+            text,
+            colors: HashMap::new(),
+            definitions: HashMap::new(),
+        };
+        let diagnostics = match self.check(uri, &mut document) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => error_diagnostics(&e, &document.source),
+        };
+        (document, diagnostics)
+    }
+
+    fn check(&self, uri: &str, document: &mut Document) -> Res<Vec<Value>> {
+        document.source = self.kdl_version.to_v1(&document.text)?.into_owned();
+        let source = &document.source;
+        let mut family = match knus::parse::<KdlThemeFamily>(uri, source) {
+            Ok(family) => family,
+            Err(e) => return Ok(parse_diagnostics(&e, source)),
+        };
+        document.definitions = family
+            .palette
+            .colors
+            .iter()
+            .filter_map(|node| Some((node.name.clone(), node.location.span()?)))
+            .collect();
+        if let Some(path) = uri_to_path(uri) {
+            resolve_includes(&mut family, &path, self.kdl_version)?;
+        }
+
+        let mut diagnostics = unknown_style_key_diagnostics(&family, source);
+        let mut findings = lint_source(&family);
+        // these are reported at every place that the key is used instead
+        findings.retain(|finding| finding.lint != Lint::UnknownStyleKey);
+        // mistakes in the palette and templates are reported by `generate_json` below
+        let mut expanded = family.clone();
+        if expanded.expand_templates().is_ok() {
+            if let Ok(palette) = expanded.palette.into_palette().resolve() {
+                document.colors = palette.colors;
+            }
+        }
+        match generate_json(family, self.version) {
+            Ok(json) => findings.extend(lint_generated(&json)),
+            Err(e) => diagnostics.extend(error_diagnostics(&e, source)),
+        }
+        diagnostics.extend(
+            findings
+                .iter()
+                .filter_map(|finding| finding_diagnostic(finding, source)),
+        );
+        Ok(diagnostics)
+    }
+}
+
+/// The string on a line of the text that the cursor is in.
+#[derive(Clone, Copy)]
+struct Cursor<'a> {
+    /// The part of the line before the opening quote
+    before: &'a str,
+    /// The contents of the string
+    content: &'a str,
+    /// The contents of the string up to the cursor
+    prefix: &'a str,
+    line: usize,
+    /// The UTF-16 column of the opening quote
+    start: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str, line: usize, character: usize) -> Option<Self> {
+        let text = text.lines().nth(line)?;
+        let cursor = byte_offset(text, character);
+        let mut start = None;
+        let mut escaped = false;
+        for (idx, c) in text.char_indices() {
+            match (c, start) {
+                ('\\', Some(_)) if !escaped => {
+                    escaped = true;
+                    continue;
+                }
+                ('"', None) => start = Some(idx),
+                ('"', Some(open)) if !escaped => {
+                    if (open + 1..=idx).contains(&cursor) {
+                        return Some(Self::from_string(text, line, open, idx, cursor));
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+            escaped = false;
+        }
+        // the string is not closed yet while it is being typed
+        let open = start.filter(|open| *open < cursor)?;
+        Some(Self::from_string(text, line, open, text.len(), cursor))
+    }
+
+    fn from_string(text: &'a str, line: usize, open: usize, close: usize, cursor: usize) -> Self {
+        Self {
+            before: text[..open].trim_end(),
+            content: &text[open + 1..close],
+            prefix: &text[open + 1..cursor],
+            line,
+            start: text[..open].chars().map(char::len_utf16).sum(),
+        }
+    }
+
+    /// The range of the contents of the string up to the cursor, which completions replace.
+    fn prefix_range(&self) -> Value {
+        let start = self.start + 1;
+        let end = start + self.prefix.chars().map(char::len_utf16).sum::<usize>();
+        json!({
+            "start": {"line": self.line, "character": start},
+            "end": {"line": self.line, "character": end},
+        })
+    }
+}
+
+fn complete(document: &Document, cursor: Cursor) -> Value {
+    let item = |label: &str, kind: u8, detail: String| {
+        json!({
+            "label": label,
+            "kind": kind,
+            "detail": detail,
+            "textEdit": {"range": cursor.prefix_range(), "newText": label},
+        })
+    };
+    let items = match cursor.before {
+        "style" => zed::STYLE_KEYS
+            .iter()
+            .map(|(key, description)| item(key, KIND_PROPERTY, (*description).to_owned()))
+            .collect::<Vec<_>>(),
+        "syntax" => zed::SYNTAX_KEYS
+            .iter()
+            .map(|(key, description)| item(key, KIND_PROPERTY, (*description).to_owned()))
+            .collect(),
+        _ => {
+            let mut names = document.colors.iter().collect::<Vec<_>>();
+            names.sort_unstable_by_key(|(name, _)| *name);
+            names
+                .into_iter()
+                .map(|(name, color)| item(name, KIND_COLOR, color.to_string()))
+                .collect()
+        }
+    };
+    json!(items)
+}
+
+fn hover(document: &Document, cursor: Cursor) -> Option<Value> {
+    let content = cursor.content;
+    let keys = match cursor.before {
+        "style" => Some(zed::STYLE_KEYS),
+        "syntax" => Some(zed::SYNTAX_KEYS),
+        _ => None,
+    };
+    let value = if let Some(keys) = keys {
+        let (_, description) = keys.iter().find(|(key, _)| *key == content)?;
+        format!("`{content}`: {description}")
+    } else if let Some(color) = document.colors.get(content) {
+        format!("{} `{content}` = `{color}`", swatch(*color))
+    } else {
+        let color = parse_hex_color(content)?;
+        format!("{} `{color}`", swatch(color))
+    };
+    Some(json!({"contents": {"kind": "markdown", "value": value}}))
+}
+
+/// A Markdown image of a swatch of `color`.
+fn swatch(color: HexColor) -> String {
+    let svg = swatch_svg(color);
+    let mut encoded = String::with_capacity(svg.len() * 2);
+    for byte in svg.trim_end().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push('%');
+            for digit in [byte >> 4, byte & 0xf] {
+                let digit = char::from_digit(u32::from(digit), 16).unwrap_or('0');
+                encoded.push(digit.to_ascii_uppercase());
+            }
+        }
+    }
+    format!("![{color}](data:image/svg+xml,{encoded})")
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: &[Value]) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn diagnostic(range: &Value, severity: u8, message: &str, code: Option<&str>) -> Value {
+    json!({
+        "range": range,
+        "severity": severity,
+        "source": "zeddy",
+        "message": message,
+        "code": code,
+    })
+}
+
+/// Turns the errors of the KDL decoder, which have their own locations, into diagnostics.
+fn parse_diagnostics(error: &dyn Diagnostic, source: &str) -> Vec<Value> {
+    if let Some(related) = error.related() {
+        let related = related
+            .flat_map(|error| parse_diagnostics(error, source))
+            .collect::<Vec<_>>();
+        if !related.is_empty() {
+            return related;
+        }
+    }
+    let message = error.to_string();
+    let labels = error
+        .labels()
+        .map(Iterator::collect::<Vec<_>>)
+        .unwrap_or_default();
+    if labels.is_empty() {
+        return vec![diagnostic(
+            &range(source, (0, 0).into()),
+            SEVERITY_ERROR,
+            &message,
+            None,
+        )];
+    }
+    labels
+        .into_iter()
+        .map(|label| {
+            let message = match label.label() {
+                Some(label) => format!("{message}: {label}"),
+                None => message.clone(),
+            };
+            diagnostic(
+                &range(source, *label.inner()),
+                SEVERITY_ERROR,
+                &message,
+                None,
+            )
+        })
+        .collect()
+}
+
+/// Turns an error that stopped the theme from being generated into a diagnostic, at the start
+/// of the file if it does not point anywhere.
+fn error_diagnostics(error: &anyhow::Error, source: &str) -> Vec<Value> {
+    let span = error
+        .downcast_ref::<LocatedError>()
+        .map_or((0, 0).into(), LocatedError::span);
+    vec![diagnostic(
+        &range(source, span),
+        SEVERITY_ERROR,
+        &error.to_string(),
+        None,
+    )]
+}
+
+fn finding_diagnostic(finding: &Finding, source: &str) -> Option<Value> {
+    let severity = match lint_levels().level(finding.lint) {
+        Level::Allow => return None,
+        Level::Warn => SEVERITY_WARNING,
+        Level::Deny => SEVERITY_ERROR,
+    };
+    let span = finding.location.span().unwrap_or((0, 0).into());
+    Some(diagnostic(
+        &range(source, span),
+        severity,
+        &finding.message,
+        Some(&finding.lint.name()),
+    ))
+}
+
+/// Reports every place that an unknown style key is used, since the lint only knows the key.
+fn unknown_style_key_diagnostics(family: &KdlThemeFamily, source: &str) -> Vec<Value> {
+    let severity = match lint_levels().level(Lint::UnknownStyleKey) {
+        Level::Allow => return Vec::new(),
+        Level::Warn => SEVERITY_WARNING,
+        Level::Deny => SEVERITY_ERROR,
+    };
+    let mut diagnostics = Vec::new();
+    for key in unknown_style_keys(family) {
+        let quoted = format!("\"{}\"", key.key);
+        for (offset, _) in source.match_indices(&quoted) {
+            diagnostics.push(diagnostic(
+                &range(source, (offset, quoted.len()).into()),
+                severity,
+                &key.to_string(),
+                Some(&Lint::UnknownStyleKey.name()),
+            ));
+        }
+    }
+    diagnostics
+}
+
+fn range(source: &str, span: SourceSpan) -> Value {
+    json!({
+        "start": position(source, span.offset()),
+        "end": position(source, span.offset() + span.len()),
+    })
+}
+
+/// Turns a byte offset into a line and a UTF-16 column, which LSP positions are made of.
+fn position(source: &str, offset: usize) -> Value {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    let character = before[line_start..]
+        .chars()
+        .map(char::len_utf16)
+        .sum::<usize>();
+    json!({"line": before.matches('\n').count(), "character": character})
+}
+
+/// Turns a UTF-16 column of a line into a byte offset.
+fn byte_offset(line: &str, character: usize) -> usize {
+    let mut column = 0;
+    for (idx, c) in line.char_indices() {
+        if column >= character {
+            return idx;
+        }
+        column += c.len_utf16();
+    }
+    line.len()
+}
+
+/// Turns a `file://` URI into a path, so that the files that a theme includes can be found.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| std::str::from_utf8(tail.get(..2)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(decoded) = decoded {
+            bytes.push(decoded);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/themes` is `C:/themes` on Windows
+    let path = match path.strip_prefix('/') {
+        Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => rest.to_owned(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
@@ -2,10 +2,6 @@
 #![deny(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 mod cli;
-mod color;
-mod generate;
-mod schema;
-mod util;
 
 use std::process::exit;
 
@@ -13,9 +9,6 @@ use clap::Parser;
 use cli::Cli;
 
 fn main() -> ! {
-    if std::env::var("RUST_LOG").is_err() {
-        std::env::set_var("RUST_LOG", "info");
-    }
     // I want to actually see the panic without having to go into /tmp if I'm in debug mode.
     // I should really make my own version of `human_panic` one day
     #[cfg(not(debug_assertions))]
@@ -23,7 +16,7 @@ fn main() -> ! {
         use human_panic::setup_panic;
         setup_panic!();
     }
-    pretty_env_logger::init();
+    cli::complete();
     let cli = Cli::parse();
     cli.run();
     exit(0)
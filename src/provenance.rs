@@ -0,0 +1,243 @@
+//! Source maps of generated themes, which record where in the KDL source the value of every key
+//! came from, so that tools can answer that without explaining the theme again.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result as Res};
+use serde::{Deserialize, Serialize};
+
+use crate::color::HexColor;
+use crate::explain::{describe_color, describe_setting, Explainer, SYNTAX_FIELDS};
+use crate::schema::json::{SchemaVersion, StyleEntry};
+use crate::schema::{JsonThemeFamily, KdlThemeFamily};
+
+/// Where the keys of a generated theme family came from. It is written next to the generated
+/// file, with `.map` added to its name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// The KDL file that the family was generated from, as it was given to zeddy
+    pub source: String,
+    /// The keys of every theme by the name of the theme. Syntax scopes are split into their
+    /// fields, such as `syntax.comment.color`.
+    pub themes: BTreeMap<String, BTreeMap<String, KeySource>>,
+}
+
+/// Where a single key got its value.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeySource {
+    /// The final color of the key, if it is a color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<HexColor>,
+    /// Every node that set or cleared the key, in the order they were applied. The last one
+    /// gave the key its value.
+    pub steps: Vec<StepSource>,
+    /// The palette entries that the final color was resolved through, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<PaletteLink>,
+}
+
+/// A node that set or cleared a key.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepSource {
+    /// The theme that the node is in: `common` or `theme`
+    pub origin: String,
+    /// The kind of node, such as `modifier`
+    pub kind: String,
+    /// The line of the node in the source, unless it is in an included file or an overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The value as it is written in the node, such as `accent lighten=0.2`, or nothing if the
+    /// node cleared the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// A palette entry that a color was resolved through.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaletteLink {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The color of the entry as it is written in the palette
+    pub value: String,
+    /// The color after the modifiers of the entry were applied
+    pub resolved: HexColor,
+}
+
+/// Returns where the source map of the generated file `outfile` is written.
+pub fn source_map_location(outfile: &Path) -> PathBuf {
+    let mut name = outfile.as_os_str().to_owned();
+    name.push(".map");
+    PathBuf::from(name)
+}
+
+/// Records where every key of every theme of `json` came from in `family`, the family it was
+/// generated from. `source` is the text of the KDL file converted to KDL v1, since that is what
+/// locations point into. Players and accents, which `explain` cannot trace either, and keys that
+/// no node set are left out.
+pub fn source_map(
+    family: KdlThemeFamily,
+    json: &JsonThemeFamily,
+    path: &Path,
+    source: &str,
+    version: SchemaVersion,
+) -> Res<SourceMap> {
+    let explainer = Explainer::new(family)?;
+    let mut themes = BTreeMap::new();
+    for theme in &json.themes {
+        let mut keys = BTreeMap::new();
+        for (key, entry) in &theme.style {
+            let paths = match entry {
+                StyleEntry::Players(_) | StyleEntry::Accents(_) => continue,
+                StyleEntry::Syntax(scopes) => scopes
+                    .keys()
+                    .map(|scope| format!("syntax.{scope}"))
+                    .collect(),
+                _ => vec![key.clone()],
+            };
+            for key in paths {
+                let explanation = explainer.explain(&theme.name, &key, version)?;
+                for field in explanation.fields {
+                    if field.steps.is_empty() {
+                        continue;
+                    }
+                    let steps = field
+                        .steps
+                        .iter()
+                        .map(|step| StepSource {
+                            origin: step.origin.to_string(),
+                            kind: step.kind.to_owned(),
+                            line: step.location.line(source),
+                            value: step.setting.as_ref().map(describe_setting),
+                        })
+                        .collect();
+                    // the first link is the color written in the step itself
+                    let palette = field
+                        .chain
+                        .iter()
+                        .filter_map(|link| {
+                            Some(PaletteLink {
+                                name: link.name.clone()?,
+                                line: link.color.location.line(source),
+                                value: describe_color(&link.color),
+                                resolved: link.resolved,
+                            })
+                        })
+                        .collect();
+                    let source = KeySource {
+                        color: field.chain.first().map(|link| link.resolved),
+                        steps,
+                        palette,
+                    };
+                    keys.insert(field.key, source);
+                }
+            }
+        }
+        themes.insert(theme.name.clone(), keys);
+    }
+    Ok(SourceMap {
+        source: path.display().to_string(),
+        themes,
+    })
+}
+
+impl SourceMap {
+    /// Reads the source map of the generated file `file`.
+    pub fn read(file: &Path) -> Res<Self> {
+        let path = source_map_location(file);
+        let text = std::fs::read_to_string(&path).map_err(|e| {
+            anyhow!(
+                "could not read the source map {}: {e}. Generate the theme with `--source-map` to write it",
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&text)
+            .map_err(|e| anyhow!("could not read the source map {}: {e}", path.display()))
+    }
+
+    /// Prints where `key` of a theme got its value, like `explain` but from the source map. A
+    /// syntax scope such as `syntax.comment` is printed field by field. The theme can be left out
+    /// if the family has only one.
+    pub fn blame(&self, theme: Option<&str>, key: &str, out: &mut impl Write) -> Res<()> {
+        let (name, keys) = if let Some(name) = theme {
+            self.themes
+                .get_key_value(name)
+                .ok_or_else(|| anyhow!("there is no theme named {name:?} in the source map"))?
+        } else {
+            self.themes
+                .first_key_value()
+                .filter(|_| self.themes.len() == 1)
+                .ok_or_else(|| {
+                    let names = self.themes.keys().map(|x| format!("{x:?}"));
+                    anyhow!(
+                        "the family has several themes, pick one with `--theme`: {}",
+                        names.collect::<Vec<_>>().join(", ")
+                    )
+                })?
+        };
+        // a syntax scope stands for its fields, but any other key only for itself, so that
+        // `border` does not include `border.variant`
+        let is_field = |path: &str| {
+            key.starts_with("syntax.")
+                && path
+                    .strip_prefix(key)
+                    .and_then(|rest| rest.strip_prefix('.'))
+                    .is_some_and(|field| SYNTAX_FIELDS.iter().any(|(name, _)| *name == field))
+        };
+        let fields = keys
+            .iter()
+            .filter(|(path, _)| *path == key || is_field(path))
+            .collect::<Vec<_>>();
+        if fields.is_empty() {
+            return Err(anyhow!(
+                "nothing in {} sets {key} in theme {name:?}",
+                self.source
+            ));
+        }
+        let at = |line: Option<usize>| {
+            line.map_or_else(
+                || "an included file or overlay".to_owned(),
+                |line| format!("{}:{line}", self.source),
+            )
+        };
+        for (path, source) in fields {
+            let value = match (source.color, source.steps.last()) {
+                (Some(color), _) => color.to_string(),
+                (None, Some(step)) => step.value.clone().unwrap_or_else(|| "not set".to_owned()),
+                (None, None) => "not set".to_owned(),
+            };
+            writeln!(out, "{path} in theme {name:?}: {value}")?;
+            for (i, step) in source.steps.iter().enumerate() {
+                let action = match &step.value {
+                    Some(value) => format!("sets {value}"),
+                    None => "clears it".to_owned(),
+                };
+                let last = if i + 1 == source.steps.len() {
+                    " (final)"
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "  {}: {} {} {action}{last}",
+                    at(step.line),
+                    step.origin,
+                    step.kind
+                )?;
+            }
+            for link in &source.palette {
+                writeln!(
+                    out,
+                    "    {}: palette {:?}: {} -> {}",
+                    at(link.line),
+                    link.name,
+                    link.value,
+                    link.resolved
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,164 @@
+pub mod include;
 pub mod json;
 pub mod kdl;
+pub mod kdl_version;
+pub mod location;
+pub mod overlay;
+pub mod overrides;
+pub mod zed;
 pub use json::ThemeFamily as JsonThemeFamily;
 pub use kdl::ThemeFamily as KdlThemeFamily;
+pub use kdl_version::KdlVersion;
 
-use knus::{Decode, DecodeScalar};
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use knus::{
+    ast::{Literal, TypeName},
+    decode::Context,
+    errors::DecodeError,
+    span::Spanned,
+    traits::ErrorSpan,
+    Decode, DecodeScalar,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, DecodeScalar, Deserialize, Serialize)]
+#[derive(Clone, Debug, DecodeScalar, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum Appearance {
     Light,
     Dark,
 }
 
-#[derive(Clone, Debug, Decode, Deserialize, Serialize)]
+/// How the window background is drawn, corresponding to `style["background.appearance"]`.
+#[derive(Clone, Copy, Debug, DecodeScalar, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowAppearance {
+    Opaque,
+    Transparent,
+    Blurred,
+}
+
+/// The style of a font in a syntax highlight.
+#[derive(Clone, Copy, Debug, DecodeScalar, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+/// The weight of a font in a syntax highlight. Only multiples of 100 from 100 to 900 are valid,
+/// which can also be written with their CSS keyword in the KDL format (e.g. `"bold"` for 700).
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct FontWeight(u16);
+
+impl FontWeight {
+    pub const KEYWORDS: [&'static str; 9] = [
+        "thin",
+        "extra-light",
+        "light",
+        "normal",
+        "medium",
+        "semi-bold",
+        "bold",
+        "extra-bold",
+        "black",
+    ];
+
+    pub fn get(self) -> u16 {
+        self.0
+    }
+
+    /// The CSS keyword for this weight.
+    pub fn keyword(self) -> &'static str {
+        Self::KEYWORDS[usize::from(self.0 / 100 - 1)]
+    }
+}
+
+impl TryFrom<u16> for FontWeight {
+    type Error = anyhow::Error;
+    fn try_from(weight: u16) -> Result<Self, Self::Error> {
+        if (100..=900).contains(&weight) && weight.is_multiple_of(100) {
+            Ok(Self(weight))
+        } else {
+            Err(anyhow!(
+                "invalid font weight {weight}: expected a multiple of 100 from 100 to 900"
+            ))
+        }
+    }
+}
+
+impl From<FontWeight> for u16 {
+    fn from(weight: FontWeight) -> Self {
+        weight.0
+    }
+}
+
+impl FromStr for FontWeight {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(weight) = s.parse::<u16>() {
+            return weight.try_into();
+        }
+        Self::KEYWORDS
+            .iter()
+            .zip((100..=900).step_by(100))
+            .find_map(|(&keyword, weight)| (keyword == s).then_some(Self(weight)))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown font weight {s:?}: expected a number or one of {}",
+                    Self::KEYWORDS.join(", ")
+                )
+            })
+    }
+}
+
+impl Display for FontWeight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<S: ErrorSpan> DecodeScalar<S> for FontWeight {
+    fn raw_decode(
+        value: &Spanned<Literal, S>,
+        ctx: &mut Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        if let Literal::String(keyword) = &**value {
+            keyword
+                .parse()
+                .map_err(|e| DecodeError::conversion(value, e))
+        } else {
+            u16::raw_decode(value, ctx)?
+                .try_into()
+                .map_err(|e| DecodeError::conversion(value, e))
+        }
+    }
+    fn type_check(_: &Option<Spanned<TypeName, S>>, _: &mut Context<S>) {}
+}
+
+#[derive(Clone, Debug, Default, Decode, Deserialize, Serialize)]
 pub struct Meta {
     #[knus(child, unwrap(argument))]
     pub name: String,
     #[knus(child, unwrap(argument))]
     pub author: String,
+    /// The version of the theme family, such as `1.2.0`, which is also the version of the
+    /// extension that publishes it
+    #[knus(child, unwrap(argument))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// An SPDX license identifier, such as `MIT`
+    #[knus(child, unwrap(argument))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// A URL of the theme family, such as its repository
+    #[knus(child, unwrap(argument))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[knus(child, unwrap(argument))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
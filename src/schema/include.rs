@@ -0,0 +1,162 @@
+//! Support for splitting a theme family across several files with `include` nodes.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result as Res};
+use knus::Decode;
+use tracing::debug;
+
+use crate::color::palette::RawPalette;
+use crate::schema::kdl::{Instance, Template, Theme, ThemeFamily};
+use crate::schema::location::Location;
+use crate::schema::KdlVersion;
+use crate::util::{is_stdio, ToAnyhow};
+
+/// An `include` node, which adds the contents of another file to the file it is in. Paths are
+/// relative to the directory of the including file.
+#[derive(Clone, Debug, Decode)]
+pub struct Include {
+    #[knus(argument)]
+    pub path: String,
+}
+
+/// A file that is included by a theme family. It is written like a theme family without a
+/// `meta` node.
+#[derive(Clone, Debug, Decode, Default)]
+pub struct Fragment {
+    #[knus(child, default)]
+    pub palette: RawPalette,
+    #[knus(children(name = "theme"))]
+    pub themes: Vec<Theme>,
+    #[knus(child)]
+    pub common: Option<Theme>,
+    #[knus(children(name = "template"))]
+    pub templates: Vec<Template>,
+    #[knus(children(name = "instantiate"))]
+    pub instances: Vec<Instance>,
+    #[knus(children(name = "include"))]
+    pub includes: Vec<Include>,
+}
+
+impl Fragment {
+    /// The `preserve-hue` of a palette is for the family, so an included palette keeps it to
+    /// its own colors.
+    fn pin_preserve_hue(&mut self) {
+        let themes = self.themes.iter_mut().chain(&mut self.common).chain(
+            self.templates
+                .iter_mut()
+                .map(|template| &mut template.theme),
+        );
+        self.palette
+            .pin_preserve_hue(themes.flat_map(Theme::colors_mut));
+    }
+
+    /// Errors are only shown with the source of the file that was read, so locations in
+    /// included files would point at the wrong lines.
+    fn forget_locations(&mut self) {
+        for node in &mut self.palette.colors {
+            node.location = Location::default();
+        }
+        let themes = self.themes.iter_mut().chain(&mut self.common).chain(
+            self.templates
+                .iter_mut()
+                .map(|template| &mut template.theme),
+        );
+        for theme in themes {
+            theme.location = Location::default();
+            for modifier in &mut theme.modifiers {
+                modifier.location = Location::default();
+            }
+            for color in theme.colors_mut() {
+                color.location = Location::default();
+            }
+        }
+        for instance in &mut self.instances {
+            instance.location = Location::default();
+            for arg in &mut instance.args {
+                arg.location = Location::default();
+            }
+        }
+    }
+}
+
+/// Adds the contents of every file included by `family`, which was read from `path`, to it.
+/// Returns the canonical paths of the files that were included, directly or through other
+/// included files.
+pub fn resolve_includes(
+    family: &mut ThemeFamily,
+    path: &Path,
+    version: KdlVersion,
+) -> Res<Vec<PathBuf>> {
+    let includes = std::mem::take(&mut family.includes);
+    // standard input cannot be included, so it does not have to be checked for cycles
+    let mut stack = if is_stdio(path) {
+        Vec::new()
+    } else {
+        vec![canonical(path)?]
+    };
+    let mut files = Vec::new();
+    for include in includes {
+        add_included(family, path, &include, version, &mut stack, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn add_included(
+    family: &mut ThemeFamily,
+    from: &Path,
+    include: &Include,
+    version: KdlVersion,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Res<()> {
+    let path = from
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(&include.path);
+    let canonical = canonical(&path)?;
+    if stack.contains(&canonical) {
+        return Err(anyhow!(
+            "{} includes itself through {}",
+            path.display(),
+            from.display()
+        ));
+    }
+    debug!("Including {}", path.display());
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("could not read the included file {}: {e}", path.display()))?;
+    let content = version.to_v1(&content)?;
+    let mut fragment =
+        knus::parse::<Fragment>(&path.display().to_string(), &content).to_anyhow()?;
+    fragment.forget_locations();
+    fragment.pin_preserve_hue();
+
+    family.palette.colors.append(&mut fragment.palette.colors);
+    family.themes.append(&mut fragment.themes);
+    family.templates.append(&mut fragment.templates);
+    family.instances.append(&mut fragment.instances);
+    if let Some(common) = fragment.common {
+        if family.common.is_some() {
+            return Err(anyhow!(
+                "{} has a `common` theme, but the family already has one",
+                path.display()
+            ));
+        }
+        family.common = Some(common);
+    }
+
+    if !files.contains(&canonical) {
+        files.push(canonical.clone());
+    }
+    stack.push(canonical);
+    for include in &fragment.includes {
+        add_included(family, &path, include, version, stack, files)?;
+    }
+    stack.pop();
+    Ok(())
+}
+
+fn canonical(path: &Path) -> Res<PathBuf> {
+    path.canonicalize()
+        .map_err(|e| anyhow!("could not find {}: {e}", path.display()))
+}
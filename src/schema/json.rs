@@ -1,9 +1,11 @@
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 use crate::{
     color::HexColor,
-    schema::{Appearance, Meta},
+    schema::{Appearance, FontStyle, FontWeight, Meta, WindowAppearance},
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -15,11 +17,30 @@ pub struct ThemeFamily {
     pub themes: Vec<JsonTheme>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct JsonTheme {
     pub name: String,
     pub appearance: Appearance,
-    pub style: HashMap<String, StyleEntry>,
+    /// Kept sorted so that generated files are reproducible.
+    pub style: BTreeMap<String, StyleEntry>,
+}
+
+impl JsonTheme {
+    /// Returns the color of a style key that holds a single color, such as `editor.background`.
+    pub fn color(&self, key: &str) -> Option<HexColor> {
+        match self.style.get(key) {
+            Some(StyleEntry::Normal(color)) => *color,
+            _ => None,
+        }
+    }
+
+    /// Returns the foreground color of a syntax scope, such as `keyword`.
+    pub fn syntax_color(&self, scope: &str) -> Option<HexColor> {
+        match self.style.get("syntax") {
+            Some(StyleEntry::Syntax(syntax)) => syntax.get(scope)?.color,
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -27,20 +48,111 @@ pub struct Player {
     pub cursor: Option<HexColor>,
     pub background: Option<HexColor>,
     pub selection: Option<HexColor>,
+    /// Fields that zeddy does not know about
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum StyleEntry {
-    Syntax(HashMap<String, Syntax>),
+    Syntax(BTreeMap<String, Syntax>),
     Players(Vec<Player>),
+    Accents(Vec<HexColor>),
+    WindowAppearance(WindowAppearance),
     Normal(Option<HexColor>),
+    /// An entry that zeddy does not know about, kept as is
+    Other(Value),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Syntax {
     pub color: Option<HexColor>,
     pub background: Option<HexColor>,
-    pub font_weight: Option<u16>,
-    pub font_style: Option<String>,
+    pub font_weight: Option<FontWeight>,
+    pub font_style: Option<FontStyle>,
+    /// Fields that zeddy does not know about
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// A version of Zed's theme JSON schema that a generated theme family can target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SchemaVersion {
+    #[default]
+    V0_1_0,
+}
+
+/// Style keys whose name differs between schema versions, as
+/// `(version, name used in the KDL format, name emitted for that version)`. A version is only
+/// added once the keys that it renames are known, so this is empty while there is just one.
+const RENAMED_STYLE_KEYS: &[(SchemaVersion, &str, &str)] = &[];
+
+/// Style keys that every theme has to set when targeting a schema version, as
+/// `(version, name emitted for that version)`. Version 0.1.0 requires none, since Zed falls back
+/// to its default theme for every key that is missing.
+const REQUIRED_STYLE_KEYS: &[(SchemaVersion, &str)] = &[];
+
+impl SchemaVersion {
+    pub const ALL: &[Self] = &[Self::V0_1_0];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V0_1_0 => "0.1.0",
+        }
+    }
+
+    /// The URL that generated files declare in `$schema`.
+    pub fn url(self) -> String {
+        format!("https://zed.dev/schema/themes/v{self}.json")
+    }
+
+    /// An offline copy of the JSON Schema published at [`SchemaVersion::url`],
+    /// if one is bundled with zeddy.
+    pub fn bundled_schema(self) -> Option<&'static str> {
+        match self {
+            Self::V0_1_0 => Some(include_str!("../../assets/theme-schema-v0.1.0.json")),
+        }
+    }
+
+    /// Iterates over the style keys that have to be renamed when targeting this version.
+    pub fn renamed_style_keys(self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        RENAMED_STYLE_KEYS
+            .iter()
+            .filter(move |(version, _, _)| *version == self)
+            .map(|&(_, from, to)| (from, to))
+    }
+
+    /// Iterates over the style keys that every theme has to set when targeting this version,
+    /// after they have been renamed.
+    pub fn required_style_keys(self) -> impl Iterator<Item = &'static str> {
+        REQUIRED_STYLE_KEYS
+            .iter()
+            .filter(move |(version, _)| *version == self)
+            .map(|&(_, key)| key)
+    }
+}
+
+impl Display for SchemaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SchemaVersion {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('v').unwrap_or(s);
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|version| version.as_str() == s)
+            .ok_or_else(|| {
+                let known = Self::ALL.iter().map(|v| v.as_str()).collect::<Vec<_>>();
+                anyhow!(
+                    "unknown schema version {s:?}, expected one of {}",
+                    known.join(", ")
+                )
+            })
+    }
 }
@@ -1,7 +1,8 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
     hash::{Hash, RandomState},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use knus::{
@@ -9,10 +10,19 @@ use knus::{
     traits::{DecodePartial, ErrorSpan},
     Decode, DecodeScalar,
 };
+use tracing::info_span;
 
-use crate::{color::palette::RawPalette, color::Color, util::ToAnyhow};
+use crate::{
+    color::palette::{ColorNode, RawPalette},
+    color::{BaseColorKind, Color, ColorModifiers, ColorName, HexColor},
+    util::{read_input, ToAnyhow},
+};
 
-use super::{Appearance, Meta};
+use super::{
+    include::{resolve_includes, Include},
+    location::Location,
+    Appearance, FontStyle, FontWeight, KdlVersion, Meta, WindowAppearance,
+};
 
 #[derive(Clone, Debug, Decode)]
 pub struct ThemeFamily {
@@ -24,6 +34,13 @@ pub struct ThemeFamily {
     pub themes: Vec<Theme>,
     #[knus(child)]
     pub common: Option<Theme>,
+    #[knus(children(name = "template"))]
+    pub templates: Vec<Template>,
+    #[knus(children(name = "instantiate"))]
+    pub instances: Vec<Instance>,
+    /// Files whose contents are added to the family. They are resolved when the family is read.
+    #[knus(children(name = "include"))]
+    pub includes: Vec<Include>,
 }
 
 #[derive(Clone, Debug, Decode)]
@@ -32,13 +49,116 @@ pub struct Theme {
     pub name: String,
     #[knus(child, unwrap(argument))]
     pub appearance: Appearance,
+    #[knus(child, unwrap(argument))]
+    pub window_appearance: Option<WindowAppearance>,
     #[knus(children(name = "player"))]
     pub players: Vec<Player>,
+    #[knus(children(name = "accent"))]
+    pub accents: Vec<Color>,
+    #[knus(child)]
+    pub terminal: Option<TerminalColors>,
     #[knus(children(name = "modifier"))]
     pub modifiers: Vec<Modifier>,
+    #[knus(child, default)]
+    pub passthrough: Passthrough,
+    #[knus(span)]
+    pub location: Location,
+}
+
+/// Chains every color of a theme, shared by [`Theme::colors`] and [`Theme::colors_mut`] so that
+/// both visit the same fields.
+macro_rules! theme_colors {
+    ($theme:expr, $iter:ident, $entries:path, $as_ref:ident $(, $mut:tt)?) => {{
+        let theme = $theme;
+        let players = theme
+            .players
+            .$iter()
+            .flat_map(|player| {
+                [
+                    &$($mut)? player.cursor,
+                    &$($mut)? player.background,
+                    &$($mut)? player.selection,
+                ]
+            })
+            .flatten();
+        let terminal = theme
+            .terminal
+            .$iter()
+            .flat_map($entries)
+            .filter_map(|(_, color)| color.$as_ref());
+        let modifiers = theme
+            .modifiers
+            .$iter()
+            .flat_map(|modifier| {
+                [
+                    &$($mut)? modifier.action.color,
+                    &$($mut)? modifier.action.background,
+                ]
+            })
+            .flatten();
+        players
+            .chain(&$($mut)? theme.accents)
+            .chain(terminal)
+            .chain(modifiers)
+    }};
 }
 
 impl Theme {
+    /// Iterates over every color used by the theme.
+    pub fn colors(&self) -> impl Iterator<Item = &Color> {
+        theme_colors!(self, iter, TerminalColors::entries, as_ref)
+    }
+
+    /// Pairs every color used by the theme with where it is used, such as `player 1 cursor`
+    /// or the paths of a modifier.
+    pub fn labeled_colors(&self) -> Vec<(String, &Color)> {
+        let mut colors = Vec::new();
+        for (idx, player) in self.players.iter().enumerate() {
+            let fields = [
+                ("cursor", &player.cursor),
+                ("background", &player.background),
+                ("selection", &player.selection),
+            ];
+            for (field, color) in fields {
+                if let Some(color) = color {
+                    colors.push((format!("player {} {field}", idx + 1), color));
+                }
+            }
+        }
+        colors.extend(
+            self.accents
+                .iter()
+                .map(|color| ("accent".to_owned(), color)),
+        );
+        if let Some(terminal) = &self.terminal {
+            colors.extend(
+                terminal.entries().into_iter().filter_map(|(name, color)| {
+                    Some((format!("terminal {name}"), color.as_ref()?))
+                }),
+            );
+        }
+        for modifier in &self.modifiers {
+            let paths = modifier
+                .apply
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            if let Some(color) = &modifier.action.background {
+                colors.push((format!("background of {paths}"), color));
+            }
+            if let Some(color) = &modifier.action.color {
+                colors.push((paths, color));
+            }
+        }
+        colors
+    }
+
+    /// Iterates over every color used by the theme.
+    pub fn colors_mut(&mut self) -> impl Iterator<Item = &mut Color> {
+        theme_colors!(self, iter_mut, TerminalColors::entries_mut, as_mut, mut)
+    }
+
     pub fn merge(&mut self, bottom: &Self) {
         let prev_mod = std::mem::take(&mut self.modifiers);
         let prev_players = std::mem::take(&mut self.players);
@@ -47,15 +167,27 @@ impl Theme {
         self.modifiers.extend_from_slice(&prev_mod);
         self.players.extend_from_slice(&bottom.players);
         self.players.extend_from_slice(&prev_players);
+        // accents are a single list, so a theme that specifies any replaces the common ones entirely
+        if self.accents.is_empty() {
+            self.accents.clone_from(&bottom.accents);
+        }
+        self.window_appearance = self.window_appearance.or(bottom.window_appearance);
+        match (&mut self.terminal, &bottom.terminal) {
+            (Some(this), Some(bottom)) => this.merge(bottom),
+            (None, Some(bottom)) => self.terminal = Some(bottom.clone()),
+            _ => {}
+        }
+        self.passthrough.merge(&bottom.passthrough);
     }
 
-    fn discard_intersection(
-        &mut self,
-        players: &[Player],
-        modifiers: &HashMap<Action, HashSet<ModifierPath>>,
-    ) {
+    fn discard_intersection(&mut self, players: &[Player], modifiers: &[Modifier]) {
+        let modifiers: HashMap<_, HashSet<_>> = modifiers
+            .iter()
+            .map(|modifier| (&modifier.action, modifier.apply.iter().collect()))
+            .collect();
         self.modifiers
             .iter_mut()
+            .filter(|modifier| modifier.if_appearance.is_none())
             .filter_map(|modifier| modifiers.get(&modifier.action).map(|x| (modifier, x)))
             .for_each(|(modifier, intersection)| {
                 modifier.apply.retain(|x| !intersection.contains(x));
@@ -72,52 +204,281 @@ impl Theme {
             .cloned()
             .collect::<Vec<_>>();
 
-        let this_modifiers: HashMap<_, _, RandomState> = self
-            .modifiers
-            .iter()
-            .map(|x| (&x.action, &x.apply))
-            .collect();
-
         let other_modifiers: HashMap<_, _, RandomState> = other
             .modifiers
             .iter()
+            .filter(|x| x.if_appearance.is_none())
             .map(|x| (&x.action, &x.apply))
             .collect();
 
-        let intersection = this_modifiers
+        // the common modifiers are kept in the order that they appear in `self` so that the
+        // output does not depend on the iteration order of a map
+        let intersection = self
+            .modifiers
             .iter()
-            .filter_map(|(action, &modifiers)| {
-                other_modifiers
-                    .get(action)
-                    .map(|&x| (Action::clone(*action), (modifiers, x)))
-            })
-            .map(|(action, (this, other))| {
-                (
-                    action,
-                    this.iter()
-                        .filter(|x| other.contains(x))
-                        .cloned()
-                        .collect::<HashSet<_>>(),
-                )
+            .filter(|x| x.if_appearance.is_none())
+            .filter_map(|modifier| {
+                let other = other_modifiers.get(&modifier.action)?;
+                let apply = modifier
+                    .apply
+                    .iter()
+                    .filter(|x| other.contains(x))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (!apply.is_empty()).then(|| Modifier {
+                    if_appearance: None,
+                    action: modifier.action.clone(),
+                    apply,
+                    location: modifier.location,
+                })
             })
-            .filter(|(_, x)| !x.is_empty())
-            .collect::<HashMap<_, _>>();
+            .collect::<Vec<_>>();
         self.discard_intersection(&player_intersect, &intersection);
         other.discard_intersection(&player_intersect, &intersection);
+        let accents = if self.accents == other.accents {
+            other.accents.clear();
+            std::mem::take(&mut self.accents)
+        } else {
+            vec![]
+        };
+        let window_appearance = if self.window_appearance == other.window_appearance {
+            other.window_appearance = None;
+            self.window_appearance.take()
+        } else {
+            None
+        };
+        let terminal = if self.terminal == other.terminal {
+            other.terminal = None;
+            self.terminal.take()
+        } else {
+            None
+        };
         Theme {
             name: "common".to_owned(),
             appearance: Appearance::Dark,
+            window_appearance,
+            terminal,
             players: player_intersect,
-            modifiers: intersection
-                .into_iter()
-                .map(|(action, path)| Modifier {
-                    action,
-                    apply: <_>::from_iter(path),
-                })
-                .collect(),
+            accents,
+            modifiers: intersection,
+            passthrough: Passthrough::default(),
+            location: Location::default(),
+        }
+    }
+
+    /// Extracts what all of `themes` have in common into a `common` theme, removing it from
+    /// every theme. Returns `None` if there are fewer than two themes.
+    pub fn extract_common_of(themes: &mut [Theme]) -> Option<Theme> {
+        let [first, second, rest @ ..] = themes else {
+            return None;
+        };
+        let mut common = first.clone().extract_common(&mut second.clone());
+        for theme in rest.iter() {
+            common = common.extract_common(&mut theme.clone());
+        }
+        for theme in themes.iter_mut() {
+            theme.discard_intersection(&common.players, &common.modifiers);
+            if !common.accents.is_empty() {
+                theme.accents.clear();
+            }
+            if common.window_appearance.is_some() {
+                theme.window_appearance = None;
+            }
+            if common.terminal.is_some() {
+                theme.terminal = None;
+            }
+        }
+        Some(common)
+    }
+}
+
+/// Parts of the JSON theme that zeddy does not know about, such as style keys that were added to
+/// Zed after zeddy was written. They are kept as JSON text so that migrating a theme and then
+/// generating it again does not lose them.
+#[derive(Clone, Debug, Decode, Default, PartialEq)]
+pub struct Passthrough {
+    /// Whole entries of the `style` object.
+    #[knus(children(name = "style"))]
+    pub style: Vec<PassthroughStyle>,
+    /// Extra fields of entries in the `syntax` object.
+    #[knus(children(name = "syntax"))]
+    pub syntax: Vec<PassthroughSyntax>,
+}
+
+#[derive(Clone, Debug, Decode, PartialEq)]
+pub struct PassthroughStyle {
+    #[knus(argument)]
+    pub key: String,
+    /// The JSON value of the entry.
+    #[knus(argument)]
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Decode, PartialEq)]
+pub struct PassthroughSyntax {
+    #[knus(argument)]
+    pub key: String,
+    /// The JSON values of the fields by name.
+    #[knus(properties)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Passthrough {
+    pub fn is_empty(&self) -> bool {
+        self.style.is_empty() && self.syntax.is_empty()
+    }
+
+    /// Puts the entries of `bottom` first, so that the entries of `self` override them.
+    fn merge(&mut self, bottom: &Self) {
+        let style = std::mem::take(&mut self.style);
+        let syntax = std::mem::take(&mut self.syntax);
+        self.style.extend(bottom.style.iter().cloned().chain(style));
+        self.syntax
+            .extend(bottom.syntax.iter().cloned().chain(syntax));
+    }
+}
+/// The ANSI colors used by the terminal, which expand into the `terminal.ansi.*` style keys.
+/// All eight normal and eight bright colors have to be provided, either directly or through
+/// the `common` theme, while the dim variants are optional.
+#[derive(Clone, Debug, Decode, Default, PartialEq)]
+pub struct TerminalColors {
+    #[knus(child)]
+    pub black: Option<Color>,
+    #[knus(child)]
+    pub red: Option<Color>,
+    #[knus(child)]
+    pub green: Option<Color>,
+    #[knus(child)]
+    pub yellow: Option<Color>,
+    #[knus(child)]
+    pub blue: Option<Color>,
+    #[knus(child)]
+    pub magenta: Option<Color>,
+    #[knus(child)]
+    pub cyan: Option<Color>,
+    #[knus(child)]
+    pub white: Option<Color>,
+    #[knus(child)]
+    pub bright_black: Option<Color>,
+    #[knus(child)]
+    pub bright_red: Option<Color>,
+    #[knus(child)]
+    pub bright_green: Option<Color>,
+    #[knus(child)]
+    pub bright_yellow: Option<Color>,
+    #[knus(child)]
+    pub bright_blue: Option<Color>,
+    #[knus(child)]
+    pub bright_magenta: Option<Color>,
+    #[knus(child)]
+    pub bright_cyan: Option<Color>,
+    #[knus(child)]
+    pub bright_white: Option<Color>,
+    #[knus(child)]
+    pub dim_black: Option<Color>,
+    #[knus(child)]
+    pub dim_red: Option<Color>,
+    #[knus(child)]
+    pub dim_green: Option<Color>,
+    #[knus(child)]
+    pub dim_yellow: Option<Color>,
+    #[knus(child)]
+    pub dim_blue: Option<Color>,
+    #[knus(child)]
+    pub dim_magenta: Option<Color>,
+    #[knus(child)]
+    pub dim_cyan: Option<Color>,
+    #[knus(child)]
+    pub dim_white: Option<Color>,
+}
+
+impl TerminalColors {
+    /// The number of colors that every terminal block needs to provide.
+    const REQUIRED: usize = 16;
+
+    /// Pairs every color with its node name, normal colors first, then bright, then dim.
+    pub fn entries(&self) -> [(&'static str, &Option<Color>); 24] {
+        [
+            ("black", &self.black),
+            ("red", &self.red),
+            ("green", &self.green),
+            ("yellow", &self.yellow),
+            ("blue", &self.blue),
+            ("magenta", &self.magenta),
+            ("cyan", &self.cyan),
+            ("white", &self.white),
+            ("bright-black", &self.bright_black),
+            ("bright-red", &self.bright_red),
+            ("bright-green", &self.bright_green),
+            ("bright-yellow", &self.bright_yellow),
+            ("bright-blue", &self.bright_blue),
+            ("bright-magenta", &self.bright_magenta),
+            ("bright-cyan", &self.bright_cyan),
+            ("bright-white", &self.bright_white),
+            ("dim-black", &self.dim_black),
+            ("dim-red", &self.dim_red),
+            ("dim-green", &self.dim_green),
+            ("dim-yellow", &self.dim_yellow),
+            ("dim-blue", &self.dim_blue),
+            ("dim-magenta", &self.dim_magenta),
+            ("dim-cyan", &self.dim_cyan),
+            ("dim-white", &self.dim_white),
+        ]
+    }
+
+    pub fn entries_mut(&mut self) -> [(&'static str, &mut Option<Color>); 24] {
+        [
+            ("black", &mut self.black),
+            ("red", &mut self.red),
+            ("green", &mut self.green),
+            ("yellow", &mut self.yellow),
+            ("blue", &mut self.blue),
+            ("magenta", &mut self.magenta),
+            ("cyan", &mut self.cyan),
+            ("white", &mut self.white),
+            ("bright-black", &mut self.bright_black),
+            ("bright-red", &mut self.bright_red),
+            ("bright-green", &mut self.bright_green),
+            ("bright-yellow", &mut self.bright_yellow),
+            ("bright-blue", &mut self.bright_blue),
+            ("bright-magenta", &mut self.bright_magenta),
+            ("bright-cyan", &mut self.bright_cyan),
+            ("bright-white", &mut self.bright_white),
+            ("dim-black", &mut self.dim_black),
+            ("dim-red", &mut self.dim_red),
+            ("dim-green", &mut self.dim_green),
+            ("dim-yellow", &mut self.dim_yellow),
+            ("dim-blue", &mut self.dim_blue),
+            ("dim-magenta", &mut self.dim_magenta),
+            ("dim-cyan", &mut self.dim_cyan),
+            ("dim-white", &mut self.dim_white),
+        ]
+    }
+
+    /// Returns the style key that the color with the given node name expands to.
+    pub fn style_key(name: &str) -> String {
+        format!("terminal.ansi.{}", name.replace('-', "_"))
+    }
+
+    /// Fills in every color that is not set from `bottom`.
+    pub fn merge(&mut self, bottom: &Self) {
+        for ((_, this), (_, other)) in self.entries_mut().into_iter().zip(bottom.entries()) {
+            if this.is_none() {
+                this.clone_from(other);
+            }
         }
     }
+
+    /// Returns the node names of the required colors that have not been provided.
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.entries()
+            .into_iter()
+            .take(Self::REQUIRED)
+            .filter_map(|(name, color)| color.is_none().then_some(name))
+            .collect()
+    }
 }
+
 #[derive(Clone, Debug, Decode, PartialEq)]
 pub struct Player {
     #[knus(child)]
@@ -126,31 +487,324 @@ pub struct Player {
     pub background: Option<Color>,
     #[knus(child)]
     pub selection: Option<Color>,
+    /// Fields of the player that zeddy does not know about, as JSON text.
+    #[knus(properties)]
+    pub extra: BTreeMap<String, String>,
 }
 
 #[derive(Clone, Debug, Decode)]
 pub struct Modifier {
+    /// Only apply the modifier to themes with this appearance, which lets the `common` theme
+    /// hold modifiers that only make sense for light or dark themes.
+    #[knus(property)]
+    pub if_appearance: Option<Appearance>,
     #[knus(child, unwrap(children))]
     pub apply: Vec<ModifierPath>,
     #[knus(flatten(child))]
     pub action: Action,
+    #[knus(span)]
+    pub location: Location,
+}
+
+/// A theme with color parameters that can be stamped out several times with `instantiate`.
+#[derive(Clone, Debug, Decode)]
+pub struct Template {
+    #[knus(argument)]
+    pub name: String,
+    #[knus(children(name = "param"))]
+    pub params: Vec<Param>,
+    /// The theme to instantiate. Its name is replaced by the name of each instance.
+    #[knus(child)]
+    pub theme: Theme,
+}
+
+#[derive(Clone, Debug, Decode)]
+pub struct Param {
+    #[knus(argument)]
+    pub name: String,
+}
+
+/// A theme created from a [`Template`], with a color for each of the template's parameters.
+#[derive(Clone, Debug, Decode)]
+pub struct Instance {
+    #[knus(argument)]
+    pub name: String,
+    #[knus(property)]
+    pub template: String,
+    #[knus(children)]
+    pub args: Vec<ColorNode>,
+    #[knus(span)]
+    pub location: Location,
 }
 
 impl ThemeFamily {
-    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<ThemeFamily> {
+    /// Turns every `instantiate` node into a theme. The colors given to an instance are added to
+    /// the palette under the name `<instance>.<param>`, and references to the parameters in the
+    /// instantiated theme are renamed to point to them.
+    pub fn expand_templates(&mut self) -> anyhow::Result<()> {
+        for instance in std::mem::take(&mut self.instances) {
+            let template = self
+                .templates
+                .iter()
+                .find(|template| template.name == instance.template)
+                .ok_or_else(|| {
+                    instance.location.error(format!(
+                        "theme {:?} instantiates the unknown template {:?}",
+                        instance.name, instance.template
+                    ))
+                })?;
+            if let Some(arg) = instance
+                .args
+                .iter()
+                .find(|arg| !template.params.iter().any(|param| param.name == arg.name))
+            {
+                return Err(arg.location.error(format!(
+                    "theme {:?} provides {:?}, which is not a parameter of template {:?}",
+                    instance.name, arg.name, template.name
+                )));
+            }
+
+            let mut bindings = HashMap::with_capacity(template.params.len());
+            for param in &template.params {
+                let arg = instance
+                    .args
+                    .iter()
+                    .find(|arg| arg.name == param.name)
+                    .ok_or_else(|| {
+                        instance.location.error(format!(
+                            "theme {:?} is missing the parameter {:?} of template {:?}",
+                            instance.name, param.name, template.name
+                        ))
+                    })?;
+                let bound = format!("{}.{}", instance.name, param.name);
+                bindings.insert(param.name.as_str(), ColorName::from(bound.as_str()));
+                self.palette.colors.push(ColorNode {
+                    name: bound,
+                    ..arg.clone()
+                });
+            }
+
+            let mut theme = template.theme.clone();
+            theme.name = instance.name;
+            for color in theme.colors_mut() {
+                for reference in color.base.references_mut() {
+                    if let Some(bound) = bindings.get(&**reference) {
+                        reference.clone_from(bound);
+                    }
+                }
+            }
+            self.themes.push(theme);
+        }
+        Ok(())
+    }
+
+    /// Keeps only the themes called one of `names`. Templates are expanded first so that
+    /// instances can be selected as well.
+    pub fn retain_themes(&mut self, names: &[String]) -> anyhow::Result<()> {
+        self.expand_templates()?;
+        if let Some(name) = names
+            .iter()
+            .find(|name| !self.themes.iter().any(|theme| &theme.name == *name))
+        {
+            return Err(anyhow::anyhow!("there is no theme called {name:?}"));
+        }
+        self.themes.retain(|theme| names.contains(&theme.name));
+        Ok(())
+    }
+
+    /// Returns the names of the palette colors that `themes` refer to, either directly or
+    /// through other palette colors. Templates have to be expanded first.
+    pub fn used_palette_colors<'a>(
+        &self,
+        themes: impl IntoIterator<Item = &'a Theme>,
+    ) -> BTreeSet<ColorName> {
+        let mut pending = themes
+            .into_iter()
+            .flat_map(Theme::colors)
+            .flat_map(|color| color.base.references())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut used = BTreeSet::new();
+        while let Some(name) = pending.pop() {
+            if !used.insert(name.clone()) {
+                continue;
+            }
+            for node in self
+                .palette
+                .colors
+                .iter()
+                .filter(|node| *node.name == *name)
+            {
+                pending.extend(node.base.references().into_iter().cloned());
+            }
+        }
+        used
+    }
+
+    /// Describes every place that refers to the palette color `name`, including other palette
+    /// colors.
+    pub fn palette_color_uses(&self, name: &str) -> Vec<String> {
+        let refers = |base: &BaseColorKind| {
+            base.references()
+                .into_iter()
+                .any(|reference| &**reference == name)
+        };
+        let palette = self
+            .palette
+            .colors
+            .iter()
+            .filter(|node| refers(&node.base))
+            .map(|node| format!("palette color {}", node.name));
+        let themes = self
+            .themes
+            .iter()
+            .chain(&self.common)
+            .chain(self.templates.iter().map(|template| &template.theme))
+            .flat_map(|theme| {
+                theme
+                    .labeled_colors()
+                    .into_iter()
+                    .filter(|(_, color)| refers(&color.base))
+                    .map(|(label, _)| format!("{}: {label}", theme.name))
+            });
+        palette.chain(themes).collect()
+    }
+
+    /// Renames a palette color along with every reference to it.
+    pub fn rename_palette_color(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        if self.palette.colors.iter().any(|node| node.name == to) {
+            return Err(anyhow::anyhow!(
+                "the palette already has a color called {to:?}"
+            ));
+        }
+        self.palette_color(from)?;
+        for node in &mut self.palette.colors {
+            if node.name == from {
+                to.clone_into(&mut node.name);
+            }
+        }
+        self.replace_references(from, &BaseColorKind::PaletteReference(to.into()));
+        Ok(())
+    }
+
+    /// Removes the palette color `from` and points its references at the palette color `into`.
+    pub fn merge_palette_color(&mut self, from: &str, into: &str) -> anyhow::Result<()> {
+        if from == into {
+            return Err(anyhow::anyhow!("cannot merge {from:?} into itself"));
+        }
+        self.palette_color(from)?;
+        self.palette_color(into)?;
+        self.palette.colors.retain(|node| node.name != from);
+        self.replace_references(from, &BaseColorKind::PaletteReference(into.into()));
+        Ok(())
+    }
+
+    /// Removes the palette color `name` and writes its color at every place that refers to it.
+    pub fn inline_palette_color(&mut self, name: &str) -> anyhow::Result<()> {
+        let node = self.palette_color(name)?;
+        if node.modifiers != ColorModifiers::default() {
+            return Err(anyhow::anyhow!(
+                "{name:?} has modifiers, so it cannot be inlined"
+            ));
+        }
+        let base = node.base.clone();
+        self.palette.colors.retain(|node| node.name != name);
+        self.replace_references(name, &base);
+        Ok(())
+    }
+
+    fn palette_color(&self, name: &str) -> anyhow::Result<&ColorNode> {
+        self.palette
+            .colors
+            .iter()
+            .find(|node| node.name == name)
+            .ok_or_else(|| anyhow::anyhow!("there is no palette color called {name:?}"))
+    }
+
+    /// Points every reference to the palette color `name`, in the palette and in the themes, at
+    /// `base` instead. The modifiers of the references are kept.
+    fn replace_references(&mut self, name: &str, base: &BaseColorKind) {
+        for node in &mut self.palette.colors {
+            node.base.replace_reference(name, base);
+        }
+        let themes = self.themes.iter_mut().chain(&mut self.common).chain(
+            self.templates
+                .iter_mut()
+                .map(|template| &mut template.theme),
+        );
+        for theme in themes {
+            for color in theme.colors_mut() {
+                color.base.replace_reference(name, base);
+            }
+        }
+    }
+
+    /// Returns the resolved palette colors that each theme uses, in the order they are declared
+    /// in. Templates have to be expanded first.
+    pub fn theme_palettes(&self) -> anyhow::Result<Vec<Vec<(String, HexColor)>>> {
+        let resolved = self.palette.clone().into_palette().resolve()?;
+        Ok(self
+            .themes
+            .iter()
+            .map(|theme| {
+                let used = self.used_palette_colors(std::iter::once(theme).chain(&self.common));
+                self.palette
+                    .colors
+                    .iter()
+                    .filter(|node| used.contains(node.name.as_str()))
+                    .filter_map(|node| {
+                        Some((node.name.clone(), *resolved.colors.get(node.name.as_str())?))
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Reads a theme family from a file written in the given version of KDL, along with the
+    /// files it includes. Errors in KDL v2 files point into the file after it has been
+    /// converted to KDL v1. A path of `-` reads from standard input.
+    pub fn read(path: impl AsRef<Path>, version: KdlVersion) -> anyhow::Result<ThemeFamily> {
         let p = path.as_ref();
-        let path_name = p.display().to_string();
-        let content = std::fs::read_to_string(p)?;
-        knus::parse::<ThemeFamily>(&path_name, &content).to_anyhow()
+        let _span = info_span!("parse", file = %p.display()).entered();
+        let content = read_input(p)?;
+        let mut family = Self::parse(&p.display().to_string(), &content, version)?;
+        resolve_includes(&mut family, p, version)?;
+        Ok(family)
+    }
+
+    /// Returns the canonical paths of the files that the theme family in `path` includes,
+    /// directly or through other included files.
+    pub fn included_files(path: &Path, version: KdlVersion) -> anyhow::Result<Vec<PathBuf>> {
+        let content = read_input(path)?;
+        let mut family = Self::parse(&path.display().to_string(), &content, version)?;
+        resolve_includes(&mut family, path, version)
+    }
+
+    /// Decodes a theme family from the text of a file written in the given version of KDL.
+    /// `name` is the name that errors refer to the text by. Includes are left as they are,
+    /// since their paths are relative to a file.
+    pub fn parse(name: &str, source: &str, version: KdlVersion) -> anyhow::Result<ThemeFamily> {
+        let source = version.to_v1(source)?;
+        knus::parse::<ThemeFamily>(name, &source).to_anyhow()
     }
 }
 
-#[derive(Clone, Debug, Decode, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Decode, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ModifierPath {
     Style(#[knus(argument)] String),
     Syntax(#[knus(argument)] String),
 }
 
+/// Writes the path the way `--set` keys are written, such as `style.editor.background`.
+impl Display for ModifierPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Style(path) => write!(f, "style.{path}"),
+            Self::Syntax(path) => write!(f, "syntax.{path}"),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum BorrowedModifierPath<'a> {
     Style(&'a str),
@@ -216,7 +870,44 @@ pub struct Action {
     #[knus(child)]
     pub background: Option<Color>,
     #[knus(child, unwrap(argument))]
-    pub font_weight: Option<u16>,
+    pub font_weight: Option<FontWeight>,
     #[knus(child, unwrap(argument))]
-    pub font_style: Option<String>,
+    pub font_style: Option<FontStyle>,
+    /// Fields to clear on the targets, or the targets themselves if no fields are given.
+    /// Fields that are neither set nor unset by an action are left as they were by previous modifiers.
+    #[knus(child, unwrap(arguments))]
+    pub unset: Option<Vec<ActionField>>,
+}
+
+impl Action {
+    /// Returns whether the action sets any field, as opposed to only clearing them.
+    pub fn sets_any(&self) -> bool {
+        self.color.is_some()
+            || self.background.is_some()
+            || self.font_weight.is_some()
+            || self.font_style.is_some()
+    }
+
+    /// Returns whether the action removes its targets entirely, which is written as an `unset`
+    /// without any fields.
+    pub fn removes_target(&self) -> bool {
+        self.unset.as_ref().is_some_and(Vec::is_empty)
+    }
+
+    /// Returns whether the action clears the given field, either explicitly or by removing
+    /// the target entirely.
+    pub fn clears(&self, field: ActionField) -> bool {
+        self.unset
+            .as_ref()
+            .is_some_and(|fields| fields.is_empty() || fields.contains(&field))
+    }
+}
+
+/// A field of an [`Action`], used to name the fields that an action clears.
+#[derive(Clone, Copy, Debug, DecodeScalar, PartialEq, Eq, Hash)]
+pub enum ActionField {
+    Color,
+    Background,
+    FontWeight,
+    FontStyle,
 }
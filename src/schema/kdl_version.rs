@@ -0,0 +1,162 @@
+//! Support for reading and writing theme files in either version of the KDL language.
+//! The decoder and the document model that we format with only understand KDL v1, so v2
+//! documents are converted to v1 when they are read and back to v2 right before they are written.
+
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Result as Res};
+use kdl::{KdlDocument, KdlIdentifier, KdlValue};
+
+/// A version of the KDL language that theme files can be written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KdlVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+/// Bare identifiers that are keywords in KDL v2 and have to be quoted.
+const V2_KEYWORDS: &[&str] = &["true", "false", "null", "inf", "-inf", "nan"];
+
+impl KdlVersion {
+    pub const ALL: &[Self] = &[Self::V1, Self::V2];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V1 => "1",
+            Self::V2 => "2",
+        }
+    }
+
+    /// Converts a document written in this version into KDL v1 so that it can be decoded.
+    pub fn to_v1(self, source: &str) -> Res<Cow<'_, str>> {
+        match self {
+            Self::V1 => Ok(Cow::Borrowed(source)),
+            Self::V2 => {
+                let mut doc = kdl_v2::KdlDocument::parse_v2(source)
+                    .map_err(|e| describe_v2_error(source, &e))?;
+                doc.ensure_v1();
+                terminate_blocks(&mut doc);
+                Ok(Cow::Owned(doc.to_string()))
+            }
+        }
+    }
+
+    /// Renders a KDL v1 document in this version, keeping its comments and formatting.
+    pub fn render(self, mut doc: KdlDocument) -> Res<String> {
+        match self {
+            Self::V1 => Ok(doc.to_string()),
+            Self::V2 => {
+                ensure_v2(&mut doc);
+                let rendered = doc.to_string();
+                // make sure that nothing slipped through the conversion
+                kdl_v2::KdlDocument::parse_v2(&rendered).map_err(|e| {
+                    anyhow!(
+                        "could not convert the document to KDL v2: {}",
+                        describe_v2_error(&rendered, &e)
+                    )
+                })?;
+                Ok(rendered)
+            }
+        }
+    }
+}
+
+/// The v1 conversion does not terminate the last node of a block that is closed on the same
+/// line, as in `apply { style "text" }`, which v1 requires.
+fn terminate_blocks(doc: &mut kdl_v2::KdlDocument) {
+    for node in doc.nodes_mut() {
+        if let Some(children) = node.children_mut() {
+            if let Some(format) = children
+                .nodes_mut()
+                .last_mut()
+                .and_then(kdl_v2::KdlNode::format_mut)
+            {
+                let ends_line =
+                    format.terminator.contains(['\n', ';']) || format.trailing.contains('\n');
+                if !ends_line {
+                    format.before_terminator.insert(0, ';');
+                }
+            }
+            terminate_blocks(children);
+        }
+    }
+}
+
+/// Rewrites the parts of a v1 document that are spelled differently in v2.
+fn ensure_v2(doc: &mut KdlDocument) {
+    for node in doc.nodes_mut() {
+        quote_keyword(node.name_mut());
+        if let Some(ty) = node.ty_mut() {
+            quote_keyword(ty);
+        }
+        for entry in node.entries_mut() {
+            match entry.value().clone() {
+                KdlValue::Bool(value) => entry.set_value_repr(format!("#{value}")),
+                KdlValue::Null => entry.set_value_repr("#null"),
+                // raw strings use hashes without the leading `r` in v2, so write them as
+                // regular strings instead
+                KdlValue::RawString(value) => {
+                    let value = KdlValue::String(value);
+                    entry.set_value_repr(value.to_string());
+                    entry.set_value(value);
+                }
+                // `\/` is not an escape in v2
+                KdlValue::String(value)
+                    if entry.value_repr().is_some_and(|repr| repr.contains("\\/")) =>
+                {
+                    entry.set_value_repr(KdlValue::String(value).to_string());
+                }
+                _ => {}
+            }
+        }
+        if let Some(children) = node.children_mut() {
+            ensure_v2(children);
+        }
+    }
+}
+
+fn quote_keyword(ident: &mut KdlIdentifier) {
+    let value = ident.value();
+    if V2_KEYWORDS.contains(&value) || value.contains('#') {
+        let quoted = KdlValue::String(value.to_owned()).to_string();
+        ident.set_repr(quoted);
+    }
+}
+
+fn describe_v2_error(source: &str, error: &kdl_v2::KdlError) -> anyhow::Error {
+    let problems = error
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let offset = diagnostic.span.offset();
+            let line = source
+                .get(..offset)
+                .map_or(0, |before| before.matches('\n').count())
+                + 1;
+            match &diagnostic.help {
+                Some(help) => format!("line {line}: {diagnostic}. {help}"),
+                None => format!("line {line}: {diagnostic}"),
+            }
+        })
+        .collect::<Vec<_>>();
+    anyhow!("invalid KDL v2 document:\n  {}", problems.join("\n  "))
+}
+
+impl Display for KdlVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for KdlVersion {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('v').unwrap_or(s);
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|version| version.as_str() == s)
+            .ok_or_else(|| anyhow!("unknown KDL version {s:?}, expected 1 or 2"))
+    }
+}
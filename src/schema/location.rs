@@ -0,0 +1,134 @@
+//! Locations of nodes in the KDL source, so that mistakes that are only found after decoding,
+//! such as a reference to a color that is not in the palette, can point at the offending line.
+
+use std::{error::Error, fmt::Display, hash::Hash, path::Path};
+
+use anyhow::anyhow;
+use knus::{decode::Context, traits::DecodeSpan, traits::ErrorSpan};
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode, SourceSpan};
+
+use crate::schema::KdlVersion;
+use crate::util::read_input;
+
+/// The location of a node in the KDL source, if it was decoded from one. Locations are ignored
+/// when comparing nodes, so the same color written in two places is still the same color.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Location(Option<SourceSpan>);
+
+impl Location {
+    /// Creates an error that points at this location if it is known.
+    pub fn error(self, message: impl Display) -> anyhow::Error {
+        match self.0 {
+            Some(span) => LocatedError {
+                message: message.to_string(),
+                span,
+                source_code: None,
+            }
+            .into(),
+            None => anyhow!("{message}"),
+        }
+    }
+
+    pub fn span(self) -> Option<SourceSpan> {
+        self.0
+    }
+
+    /// Finds the 1-based line of `source` that this location starts on, if it is known.
+    pub fn line(self, source: &str) -> Option<usize> {
+        let offset = self.0?.offset();
+        let before = source.get(..offset)?;
+        Some(before.matches('\n').count() + 1)
+    }
+}
+
+impl PartialEq for Location {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Location {}
+
+impl Hash for Location {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl<S: ErrorSpan> DecodeSpan<S> for Location {
+    fn decode_span(span: &S, _ctx: &mut Context<S>) -> Self {
+        Self(Some(span.clone().into()))
+    }
+}
+
+/// An error in a theme that was found after decoding it.
+#[derive(Debug)]
+pub struct LocatedError {
+    message: String,
+    span: SourceSpan,
+    source_code: Option<NamedSource>,
+}
+
+impl LocatedError {
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl Display for LocatedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for LocatedError {}
+
+impl Diagnostic for LocatedError {
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.source_code
+            .as_ref()
+            .map(|source| source as &dyn SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some("here".to_owned()),
+            self.span,
+        ))))
+    }
+}
+
+/// Shows the lines of the KDL file at `path` that an error points at, if it points anywhere.
+/// Errors in KDL v2 files point into the file after it has been converted to KDL v1.
+pub fn with_source(error: anyhow::Error, path: &Path, version: KdlVersion) -> anyhow::Error {
+    let located = match error.downcast::<LocatedError>() {
+        Ok(located) => located,
+        Err(error) => return error,
+    };
+    let Ok(content) = read_input(path) else {
+        return located.into();
+    };
+    with_source_text(
+        located.into(),
+        &path.display().to_string(),
+        &content,
+        version,
+    )
+}
+
+/// Shows the lines of `source`, the text of a KDL file called `name`, that an error points at,
+/// like [`with_source`] does for files.
+pub fn with_source_text(
+    error: anyhow::Error,
+    name: &str,
+    source: &str,
+    version: KdlVersion,
+) -> anyhow::Error {
+    let mut located = match error.downcast::<LocatedError>() {
+        Ok(located) => located,
+        Err(error) => return error,
+    };
+    let Ok(content) = version.to_v1(source) else {
+        return located.into();
+    };
+    located.source_code = Some(NamedSource::new(name, content.into_owned()));
+    anyhow!("{:?}", miette::Report::new(located))
+}
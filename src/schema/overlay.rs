@@ -0,0 +1,96 @@
+//! Overlays are partial theme families that are applied on top of a theme family when it is
+//! generated, so that personal tweaks can live outside of an upstream theme file.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use knus::Decode;
+
+use crate::color::palette::RawPalette;
+use crate::schema::kdl::{Modifier, ThemeFamily};
+use crate::schema::location::Location;
+use crate::schema::KdlVersion;
+use crate::util::ToAnyhow;
+
+/// A partial theme family. Its palette entries replace the ones of the same name in the base
+/// family, and its modifiers are applied after all modifiers of the base themes.
+#[derive(Clone, Debug, Decode, Default)]
+pub struct Overlay {
+    #[knus(child, default)]
+    pub palette: RawPalette,
+    /// Modifiers that are added to every theme.
+    #[knus(child)]
+    pub common: Option<OverlayTheme>,
+    /// Modifiers that are added to the theme with the same name.
+    #[knus(children(name = "theme"))]
+    pub themes: Vec<OverlayTheme>,
+}
+
+#[derive(Clone, Debug, Decode, Default)]
+pub struct OverlayTheme {
+    #[knus(child, unwrap(argument))]
+    pub name: Option<String>,
+    #[knus(children(name = "modifier"))]
+    pub modifiers: Vec<Modifier>,
+}
+
+impl Overlay {
+    /// Reads an overlay from a file written in the given version of KDL.
+    pub fn read(path: impl AsRef<Path>, version: KdlVersion) -> anyhow::Result<Self> {
+        let p = path.as_ref();
+        let path_name = p.display().to_string();
+        let content = std::fs::read_to_string(p)?;
+        let content = version.to_v1(&content)?;
+        knus::parse::<Self>(&path_name, &content).to_anyhow()
+    }
+
+    /// Applies the overlay on top of `family`. Templates are expanded first so that the overlay
+    /// can refer to instantiated themes by name.
+    pub fn apply(mut self, family: &mut ThemeFamily) -> anyhow::Result<()> {
+        // errors are reported against the base file, so locations in the overlay would point
+        // at the wrong lines
+        self.forget_locations();
+        family.expand_templates()?;
+        for node in self.palette.colors {
+            family.palette.insert(node);
+        }
+        if let Some(common) = &self.common {
+            for theme in &mut family.themes {
+                theme.modifiers.extend_from_slice(&common.modifiers);
+            }
+        }
+        for overlay in self.themes {
+            let name = overlay
+                .name
+                .ok_or_else(|| anyhow!("every `theme` of an overlay needs a `name`"))?;
+            let theme = family
+                .themes
+                .iter_mut()
+                .find(|theme| theme.name == name)
+                .ok_or_else(|| anyhow!("the overlay modifies the unknown theme {name:?}"))?;
+            theme.modifiers.extend(overlay.modifiers);
+        }
+        Ok(())
+    }
+
+    fn forget_locations(&mut self) {
+        for node in &mut self.palette.colors {
+            node.location = Location::default();
+        }
+        let modifiers = self
+            .common
+            .iter_mut()
+            .chain(&mut self.themes)
+            .flat_map(|theme| &mut theme.modifiers);
+        for modifier in modifiers {
+            modifier.location = Location::default();
+            let action = &mut modifier.action;
+            for color in [&mut action.color, &mut action.background]
+                .into_iter()
+                .flatten()
+            {
+                color.location = Location::default();
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Overrides given on the command line, which patch a theme family after it is read so that
+//! a color can be tried out without editing the theme file.
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::color::{palette::ColorNode, BaseColorKind, Color};
+use crate::schema::kdl::{Action, Modifier, ModifierPath, ThemeFamily};
+use crate::schema::location::Location;
+
+/// A single `key=color` override, such as `style.editor.background=#101418` or
+/// `palette.accent=#ff7733`. The color can be a hex color or the name of a palette color.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Override {
+    /// Replaces the color of a palette entry, or adds it if it does not exist.
+    Palette { name: String, color: Color },
+    /// Sets the color of a `style` or `syntax` path in every theme, after all other modifiers.
+    Path { path: ModifierPath, color: Color },
+}
+
+impl Override {
+    /// Applies the override to every theme of the family, including the ones that will be
+    /// created from templates.
+    pub fn apply(&self, family: &mut ThemeFamily) {
+        match self {
+            Self::Palette { name, color } => {
+                family.palette.insert(ColorNode {
+                    name: name.clone(),
+                    base: color.base.clone(),
+                    modifiers: color.modifiers,
+                    lock: false,
+                    location: Location::default(),
+                });
+            }
+            Self::Path { path, color } => {
+                let modifier = Modifier {
+                    if_appearance: None,
+                    apply: vec![path.clone()],
+                    action: Action {
+                        color: Some(color.clone()),
+                        ..Action::default()
+                    },
+                    location: Location::default(),
+                };
+                let themes = family.themes.iter_mut().chain(
+                    family
+                        .templates
+                        .iter_mut()
+                        .map(|template| &mut template.theme),
+                );
+                for theme in themes {
+                    theme.modifiers.push(modifier.clone());
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Override {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected `key=color`, got {s:?}"))?;
+        let color = Color {
+            base: BaseColorKind::from_str(value.trim())?,
+            ..Color::default()
+        };
+        let key = key.trim();
+        if let Some(name) = key.strip_prefix("palette.") {
+            return Ok(Self::Palette {
+                name: name.to_owned(),
+                color,
+            });
+        }
+        let path = if let Some(path) = key.strip_prefix("style.") {
+            ModifierPath::Style(path.to_owned())
+        } else if let Some(path) = key.strip_prefix("syntax.") {
+            ModifierPath::Syntax(path.to_owned())
+        } else {
+            return Err(anyhow!(
+                "expected the key {key:?} to start with `style.`, `syntax.` or `palette.`"
+            ));
+        };
+        Ok(Self::Path { path, color })
+    }
+}
@@ -0,0 +1,321 @@
+//! Knowledge about Zed's own theme schema that cannot be inferred from the KDL input.
+
+/// Every key accepted in the `style` object of Zed's theme schema along with a short
+/// description, excluding `players` and `syntax`, which are modeled separately.
+pub const STYLE_KEYS: &[(&str, &str)] = &[
+    ("background", "Background of the workspace"),
+    ("border", "Default border color"),
+    ("border.disabled", "Border of disabled elements"),
+    ("border.focused", "Border of focused elements"),
+    ("border.selected", "Border of selected elements"),
+    (
+        "border.transparent",
+        "Border that is meant to be invisible but still take up space",
+    ),
+    (
+        "border.variant",
+        "Subtler border used for dividers within elements",
+    ),
+    ("conflict", "Foreground for merge conflicts"),
+    ("conflict.background", "Background for merge conflicts"),
+    ("conflict.border", "Border for merge conflicts"),
+    ("created", "Foreground for created files and lines"),
+    (
+        "created.background",
+        "Background for created files and lines",
+    ),
+    ("created.border", "Border for created files and lines"),
+    ("deleted", "Foreground for deleted files and lines"),
+    (
+        "deleted.background",
+        "Background for deleted files and lines",
+    ),
+    ("deleted.border", "Border for deleted files and lines"),
+    (
+        "drop_target.background",
+        "Background of a region that accepts a drag and drop",
+    ),
+    (
+        "editor.active_line.background",
+        "Background of the line containing the cursor",
+    ),
+    (
+        "editor.active_line_number",
+        "Line number of the line containing the cursor",
+    ),
+    (
+        "editor.active_wrap_guide",
+        "Wrap guide of the active editor",
+    ),
+    ("editor.background", "Background of the editor"),
+    (
+        "editor.document_highlight.bracket_background",
+        "Background of matching brackets",
+    ),
+    (
+        "editor.document_highlight.read_background",
+        "Background of read occurrences of the symbol under the cursor",
+    ),
+    (
+        "editor.document_highlight.write_background",
+        "Background of write occurrences of the symbol under the cursor",
+    ),
+    ("editor.foreground", "Default text color in the editor"),
+    (
+        "editor.gutter.background",
+        "Background of the editor gutter",
+    ),
+    (
+        "editor.highlighted_line.background",
+        "Background of highlighted lines",
+    ),
+    ("editor.indent_guide", "Indent guides in the editor"),
+    (
+        "editor.indent_guide_active",
+        "Indent guide of the current scope",
+    ),
+    (
+        "editor.invisible",
+        "Rendered whitespace and other invisible characters",
+    ),
+    ("editor.line_number", "Line numbers in the gutter"),
+    (
+        "editor.subheader.background",
+        "Background of excerpt headers in multibuffers",
+    ),
+    ("editor.wrap_guide", "Wrap guides in the editor"),
+    ("element.active", "Background of pressed elements"),
+    (
+        "element.background",
+        "Background of elements such as buttons",
+    ),
+    ("element.disabled", "Background of disabled elements"),
+    ("element.hover", "Background of hovered elements"),
+    ("element.selected", "Background of selected elements"),
+    (
+        "elevated_surface.background",
+        "Background of floating surfaces such as popovers and modals",
+    ),
+    ("error", "Foreground for errors"),
+    ("error.background", "Background for errors"),
+    ("error.border", "Border for errors"),
+    (
+        "ghost_element.active",
+        "Background of pressed ghost elements",
+    ),
+    (
+        "ghost_element.background",
+        "Background of elements with no background of their own",
+    ),
+    (
+        "ghost_element.disabled",
+        "Background of disabled ghost elements",
+    ),
+    (
+        "ghost_element.hover",
+        "Background of hovered ghost elements",
+    ),
+    (
+        "ghost_element.selected",
+        "Background of selected ghost elements",
+    ),
+    ("hidden", "Foreground for hidden entries"),
+    ("hidden.background", "Background for hidden entries"),
+    ("hidden.border", "Border for hidden entries"),
+    ("hint", "Foreground for inlay hints and hint diagnostics"),
+    (
+        "hint.background",
+        "Background for inlay hints and hint diagnostics",
+    ),
+    ("hint.border", "Border for inlay hints and hint diagnostics"),
+    ("icon", "Default icon color"),
+    ("icon.accent", "Accented icons"),
+    ("icon.disabled", "Icons of disabled elements"),
+    ("icon.muted", "Less prominent icons"),
+    ("icon.placeholder", "Placeholder icons"),
+    ("ignored", "Foreground for ignored files"),
+    ("ignored.background", "Background for ignored files"),
+    ("ignored.border", "Border for ignored files"),
+    ("info", "Foreground for informational diagnostics"),
+    (
+        "info.background",
+        "Background for informational diagnostics",
+    ),
+    ("info.border", "Border for informational diagnostics"),
+    ("link_text.hover", "Hovered links"),
+    ("modified", "Foreground for modified files and lines"),
+    (
+        "modified.background",
+        "Background for modified files and lines",
+    ),
+    ("modified.border", "Border for modified files and lines"),
+    ("pane.focused_border", "Border of the focused pane"),
+    ("pane_group.border", "Border between panes"),
+    (
+        "panel.background",
+        "Background of panels such as the project panel",
+    ),
+    ("panel.focused_border", "Border of the focused panel"),
+    ("panel.indent_guide", "Indent guides in panels"),
+    (
+        "panel.indent_guide_active",
+        "Indent guide of the selected entry in panels",
+    ),
+    (
+        "panel.indent_guide_hover",
+        "Hovered indent guides in panels",
+    ),
+    ("predictive", "Foreground for predicted edits"),
+    ("predictive.background", "Background for predicted edits"),
+    ("predictive.border", "Border for predicted edits"),
+    ("renamed", "Foreground for renamed files"),
+    ("renamed.background", "Background for renamed files"),
+    ("renamed.border", "Border for renamed files"),
+    (
+        "scrollbar.thumb.active_background",
+        "Scrollbar thumb while dragging",
+    ),
+    ("scrollbar.thumb.background", "Scrollbar thumb"),
+    ("scrollbar.thumb.border", "Border of the scrollbar thumb"),
+    (
+        "scrollbar.thumb.hover_background",
+        "Hovered scrollbar thumb",
+    ),
+    ("scrollbar.track.background", "Scrollbar track"),
+    ("scrollbar.track.border", "Border of the scrollbar track"),
+    ("search.match_background", "Background of search matches"),
+    ("status_bar.background", "Background of the status bar"),
+    ("success", "Foreground for successful operations"),
+    ("success.background", "Background for successful operations"),
+    ("success.border", "Border for successful operations"),
+    (
+        "surface.background",
+        "Background of surfaces such as panels",
+    ),
+    ("tab.active_background", "Background of the active tab"),
+    ("tab.inactive_background", "Background of inactive tabs"),
+    ("tab_bar.background", "Background of the tab bar"),
+    (
+        "terminal.ansi.background",
+        "Background for ANSI colored text in the terminal",
+    ),
+    ("terminal.ansi.black", "ANSI black"),
+    ("terminal.ansi.blue", "ANSI blue"),
+    ("terminal.ansi.bright_black", "ANSI bright black"),
+    ("terminal.ansi.bright_blue", "ANSI bright blue"),
+    ("terminal.ansi.bright_cyan", "ANSI bright cyan"),
+    ("terminal.ansi.bright_green", "ANSI bright green"),
+    ("terminal.ansi.bright_magenta", "ANSI bright magenta"),
+    ("terminal.ansi.bright_red", "ANSI bright red"),
+    ("terminal.ansi.bright_white", "ANSI bright white"),
+    ("terminal.ansi.bright_yellow", "ANSI bright yellow"),
+    ("terminal.ansi.cyan", "ANSI cyan"),
+    ("terminal.ansi.dim_black", "ANSI dim black"),
+    ("terminal.ansi.dim_blue", "ANSI dim blue"),
+    ("terminal.ansi.dim_cyan", "ANSI dim cyan"),
+    ("terminal.ansi.dim_green", "ANSI dim green"),
+    ("terminal.ansi.dim_magenta", "ANSI dim magenta"),
+    ("terminal.ansi.dim_red", "ANSI dim red"),
+    ("terminal.ansi.dim_white", "ANSI dim white"),
+    ("terminal.ansi.dim_yellow", "ANSI dim yellow"),
+    ("terminal.ansi.green", "ANSI green"),
+    ("terminal.ansi.magenta", "ANSI magenta"),
+    ("terminal.ansi.red", "ANSI red"),
+    ("terminal.ansi.white", "ANSI white"),
+    ("terminal.ansi.yellow", "ANSI yellow"),
+    ("terminal.background", "Background of the terminal"),
+    ("terminal.bright_foreground", "Bright text in the terminal"),
+    ("terminal.dim_foreground", "Dim text in the terminal"),
+    ("terminal.foreground", "Default text in the terminal"),
+    ("text", "Default text color"),
+    ("text.accent", "Accented text"),
+    ("text.disabled", "Text of disabled elements"),
+    ("text.muted", "Less prominent text"),
+    ("text.placeholder", "Placeholder text"),
+    ("title_bar.background", "Background of the title bar"),
+    (
+        "title_bar.inactive_background",
+        "Background of the title bar when the window is inactive",
+    ),
+    ("toolbar.background", "Background of the toolbar"),
+    ("unreachable", "Foreground for unreachable code"),
+    ("unreachable.background", "Background for unreachable code"),
+    ("unreachable.border", "Border for unreachable code"),
+    ("warning", "Foreground for warnings"),
+    ("warning.background", "Background for warnings"),
+    ("warning.border", "Border for warnings"),
+];
+
+/// The syntax scopes that Zed's bundled languages highlight, which are the keys
+/// commonly used in the `style.syntax` object. Unlike style keys, languages are free to
+/// define their own scopes, so this list is not exhaustive.
+pub const SYNTAX_KEYS: &[(&str, &str)] = &[
+    ("attribute", "Attributes and annotations"),
+    ("boolean", "Boolean literals"),
+    ("comment", "Comments"),
+    ("comment.doc", "Documentation comments"),
+    ("constant", "Constants"),
+    ("constructor", "Constructors and struct literals"),
+    ("embedded", "Embedded code in another language"),
+    ("emphasis", "Emphasized text in markup"),
+    ("emphasis.strong", "Strongly emphasized text in markup"),
+    ("enum", "Enums and enum variants"),
+    ("function", "Functions and methods"),
+    ("hint", "Inlay hints"),
+    ("keyword", "Keywords"),
+    ("label", "Labels, such as loop labels and lifetimes"),
+    ("link_text", "Link text in markup"),
+    ("link_uri", "Link targets in markup"),
+    ("number", "Numeric literals"),
+    ("operator", "Operators"),
+    ("predictive", "Predicted text from edit predictions"),
+    ("preproc", "Preprocessor directives"),
+    ("primary", "Text without a more specific scope"),
+    ("property", "Fields and properties"),
+    ("punctuation", "Punctuation"),
+    ("punctuation.bracket", "Brackets, braces, and parentheses"),
+    (
+        "punctuation.delimiter",
+        "Delimiters such as commas and semicolons",
+    ),
+    ("punctuation.list_marker", "List markers in markup"),
+    (
+        "punctuation.special",
+        "Special punctuation such as string interpolation",
+    ),
+    ("string", "String literals"),
+    ("string.escape", "Escape sequences in strings"),
+    ("string.regex", "Regular expressions"),
+    ("string.special", "Special strings"),
+    ("string.special.symbol", "Symbols and atoms"),
+    ("tag", "Markup tags"),
+    ("text.literal", "Literal text in markup, such as code spans"),
+    ("title", "Headings in markup"),
+    ("type", "Types"),
+    ("variable", "Variables"),
+    (
+        "variable.special",
+        "Special variables such as `self` and `this`",
+    ),
+    ("variant", "Enum variants"),
+];
+
+/// The largest edit distance at which a known key is still offered as a suggestion.
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// Returns whether `key` is a style key that Zed knows about.
+pub fn is_style_key(key: &str) -> bool {
+    STYLE_KEYS
+        .binary_search_by_key(&key, |&(known, _)| known)
+        .is_ok()
+}
+
+/// Finds the known style key closest to `key`, if any is close enough to be a likely typo.
+pub fn suggest_style_key(key: &str) -> Option<&'static str> {
+    STYLE_KEYS
+        .iter()
+        .map(|&(known, _)| (strsim::levenshtein(key, known), known))
+        .filter(|&(distance, _)| distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, known)| known)
+}
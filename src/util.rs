@@ -1,8 +1,12 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::OnceLock;
 use std::{fmt::Display, panic::Location, process::exit};
 
 use anyhow::{anyhow, Result};
-use log::error;
 use miette::Diagnostic;
+use tracing::error;
 
 /// An extension trait for `Option` and `Result` to log errors and then exit.
 /// These errors are meant to be seen by the user and are intentional.
@@ -33,3 +37,111 @@ impl<T, E: Display> LogExpect<T> for Result<T, E> {
         }
     }
 }
+
+impl<T> LogExpect<T> for Option<T> {
+    #[track_caller]
+    fn log_expect(self, msg: &str) -> T {
+        if let Some(o) = self {
+            o
+        } else {
+            error!("{msg}\n    at {}", Location::caller());
+            exit(1)
+        }
+    }
+}
+
+/// The path that stands for standard input or standard output on the command line.
+pub const STDIO_PATH: &str = "-";
+
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+/// Reads a file, or standard input if `path` is `-`. Standard input is kept after it is read
+/// so that it can be read again, for example to show the source of an error.
+pub fn read_input(path: &Path) -> io::Result<String> {
+    static STDIN: OnceLock<String> = OnceLock::new();
+    if !is_stdio(path) {
+        return std::fs::read_to_string(path);
+    }
+    if let Some(content) = STDIN.get() {
+        return Ok(content.clone());
+    }
+    let mut content = String::new();
+    io::stdin().read_to_string(&mut content)?;
+    Ok(STDIN.get_or_init(|| content).clone())
+}
+
+/// Creates a file to write to, along with its parent directories, or returns standard output
+/// if `path` is `-`.
+pub fn create_output(path: &Path) -> Result<Box<dyn Write>> {
+    if is_stdio(path) {
+        return Ok(Box::new(BufWriter::new(io::stdout().lock())));
+    }
+    let prefix = path
+        .parent()
+        .ok_or_else(|| anyhow!("output file has no parent"))?;
+    std::fs::create_dir_all(prefix)?;
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
+
+/// Writes `content` like [`create_output`], unless `path` is a file that already has exactly
+/// that content. Returns whether it was written.
+pub fn write_if_changed(path: &Path, content: &[u8]) -> Result<bool> {
+    if !is_stdio(path) && std::fs::read(path).is_ok_and(|existing| existing == content) {
+        return Ok(false);
+    }
+    let mut writer = create_output(path)?;
+    writer.write_all(content)?;
+    writer.flush()?;
+    Ok(true)
+}
+
+/// Turns a theme name into a file name, e.g. `Foo Dark (Blue)` becomes `foo-dark-blue`.
+pub fn slug(name: &str) -> String {
+    let slug = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "theme".to_owned()
+    } else {
+        slug
+    }
+}
+
+/// Whether `text` is an HTTP(S) URL rather than a path.
+pub fn is_url(text: &str) -> bool {
+    text.starts_with("http://") || text.starts_with("https://")
+}
+
+/// Downloads a file over HTTP(S), failing if it is larger than `max_size` bytes.
+#[cfg(feature = "download")]
+pub fn download(url: &str, max_size: u64) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| anyhow!("could not download {url}: {e}"))?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(max_size + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| anyhow!("could not download {url}: {e}"))?;
+    if body.len() as u64 > max_size {
+        return Err(anyhow!(
+            "{url} is larger than the limit of {} MiB",
+            max_size / 1024 / 1024
+        ));
+    }
+    Ok(body)
+}
+
+/// Fails, since downloading needs the `download` feature.
+#[cfg(not(feature = "download"))]
+pub fn download(url: &str, _max_size: u64) -> Result<Vec<u8>> {
+    Err(anyhow!(
+        "could not download {url}: zeddy was built without the `download` feature"
+    ))
+}
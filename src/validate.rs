@@ -0,0 +1,113 @@
+//! Checks for mistakes in a theme family that the KDL decoder cannot catch on its own,
+//! both in the KDL source and in the JSON generated from it.
+
+use std::{collections::BTreeSet, fmt::Display};
+
+use anyhow::anyhow;
+use serde_json::Value;
+
+use crate::schema::{
+    json::ThemeFamily as JsonThemeFamily,
+    kdl::{ModifierPath, ThemeFamily},
+    zed,
+};
+
+/// A `style` path that does not correspond to anything in Zed's theme schema.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownStyleKey {
+    pub key: String,
+    pub suggestion: Option<&'static str>,
+}
+
+impl Display for UnknownStyleKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown style key `{}`", self.key)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects every distinct unknown `style` path referenced by the themes in the family,
+/// including the `common` theme and the themes of templates.
+pub fn unknown_style_keys(family: &ThemeFamily) -> Vec<UnknownStyleKey> {
+    family
+        .themes
+        .iter()
+        .chain(&family.common)
+        .chain(family.templates.iter().map(|template| &template.theme))
+        .flat_map(|theme| &theme.modifiers)
+        .flat_map(|modifier| &modifier.apply)
+        .filter_map(|path| match path {
+            ModifierPath::Style(key) if !zed::is_style_key(key) => Some(key),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|key| UnknownStyleKey {
+            key: key.clone(),
+            suggestion: zed::suggest_style_key(key),
+        })
+        .collect()
+}
+
+/// A place where a generated theme family does not conform to Zed's theme JSON Schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// A human readable path to the offending value, such as `theme "Foo Dark": style.text`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Validates a generated theme family against a theme JSON Schema, returning every violation.
+pub fn schema_violations(
+    family: &JsonThemeFamily,
+    schema: &Value,
+) -> anyhow::Result<Vec<SchemaViolation>> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| anyhow!("invalid theme schema: {e}"))?;
+    let instance = serde_json::to_value(family)?;
+    Ok(validator
+        .iter_errors(&instance)
+        .map(|error| SchemaViolation {
+            path: describe_pointer(&instance, error.instance_path.as_str()),
+            message: error.to_string(),
+        })
+        .collect())
+}
+
+/// Turns a JSON pointer into the family into something a theme author can recognize,
+/// replacing theme indices with theme names and joining style keys with dots.
+fn describe_pointer(instance: &Value, pointer: &str) -> String {
+    let segments = pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>();
+    match segments.as_slice() {
+        [] => "<root>".to_owned(),
+        [themes, index, rest @ ..] if themes == "themes" => {
+            let name = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| instance["themes"][i]["name"].as_str())
+                .map_or_else(
+                    || format!("theme #{index}"),
+                    |name| format!("theme {name:?}"),
+                );
+            if rest.is_empty() {
+                name
+            } else {
+                format!("{name}: {}", rest.join("."))
+            }
+        }
+        segments => segments.join("."),
+    }
+}
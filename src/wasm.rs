@@ -0,0 +1,29 @@
+//! Bindings to the conversion core for JavaScript, so that a playground in the browser can
+//! generate themes with the same code as the command line interface. Files cannot be read there,
+//! so themes are passed as text and cannot include other files.
+
+use wasm_bindgen::prelude::*;
+
+use crate::embed;
+
+/// Generates the JSON theme family of a KDL theme file, written in version `kdl_version` of KDL
+/// (`1` or `2`), for version `schema_version` of Zed's theme schema, such as `0.1.0`.
+#[wasm_bindgen(js_name = generateJson)]
+pub fn generate_json(
+    source: &str,
+    kdl_version: &str,
+    schema_version: &str,
+) -> Result<String, JsError> {
+    embed::kdl_to_json(source, None, kdl_version, schema_version).map_err(|e| to_js(&e))
+}
+
+/// Resolves the palette of a KDL theme file, written in version `kdl_version` of KDL, into a JSON
+/// object of the hex color of every palette entry by name.
+#[wasm_bindgen(js_name = resolvePalette)]
+pub fn resolve_palette(source: &str, kdl_version: &str) -> Result<String, JsError> {
+    embed::resolve_palette(source, None, kdl_version).map_err(|e| to_js(&e))
+}
+
+fn to_js(error: &anyhow::Error) -> JsError {
+    JsError::new(&format!("{error:?}"))
+}